@@ -0,0 +1,218 @@
+-- SQLite schema, the same tables as cashlog.sql with all migrations.
+-- It's applied when SQLite database is opened, see `storage::sqlite`.
+--
+-- Timestamps are text formatted as 'YYYY-MM-DD HH:MM:SS.ffffff', so they
+-- sort and compare as text. Amounts and prices are decimal text, as
+-- entered, and are cast to real only to compute values.
+
+create table if not exists account (
+    id integer primary key,
+    disabled boolean not null default false,
+    created text not null,
+    modified text not null
+);
+
+create table if not exists account_email (
+    id integer primary key,
+    account integer not null references account,
+    email text not null,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists account_email_ui on account_email (email);
+
+create table if not exists login_token (
+    id integer primary key,
+    account integer not null references account,
+    token text not null,
+    purpose text not null default 'login'
+        check (purpose in ('login', 'delete-account')),
+    used boolean not null,
+    used_ts text,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists login_token_token_ui on login_token (token);
+
+create table if not exists session (
+    id integer primary key,
+    key text not null,
+    name text not null,
+    value text not null,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists session_key_name_i on session (key, name);
+
+create table if not exists bank_account (
+    id integer primary key,
+    account integer not null references account,
+    name text not null,
+    currency text not null,
+    group_name text,
+    kind text not null default 'asset' check (kind in ('asset', 'liability')),
+    commodity text,
+    deleted boolean not null default false,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists bank_account_account_name_currency_i
+on bank_account (account, name, currency);
+
+create table if not exists entry (
+    id integer primary key,
+    bank_account integer not null references bank_account,
+    ts text not null,
+    amount text not null,
+    deleted boolean not null default false,
+    created text not null,
+    modified text not null,
+    note text
+);
+
+create index if not exists entry_bank_account_ts_i on entry (bank_account, ts);
+
+create table if not exists price (
+    id integer primary key,
+    account integer not null references account,
+    commodity text not null,
+    currency text not null,
+    ts text not null,
+    price text not null,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists price_account_commodity_currency_ts_ui
+on price (account, commodity, currency, ts);
+
+create table if not exists bank_account_share (
+    id integer primary key,
+    bank_account integer not null references bank_account,
+    account integer not null references account,
+    role text not null check (role in ('viewer', 'editor')),
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists bank_account_share_bank_account_account_ui
+on bank_account_share (bank_account, account);
+
+create index if not exists bank_account_share_account_i
+on bank_account_share (account);
+
+create table if not exists api_token (
+    id integer primary key,
+    account integer not null references account,
+    name text not null,
+    token text not null,
+    last_used text,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists api_token_token_ui on api_token (token);
+
+create table if not exists account_preference (
+    account integer primary key references account,
+    theme text not null default 'light'
+        check (theme in ('light', 'dark', 'high-contrast')),
+    landing_page text not null default 'entries'
+        check (landing_page in ('entries', 'accounts', 'currency', 'check-in', 'report')),
+    default_bank_account integer references bank_account,
+    entries_per_page integer not null default 100
+        check (entries_per_page between 1 and 1024),
+    created text not null,
+    modified text not null
+);
+
+create table if not exists webhook (
+    id integer primary key,
+    account integer not null references account,
+    url text not null,
+    secret text not null,
+    created text not null,
+    modified text not null
+);
+
+create index if not exists webhook_account_i on webhook (account);
+
+create table if not exists webhook_delivery (
+    id integer primary key,
+    webhook integer not null references webhook,
+    event text not null,
+    payload text not null,
+    status text not null default 'pending'
+        check (status in ('pending', 'delivered', 'failed')),
+    attempts integer not null default 0,
+    next_attempt text,
+    last_attempt text,
+    response_status integer,
+    error text,
+    created text not null,
+    modified text not null
+);
+
+create index if not exists webhook_delivery_webhook_i on webhook_delivery (webhook);
+
+create index if not exists webhook_delivery_next_attempt_i on webhook_delivery (next_attempt)
+where status = 'pending';
+
+create table if not exists account_digest (
+    account integer primary key references account,
+    frequency text not null
+        check (frequency in ('weekly', 'monthly')),
+    sent text,
+    created text not null,
+    modified text not null
+);
+
+create table if not exists login_request (
+    id integer primary key,
+    email text not null,
+    ip text,
+    token text,
+    used boolean not null default false,
+    invite text,
+    created text not null,
+    modified text not null
+);
+
+create index if not exists login_request_email_i on login_request (email);
+
+create index if not exists login_request_ip_i on login_request (ip);
+
+create unique index if not exists login_request_token_ui on login_request (token);
+
+create table if not exists account_totp (
+    account integer primary key references account,
+    secret text not null,
+    enabled boolean not null default false,
+    last_step integer,
+    created text not null,
+    modified text not null
+);
+
+create table if not exists account_recovery_code (
+    id integer primary key,
+    account integer not null references account,
+    code_hash text not null,
+    created text not null
+);
+
+create index if not exists account_recovery_code_account_i on account_recovery_code (account);
+
+create table if not exists invite (
+    id integer primary key,
+    code text not null,
+    note text,
+    used text,
+    created text not null,
+    modified text not null
+);
+
+create unique index if not exists invite_code_ui on invite (code);
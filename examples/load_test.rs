@@ -0,0 +1,187 @@
+//! Load test of a running server, to see how it copes with slow database.
+//!
+//!     cargo run --release --example load_test -- <server URL> <session key>
+//!         [--clients N] [--seconds N] [--slow-db CONFIG] [--lock-ms N]
+//!
+//! Clients fetch the main page, which queries entries, in a loop. One more
+//! client fetches the about page, which doesn't use the database at all.
+//! With `--slow-db` the test holds an exclusive lock on the entry table of
+//! the PostgreSQL database from given config for `--lock-ms` of every
+//! second, so the main page queries wait like slow queries would.
+//!
+//! Prints throughput and latencies of both pages. The about page should
+//! stay fast however slow the database is; when handlers block actix
+//! workers on the database, it waits for the lock too.
+
+extern crate cashlog;
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use cashlog::common;
+
+const USAGE: &str = "\
+Usage: load_test <server URL> <session key> [--clients N] [--seconds N]
+                 [--slow-db CONFIG] [--lock-ms N]";
+
+struct Options {
+    url: String,
+    session_key: String,
+    clients: usize,
+    seconds: u64,
+    slow_db: Option<String>,
+    lock_ms: u64,
+}
+
+/// Latencies of successful requests, and count of failed ones.
+#[derive(Default)]
+struct Stats {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut positional = Vec::new();
+    let mut options = Options {
+        url: String::new(),
+        session_key: String::new(),
+        clients: 32,
+        seconds: 10,
+        slow_db: None,
+        lock_ms: 500,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value of {}", arg))
+        };
+        let number = |s: String| s.parse().map_err(|_| format!("Invalid number: {}", s));
+        match arg.as_str() {
+            "--clients" => options.clients = number(value()?)? as usize,
+            "--seconds" => options.seconds = number(value()?)?,
+            "--slow-db" => options.slow_db = Some(value()?),
+            "--lock-ms" => options.lock_ms = number(value()?)?,
+            _ => positional.push(arg.clone()),
+        }
+    }
+    match positional.as_slice() {
+        [url, session_key] => {
+            options.url = url.trim_end_matches('/').to_string();
+            options.session_key = session_key.clone();
+            Ok(options)
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Fetch `url` until `done`, recording latencies.
+fn client(url: String, session_key: String, done: Arc<AtomicBool>, stats: Arc<Mutex<Stats>>) {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(60))
+        .build();
+    while !done.load(Ordering::Relaxed) {
+        let start = Instant::now();
+        let result = agent
+            .get(&url)
+            .set("Cookie", &format!("session={}", session_key))
+            .call()
+            .map(|response| response.into_string());
+        let mut stats = stats.lock().unwrap();
+        match result {
+            Ok(Ok(_)) => stats.latencies.push(start.elapsed()),
+            _ => stats.errors += 1,
+        }
+    }
+}
+
+/// Hold exclusive lock on entry table for `lock_ms` of every second.
+fn slow_db(config: &str, lock_ms: u64, done: Arc<AtomicBool>) {
+    let conf = common::load_config_or_exit(config);
+    let mut client = postgres::Config::new()
+        .host(&conf.db_host)
+        .port(conf.db_port)
+        .dbname(&conf.db_name)
+        .user(&conf.db_username)
+        .password(&conf.db_password)
+        .connect(postgres::NoTls)
+        .expect("Failed to connect to database");
+    while !done.load(Ordering::Relaxed) {
+        let mut transaction = client.transaction().unwrap();
+        transaction
+            .batch_execute("lock table entry in access exclusive mode")
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(lock_ms));
+        transaction.commit().unwrap();
+        std::thread::sleep(Duration::from_millis(1000u64.saturating_sub(lock_ms)));
+    }
+}
+
+fn print_stats(name: &str, stats: &Mutex<Stats>, elapsed: Duration) {
+    let mut stats = stats.lock().unwrap();
+    stats.latencies.sort();
+    let percentile = |p: usize| -> f64 {
+        match stats.latencies.len() {
+            0 => 0.0,
+            n => stats.latencies[(n - 1) * p / 100].as_secs_f64() * 1000.0,
+        }
+    };
+    println!(
+        "{:<6} {:>8} {:>7} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+        name,
+        stats.latencies.len(),
+        stats.errors,
+        stats.latencies.len() as f64 / elapsed.as_secs_f64(),
+        percentile(50),
+        percentile(99),
+        percentile(100),
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let done = Arc::new(AtomicBool::new(false));
+    let main_stats = Arc::new(Mutex::new(Stats::default()));
+    let about_stats = Arc::new(Mutex::new(Stats::default()));
+    let mut threads = Vec::new();
+    if let Some(config) = options.slow_db.clone() {
+        let (done, lock_ms) = (done.clone(), options.lock_ms);
+        threads.push(std::thread::spawn(move || slow_db(&config, lock_ms, done)));
+    }
+    for i in 0..=options.clients {
+        let (url, stats) = if i == options.clients {
+            (format!("{}/about", options.url), about_stats.clone())
+        } else {
+            (format!("{}/", options.url), main_stats.clone())
+        };
+        let (session_key, done) = (options.session_key.clone(), done.clone());
+        threads.push(std::thread::spawn(move || {
+            client(url, session_key, done, stats)
+        }));
+    }
+    let start = Instant::now();
+    std::thread::sleep(Duration::from_secs(options.seconds));
+    done.store(true, Ordering::Relaxed);
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<6} {:>8} {:>7} {:>9} {:>9} {:>9} {:>9}",
+        "page", "requests", "errors", "req/s", "p50 ms", "p99 ms", "max ms"
+    );
+    print_stats("main", &main_stats, elapsed);
+    print_stats("about", &about_stats, elapsed);
+}
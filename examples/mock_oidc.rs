@@ -0,0 +1,246 @@
+//! Mock OpenID Connect provider, to try and test OIDC login locally.
+//!
+//!     cargo run --example mock_oidc -- [port]
+//!
+//! Port is 14081 by default. Point cashlog at it with:
+//!
+//!     [config.oidc]
+//!     issuer = "http://localhost:14081"
+//!     client_id = "cashlog"
+//!     client_secret = "secret"
+//!
+//! The login page asks for any email, and whether the provider should say
+//! it's verified. Any client id and secret are accepted, but the code has
+//! to be redeemed once, by the same client, with the same redirect URL and
+//! the PKCE verifier. ID tokens are not signed.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use actix_web::web;
+use actix_web::App;
+use actix_web::HttpResponse;
+use actix_web::HttpServer;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use maud::html;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Digest;
+use sha2::Sha256;
+
+const DEFAULT_PORT: u16 = 14081;
+
+/// Issued authorization code, waiting to be redeemed.
+struct Grant {
+    client_id: String,
+    redirect_uri: String,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    email: String,
+    email_verified: bool,
+}
+
+struct Provider {
+    issuer: String,
+    grants: Mutex<HashMap<String, Grant>>,
+}
+
+#[derive(Deserialize)]
+struct AuthorizeParams {
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AuthorizeForm {
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    email: String,
+    email_verified: Option<String>,
+    deny: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    code: String,
+    client_id: String,
+    redirect_uri: String,
+    code_verifier: Option<String>,
+}
+
+fn random_token() -> String {
+    let mut buf = [0u8; 16];
+    getrandom::getrandom(&mut buf).unwrap();
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn redirect(uri: &str, params: &[(&str, &str)]) -> HttpResponse {
+    let mut url = match url::Url::parse(uri) {
+        Ok(url) => url,
+        Err(_) => return HttpResponse::BadRequest().body("Invalid redirect_uri"),
+    };
+    for (k, v) in params {
+        url.query_pairs_mut().append_pair(k, v);
+    }
+    HttpResponse::SeeOther()
+        .header("Location", url.to_string())
+        .finish()
+}
+
+async fn discovery(provider: web::Data<Provider>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": provider.issuer,
+        "authorization_endpoint": format!("{}/authorize", provider.issuer),
+        "token_endpoint": format!("{}/token", provider.issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["none"],
+        "code_challenge_methods_supported": ["S256"],
+    }))
+}
+
+async fn authorize(params: web::Query<AuthorizeParams>) -> HttpResponse {
+    let content = html! {
+        html {
+            body {
+                h1 { "Mock OpenID Connect provider" }
+                p { "Log in to " (params.client_id) " as:" }
+                form method="post" action="/authorize" {
+                    input type="hidden" name="client_id" value=(params.client_id) /
+                    input type="hidden" name="redirect_uri" value=(params.redirect_uri) /
+                    @if let Some(state) = &params.state {
+                        input type="hidden" name="state" value=(state) /
+                    }
+                    @if let Some(nonce) = &params.nonce {
+                        input type="hidden" name="nonce" value=(nonce) /
+                    }
+                    @if let Some(challenge) = &params.code_challenge {
+                        input type="hidden" name="code_challenge" value=(challenge) /
+                    }
+                    p {
+                        "Email: "
+                        input type="text" name="email" /
+                    }
+                    p {
+                        label {
+                            input type="checkbox" name="email_verified" value="true" checked /
+                            " Email is verified"
+                        }
+                    }
+                    button type="submit" { "Log in" }
+                    " "
+                    button type="submit" name="deny" value="1" { "Deny" }
+                }
+            }
+        }
+    };
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content.into_string())
+}
+
+async fn post_authorize(
+    provider: web::Data<Provider>,
+    form: web::Form<AuthorizeForm>,
+) -> HttpResponse {
+    let form = form.into_inner();
+    let state = form.state.clone().unwrap_or_default();
+    if form.deny.is_some() {
+        return redirect(
+            &form.redirect_uri,
+            &[
+                ("error", "access_denied"),
+                ("error_description", "User denied access"),
+                ("state", &state),
+            ],
+        );
+    }
+    let code = random_token();
+    let redirect_uri = form.redirect_uri.clone();
+    provider.grants.lock().unwrap().insert(
+        code.clone(),
+        Grant {
+            client_id: form.client_id,
+            redirect_uri: form.redirect_uri,
+            nonce: form.nonce,
+            code_challenge: form.code_challenge,
+            email: form.email.trim().to_string(),
+            email_verified: form.email_verified.is_some(),
+        },
+    );
+    redirect(&redirect_uri, &[("code", &code), ("state", &state)])
+}
+
+async fn token(provider: web::Data<Provider>, form: web::Form<TokenForm>) -> HttpResponse {
+    let invalid_grant = HttpResponse::BadRequest().json(json!({"error": "invalid_grant"}));
+    let grant = match provider.grants.lock().unwrap().remove(&form.code) {
+        Some(grant) => grant,
+        None => return invalid_grant,
+    };
+    if grant.client_id != form.client_id || grant.redirect_uri != form.redirect_uri {
+        return invalid_grant;
+    }
+    if let Some(challenge) = &grant.code_challenge {
+        let verifier = form.code_verifier.as_deref().unwrap_or_default();
+        if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != *challenge {
+            return invalid_grant;
+        }
+    }
+    let now = chrono::Utc::now().timestamp();
+    let header = json!({"alg": "none", "typ": "JWT"});
+    let claims = json!({
+        "iss": provider.issuer,
+        "sub": grant.email,
+        "aud": grant.client_id,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": grant.nonce,
+        "email": grant.email,
+        "email_verified": grant.email_verified,
+    });
+    let id_token = format!(
+        "{}.{}.",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    HttpResponse::Ok().json(json!({
+        "access_token": random_token(),
+        "token_type": "Bearer",
+        "expires_in": 300,
+        "id_token": id_token,
+    }))
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let port: u16 = match std::env::args().nth(1) {
+        Some(p) => p.parse().expect("Port must be a number"),
+        None => DEFAULT_PORT,
+    };
+    let provider = web::Data::new(Provider {
+        issuer: format!("http://localhost:{}", port),
+        grants: Mutex::new(HashMap::new()),
+    });
+    println!("Mock OpenID Connect provider at {}", provider.issuer);
+    HttpServer::new(move || {
+        App::new()
+            .app_data(provider.clone())
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(discovery),
+            )
+            .route("/authorize", web::get().to(authorize))
+            .route("/authorize", web::post().to(post_authorize))
+            .route("/token", web::post().to(token))
+    })
+    .bind(("127.0.0.1", port))?
+    .run()
+    .await
+}
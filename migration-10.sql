@@ -0,0 +1,40 @@
+create sequence webhook_seq;
+
+-- Endpoint that gets signed JSON when entries or bank accounts change, see
+-- `webhook` module. Secret is used to sign payloads with HMAC-SHA256.
+create table webhook (
+    id bigint primary key,
+    account bigint not null references account,
+    url varchar(1024) not null,
+    secret varchar(128) not null,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+create index webhook_account_i on webhook (account);
+
+create sequence webhook_delivery_seq;
+
+-- Outbox of webhook calls, rows are inserted with the change and sent by
+-- the delivery worker. Pending deliveries are retried at next_attempt until
+-- they succeed or run out of attempts, then they stay as delivery log.
+create table webhook_delivery (
+    id bigint primary key,
+    webhook bigint not null references webhook,
+    event varchar(32) not null,
+    payload text not null,
+    status varchar(16) not null default 'pending'
+        check (status in ('pending', 'delivered', 'failed')),
+    attempts integer not null default 0,
+    next_attempt timestamp without time zone,
+    last_attempt timestamp without time zone,
+    response_status integer,
+    error text,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+create index webhook_delivery_webhook_i on webhook_delivery (webhook);
+
+create index webhook_delivery_next_attempt_i on webhook_delivery (next_attempt)
+where status = 'pending';
@@ -0,0 +1,11 @@
+-- Email digest of balances the user account opted in to, see `digest`
+-- module. Row is deleted when digest is turned off. Sent is when the last
+-- digest was sent, changes in the next one are counted since then.
+create table account_digest (
+    account bigint primary key references account,
+    frequency varchar(16) not null
+        check (frequency in ('weekly', 'monthly')),
+    sent timestamp without time zone,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
@@ -0,0 +1,3 @@
+-- Optional free-text note on entry, e.g. what explains a balance jump.
+alter table entry
+add column note varchar(1024);
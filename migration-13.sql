@@ -0,0 +1,21 @@
+create sequence login_request_seq;
+
+-- Login form submissions, used to rate limit login emails per email and per
+-- IP address. Token is set when the email has no user account yet, the
+-- account is created only when the link with it is clicked. Rows older than
+-- a day are deleted when new ones are inserted.
+create table login_request (
+    id bigint primary key,
+    email varchar(128) not null,
+    ip varchar(64),
+    token varchar(128),
+    used boolean not null default false,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+create index login_request_email_i on login_request (email);
+
+create index login_request_ip_i on login_request (ip);
+
+create unique index login_request_token_ui on login_request (token);
@@ -0,0 +1,26 @@
+-- TOTP second factor of user account, see `totp` module. Secret is set when
+-- enrolment starts, enabled is set once the first code is confirmed. Last
+-- step is the time step of the last accepted code, so codes can't be
+-- reused.
+create table account_totp (
+    account bigint primary key references account,
+    secret varchar(64) not null,
+    enabled boolean not null default false,
+    last_step bigint,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+create sequence account_recovery_code_seq;
+
+-- One-time codes that can be used instead of TOTP code, e.g. when the
+-- authenticator is lost. Only SHA-256 hex of the code is stored, used codes
+-- are deleted.
+create table account_recovery_code (
+    id bigint primary key,
+    account bigint not null references account,
+    code_hash varchar(64) not null,
+    created timestamp without time zone not null
+);
+
+create index account_recovery_code_account_i on account_recovery_code (account);
@@ -0,0 +1,19 @@
+create sequence invite_seq;
+
+-- Invite codes for `registration = "invite"`, see `registration` module.
+-- Used is when user account was created with the code, codes work once.
+create table invite (
+    id bigint primary key,
+    code varchar(32) not null,
+    note varchar(256),
+    used timestamp without time zone,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+create unique index invite_code_ui on invite (code);
+
+-- Invite code given on login form, used up when the link creates the user
+-- account.
+alter table login_request
+add column invite varchar(32);
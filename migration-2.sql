@@ -0,0 +1,3 @@
+-- User defined group of bank account, e.g. "emergency fund" or "spending".
+-- Accounts without group have null here.
+alter table bank_account add column group_name varchar(32);
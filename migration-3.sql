@@ -0,0 +1,6 @@
+-- Asset bank accounts hold money, liabilities (credit cards, loans) owe it.
+-- Liability balance is entered as positive outstanding amount and is
+-- subtracted in totals.
+alter table bank_account
+add column kind varchar(16) not null default 'asset'
+check (kind in ('asset', 'liability'));
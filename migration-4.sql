@@ -0,0 +1,21 @@
+-- Holdings: when bank account has commodity (ticker, fund, gram of gold),
+-- its entry amounts are quantities and are valued by price.
+alter table bank_account add column commodity varchar(32);
+
+create sequence price_seq;
+
+-- Unit price of commodity in currency, entered by user.
+create table price (
+    id bigint primary key,
+    account bigint not null references account,
+    commodity varchar(32) not null,
+    currency varchar(3) not null,
+    ts timestamp without time zone not null,
+    price numeric not null,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+-- One price per commodity per ts, also used to find latest price.
+create unique index price_account_commodity_currency_ts_ui
+on price (account, commodity, currency, ts);
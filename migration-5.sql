@@ -0,0 +1,20 @@
+create sequence bank_account_share_seq;
+
+-- Bank account shared by its owner with another user account, e.g. joint
+-- account of a household. Viewers see the bank account in their lists and
+-- totals, editors can also add, edit and delete its entries.
+create table bank_account_share (
+    id bigint primary key,
+    bank_account bigint not null references bank_account,
+    account bigint not null references account,
+    role varchar(16) not null check (role in ('viewer', 'editor')),
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+-- Bank account is shared with given user account once.
+create unique index bank_account_share_bank_account_account_ui
+on bank_account_share (bank_account, account);
+
+-- Used to find bank accounts shared with user account.
+create index bank_account_share_account_i on bank_account_share (account);
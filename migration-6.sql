@@ -0,0 +1,5 @@
+-- Login tokens are also used to confirm other actions over email, e.g.
+-- account deletion. Token can only be used for its purpose.
+alter table login_token
+add column purpose varchar(16) not null default 'login'
+check (purpose in ('login', 'delete-account'));
@@ -0,0 +1,15 @@
+create sequence api_token_seq;
+
+-- Personal token that gives access to the JSON API, e.g. for cashlog-cli.
+-- Unlike login tokens these are used many times, until revoked.
+create table api_token (
+    id bigint primary key,
+    account bigint not null references account,
+    name varchar(64) not null,
+    token varchar(128) not null,
+    last_used timestamp without time zone,
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
+
+create unique index api_token_token_ui on api_token (token);
@@ -0,0 +1,4 @@
+-- Disabled account can't log in and its API tokens don't work, but nothing
+-- is deleted, so it can be enabled again. See `cashlog admin`.
+alter table account
+add column disabled bool not null default false;
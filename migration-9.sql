@@ -0,0 +1,14 @@
+-- Settings of user account, see settings page. Row is created when user
+-- saves settings for the first time, defaults are used until then.
+create table account_preference (
+    account bigint primary key references account,
+    theme varchar(16) not null default 'light'
+        check (theme in ('light', 'dark', 'high-contrast')),
+    landing_page varchar(16) not null default 'entries'
+        check (landing_page in ('entries', 'accounts', 'currency', 'check-in', 'report')),
+    default_bank_account bigint references bank_account,
+    entries_per_page integer not null default 100
+        check (entries_per_page between 1 and 1024),
+    created timestamp without time zone not null,
+    modified timestamp without time zone not null
+);
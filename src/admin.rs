@@ -0,0 +1,139 @@
+//! `cashlog admin` subcommands for operating an instance from the shell,
+//! e.g. to bootstrap the first user account without sending emails.
+
+use crate::common;
+use crate::db;
+use crate::model::Config;
+use crate::registration;
+use crate::storage;
+use crate::storage::Storage;
+
+const USAGE: &str = "\
+Usage: cashlog admin <command>
+
+Commands:
+    create-user <email>   create user account with given email
+    login-link <email>    print a fresh login link for the user account
+    list-users            list user accounts with entry counts
+    disable <email>       disable user account and log out its sessions
+    enable <email>        enable disabled user account
+    create-invite [note]  print a new invite code for registration
+    list-invites          list invite codes and when they were used
+";
+
+/// Same as `invite.note` column size.
+const MAX_NOTE_LENGTH: usize = 256;
+
+fn db_error(e: db::DbError) -> String {
+    e.to_string()
+}
+
+fn account_id(conn: &mut dyn Storage, email: &str) -> Result<i64, String> {
+    conn.get_acc_id_by_email(email)
+        .map_err(db_error)?
+        .ok_or_else(|| format!("No user account with email {}", email))
+}
+
+fn cmd_create_user(conn: &mut dyn Storage, email: &str) -> Result<(), String> {
+    if conn.get_acc_id_by_email(email).map_err(db_error)?.is_some() {
+        return Err(format!("User account with email {} already exists", email));
+    }
+    let account_id = conn.create_acc_with_email(email).map_err(db_error)?;
+    println!("Created user account {} for {}", account_id, email);
+    Ok(())
+}
+
+fn cmd_login_link(conn: &mut dyn Storage, conf: &Config, email: &str) -> Result<(), String> {
+    let account_id = account_id(conn, email)?;
+    if conn.is_account_disabled(account_id).map_err(db_error)? {
+        return Err(format!("User account {} is disabled", account_id));
+    }
+    let token: String = uuid::Uuid::new_v4().to_string();
+    conn.insert_login_token(&account_id, &token)
+        .map_err(db_error)?;
+    println!("{}", common::login_url(&conf.base_url, &token));
+    Ok(())
+}
+
+fn cmd_list_users(conn: &mut dyn Storage) -> Result<(), String> {
+    println!(
+        "{:>6}  {:<19}  {:>8}  {:>7}  {:<8}  emails",
+        "id", "created", "accounts", "entries", "status"
+    );
+    for a in conn.get_account_summaries().map_err(db_error)? {
+        println!(
+            "{:>6}  {:<19}  {:>8}  {:>7}  {:<8}  {}",
+            a.id,
+            a.created.format("%Y-%m-%d %H:%M:%S"),
+            a.bank_accounts,
+            a.entries,
+            if a.disabled { "disabled" } else { "enabled" },
+            a.emails.join(" ")
+        );
+    }
+    Ok(())
+}
+
+fn cmd_set_disabled(conn: &mut dyn Storage, email: &str, disabled: bool) -> Result<(), String> {
+    let account_id = account_id(conn, email)?;
+    conn.set_account_disabled(account_id, disabled)
+        .map_err(db_error)?;
+    println!(
+        "{} user account {}",
+        if disabled { "Disabled" } else { "Enabled" },
+        account_id
+    );
+    Ok(())
+}
+
+fn cmd_create_invite(conn: &mut dyn Storage, note: Option<&str>) -> Result<(), String> {
+    if note.is_some_and(|n| n.chars().count() > MAX_NOTE_LENGTH) {
+        return Err(format!(
+            "Note can have at most {} characters",
+            MAX_NOTE_LENGTH
+        ));
+    }
+    let code = registration::generate_invite();
+    conn.insert_invite(&code, note).map_err(db_error)?;
+    println!("{}", code);
+    Ok(())
+}
+
+fn cmd_list_invites(conn: &mut dyn Storage) -> Result<(), String> {
+    println!("{:<14}  {:<19}  {:<19}  note", "code", "created", "used");
+    for i in conn.get_invites().map_err(db_error)? {
+        println!(
+            "{:<14}  {:<19}  {:<19}  {}",
+            i.code,
+            i.created.format("%Y-%m-%d %H:%M:%S"),
+            i.used
+                .map(|u| u.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            i.note.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// Run admin subcommand, `args` are the arguments after `admin`.
+pub fn run(conf: &Config, args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if args.is_empty() || args.contains(&"--help") {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+    let mut conn = storage::open_pool(conf)
+        .and_then(|pool| pool.get())
+        .map_err(db_error)?;
+    match args.as_slice() {
+        ["create-user", email] => cmd_create_user(&mut *conn, email),
+        ["login-link", email] => cmd_login_link(&mut *conn, conf, email),
+        ["list-users"] => cmd_list_users(&mut *conn),
+        ["disable", email] => cmd_set_disabled(&mut *conn, email, true),
+        ["enable", email] => cmd_set_disabled(&mut *conn, email, false),
+        ["create-invite"] => cmd_create_invite(&mut *conn, None),
+        ["create-invite", note @ ..] => cmd_create_invite(&mut *conn, Some(&note.join(" "))),
+        ["list-invites"] => cmd_list_invites(&mut *conn),
+        _ => Err(format!("Invalid command: {}\n\n{}", args.join(" "), USAGE)),
+    }
+}
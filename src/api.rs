@@ -0,0 +1,135 @@
+//! Types of the JSON API, used by the API handlers and by `cashlog-cli`.
+//!
+//! API is authorized by personal token in `Authorization: Bearer` header.
+//! Timestamps are strings formatted as `YYYY-MM-DD HH:MM:SS.ffffff`, amounts
+//! are decimal strings.
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::db;
+use crate::model::BankAccountKind;
+use crate::model::EntryInfo;
+use crate::model::ShareRole;
+use crate::storage::Storage;
+
+pub fn format_api_ts(ts: DateTime<Utc>) -> String {
+    ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+/// Parse timestamp formatted by `format_api_ts`, fraction is optional.
+pub fn parse_api_ts(s: &str) -> Result<DateTime<Utc>, String> {
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|ts| ts.and_utc())
+        .map_err(|e| format!("Error parsing datetime {}: {}", s, e))
+}
+
+/// Bank account with its latest balance, if it has entries.
+#[derive(Serialize, Deserialize)]
+pub struct ApiBankAccount {
+    pub id: i64,
+    pub name: String,
+    pub currency: String,
+    pub group_name: Option<String>,
+    /// See `BankAccountKind::as_str`.
+    pub kind: String,
+    pub commodity: Option<String>,
+    /// See `ShareRole::as_str`, `None` for owner.
+    pub role: Option<String>,
+    pub amount: Option<String>,
+    pub value: Option<String>,
+    pub ts: Option<String>,
+}
+
+impl ApiBankAccount {
+    pub fn can_edit(&self) -> bool {
+        ShareRole::can_edit(self.role.as_deref().and_then(ShareRole::parse))
+    }
+}
+
+/// Get bank accounts the user account can see, with latest balances.
+pub fn get_bank_accounts(
+    conn: &mut dyn Storage,
+    account_id: i64,
+) -> Result<Vec<ApiBankAccount>, db::DbError> {
+    let infos = conn.get_bank_account_infos(account_id, None)?;
+    Ok(conn
+        .get_bank_accounts(account_id)?
+        .into_iter()
+        .map(|ba| {
+            let info = infos.iter().find(|info| info.id == ba.id);
+            ApiBankAccount {
+                id: ba.id,
+                name: ba.name,
+                currency: ba.currency,
+                group_name: ba.group_name,
+                kind: ba.kind.as_str().to_string(),
+                commodity: ba.commodity,
+                role: ba.role.map(|role| role.as_str().to_string()),
+                amount: info.map(|info| info.amount.clone()),
+                value: info.and_then(|info| info.value.clone()),
+                ts: info.map(|info| format_api_ts(info.ts)),
+            }
+        })
+        .collect())
+}
+
+/// See `EntryInfo`.
+#[derive(Serialize, Deserialize)]
+pub struct ApiEntry {
+    pub id: i64,
+    pub bank_account: String,
+    pub bank_account_id: i64,
+    pub currency: String,
+    pub amount: String,
+    pub ts: String,
+    pub kind: String,
+    pub commodity: Option<String>,
+    pub value: Option<String>,
+    pub role: Option<String>,
+    pub note: Option<String>,
+}
+
+impl ApiEntry {
+    pub fn new(e: &EntryInfo) -> ApiEntry {
+        ApiEntry {
+            id: e.id,
+            bank_account: e.bank_account.clone(),
+            bank_account_id: e.bank_account_id,
+            currency: e.currency.clone(),
+            amount: e.amount.clone(),
+            ts: format_api_ts(e.ts),
+            kind: e.kind.as_str().to_string(),
+            commodity: e.commodity.clone(),
+            value: e.value.clone(),
+            role: e.role.map(|role| role.as_str().to_string()),
+            note: e.note.clone(),
+        }
+    }
+
+    pub fn into_entry_info(self) -> Result<EntryInfo, String> {
+        Ok(EntryInfo {
+            ts: parse_api_ts(&self.ts)?,
+            kind: BankAccountKind::parse(&self.kind)
+                .ok_or_else(|| format!("Invalid type: {}", self.kind))?,
+            role: self.role.as_deref().and_then(ShareRole::parse),
+            id: self.id,
+            bank_account: self.bank_account,
+            bank_account_id: self.bank_account_id,
+            currency: self.currency,
+            amount: self.amount,
+            commodity: self.commodity,
+            value: self.value,
+            note: self.note,
+        })
+    }
+}
+
+/// Entry to add, `ts` defaults to now.
+#[derive(Serialize, Deserialize)]
+pub struct ApiNewEntry {
+    pub bank_account: i64,
+    pub amount: String,
+    pub ts: Option<String>,
+    pub note: Option<String>,
+}
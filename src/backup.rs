@@ -0,0 +1,542 @@
+//! Full backup of user account as JSON archive, and entries as CSV.
+//!
+//! Unlike CSV export this has everything the user account owns: emails,
+//! bank accounts with their settings (also those without entries and
+//! deleted ones), all entries including deleted ones, prices and
+//! preferences.
+//! Sharing is not included, since it links to other user accounts.
+//!
+//! Timestamps are strings formatted as `YYYY-MM-DD HH:MM:SS.ffffff` and
+//! amounts are decimal strings, so nothing is lost in floats.
+
+use std::collections::BTreeMap;
+
+use crate::currency;
+use crate::model::BankAccountKind;
+use crate::model::DigestFrequency;
+use crate::model::EntryInfo;
+use crate::model::LandingPage;
+use crate::model::Preferences;
+use crate::model::Theme;
+use crate::model::MAX_BANK_ACCOUNT_NAME_LENGTH;
+use crate::model::MAX_COMMODITY_LENGTH;
+use crate::model::MAX_ENTRIES_PER_PAGE;
+use crate::model::MAX_GROUP_NAME_LENGTH;
+use crate::model::MAX_NOTE_LENGTH;
+use crate::util::csv_field;
+
+/// Format version, increased on incompatible changes.
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    /// When the backup was made.
+    pub exported: String,
+    pub emails: Vec<String>,
+    pub bank_accounts: Vec<BackupBankAccount>,
+    pub prices: Vec<BackupPrice>,
+    /// Missing in backups made before preferences were included.
+    #[serde(default)]
+    pub preferences: Option<BackupPreferences>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupBankAccount {
+    pub name: String,
+    pub currency: String,
+    pub group_name: Option<String>,
+    /// See `BankAccountKind::as_str`.
+    pub kind: String,
+    pub commodity: Option<String>,
+    pub deleted: bool,
+    pub created: String,
+    pub modified: String,
+    pub entries: Vec<BackupEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub ts: String,
+    pub amount: String,
+    pub deleted: bool,
+    pub created: String,
+    pub modified: String,
+    /// Missing in backups made before entries had notes.
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupPrice {
+    pub commodity: String,
+    pub currency: String,
+    pub ts: String,
+    pub price: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupPreferences {
+    /// See `Theme::as_str`.
+    pub theme: String,
+    /// See `LandingPage::as_str`.
+    pub landing_page: String,
+    /// Index in `Backup::bank_accounts`, since ids change on restore.
+    pub default_bank_account: Option<usize>,
+    pub entries_per_page: i64,
+    /// See `DigestFrequency::as_str`.
+    pub digest: String,
+}
+
+impl BackupPreferences {
+    /// `bank_account_ids` are ids of `Backup::bank_accounts`, in order.
+    pub fn new(preferences: &Preferences, bank_account_ids: &[i64]) -> BackupPreferences {
+        BackupPreferences {
+            theme: preferences.theme.as_str().to_string(),
+            landing_page: preferences.landing_page.as_str().to_string(),
+            default_bank_account: preferences
+                .default_bank_account
+                .and_then(|id| bank_account_ids.iter().position(|i| *i == id)),
+            entries_per_page: preferences.entries_per_page,
+            digest: preferences.digest.as_str().to_string(),
+        }
+    }
+
+    /// Preferences with ids of restored bank accounts, in order of
+    /// `Backup::bank_accounts`. Anything `parse` rejects is left default.
+    pub fn to_preferences(&self, bank_account_ids: &[i64]) -> Preferences {
+        let defaults = Preferences::default();
+        Preferences {
+            theme: Theme::parse(&self.theme).unwrap_or(defaults.theme),
+            landing_page: LandingPage::parse(&self.landing_page).unwrap_or(defaults.landing_page),
+            default_bank_account: self
+                .default_bank_account
+                .and_then(|i| bank_account_ids.get(i).copied()),
+            entries_per_page: self.entries_per_page,
+            digest: DigestFrequency::parse(&self.digest).unwrap_or(defaults.digest),
+        }
+    }
+}
+
+/// What restore did, or would do in dry run.
+/// Things already in the user account are matched and not added again, so
+/// restoring the same backup twice adds nothing the second time.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub emails_existing: u64,
+    /// Emails that aren't in the user account. Restore never adds emails,
+    /// user has to add them, so they are confirmed.
+    pub emails_skipped: u64,
+    pub bank_accounts_added: u64,
+    pub bank_accounts_existing: u64,
+    pub entries_added: u64,
+    pub entries_existing: u64,
+    pub prices_added: u64,
+    pub prices_existing: u64,
+    /// Preferences replace the current ones, if the backup has them.
+    pub preferences_restored: bool,
+}
+
+fn check_ts(what: &str, ts: &str) -> Result<(), String> {
+    chrono::NaiveDateTime::parse_from_str(ts, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|_| ())
+        .map_err(|_| format!("Invalid {} timestamp: {}", what, ts))
+}
+
+fn check_amount(what: &str, amount: &str) -> Result<(), String> {
+    amount
+        .parse::<f64>()
+        .map(|_| ())
+        .map_err(|_| format!("Invalid {}: {}", what, amount))
+}
+
+/// Check bank account fields against column sizes and known currencies.
+fn check_bank_account_fields(
+    name: &str,
+    currency: &str,
+    group_name: Option<&str>,
+    commodity: Option<&str>,
+) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Name is required".to_string());
+    }
+    if name.chars().count() > MAX_BANK_ACCOUNT_NAME_LENGTH {
+        return Err(format!(
+            "Name is longer than {} characters",
+            MAX_BANK_ACCOUNT_NAME_LENGTH
+        ));
+    }
+    if currency::find(currency).is_none() {
+        return Err(format!("Unknown currency {}", currency));
+    }
+    if group_name.map_or(0, |g| g.chars().count()) > MAX_GROUP_NAME_LENGTH {
+        return Err(format!(
+            "Group is longer than {} characters",
+            MAX_GROUP_NAME_LENGTH
+        ));
+    }
+    if commodity.map_or(0, |c| c.chars().count()) > MAX_COMMODITY_LENGTH {
+        return Err(format!(
+            "Commodity is longer than {} characters",
+            MAX_COMMODITY_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+fn check_bank_account(ba: &BackupBankAccount) -> Result<(), String> {
+    check_bank_account_fields(
+        &ba.name,
+        &ba.currency,
+        ba.group_name.as_deref(),
+        ba.commodity.as_deref(),
+    )?;
+    if BankAccountKind::parse(&ba.kind).is_none() {
+        return Err(format!("Invalid bank account type: {}", ba.kind));
+    }
+    check_ts("bank account", &ba.created)?;
+    check_ts("bank account", &ba.modified)?;
+    for (i, e) in ba.entries.iter().enumerate() {
+        check_entry(e).map_err(|e| format!("Entry {}: {}", i + 1, e))?;
+    }
+    Ok(())
+}
+
+fn check_entry(e: &BackupEntry) -> Result<(), String> {
+    check_ts("entry", &e.ts)?;
+    check_ts("entry", &e.created)?;
+    check_ts("entry", &e.modified)?;
+    check_amount("amount", &e.amount)?;
+    if e.note.as_ref().map_or(0, |n| n.chars().count()) > MAX_NOTE_LENGTH {
+        return Err(format!("Entry note longer than {}", MAX_NOTE_LENGTH));
+    }
+    Ok(())
+}
+
+fn check_price(p: &BackupPrice) -> Result<(), String> {
+    if p.commodity.is_empty() {
+        return Err("Commodity is required".to_string());
+    }
+    if p.commodity.chars().count() > MAX_COMMODITY_LENGTH {
+        return Err(format!(
+            "Commodity is longer than {} characters",
+            MAX_COMMODITY_LENGTH
+        ));
+    }
+    if currency::find(&p.currency).is_none() {
+        return Err(format!("Unknown currency {}", p.currency));
+    }
+    check_ts("price", &p.ts)?;
+    check_amount("price", &p.price)
+}
+
+/// Parse backup and check it before anything is written to database.
+/// Errors tell which bank account, entry or price is invalid, counting
+/// from 1 in the order of the backup.
+pub fn parse(json: &str) -> Result<Backup, String> {
+    let version = serde_json::from_str::<serde_json::Value>(json)
+        .map_err(|e| format!("Invalid JSON: {}", e))?
+        .get("version")
+        .and_then(|v| v.as_u64());
+    if version != Some(VERSION as u64) {
+        return Err(format!(
+            "Unsupported backup version {}, expected {}",
+            version.map_or("none".to_string(), |v| v.to_string()),
+            VERSION
+        ));
+    }
+    let mut backup: Backup =
+        serde_json::from_str(json).map_err(|e| format!("Invalid backup: {}", e))?;
+    for (i, ba) in backup.bank_accounts.iter_mut().enumerate() {
+        ba.currency = ba.currency.to_uppercase();
+        check_bank_account(ba).map_err(|e| format!("Bank account {}: {}", i + 1, e))?;
+    }
+    for (i, p) in backup.prices.iter_mut().enumerate() {
+        p.currency = p.currency.to_uppercase();
+        check_price(p).map_err(|e| format!("Price {}: {}", i + 1, e))?;
+    }
+    if let Some(ref p) = backup.preferences {
+        if Theme::parse(&p.theme).is_none() {
+            return Err(format!("Invalid theme: {}", p.theme));
+        }
+        if LandingPage::parse(&p.landing_page).is_none() {
+            return Err(format!("Invalid landing page: {}", p.landing_page));
+        }
+        if p.default_bank_account
+            .is_some_and(|i| i >= backup.bank_accounts.len())
+        {
+            return Err("Invalid default bank account".to_string());
+        }
+        if !(1..=MAX_ENTRIES_PER_PAGE).contains(&p.entries_per_page) {
+            return Err(format!(
+                "Entries per page must be from 1 to {}",
+                MAX_ENTRIES_PER_PAGE
+            ));
+        }
+        if DigestFrequency::parse(&p.digest).is_none() {
+            return Err(format!("Invalid digest frequency: {}", p.digest));
+        }
+    }
+    Ok(backup)
+}
+
+/// Header of entries CSV.
+const CSV_HEADER: &str = "ts,account,amount,currency,type,commodity,note";
+
+/// Header of CSV exported before bank accounts had types, it can still be
+/// imported: bank accounts are assets without commodity, entries have no
+/// notes.
+const OLD_CSV_HEADER: &str = "ts,account,amount,currency";
+
+/// Split CSV line into fields, fields may be quoted by `util::csv_field`.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Format entries as CSV, as in export.
+pub fn entries_to_csv(entries: &[EntryInfo]) -> String {
+    let mut csv: String = String::new();
+    csv.push_str(CSV_HEADER);
+    csv.push('\n');
+    for e in entries {
+        let ts_str = e.ts.format("%Y-%m-%d %H:%M:%S%.f");
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            ts_str,
+            csv_field(&e.bank_account),
+            e.amount,
+            e.currency,
+            e.kind.as_str(),
+            csv_field(e.commodity.as_deref().unwrap_or("")),
+            csv_field(e.note.as_deref().unwrap_or(""))
+        ));
+    }
+    csv
+}
+
+/// Parse entries CSV made by `entries_to_csv`, or by older versions, into
+/// backup, so it can be imported by restore. Bank accounts are made of
+/// account name and currency, type and commodity are taken from their
+/// first entry.
+pub fn from_csv(csv: &str) -> Result<Backup, String> {
+    let now = chrono::Utc::now()
+        .format("%Y-%m-%d %H:%M:%S%.6f")
+        .to_string();
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    let field_count = match lines.next() {
+        Some((_, header)) if header.trim() == CSV_HEADER => 7,
+        Some((_, header)) if header.trim() == OLD_CSV_HEADER => 4,
+        _ => return Err(format!("First line must be: {}", CSV_HEADER)),
+    };
+    let mut bank_accounts: BTreeMap<(String, String), BackupBankAccount> = BTreeMap::new();
+    for (i, line) in lines {
+        let fields = split_csv_line(line);
+        let fields: Vec<&str> = fields.iter().map(|f| f.trim()).collect();
+        if fields.len() != field_count {
+            return Err(format!("Line {}: expected {} fields", i + 1, field_count));
+        }
+        let (ts, name, amount) = (fields[0], fields[1], fields[2]);
+        let currency = fields[3].to_uppercase();
+        let kind = fields
+            .get(4)
+            .copied()
+            .unwrap_or(BankAccountKind::Asset.as_str());
+        let commodity = fields.get(5).copied().unwrap_or("");
+        let note = fields.get(6).copied().filter(|n| !n.is_empty());
+        if note.map_or(0, |n| n.chars().count()) > MAX_NOTE_LENGTH {
+            return Err(format!(
+                "Line {}: Note longer than {}",
+                i + 1,
+                MAX_NOTE_LENGTH
+            ));
+        }
+        check_ts("entry", ts).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        check_amount("amount", amount).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        if BankAccountKind::parse(kind).is_none() {
+            return Err(format!("Line {}: Invalid type: {}", i + 1, kind));
+        }
+        if name.is_empty() || currency.is_empty() {
+            return Err(format!("Line {}: Account and currency are required", i + 1));
+        }
+        check_bank_account_fields(name, &currency, None, Some(commodity))
+            .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        bank_accounts
+            .entry((name.to_string(), currency.clone()))
+            .or_insert_with(|| BackupBankAccount {
+                name: name.to_string(),
+                currency,
+                group_name: None,
+                kind: kind.to_string(),
+                commodity: Some(commodity.to_string()).filter(|c| !c.is_empty()),
+                deleted: false,
+                created: now.clone(),
+                modified: now.clone(),
+                entries: Vec::new(),
+            })
+            .entries
+            .push(BackupEntry {
+                ts: ts.to_string(),
+                amount: amount.to_string(),
+                deleted: false,
+                created: now.clone(),
+                modified: now.clone(),
+                note: note.map(str::to_string),
+            });
+    }
+    Ok(Backup {
+        version: VERSION,
+        exported: now,
+        emails: Vec::new(),
+        bank_accounts: bank_accounts.into_values().collect(),
+        prices: Vec::new(),
+        preferences: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let backup = from_csv(
+            "ts,account,amount,currency,type,commodity,note
+            2024-01-02 00:00:00,\"Cash, wallet\",10.5,PLN,asset,,\"Lunch, \"\"Bar\"\"\"
+            2024-01-03 00:00:00,ETF,3,EUR,liability,VWCE,",
+        )
+        .unwrap();
+        assert_eq!(backup.bank_accounts.len(), 2);
+        let cash = &backup.bank_accounts[0];
+        assert_eq!(
+            (cash.name.as_str(), cash.currency.as_str()),
+            ("Cash, wallet", "PLN")
+        );
+        assert_eq!(cash.entries[0].note.as_deref(), Some("Lunch, \"Bar\""));
+        let etf = &backup.bank_accounts[1];
+        assert_eq!(
+            (etf.kind.as_str(), etf.commodity.as_deref()),
+            ("liability", Some("VWCE"))
+        );
+        assert_eq!(etf.entries[0].note, None);
+        assert!(from_csv("ts,account,amount\n").is_err());
+        assert!(from_csv("ts,account,amount,currency,type,commodity\n").is_err());
+    }
+
+    #[test]
+    fn invalid_csv() {
+        let header = "ts,account,amount,currency,type,commodity,note\n";
+        let long = "X".repeat(33);
+        let line = format!("2024-01-02 00:00:00,{},1,PLN,asset,,", long);
+        assert_eq!(
+            from_csv(&format!("{}{}", header, line)).err(),
+            Some("Line 2: Name is longer than 32 characters".to_string())
+        );
+        let line = "2024-01-02 00:00:00,Cash,1,XYZ,asset,,";
+        assert_eq!(
+            from_csv(&format!("{}{}", header, line)).err(),
+            Some("Line 2: Unknown currency XYZ".to_string())
+        );
+        let line = format!("2024-01-02 00:00:00,Cash,1,PLN,asset,{},", long);
+        assert!(from_csv(&format!("{}{}", header, line)).is_err());
+        let backup = from_csv(&format!("{}2024-01-02 00:00:00,Cash,1,pln,asset,,", header));
+        assert_eq!(backup.unwrap().bank_accounts[0].currency, "PLN");
+    }
+
+    fn backup_json(bank_account: &str, price: &str) -> String {
+        format!(
+            r#"{{"version": 1, "exported": "2024-01-02 00:00:00", "emails": [],
+            "bank_accounts": [
+                {{"name": "Cash", "currency": "PLN", "group_name": null, "kind": "asset",
+                "commodity": null, "deleted": false, "created": "2024-01-02 00:00:00",
+                "modified": "2024-01-02 00:00:00", "entries": []}},
+                {}
+            ],
+            "prices": [{}]}}"#,
+            bank_account, price
+        )
+    }
+
+    #[test]
+    fn json() {
+        let bank_account = r#"{"name": "ETF", "currency": "eur", "group_name": "Savings",
+            "kind": "asset", "commodity": "VWCE", "deleted": false,
+            "created": "2024-01-02 00:00:00", "modified": "2024-01-02 00:00:00",
+            "entries": [{"ts": "2024-01-02 00:00:00", "amount": "1.5", "deleted": false,
+            "created": "2024-01-02 00:00:00", "modified": "2024-01-02 00:00:00"}]}"#;
+        let price = r#"{"commodity": "VWCE", "currency": "EUR", "ts": "2024-01-02 00:00:00",
+            "price": "101.5"}"#;
+        let backup = parse(&backup_json(bank_account, price)).unwrap();
+        assert_eq!(backup.bank_accounts[1].currency, "EUR");
+        assert_eq!(backup.bank_accounts[1].entries[0].note, None);
+        for (invalid, error) in [
+            (
+                bank_account.replace("\"ETF\"", &format!("\"{}\"", "X".repeat(33))),
+                "Bank account 2: Name is longer than 32 characters",
+            ),
+            (
+                bank_account.replace("eur", "XYZ"),
+                "Bank account 2: Unknown currency XYZ",
+            ),
+            (
+                bank_account.replace("Savings", &"X".repeat(33)),
+                "Bank account 2: Group is longer than 32 characters",
+            ),
+            (
+                bank_account.replace("\"VWCE\"", &format!("\"{}\"", "X".repeat(33))),
+                "Bank account 2: Commodity is longer than 32 characters",
+            ),
+            (
+                bank_account.replace("1.5", "x"),
+                "Bank account 2: Entry 1: Invalid amount: x",
+            ),
+        ] {
+            assert_eq!(
+                parse(&backup_json(&invalid, price)).err().as_deref(),
+                Some(error)
+            );
+        }
+        assert_eq!(
+            parse(&backup_json(bank_account, &price.replace("EUR", "EURO")))
+                .err()
+                .as_deref(),
+            Some("Price 1: Unknown currency EURO")
+        );
+    }
+
+    #[test]
+    fn old_csv() {
+        let backup = from_csv(
+            "ts,account,amount,currency
+            2024-01-02 00:00:00.5,Cash,10.5,PLN
+            2024-01-03 00:00:00,Cash,-2,PLN",
+        )
+        .unwrap();
+        assert_eq!(backup.bank_accounts.len(), 1);
+        let cash = &backup.bank_accounts[0];
+        assert_eq!(cash.kind, "asset");
+        assert_eq!(cash.commodity, None);
+        assert_eq!(cash.entries.len(), 2);
+        assert_eq!(cash.entries[1].amount, "-2");
+        assert_eq!(cash.entries[1].note, None);
+        assert!(
+            from_csv("ts,account,amount,currency\n2024-01-02 00:00:00,Cash,1,PLN,asset").is_err()
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Command line client, e.g. to log balances from cron.
+//!
+//! Works either with the server's JSON API using personal token (created on
+//! profile page), or directly with the database configured in
+//! `cashlog.toml`, as user account given by email.
+
+extern crate cashlog;
+
+use std::io::Read;
+
+use cashlog::api;
+use cashlog::api::ApiBankAccount;
+use cashlog::api::ApiEntry;
+use cashlog::api::ApiNewEntry;
+use cashlog::backup;
+use cashlog::backup::Backup;
+use cashlog::backup::RestoreSummary;
+use cashlog::common;
+use cashlog::db;
+use cashlog::logging;
+use cashlog::model::EntryInfo;
+use cashlog::storage;
+use cashlog::storage::Storage;
+use cashlog::util;
+
+const USAGE: &str = "\
+Usage: cashlog-cli [options] <command>
+
+Commands:
+    add <account> <amount> [--ts TS] [--currency CUR] [--note NOTE]
+                            add balance entry, TS defaults to now
+    accounts                list bank accounts with balances
+    entries [--since DATE]  list entries, oldest first
+    export [--format csv|json]
+                            entries as CSV or full backup as JSON
+    import [--format csv|json] [--dry-run] <file>
+                            import CSV entries or JSON backup, - is stdin
+
+Options:
+    --server URL     use server API, or CASHLOG_SERVER env var
+    --token TOKEN    API token, or CASHLOG_TOKEN env var
+    --config FILE    use database from config, default cashlog.toml
+    --email EMAIL    user account in database mode
+";
+
+/// Server JSON API.
+struct Server {
+    url: String,
+    token: String,
+}
+
+impl Server {
+    fn get(&self, path: &str) -> Result<ureq::Response, String> {
+        ureq::get(&format!("{}{}", self.url, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call()
+            .map_err(http_error)
+    }
+
+    fn post(&self, path: &str) -> ureq::Request {
+        ureq::post(&format!("{}{}", self.url, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+    }
+}
+
+/// Where the data lives.
+enum Backend {
+    Db {
+        conn: Box<dyn Storage>,
+        account_id: i64,
+    },
+    Server(Server),
+}
+
+fn db_error(e: db::DbError) -> String {
+    e.to_string()
+}
+
+fn http_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, response) => {
+            let error = response
+                .into_json::<serde_json::Value>()
+                .ok()
+                .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(String::from));
+            format!("Server error {}: {}", code, error.unwrap_or_default())
+        }
+        e => format!("Request failed: {}", e),
+    }
+}
+
+impl Backend {
+    fn bank_accounts(&mut self) -> Result<Vec<ApiBankAccount>, String> {
+        match self {
+            Backend::Db { conn, account_id } => {
+                api::get_bank_accounts(&mut **conn, *account_id).map_err(db_error)
+            }
+            Backend::Server(server) => server
+                .get("/api/accounts")?
+                .into_json()
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn add_entry(&mut self, entry: &ApiNewEntry) -> Result<(), String> {
+        match self {
+            Backend::Db { conn, account_id } => {
+                let ts = match &entry.ts {
+                    Some(ts) => api::parse_api_ts(ts)?,
+                    None => chrono::Utc::now(),
+                };
+                let note = util::parse_note(entry.note.as_deref())?;
+                conn.insert_entry(
+                    account_id,
+                    &entry.bank_account,
+                    &ts,
+                    &entry.amount,
+                    note.as_deref(),
+                )
+                .map_err(db_error)
+            }
+            Backend::Server(server) => server
+                .post("/api/entries")
+                .send_json(serde_json::to_value(entry).unwrap())
+                .map(|_| ())
+                .map_err(http_error),
+        }
+    }
+
+    /// All entries, or since given ts.
+    fn entries(&mut self, since: Option<&str>) -> Result<Vec<EntryInfo>, String> {
+        match self {
+            Backend::Db { conn, account_id } => {
+                let since = since.map(util::parse_since).transpose()?;
+                Ok(conn
+                    .get_all_entries(*account_id)
+                    .map_err(db_error)?
+                    .into_iter()
+                    .filter(|e| since.is_none_or(|since| e.ts >= since))
+                    .collect())
+            }
+            Backend::Server(server) => {
+                let path = match since {
+                    Some(since) => format!("/api/entries?since={}", util::url_encode(since)),
+                    None => "/api/entries".to_string(),
+                };
+                let entries: Vec<ApiEntry> =
+                    server.get(&path)?.into_json().map_err(|e| e.to_string())?;
+                entries.into_iter().map(ApiEntry::into_entry_info).collect()
+            }
+        }
+    }
+
+    fn backup(&mut self) -> Result<Backup, String> {
+        match self {
+            Backend::Db { conn, account_id } => conn.get_backup(*account_id).map_err(db_error),
+            Backend::Server(server) => server
+                .get("/api/backup")?
+                .into_json()
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn restore(&mut self, backup: &Backup, dry_run: bool) -> Result<RestoreSummary, String> {
+        match self {
+            Backend::Db { conn, account_id } => conn
+                .restore_backup(*account_id, backup, dry_run)
+                .map_err(db_error),
+            Backend::Server(server) => server
+                .post(&format!("/api/restore?dry_run={}", dry_run))
+                .send_string(&serde_json::to_string(backup).unwrap())
+                .map_err(http_error)?
+                .into_json()
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Command line split into options and positional args.
+struct Args {
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+    positional: Vec<String>,
+}
+
+/// Options that are flags, all others take value.
+const FLAGS: &[&str] = &["--dry-run", "--help"];
+
+fn parse_args(args: Vec<String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        options: Vec::new(),
+        flags: Vec::new(),
+        positional: Vec::new(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if FLAGS.contains(&arg.as_str()) {
+            parsed.flags.push(arg);
+        } else if arg.starts_with("--") {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value of {}", arg))?;
+            parsed.options.push((arg, value));
+        } else {
+            parsed.positional.push(arg);
+        }
+    }
+    Ok(parsed)
+}
+
+impl Args {
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+}
+
+fn connect(args: &Args) -> Result<Backend, String> {
+    let server = args
+        .option("--server")
+        .map(String::from)
+        .or_else(|| std::env::var("CASHLOG_SERVER").ok());
+    if let Some(url) = server {
+        let token = args
+            .option("--token")
+            .map(String::from)
+            .or_else(|| std::env::var("CASHLOG_TOKEN").ok())
+            .ok_or("API token is required with --server")?;
+        return Ok(Backend::Server(Server {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+        }));
+    }
+    let email = args
+        .option("--email")
+        .ok_or("Either --server or --email is required")?;
+    let conf = common::load_config_or_exit(args.option("--config").unwrap_or("cashlog.toml"));
+    let mut conn = storage::open_pool(&conf)
+        .and_then(|pool| pool.get())
+        .map_err(db_error)?;
+    let account_id = conn
+        .get_acc_id_by_email(email)
+        .map_err(db_error)?
+        .ok_or_else(|| format!("No user account with email {}", email))?;
+    Ok(Backend::Db { conn, account_id })
+}
+
+fn cmd_add(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let (name, amount) = match &args.positional[1..] {
+        [name, amount] => (name, amount),
+        _ => return Err("Usage: add <account> <amount> [--ts TS]".to_string()),
+    };
+    amount
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid amount: {}", amount))?;
+    let ts = args
+        .option("--ts")
+        .map(|ts| util::parse_ts(ts).map(|ts| ts.format("%Y-%m-%d %H:%M:%S").to_string()))
+        .transpose()?;
+    let currency = args.option("--currency");
+    let bank_accounts: Vec<ApiBankAccount> = backend
+        .bank_accounts()?
+        .into_iter()
+        .filter(|ba| &ba.name == name && currency.is_none_or(|c| ba.currency == c))
+        .collect();
+    let bank_account = match bank_accounts.as_slice() {
+        [bank_account] => bank_account,
+        [] => return Err(format!("No bank account {}", name)),
+        _ => return Err(format!("Several bank accounts {}, use --currency", name)),
+    };
+    if !bank_account.can_edit() {
+        return Err(format!("Bank account {} is shared read only", name));
+    }
+    backend.add_entry(&ApiNewEntry {
+        bank_account: bank_account.id,
+        amount: amount.to_string(),
+        ts,
+        note: args.option("--note").map(String::from),
+    })
+}
+
+fn cmd_accounts(backend: &mut Backend) -> Result<(), String> {
+    for ba in backend.bank_accounts()? {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            ba.name,
+            ba.commodity.as_ref().unwrap_or(&ba.currency),
+            ba.amount.as_deref().unwrap_or("-"),
+            ba.value
+                .as_ref()
+                .map(|v| format!("{} {}", v, ba.currency))
+                .unwrap_or_else(|| "-".to_string()),
+            ba.ts.as_deref().unwrap_or("-"),
+        );
+    }
+    Ok(())
+}
+
+fn cmd_entries(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    for e in backend.entries(args.option("--since"))? {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            util::format_ts(e.ts),
+            e.bank_account,
+            e.amount,
+            e.commodity.as_ref().unwrap_or(&e.currency),
+            e.note.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+fn cmd_export(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    match args.option("--format").unwrap_or("csv") {
+        "csv" => print!("{}", backup::entries_to_csv(&backend.entries(None)?)),
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&backend.backup()?).unwrap()
+        ),
+        format => return Err(format!("Unknown format: {}", format)),
+    }
+    Ok(())
+}
+
+fn cmd_import(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let filename = match &args.positional[1..] {
+        [filename] => filename,
+        _ => return Err("Usage: import [--format csv|json] [--dry-run] <file>".to_string()),
+    };
+    let mut source = String::new();
+    if filename == "-" {
+        std::io::stdin().read_to_string(&mut source)
+    } else {
+        std::fs::File::open(filename).and_then(|mut f| f.read_to_string(&mut source))
+    }
+    .map_err(|e| format!("Failed to read {}: {}", filename, e))?;
+    // Backup is JSON object, anything else is taken as CSV.
+    let format = args.option("--format").unwrap_or_else(|| {
+        if source.trim_start().starts_with('{') {
+            "json"
+        } else {
+            "csv"
+        }
+    });
+    let backup = match format {
+        "csv" => backup::from_csv(&source)?,
+        "json" => backup::parse(&source)?,
+        format => return Err(format!("Unknown format: {}", format)),
+    };
+    let dry_run = args.flag("--dry-run");
+    let s = backend.restore(&backup, dry_run)?;
+    if dry_run {
+        println!("Dry run, nothing was changed.");
+    }
+    println!(
+        "emails: {} existing, {} skipped",
+        s.emails_existing, s.emails_skipped
+    );
+    println!(
+        "bank accounts: {} added, {} existing",
+        s.bank_accounts_added, s.bank_accounts_existing
+    );
+    println!(
+        "entries: {} added, {} existing",
+        s.entries_added, s.entries_existing
+    );
+    println!(
+        "prices: {} added, {} existing",
+        s.prices_added, s.prices_existing
+    );
+    if s.preferences_restored {
+        println!("preferences: replaced");
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let command = match args.positional.first() {
+        Some(command) if !args.flag("--help") => command.as_str(),
+        _ => {
+            print!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let mut backend = connect(&args)?;
+    match command {
+        "add" => cmd_add(&mut backend, &args),
+        "accounts" => cmd_accounts(&mut backend),
+        "entries" => cmd_entries(&mut backend, &args),
+        "export" => cmd_export(&mut backend, &args),
+        "import" => cmd_import(&mut backend, &args),
+        _ => Err(format!("Unknown command: {}\n\n{}", command, USAGE)),
+    }
+}
+
+fn main() {
+    logging::env_logger_init_with_level(log::LevelFilter::Warn);
+    let result = parse_args(std::env::args().skip(1).collect()).and_then(run);
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
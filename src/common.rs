@@ -2,11 +2,14 @@
 //! This code can use other parts of CashLog.
 
 use std;
+use std::io::Read;
 
 use r2d2;
 use r2d2_postgres;
 use url;
 
+use crate::model;
+
 pub type DatabasePool =
     r2d2::Pool<r2d2_postgres::PostgresConnectionManager<r2d2_postgres::postgres::NoTls>>;
 
@@ -47,6 +50,77 @@ impl From<String> for Error {
     }
 }
 
+/// Load config or exit.
+pub fn load_config_or_exit(config_filename: &str) -> model::Config {
+    let mut toml_source = String::new();
+    {
+        let mut f = match std::fs::File::open(config_filename) {
+            Ok(f) => f,
+            Err(e) => {
+                error!("Failed to open config file {}: {}.", config_filename, e);
+                debug!(
+                    "Current directory: {}.",
+                    std::env::current_dir()
+                        .map(|d| d.to_str().unwrap_or("<unknown>").to_string())
+                        .unwrap_or("<unknown>".to_string())
+                );
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = f.read_to_string(&mut toml_source) {
+            error!("Failed to read config file: {}.", e);
+            std::process::exit(1);
+        }
+    }
+    // This inline struct is only so that I can have top level "config" key
+    // in toml config file, without having to go through Value object.
+    #[derive(Debug, Deserialize)]
+    struct ConfigWrapper {
+        config: model::Config,
+    }
+    match toml::from_str::<ConfigWrapper>(&toml_source) {
+        Err(decode_error) => {
+            error!("Decode error while parsing config: {}.", decode_error);
+            std::process::exit(1);
+        }
+        Ok(conf) => conf.config,
+    }
+}
+
+/// Sizing and timeouts of database connection pool, set by `db_pool_size`
+/// and `db_*_timeout` in config.
+#[derive(Clone, Debug)]
+pub struct PoolSettings {
+    pub max_size: u32,
+    /// How long to wait for a free connection before giving up.
+    pub connection_timeout: std::time::Duration,
+    pub max_lifetime: Option<std::time::Duration>,
+    pub idle_timeout: Option<std::time::Duration>,
+}
+
+impl PoolSettings {
+    /// Settings from config, `default_max_size` is used when the pool size
+    /// isn't set.
+    pub fn from_config(conf: &model::Config, default_max_size: u32) -> PoolSettings {
+        let secs = std::time::Duration::from_secs;
+        PoolSettings {
+            max_size: conf.db_pool_size.unwrap_or(default_max_size),
+            connection_timeout: secs(conf.db_connection_timeout.unwrap_or(30)),
+            max_lifetime: Some(secs(conf.db_max_lifetime.unwrap_or(60))),
+            idle_timeout: Some(secs(conf.db_idle_timeout.unwrap_or(10))),
+        }
+    }
+
+    pub fn builder<M: r2d2::ManageConnection>(&self) -> r2d2::Builder<M> {
+        r2d2::Pool::builder()
+            .max_size(self.max_size)
+            .min_idle(Some(0))
+            .connection_timeout(self.connection_timeout)
+            .max_lifetime(self.max_lifetime)
+            .idle_timeout(self.idle_timeout)
+    }
+}
+
 /// Create database pool, die if can't create.
 pub fn create_database_pool(
     host: &str,
@@ -54,6 +128,7 @@ pub fn create_database_pool(
     database_name: &str,
     username: &str,
     password: &str,
+    settings: &PoolSettings,
 ) -> DatabasePool {
     let config: postgres::config::Config = {
         let mut config = r2d2_postgres::postgres::Config::new();
@@ -66,16 +141,18 @@ pub fn create_database_pool(
     };
     let manager =
         r2d2_postgres::PostgresConnectionManager::new(config, r2d2_postgres::postgres::NoTls);
-    let pool = r2d2::Pool::builder()
-        .max_size(100)
-        .min_idle(Some(0))
-        .max_lifetime(Some(std::time::Duration::from_secs(60)))
-        .idle_timeout(Some(std::time::Duration::from_secs(10)))
+    let pool = settings
+        .builder()
         .build(manager)
         .expect("Failed to create R2D2 Pool");
     pool
 }
 
+/// Link that logs in with given login token.
+pub fn login_url(base_url: &str, key: &str) -> String {
+    format!("{}/new-session/{}", base_url, key)
+}
+
 /// Send the login email.
 pub fn send_email_login_email(
     base_url: &str,
@@ -83,17 +160,63 @@ pub fn send_email_login_email(
     key: &str,
     use_email: bool,
 ) -> Result<(), Error> {
-    use lettre::Transport;
-    let url = format!("{}/new-session/{}", base_url, key);
+    let url = login_url(base_url, key);
     let body = format!("Click this link to login to CashLog: {}", url);
+    send_email(email, "CashLog Email Login Link", &body, use_email)
+}
+
+/// Send the email that confirms account deletion.
+pub fn send_delete_account_email(
+    base_url: &str,
+    email: &str,
+    key: &str,
+    use_email: bool,
+) -> Result<(), Error> {
+    let url = format!("{}/delete-account/{}", base_url, key);
+    let body = format!(
+        "Someone asked to delete your CashLog account with all its data.\n\
+        If it was you, click this link to confirm: {}\n\
+        Otherwise just ignore this email.",
+        url
+    );
+    send_email(email, "CashLog Account Deletion", &body, use_email)
+}
+
+/// Send email, or only log it when email is turned off in config.
+pub fn send_email(email: &str, subject: &str, body: &str, use_email: bool) -> Result<(), Error> {
+    send(email, subject, body, None, use_email)
+}
+
+/// Send email with text and HTML alternatives, like `send_email`.
+pub fn send_html_email(
+    email: &str,
+    subject: &str,
+    text: &str,
+    html: &str,
+    use_email: bool,
+) -> Result<(), Error> {
+    send(email, subject, text, Some(html), use_email)
+}
+
+fn send(
+    email: &str,
+    subject: &str,
+    body: &str,
+    html: Option<&str>,
+    use_email: bool,
+) -> Result<(), Error> {
+    use lettre::Transport;
     if use_email {
-        let m = lettre_email::EmailBuilder::new()
+        let builder = lettre_email::EmailBuilder::new()
             .to(email)
             .from("cashlog@hell.cx")
-            .subject("CashLog Email Login Link")
-            .text(&body)
-            .build()
-            .unwrap();
+            .subject(subject);
+        let m = match html {
+            Some(html) => builder.alternative(html, body),
+            None => builder.text(body),
+        }
+        .build()
+        .unwrap();
         let smtp_client =
             lettre::smtp::SmtpClient::new_unencrypted_localhost().expect("Error creating client");
         let mut mailer = lettre::smtp::SmtpTransport::new(smtp_client);
@@ -0,0 +1,995 @@
+//! ISO 4217 currency registry.
+//!
+//! Active currencies that bank accounts can be created in. Existing bank
+//! accounts with codes outside the list, e.g. withdrawn ones, keep working,
+//! the list only restricts new ones.
+
+/// Currency as listed in ISO 4217.
+pub struct Currency {
+    /// Three letter code, e.g. "PLN".
+    pub code: &'static str,
+    pub name: &'static str,
+    /// Number of digits after the decimal point, e.g. 2 for cents.
+    pub minor_units: u8,
+    /// Local symbol, may be shared by several currencies, e.g. "$".
+    pub symbol: &'static str,
+}
+
+impl Currency {
+    /// Label for pickers, e.g. "PLN - Zloty (zł)".
+    pub fn label(&self) -> String {
+        format!("{} - {} ({})", self.code, self.name, self.symbol)
+    }
+}
+
+/// Currencies sorted by code.
+pub const CURRENCIES: &[Currency] = &[
+    Currency {
+        code: "AED",
+        name: "UAE Dirham",
+        minor_units: 2,
+        symbol: "د.إ",
+    },
+    Currency {
+        code: "AFN",
+        name: "Afghani",
+        minor_units: 2,
+        symbol: "؋",
+    },
+    Currency {
+        code: "ALL",
+        name: "Lek",
+        minor_units: 2,
+        symbol: "L",
+    },
+    Currency {
+        code: "AMD",
+        name: "Armenian Dram",
+        minor_units: 2,
+        symbol: "֏",
+    },
+    Currency {
+        code: "AOA",
+        name: "Kwanza",
+        minor_units: 2,
+        symbol: "Kz",
+    },
+    Currency {
+        code: "ARS",
+        name: "Argentine Peso",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "AUD",
+        name: "Australian Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "AWG",
+        name: "Aruban Florin",
+        minor_units: 2,
+        symbol: "ƒ",
+    },
+    Currency {
+        code: "AZN",
+        name: "Azerbaijan Manat",
+        minor_units: 2,
+        symbol: "₼",
+    },
+    Currency {
+        code: "BAM",
+        name: "Convertible Mark",
+        minor_units: 2,
+        symbol: "KM",
+    },
+    Currency {
+        code: "BBD",
+        name: "Barbados Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "BDT",
+        name: "Taka",
+        minor_units: 2,
+        symbol: "৳",
+    },
+    Currency {
+        code: "BGN",
+        name: "Bulgarian Lev",
+        minor_units: 2,
+        symbol: "лв",
+    },
+    Currency {
+        code: "BHD",
+        name: "Bahraini Dinar",
+        minor_units: 3,
+        symbol: ".د.ب",
+    },
+    Currency {
+        code: "BIF",
+        name: "Burundi Franc",
+        minor_units: 0,
+        symbol: "FBu",
+    },
+    Currency {
+        code: "BMD",
+        name: "Bermudian Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "BND",
+        name: "Brunei Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "BOB",
+        name: "Boliviano",
+        minor_units: 2,
+        symbol: "Bs",
+    },
+    Currency {
+        code: "BRL",
+        name: "Brazilian Real",
+        minor_units: 2,
+        symbol: "R$",
+    },
+    Currency {
+        code: "BSD",
+        name: "Bahamian Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "BTN",
+        name: "Ngultrum",
+        minor_units: 2,
+        symbol: "Nu.",
+    },
+    Currency {
+        code: "BWP",
+        name: "Pula",
+        minor_units: 2,
+        symbol: "P",
+    },
+    Currency {
+        code: "BYN",
+        name: "Belarusian Ruble",
+        minor_units: 2,
+        symbol: "Br",
+    },
+    Currency {
+        code: "BZD",
+        name: "Belize Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "CAD",
+        name: "Canadian Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "CDF",
+        name: "Congolese Franc",
+        minor_units: 2,
+        symbol: "FC",
+    },
+    Currency {
+        code: "CHF",
+        name: "Swiss Franc",
+        minor_units: 2,
+        symbol: "CHF",
+    },
+    Currency {
+        code: "CLP",
+        name: "Chilean Peso",
+        minor_units: 0,
+        symbol: "$",
+    },
+    Currency {
+        code: "CNY",
+        name: "Yuan Renminbi",
+        minor_units: 2,
+        symbol: "¥",
+    },
+    Currency {
+        code: "COP",
+        name: "Colombian Peso",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "CRC",
+        name: "Costa Rican Colon",
+        minor_units: 2,
+        symbol: "₡",
+    },
+    Currency {
+        code: "CUP",
+        name: "Cuban Peso",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "CVE",
+        name: "Cabo Verde Escudo",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "CZK",
+        name: "Czech Koruna",
+        minor_units: 2,
+        symbol: "Kč",
+    },
+    Currency {
+        code: "DJF",
+        name: "Djibouti Franc",
+        minor_units: 0,
+        symbol: "Fdj",
+    },
+    Currency {
+        code: "DKK",
+        name: "Danish Krone",
+        minor_units: 2,
+        symbol: "kr",
+    },
+    Currency {
+        code: "DOP",
+        name: "Dominican Peso",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "DZD",
+        name: "Algerian Dinar",
+        minor_units: 2,
+        symbol: "د.ج",
+    },
+    Currency {
+        code: "EGP",
+        name: "Egyptian Pound",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "ERN",
+        name: "Nakfa",
+        minor_units: 2,
+        symbol: "Nfk",
+    },
+    Currency {
+        code: "ETB",
+        name: "Ethiopian Birr",
+        minor_units: 2,
+        symbol: "Br",
+    },
+    Currency {
+        code: "EUR",
+        name: "Euro",
+        minor_units: 2,
+        symbol: "€",
+    },
+    Currency {
+        code: "FJD",
+        name: "Fiji Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "FKP",
+        name: "Falkland Islands Pound",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "GBP",
+        name: "Pound Sterling",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "GEL",
+        name: "Lari",
+        minor_units: 2,
+        symbol: "₾",
+    },
+    Currency {
+        code: "GHS",
+        name: "Ghana Cedi",
+        minor_units: 2,
+        symbol: "₵",
+    },
+    Currency {
+        code: "GIP",
+        name: "Gibraltar Pound",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "GMD",
+        name: "Dalasi",
+        minor_units: 2,
+        symbol: "D",
+    },
+    Currency {
+        code: "GNF",
+        name: "Guinean Franc",
+        minor_units: 0,
+        symbol: "FG",
+    },
+    Currency {
+        code: "GTQ",
+        name: "Quetzal",
+        minor_units: 2,
+        symbol: "Q",
+    },
+    Currency {
+        code: "GYD",
+        name: "Guyana Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "HKD",
+        name: "Hong Kong Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "HNL",
+        name: "Lempira",
+        minor_units: 2,
+        symbol: "L",
+    },
+    Currency {
+        code: "HTG",
+        name: "Gourde",
+        minor_units: 2,
+        symbol: "G",
+    },
+    Currency {
+        code: "HUF",
+        name: "Forint",
+        minor_units: 2,
+        symbol: "Ft",
+    },
+    Currency {
+        code: "IDR",
+        name: "Rupiah",
+        minor_units: 2,
+        symbol: "Rp",
+    },
+    Currency {
+        code: "ILS",
+        name: "New Israeli Sheqel",
+        minor_units: 2,
+        symbol: "₪",
+    },
+    Currency {
+        code: "INR",
+        name: "Indian Rupee",
+        minor_units: 2,
+        symbol: "₹",
+    },
+    Currency {
+        code: "IQD",
+        name: "Iraqi Dinar",
+        minor_units: 3,
+        symbol: "ع.د",
+    },
+    Currency {
+        code: "IRR",
+        name: "Iranian Rial",
+        minor_units: 2,
+        symbol: "﷼",
+    },
+    Currency {
+        code: "ISK",
+        name: "Iceland Krona",
+        minor_units: 0,
+        symbol: "kr",
+    },
+    Currency {
+        code: "JMD",
+        name: "Jamaican Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "JOD",
+        name: "Jordanian Dinar",
+        minor_units: 3,
+        symbol: "د.ا",
+    },
+    Currency {
+        code: "JPY",
+        name: "Yen",
+        minor_units: 0,
+        symbol: "¥",
+    },
+    Currency {
+        code: "KES",
+        name: "Kenyan Shilling",
+        minor_units: 2,
+        symbol: "KSh",
+    },
+    Currency {
+        code: "KGS",
+        name: "Som",
+        minor_units: 2,
+        symbol: "сом",
+    },
+    Currency {
+        code: "KHR",
+        name: "Riel",
+        minor_units: 2,
+        symbol: "៛",
+    },
+    Currency {
+        code: "KMF",
+        name: "Comorian Franc",
+        minor_units: 0,
+        symbol: "CF",
+    },
+    Currency {
+        code: "KPW",
+        name: "North Korean Won",
+        minor_units: 2,
+        symbol: "₩",
+    },
+    Currency {
+        code: "KRW",
+        name: "Won",
+        minor_units: 0,
+        symbol: "₩",
+    },
+    Currency {
+        code: "KWD",
+        name: "Kuwaiti Dinar",
+        minor_units: 3,
+        symbol: "د.ك",
+    },
+    Currency {
+        code: "KYD",
+        name: "Cayman Islands Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "KZT",
+        name: "Tenge",
+        minor_units: 2,
+        symbol: "₸",
+    },
+    Currency {
+        code: "LAK",
+        name: "Lao Kip",
+        minor_units: 2,
+        symbol: "₭",
+    },
+    Currency {
+        code: "LBP",
+        name: "Lebanese Pound",
+        minor_units: 2,
+        symbol: "ل.ل",
+    },
+    Currency {
+        code: "LKR",
+        name: "Sri Lanka Rupee",
+        minor_units: 2,
+        symbol: "Rs",
+    },
+    Currency {
+        code: "LRD",
+        name: "Liberian Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "LSL",
+        name: "Loti",
+        minor_units: 2,
+        symbol: "L",
+    },
+    Currency {
+        code: "LYD",
+        name: "Libyan Dinar",
+        minor_units: 3,
+        symbol: "ل.د",
+    },
+    Currency {
+        code: "MAD",
+        name: "Moroccan Dirham",
+        minor_units: 2,
+        symbol: "د.م.",
+    },
+    Currency {
+        code: "MDL",
+        name: "Moldovan Leu",
+        minor_units: 2,
+        symbol: "L",
+    },
+    Currency {
+        code: "MGA",
+        name: "Malagasy Ariary",
+        minor_units: 2,
+        symbol: "Ar",
+    },
+    Currency {
+        code: "MKD",
+        name: "Denar",
+        minor_units: 2,
+        symbol: "ден",
+    },
+    Currency {
+        code: "MMK",
+        name: "Kyat",
+        minor_units: 2,
+        symbol: "K",
+    },
+    Currency {
+        code: "MNT",
+        name: "Tugrik",
+        minor_units: 2,
+        symbol: "₮",
+    },
+    Currency {
+        code: "MOP",
+        name: "Pataca",
+        minor_units: 2,
+        symbol: "MOP$",
+    },
+    Currency {
+        code: "MRU",
+        name: "Ouguiya",
+        minor_units: 2,
+        symbol: "UM",
+    },
+    Currency {
+        code: "MUR",
+        name: "Mauritius Rupee",
+        minor_units: 2,
+        symbol: "Rs",
+    },
+    Currency {
+        code: "MVR",
+        name: "Rufiyaa",
+        minor_units: 2,
+        symbol: "Rf",
+    },
+    Currency {
+        code: "MWK",
+        name: "Malawi Kwacha",
+        minor_units: 2,
+        symbol: "MK",
+    },
+    Currency {
+        code: "MXN",
+        name: "Mexican Peso",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "MYR",
+        name: "Malaysian Ringgit",
+        minor_units: 2,
+        symbol: "RM",
+    },
+    Currency {
+        code: "MZN",
+        name: "Mozambique Metical",
+        minor_units: 2,
+        symbol: "MT",
+    },
+    Currency {
+        code: "NAD",
+        name: "Namibia Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "NGN",
+        name: "Naira",
+        minor_units: 2,
+        symbol: "₦",
+    },
+    Currency {
+        code: "NIO",
+        name: "Cordoba Oro",
+        minor_units: 2,
+        symbol: "C$",
+    },
+    Currency {
+        code: "NOK",
+        name: "Norwegian Krone",
+        minor_units: 2,
+        symbol: "kr",
+    },
+    Currency {
+        code: "NPR",
+        name: "Nepalese Rupee",
+        minor_units: 2,
+        symbol: "Rs",
+    },
+    Currency {
+        code: "NZD",
+        name: "New Zealand Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "OMR",
+        name: "Rial Omani",
+        minor_units: 3,
+        symbol: "ر.ع.",
+    },
+    Currency {
+        code: "PAB",
+        name: "Balboa",
+        minor_units: 2,
+        symbol: "B/.",
+    },
+    Currency {
+        code: "PEN",
+        name: "Sol",
+        minor_units: 2,
+        symbol: "S/",
+    },
+    Currency {
+        code: "PGK",
+        name: "Kina",
+        minor_units: 2,
+        symbol: "K",
+    },
+    Currency {
+        code: "PHP",
+        name: "Philippine Peso",
+        minor_units: 2,
+        symbol: "₱",
+    },
+    Currency {
+        code: "PKR",
+        name: "Pakistan Rupee",
+        minor_units: 2,
+        symbol: "Rs",
+    },
+    Currency {
+        code: "PLN",
+        name: "Zloty",
+        minor_units: 2,
+        symbol: "zł",
+    },
+    Currency {
+        code: "PYG",
+        name: "Guarani",
+        minor_units: 0,
+        symbol: "₲",
+    },
+    Currency {
+        code: "QAR",
+        name: "Qatari Rial",
+        minor_units: 2,
+        symbol: "ر.ق",
+    },
+    Currency {
+        code: "RON",
+        name: "Romanian Leu",
+        minor_units: 2,
+        symbol: "lei",
+    },
+    Currency {
+        code: "RSD",
+        name: "Serbian Dinar",
+        minor_units: 2,
+        symbol: "дин.",
+    },
+    Currency {
+        code: "RUB",
+        name: "Russian Ruble",
+        minor_units: 2,
+        symbol: "₽",
+    },
+    Currency {
+        code: "RWF",
+        name: "Rwanda Franc",
+        minor_units: 0,
+        symbol: "FRw",
+    },
+    Currency {
+        code: "SAR",
+        name: "Saudi Riyal",
+        minor_units: 2,
+        symbol: "ر.س",
+    },
+    Currency {
+        code: "SBD",
+        name: "Solomon Islands Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "SCR",
+        name: "Seychelles Rupee",
+        minor_units: 2,
+        symbol: "Rs",
+    },
+    Currency {
+        code: "SDG",
+        name: "Sudanese Pound",
+        minor_units: 2,
+        symbol: "ج.س.",
+    },
+    Currency {
+        code: "SEK",
+        name: "Swedish Krona",
+        minor_units: 2,
+        symbol: "kr",
+    },
+    Currency {
+        code: "SGD",
+        name: "Singapore Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "SHP",
+        name: "Saint Helena Pound",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "SLE",
+        name: "Leone",
+        minor_units: 2,
+        symbol: "Le",
+    },
+    Currency {
+        code: "SOS",
+        name: "Somali Shilling",
+        minor_units: 2,
+        symbol: "Sh",
+    },
+    Currency {
+        code: "SRD",
+        name: "Surinam Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "SSP",
+        name: "South Sudanese Pound",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "STN",
+        name: "Dobra",
+        minor_units: 2,
+        symbol: "Db",
+    },
+    Currency {
+        code: "SVC",
+        name: "El Salvador Colon",
+        minor_units: 2,
+        symbol: "₡",
+    },
+    Currency {
+        code: "SYP",
+        name: "Syrian Pound",
+        minor_units: 2,
+        symbol: "£",
+    },
+    Currency {
+        code: "SZL",
+        name: "Lilangeni",
+        minor_units: 2,
+        symbol: "L",
+    },
+    Currency {
+        code: "THB",
+        name: "Baht",
+        minor_units: 2,
+        symbol: "฿",
+    },
+    Currency {
+        code: "TJS",
+        name: "Somoni",
+        minor_units: 2,
+        symbol: "SM",
+    },
+    Currency {
+        code: "TMT",
+        name: "Turkmenistan New Manat",
+        minor_units: 2,
+        symbol: "m",
+    },
+    Currency {
+        code: "TND",
+        name: "Tunisian Dinar",
+        minor_units: 3,
+        symbol: "د.ت",
+    },
+    Currency {
+        code: "TOP",
+        name: "Pa'anga",
+        minor_units: 2,
+        symbol: "T$",
+    },
+    Currency {
+        code: "TRY",
+        name: "Turkish Lira",
+        minor_units: 2,
+        symbol: "₺",
+    },
+    Currency {
+        code: "TTD",
+        name: "Trinidad and Tobago Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "TWD",
+        name: "New Taiwan Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "TZS",
+        name: "Tanzanian Shilling",
+        minor_units: 2,
+        symbol: "TSh",
+    },
+    Currency {
+        code: "UAH",
+        name: "Hryvnia",
+        minor_units: 2,
+        symbol: "₴",
+    },
+    Currency {
+        code: "UGX",
+        name: "Uganda Shilling",
+        minor_units: 0,
+        symbol: "USh",
+    },
+    Currency {
+        code: "USD",
+        name: "US Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "UYU",
+        name: "Peso Uruguayo",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "UZS",
+        name: "Uzbekistan Sum",
+        minor_units: 2,
+        symbol: "soʻm",
+    },
+    Currency {
+        code: "VES",
+        name: "Bolívar Soberano",
+        minor_units: 2,
+        symbol: "Bs.",
+    },
+    Currency {
+        code: "VND",
+        name: "Dong",
+        minor_units: 0,
+        symbol: "₫",
+    },
+    Currency {
+        code: "VUV",
+        name: "Vatu",
+        minor_units: 0,
+        symbol: "VT",
+    },
+    Currency {
+        code: "WST",
+        name: "Tala",
+        minor_units: 2,
+        symbol: "T",
+    },
+    Currency {
+        code: "XAF",
+        name: "CFA Franc BEAC",
+        minor_units: 0,
+        symbol: "FCFA",
+    },
+    Currency {
+        code: "XCD",
+        name: "East Caribbean Dollar",
+        minor_units: 2,
+        symbol: "$",
+    },
+    Currency {
+        code: "XCG",
+        name: "Caribbean Guilder",
+        minor_units: 2,
+        symbol: "Cg",
+    },
+    Currency {
+        code: "XOF",
+        name: "CFA Franc BCEAO",
+        minor_units: 0,
+        symbol: "CFA",
+    },
+    Currency {
+        code: "XPF",
+        name: "CFP Franc",
+        minor_units: 0,
+        symbol: "₣",
+    },
+    Currency {
+        code: "YER",
+        name: "Yemeni Rial",
+        minor_units: 2,
+        symbol: "﷼",
+    },
+    Currency {
+        code: "ZAR",
+        name: "Rand",
+        minor_units: 2,
+        symbol: "R",
+    },
+    Currency {
+        code: "ZMW",
+        name: "Zambian Kwacha",
+        minor_units: 2,
+        symbol: "ZK",
+    },
+    Currency {
+        code: "ZWG",
+        name: "Zimbabwe Gold",
+        minor_units: 2,
+        symbol: "ZiG",
+    },
+];
+
+/// Find currency by code, case-insensitive.
+pub fn find(code: &str) -> Option<&'static Currency> {
+    let code = code.to_uppercase();
+    CURRENCIES
+        .binary_search_by(|c| c.code.cmp(code.as_str()))
+        .ok()
+        .map(|i| &CURRENCIES[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted() {
+        // `find` is a binary search.
+        for pair in CURRENCIES.windows(2) {
+            assert!(
+                pair[0].code < pair[1].code,
+                "{} before {}",
+                pair[0].code,
+                pair[1].code
+            );
+        }
+    }
+
+    #[test]
+    fn codes() {
+        assert!(CURRENCIES
+            .iter()
+            .all(|c| c.code.len() == 3 && c.code.chars().all(|ch| ch.is_ascii_uppercase())));
+        assert_eq!(find("pln").map(|c| c.code), Some("PLN"));
+        assert_eq!(find("EUR").map(|c| c.minor_units), Some(2));
+        assert!(find("XYZ").is_none());
+        assert!(find("").is_none());
+    }
+}
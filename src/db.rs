@@ -5,11 +5,39 @@ use std::fmt;
 use chrono::TimeZone;
 use postgres;
 
+use crate::backup;
+use crate::backup::Backup;
+use crate::backup::RestoreSummary;
+use crate::login_limit;
 use crate::model::AccountInfo;
+use crate::model::AccountSummary;
+use crate::model::ApiToken;
 use crate::model::BankAccount;
 use crate::model::BankAccountInfo;
+use crate::model::BankAccountKind;
+use crate::model::BankAccountShare;
 use crate::model::CurrencyInfo;
+use crate::model::DeliveryStatus;
+use crate::model::DigestFrequency;
+use crate::model::DigestSubscription;
 use crate::model::EntryInfo;
+use crate::model::GroupInfo;
+use crate::model::Invite;
+use crate::model::LandingPage;
+use crate::model::LoginRequest;
+use crate::model::Preferences;
+use crate::model::Price;
+use crate::model::PriceInfo;
+use crate::model::ShareRole;
+use crate::model::Theme;
+use crate::model::Totp;
+use crate::model::Webhook;
+use crate::model::WebhookDelivery;
+use crate::model::WebhookEvent;
+use crate::model::MAX_ENTRIES_PER_PAGE;
+use crate::webhook;
+use crate::webhook::WebhookBankAccount;
+use crate::webhook::WebhookEntry;
 
 #[derive(Debug)]
 pub struct DbError {
@@ -17,7 +45,7 @@ pub struct DbError {
 }
 
 impl DbError {
-    fn new<X>(desc: X) -> DbError
+    pub(crate) fn new<X>(desc: X) -> DbError
     where
         X: ToString,
     {
@@ -45,6 +73,150 @@ impl std::convert::From<postgres::error::Error> for DbError {
     }
 }
 
+/// Parse timestamp formatted by `to_char(ts, 'YYYY-MM-DD HH24:MI:SS.US')`.
+pub(crate) fn parse_db_ts(s: &str) -> chrono::DateTime<chrono::Utc> {
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .unwrap()
+        .and_utc()
+}
+
+/// Bank account kind as stored in `bank_account.kind`.
+pub(crate) fn parse_db_kind(s: &str) -> BankAccountKind {
+    BankAccountKind::parse(s).unwrap_or(BankAccountKind::Asset)
+}
+
+/// Share role as stored in `bank_account_share.role`, null for owner.
+pub(crate) fn parse_db_role(s: Option<&str>) -> Option<ShareRole> {
+    s.and_then(ShareRole::parse)
+}
+
+/// SQL condition that user account `account` can see `bank_account`, that
+/// is it owns the bank account or the bank account is shared with it.
+/// Both are SQL expressions.
+pub(crate) fn can_read_sql(bank_account: &str, account: &str) -> String {
+    format!(
+        "
+            (
+                {bank_account}.account = {account}
+                or exists (
+                    select 1
+                    from bank_account_share
+                    where
+                        bank_account_share.bank_account = {bank_account}.id
+                        and bank_account_share.account = {account}
+                )
+            )",
+        bank_account = bank_account,
+        account = account
+    )
+}
+
+/// Like `can_read_sql`, but only owner and editors can change entries.
+pub(crate) fn can_write_sql(bank_account: &str, account: &str) -> String {
+    format!(
+        "
+            (
+                {bank_account}.account = {account}
+                or exists (
+                    select 1
+                    from bank_account_share
+                    where
+                        bank_account_share.bank_account = {bank_account}.id
+                        and bank_account_share.account = {account}
+                        and bank_account_share.role = 'editor'
+                )
+            )",
+        bank_account = bank_account,
+        account = account
+    )
+}
+
+/// SQL expression for the share role of user account `account` in
+/// `bank_account`, null when it's the owner.
+pub(crate) fn role_sql(bank_account: &str, account: &str) -> String {
+    format!(
+        "
+            (
+                select bank_account_share.role
+                from bank_account_share
+                where
+                    bank_account_share.bank_account = {bank_account}.id
+                    and bank_account_share.account = {account}
+                    and {bank_account}.account <> {account}
+            )",
+        bank_account = bank_account,
+        account = account
+    )
+}
+
+/// SQL expression for the value of `amount` held on `bank_account` at
+/// `ts`, all three are SQL expressions.
+/// For holdings (bank accounts with commodity) the amount is the quantity,
+/// so the value is quantity times latest price at or before `ts`, or null
+/// if there's no such price. For other bank accounts it's just the amount.
+fn value_sql(bank_account: &str, amount: &str, ts: &str) -> String {
+    format!(
+        "
+        case
+            when {bank_account}.commodity is null then {amount}
+            else {amount} * (
+                select price.price
+                from price
+                where
+                    price.account = {bank_account}.account
+                    and price.commodity = {bank_account}.commodity
+                    and price.currency = {bank_account}.currency
+                    and price.ts <= {ts}
+                order by price.ts desc
+                limit 1
+            )
+        end",
+        bank_account = bank_account,
+        amount = amount,
+        ts = ts
+    )
+}
+
+/// Columns read by `entry_info_from_row`, for queries that join `entry`
+/// with `bank_account` and have the user account as `$1`.
+fn entry_info_columns() -> String {
+    format!(
+        "
+            entry.id,
+            bank_account.name,
+            entry.amount::text,
+            bank_account.currency,
+            to_char(
+                entry.ts,
+                'YYYY-MM-DD HH24:MI:SS.US'
+            ),
+            bank_account.kind,
+            bank_account.commodity,
+            ({})::text,
+            {},
+            entry.note,
+            bank_account.id",
+        value_sql("bank_account", "entry.amount", "entry.ts"),
+        role_sql("bank_account", "$1")
+    )
+}
+
+fn entry_info_from_row(row: &postgres::Row) -> EntryInfo {
+    EntryInfo {
+        id: row.get(0),
+        bank_account: row.get(1),
+        amount: row.get(2),
+        currency: row.get(3),
+        ts: parse_db_ts(row.get(4)),
+        kind: parse_db_kind(row.get(5)),
+        commodity: row.get(6),
+        value: row.get(7),
+        role: parse_db_role(row.get(8)),
+        note: row.get(9),
+        bank_account_id: row.get(10),
+    }
+}
+
 pub fn get_sess_val(conn: &mut postgres::Client, sess_key: &str, name: &str) -> Option<String> {
     let sql = "select value from session where key = $1 and name = $2";
     match conn.query(sql, &[&sess_key, &name]) {
@@ -66,7 +238,7 @@ pub fn get_sess_val(conn: &mut postgres::Client, sess_key: &str, name: &str) ->
 }
 
 pub fn set_session_value(
-    conn: &mut postgres::Client,
+    conn: &mut impl postgres::GenericClient,
     session_key: &str,
     name: &str,
     value: &str,
@@ -96,6 +268,24 @@ pub fn set_session_value(
     }
 }
 
+pub fn increment_session_value(
+    conn: &mut postgres::Client,
+    session_key: &str,
+    name: &str,
+) -> Result<Option<i64>, DbError> {
+    let row = conn.query_opt(
+        "
+        update session
+        set
+            value = (value::bigint + 1)::text,
+            modified = current_timestamp
+        where key = $1 and name = $2
+        returning value::bigint",
+        &[&session_key, &name],
+    )?;
+    Ok(row.map(|row| row.get(0)))
+}
+
 pub fn delete_session(conn: &mut postgres::Client, session_key: &str) -> Result<(), DbError> {
     match conn.execute("delete from session where key = $1", &[&session_key]) {
         Ok(_) => Ok(()),
@@ -250,12 +440,32 @@ pub fn insert_login_token(
     conn: &mut postgres::Client,
     account_id: &i64,
     token: &str,
+) -> Result<(), DbError> {
+    insert_token(conn, account_id, token, "login")
+}
+
+/// Insert token that confirms account deletion, see `delete_account`.
+pub fn insert_delete_account_token(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    token: &str,
+) -> Result<(), DbError> {
+    insert_token(conn, &account_id, token, "delete-account")
+}
+
+/// Insert token for given `login_token.purpose`.
+fn insert_token(
+    conn: &mut postgres::Client,
+    account_id: &i64,
+    token: &str,
+    purpose: &str,
 ) -> Result<(), DbError> {
     match conn.execute(
         "insert into login_token (
             id,
             account,
             token,
+            purpose,
             used,
             used_ts,
             created,
@@ -264,12 +474,13 @@ pub fn insert_login_token(
             nextval('login_token_seq'),
             $1,
             $2,
+            $3,
             false,
             null,
             current_timestamp,
             current_timestamp
         )",
-        &[&account_id, &token],
+        &[&account_id, &token, &purpose],
     ) {
         Ok(_) => Ok(()),
         Err(e) => Err(DbError::new(&e.to_string())),
@@ -277,15 +488,20 @@ pub fn insert_login_token(
 }
 
 /// Get account id by login token.
-/// Only active tokens are queried.
+/// Only active tokens of enabled accounts are queried.
 pub fn get_login_token_account(
     conn: &mut postgres::Client,
     token: &str,
 ) -> Result<Option<i64>, DbError> {
     match conn.query(
-        "select account
+        "select login_token.account
         from login_token
-        where token = $1 and used = false",
+        join account on account.id = login_token.account
+        where
+            login_token.token = $1
+            and login_token.used = false
+            and login_token.purpose = 'login'
+            and account.disabled = false",
         &[&token],
     ) {
         Err(e) => Err(DbError::new(&e.to_string())),
@@ -296,376 +512,2218 @@ pub fn get_login_token_account(
     }
 }
 
-pub fn insert_entry(
+/// Mark login token as used and set the session values, see
+/// `Storage::use_login_token`.
+pub fn use_login_token(
     conn: &mut postgres::Client,
-    account_id: &i64,
-    bank_account: &i64,
-    ts: &chrono::DateTime<chrono::Utc>,
-    amount_str: &str,
-) -> Result<(), DbError> {
-    match conn.execute(
-        "insert into entry (
-                id,
-                bank_account,
-                ts,
-                amount,
-                deleted,
-                created,
-                modified
-        ) values (
-            nextval('entry_seq'),
-            (
-                select bank_account.id
-                from bank_account
-                where account = $1
-                and id = $2
-            ),                 -- bank account
-            to_timestamp(
-                $3,
-                'YYYY-MM DD HH24:MI:SS.US'
-            ),                 -- ts
-            $4::text::numeric, -- amount
-            false,
-            current_timestamp,
-            current_timestamp
-        )",
-        &[
-            &account_id,
-            &bank_account,
-            &ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
-            &amount_str,
-        ],
-    ) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(DbError::new(&format!("Failed to insert entry: {}", e))),
+    token: &str,
+    session_key: &str,
+    values: &[(&str, &str)],
+) -> Result<bool, DbError> {
+    let mut transaction = conn.transaction()?;
+    let n = transaction.execute(
+        "
+        update login_token
+        set
+            used = true,
+            used_ts = current_timestamp,
+            modified = current_timestamp
+        where
+            token = $1
+            and used = false
+            and purpose = 'login'
+            and account in (select id from account where disabled = false)",
+        &[&token],
+    )?;
+    if n == 0 {
+        return Ok(false);
     }
+    for (name, value) in values {
+        set_session_value(&mut transaction, session_key, name, value)?;
+    }
+    transaction.commit()?;
+    Ok(true)
 }
 
-pub fn get_entry(
+/// Get account id by account deletion token.
+/// The token is valid for a day after it was sent.
+pub fn get_delete_account_token_account(
     conn: &mut postgres::Client,
-    acc_id: i64,
-    entry_id: i64,
-) -> Result<Option<EntryInfo>, DbError> {
-    let sql = "
-        select
-            entry.id,
-            entry.amount::text,
-            to_char(
-                entry.ts,
-                'YYYY-MM-DD HH24:MI:SS.US'),
-            bank_account.name,
-            bank_account.currency
-        from
-            entry
-            join bank_account on (bank_account.id = entry.bank_account)
+    token: &str,
+) -> Result<Option<i64>, DbError> {
+    let rows = conn.query(
+        "
+        select account
+        from login_token
         where
-            bank_account.account = $1
-            and entry.id = $2
-            and entry.deleted = false
-            and bank_account.deleted = false
-    ";
-    let rows = conn.query(sql, &[&acc_id, &entry_id])?;
-    match rows.iter().next() {
-        Some(row) => {
-            let id = row.get(0);
-            let amount = row.get(1);
-            let ts = chrono::Utc
-                .datetime_from_str(row.get(2), "%Y-%m-%d %H:%M:%S%.f")
-                .unwrap();
-            let bank_account_name = row.get(3);
-            let bank_account_currency = row.get(4);
-            Ok(Some(EntryInfo {
-                amount: amount,
-                bank_account: bank_account_name,
-                currency: bank_account_currency,
-                id: id,
-                ts: ts,
-            }))
-        }
-        None => Ok(None),
-    }
+            token = $1
+            and used = false
+            and purpose = 'delete-account'
+            and created > current_timestamp - interval '1 day'",
+        &[&token],
+    )?;
+    Ok(rows.first().map(|row| row.get(0)))
 }
 
-pub fn update_entry_amount(
+/// Record login form submission, see `login_request` table.
+pub fn insert_login_request(
     conn: &mut postgres::Client,
-    account_id: i64,
-    entry_id: i64,
-    amount: String,
-) -> Result<(), DbError> {
-    let sql = "
-        update entry
-        set
-            amount = $1::text::numeric,
-            modified = current_timestamp
-        where
-            id = (
-                select
-                    entry.id
-                from
-                    entry
-                    join bank_account on (bank_account.id = entry.bank_account)
-                where
-                    entry.id = $2
-                    and bank_account.account = $3
-                    and entry.deleted = false
-                    and bank_account.deleted = false
-            )
-    ";
-    conn.execute(sql, &[&amount, &entry_id, &account_id])?;
-    Ok(())
+    email: &str,
+    ip: Option<&str>,
+    token: Option<&str>,
+    invite: Option<&str>,
+    now: &chrono::DateTime<chrono::Utc>,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, DbError> {
+    let email = &login_limit::normalize_email(email);
+    let mut transaction = conn.transaction()?;
+    // Concurrent login requests wait here, so each sees the ones before.
+    transaction.execute("lock table login_request in share row exclusive mode", &[])?;
+    let requests = get_login_requests(
+        &mut transaction,
+        email,
+        ip,
+        &login_limit::window_start(*now),
+    )?;
+    if let Some(retry) = login_limit::retry_at(&requests, email, ip, *now) {
+        transaction.rollback()?;
+        return Ok(Some(retry));
+    }
+    let now = now.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+    transaction.execute(
+        "
+        delete from login_request
+        where created < $1::text::timestamp - interval '1 day'",
+        &[&now],
+    )?;
+    transaction.execute(
+        "
+        insert into login_request (
+            id,
+            email,
+            ip,
+            token,
+            invite,
+            used,
+            created,
+            modified
+        ) values (
+            nextval('login_request_seq'),
+            $1,
+            $2,
+            $3,
+            $4,
+            false,
+            $5::text::timestamp,
+            $5::text::timestamp
+        )",
+        &[&email, &ip, &token, &invite, &now],
+    )?;
+    transaction.commit()?;
+    Ok(None)
 }
 
-/// Get CashLog entries for given account id.
-/// Currently amount is String on Rust side - probably should be pair
-/// of ints or something.
-/// Returns only those entries that were not deleted (are not marked deleted).
-pub fn get_entries(
-    conn: &mut postgres::Client,
-    account_id: i64,
-) -> Result<Vec<EntryInfo>, DbError> {
-    let sql = "select
-            entry.id,
-            bank_account.name,
-            entry.amount::text,
-            bank_account.currency,
-            to_char(entry.ts, 'YYYY-MM-DD HH24:MI:SS.US')
-        from
-            entry
-            join bank_account on (bank_account.id = entry.bank_account)
+/// Get login form submissions for the email or from the IP address.
+pub fn get_login_requests(
+    conn: &mut impl postgres::GenericClient,
+    email: &str,
+    ip: Option<&str>,
+    since: &chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<LoginRequest>, DbError> {
+    let rows = conn.query(
+        "
+        select
+            email,
+            ip,
+            to_char(created, 'YYYY-MM-DD HH24:MI:SS.US')
+        from login_request
         where
-            bank_account.account = $1
-            and bank_account.deleted = false
-            and entry.deleted = false
-        order by entry.ts desc
-        limit 1024";
-    match conn.query(sql, &[&account_id]) {
-        Ok(rows) => Ok(rows
-            .iter()
-            .map(|row| EntryInfo {
-                id: row.get(0),
-                bank_account: row.get(1),
-                amount: row.get(2),
-                currency: row.get(3),
-                ts: chrono::Utc
-                    .datetime_from_str(row.get(4), "%Y-%m-%d %H:%M:%S%.f")
-                    .unwrap(),
-            })
-            .collect()),
-        Err(e) => Err(DbError::new(&e.to_string())),
-    }
+            (email = $1 or ip = $2)
+            and created > $3::text::timestamp
+        order by created, id",
+        &[
+            &email,
+            &ip,
+            &since.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+        ],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| LoginRequest {
+            email: row.get(0),
+            ip: row.get(1),
+            created: parse_db_ts(row.get(2)),
+        })
+        .collect())
 }
 
-pub fn get_entries_by_bank_account(
+/// Mark login request token as used, it's valid for a day.
+pub fn use_login_request_token(
     conn: &mut postgres::Client,
-    account_id: i64,
-    bank_account_name: &str,
-) -> Result<Vec<EntryInfo>, DbError> {
-    let sql = "
-        select
-            entry.id,
-            bank_account.name,
-            entry.amount::text,
-            bank_account.currency,
-            to_char(
-                entry.ts,
-                'YYYY-MM-DD HH24:MI:SS.US'
-            )
-        from
-            entry
-            join bank_account on (bank_account.id = entry.bank_account)
+    token: &str,
+) -> Result<Option<(String, Option<String>)>, DbError> {
+    // Requests are recorded with UTC time, see `insert_login_request`.
+    let day_ago = (chrono::Utc::now() - chrono::Duration::days(1))
+        .format("%Y-%m-%d %H:%M:%S%.6f")
+        .to_string();
+    let rows = conn.query(
+        "
+        update login_request
+        set
+            used = true,
+            modified = current_timestamp
         where
-            bank_account.account = $1
-            and bank_account.name = $2
-            and entry.deleted = false
-            and bank_account.deleted = false
-        order by entry.ts
-        limit 4096";
-    match conn.query(sql, &[&account_id, &bank_account_name]) {
-        Ok(rows) => Ok(rows
-            .iter()
-            .map(|row| EntryInfo {
-                id: row.get(0),
-                bank_account: row.get(1),
-                amount: row.get(2),
-                currency: row.get(3),
-                ts: chrono::Utc
-                    .datetime_from_str(row.get(4), "%Y-%m-%d %H:%M:%S%.f")
-                    .unwrap(),
-            })
-            .collect()),
-        Err(err) => Err(DbError::new(&err.to_string())),
-    }
+            token = $1
+            and used = false
+            and created > $2::text::timestamp
+        returning email, invite",
+        &[&token, &day_ago],
+    )?;
+    Ok(rows.first().map(|row| (row.get(0), row.get(1))))
 }
 
-/// Delete entry.
-/// The account_id is redundant, but we use it for security.
-pub fn delete_entry(
+pub fn insert_invite(
     conn: &mut postgres::Client,
-    account_id: i64,
-    entry_id: i64,
+    code: &str,
+    note: Option<&str>,
 ) -> Result<(), DbError> {
     conn.execute(
-        "update entry
-                 set deleted = true, modified = current_timestamp
-                 where id = $2
-                 and bank_account in (select id from bank_account where account = $1)",
-        &[&account_id, &entry_id],
+        "
+        insert into invite (
+            id,
+            code,
+            note,
+            used,
+            created,
+            modified
+        ) values (
+            nextval('invite_seq'),
+            $1,
+            $2,
+            null,
+            current_timestamp,
+            current_timestamp
+        )",
+        &[&code, &note],
     )?;
     Ok(())
 }
 
-pub fn get_bank_accounts(
-    conn: &mut postgres::Client,
-    account_id: i64,
-) -> Result<Vec<BankAccount>, DbError> {
-    let sql = "
+pub fn get_invites(conn: &mut postgres::Client) -> Result<Vec<Invite>, DbError> {
+    let rows = conn.query(
+        "
         select
-            bank_account.id,
-            bank_account.name,
-            bank_account.currency
-        from bank_account
-        where
-            bank_account.account = $1
-            and bank_account.deleted = false
-        order by bank_account.name, bank_account.id";
-    match conn.query(sql, &[&account_id]) {
-        Ok(rows) => Ok(rows
-            .iter()
-            .map(|row| BankAccount {
-                id: row.get(0),
-                name: row.get(1),
-                currency: row.get(2),
-            })
-            .collect()),
-        Err(err) => Err(DbError::from(err)),
-    }
+            code,
+            note,
+            to_char(created, 'YYYY-MM-DD HH24:MI:SS.US'),
+            to_char(used, 'YYYY-MM-DD HH24:MI:SS.US')
+        from invite
+        order by created desc, id desc",
+        &[],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| Invite {
+            code: row.get(0),
+            note: row.get(1),
+            created: parse_db_ts(row.get(2)),
+            used: row.get::<_, Option<&str>>(3).map(parse_db_ts),
+        })
+        .collect())
 }
 
-pub fn get_bank_account_infos(
-    conn: &mut postgres::Client,
-    account_id: i64,
-) -> Result<Vec<BankAccountInfo>, DbError> {
-    let sql = "
+pub fn is_invite_valid(conn: &mut postgres::Client, code: &str) -> Result<bool, DbError> {
+    let rows = conn.query(
+        "select id from invite where code = $1 and used is null",
+        &[&code],
+    )?;
+    Ok(!rows.is_empty())
+}
+
+pub fn use_invite(conn: &mut postgres::Client, code: &str) -> Result<bool, DbError> {
+    let n = conn.execute(
+        "
+        update invite
+        set
+            used = current_timestamp,
+            modified = current_timestamp
+        where code = $1 and used is null",
+        &[&code],
+    )?;
+    Ok(n > 0)
+}
+
+pub fn get_totp(conn: &mut postgres::Client, account_id: i64) -> Result<Option<Totp>, DbError> {
+    let rows = conn.query(
+        "
         select
-            bank_account_and_last_entry_id.name,
-            last_entry.amount::text,
-            bank_account_and_last_entry_id.currency,
-            to_char(
-                last_entry.ts,
-                'YYYY-MM-DD HH24:MI:SS.US'
-            )
-        from
+            secret,
+            enabled,
+            last_step,
             (
-                select
-                    bank_account.*,
-                    (
-                        select entry.id
-                        from entry
-                        where
-                            entry.bank_account = bank_account.id
-                            and entry.deleted = false
-                        order by entry.ts desc
-                        limit 1
-                    ) as last_entry_id
-                from bank_account
-            ) as bank_account_and_last_entry_id
-            left join entry as last_entry on (
-                last_entry.id = bank_account_and_last_entry_id.last_entry_id
+                select count(*)
+                from account_recovery_code
+                where account_recovery_code.account = account_totp.account
             )
-        where
-            bank_account_and_last_entry_id.account = $1
-            and bank_account_and_last_entry_id.deleted = false
-            and bank_account_and_last_entry_id.last_entry_id is not null
-        order by
-            bank_account_and_last_entry_id.name,
-            bank_account_and_last_entry_id.currency
-    ";
-    match conn.query(sql, &[&account_id]) {
-        Ok(rows) => Ok(rows
-            .iter()
-            .map(|row| BankAccountInfo {
-                bank_account: row.get(0),
-                amount: row.get(1),
-                currency: row.get(2),
-                ts: chrono::Utc
-                    .datetime_from_str(row.get(3), "%Y-%m-%d %H:%M:%S%.f")
-                    .unwrap(),
-            })
-            .collect()),
-        Err(e) => Err(DbError::from(e)),
+        from account_totp
+        where account = $1",
+        &[&account_id],
+    )?;
+    Ok(rows.first().map(|row| Totp {
+        secret: row.get(0),
+        enabled: row.get(1),
+        last_step: row.get(2),
+        recovery_codes: row.get(3),
+    }))
+}
+
+pub fn set_totp_secret(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    secret: &str,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        insert into account_totp (
+            account,
+            secret,
+            enabled,
+            last_step,
+            created,
+            modified
+        ) values (
+            $1,
+            $2,
+            false,
+            null,
+            current_timestamp,
+            current_timestamp
+        )
+        on conflict (account) do update
+        set
+            secret = excluded.secret,
+            last_step = null,
+            modified = current_timestamp
+        where account_totp.enabled = false",
+        &[&account_id, &secret],
+    )?;
+    Ok(())
+}
+
+pub fn enable_totp(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    step: i64,
+    recovery_code_hashes: &[String],
+) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    transaction.execute(
+        "
+        update account_totp
+        set
+            enabled = true,
+            last_step = $2,
+            modified = current_timestamp
+        where account = $1",
+        &[&account_id, &step],
+    )?;
+    transaction.execute(
+        "delete from account_recovery_code where account = $1",
+        &[&account_id],
+    )?;
+    for code_hash in recovery_code_hashes {
+        transaction.execute(
+            "
+            insert into account_recovery_code (
+                id,
+                account,
+                code_hash,
+                created
+            ) values (
+                nextval('account_recovery_code_seq'),
+                $1,
+                $2,
+                current_timestamp
+            )",
+            &[&account_id, code_hash],
+        )?;
     }
+    transaction.commit()?;
+    Ok(())
 }
 
-pub fn insert_bank_account(
+pub fn set_totp_step(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    step: i64,
+) -> Result<bool, DbError> {
+    let n = conn.execute(
+        "
+        update account_totp
+        set
+            last_step = $2,
+            modified = current_timestamp
+        where
+            account = $1
+            and (last_step is null or last_step < $2)",
+        &[&account_id, &step],
+    )?;
+    Ok(n > 0)
+}
+
+pub fn use_recovery_code(
     conn: &mut postgres::Client,
     account_id: i64,
+    code_hash: &str,
+) -> Result<bool, DbError> {
+    let n = conn.execute(
+        "
+        delete from account_recovery_code
+        where account = $1 and code_hash = $2",
+        &[&account_id, &code_hash],
+    )?;
+    Ok(n > 0)
+}
+
+pub fn delete_totp(conn: &mut postgres::Client, account_id: i64) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    transaction.execute(
+        "delete from account_recovery_code where account = $1",
+        &[&account_id],
+    )?;
+    transaction.execute(
+        "delete from account_totp where account = $1",
+        &[&account_id],
+    )?;
+    // Logins waiting for the code can't finish now.
+    delete_sessions_by(&mut transaction, account_id, "totp_account")?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Log out all sessions of given user account, also logins waiting for
+/// TOTP code.
+fn delete_account_sessions(
+    conn: &mut impl postgres::GenericClient,
+    account_id: i64,
+) -> Result<(), DbError> {
+    delete_sessions_by(conn, account_id, "account")?;
+    delete_sessions_by(conn, account_id, "totp_account")
+}
+
+/// Delete sessions that have the account id as value `name`.
+fn delete_sessions_by(
+    conn: &mut impl postgres::GenericClient,
+    account_id: i64,
     name: &str,
-    currency: &str,
 ) -> Result<(), DbError> {
-    let sql = "insert into bank_account (
-        id,
-        account,
-        name,
-        currency,
-        created,
-        modified
-    ) values (
-        nextval('bank_account_seq'),
-        $1,
-        $2,
-        $3,
-        current_timestamp,
-        current_timestamp
-    )";
-    conn.execute(sql, &[&account_id, &name, &currency])?;
+    // Session rows are k-v, so delete all rows of the matching sessions.
+    conn.execute(
+        "
+        delete from session
+        where key in (
+            select key
+            from session
+            where name = $2 and value = $1::bigint::text
+        )",
+        &[&account_id, &name],
+    )?;
     Ok(())
 }
 
-pub fn get_currency_info(
+/// Disable or enable user account. Disabling also logs out its sessions.
+pub fn set_account_disabled(
     conn: &mut postgres::Client,
     account_id: i64,
-) -> Result<Vec<CurrencyInfo>, DbError> {
-    let sql = "
+    disabled: bool,
+) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    transaction.execute(
+        "
+        update account
+        set disabled = $2, modified = current_timestamp
+        where id = $1",
+        &[&account_id, &disabled],
+    )?;
+    if disabled {
+        delete_account_sessions(&mut transaction, account_id)?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+pub fn is_account_disabled(conn: &mut postgres::Client, account_id: i64) -> Result<bool, DbError> {
+    let rows = conn.query("select disabled from account where id = $1", &[&account_id])?;
+    Ok(rows.first().is_some_and(|row| row.get(0)))
+}
+
+/// Get all user accounts with counts of their live bank accounts and
+/// entries.
+pub fn get_account_summaries(conn: &mut postgres::Client) -> Result<Vec<AccountSummary>, DbError> {
+    let rows = conn.query(
+        "
         select
-            -- Finally, group those selected entries to produce summaries.
-            currency,
-            sum(amount)::text,
-            to_char(
-                max(ts),
-                'YYYY-MM-DD HH24:MI:SS.US'
-            )
-        from
+            account.id,
+            coalesce(
+                (
+                    select array_agg(email::text order by email)
+                    from account_email
+                    where account = account.id
+                ),
+                '{}'),
+            to_char(account.created, 'YYYY-MM-DD HH24:MI:SS.US'),
+            account.disabled,
             (
-                select
-                    bank_account.currency as currency,
-                    (
-                        select id
-                        from entry
-                        where
-                            entry.bank_account = bank_account.id
-                            and entry.deleted = false
-                        order by ts desc
-                        limit 1
-                    ) as entry_id
-                from
-                    bank_account
+                select count(*)
+                from bank_account
+                where account = account.id and deleted = false
+            ),
+            (
+                select count(*)
+                from entry
+                join bank_account on bank_account.id = entry.bank_account
                 where
-                    bank_account.account = $1
+                    bank_account.account = account.id
                     and bank_account.deleted = false
-            ) as bank_account_last_entry_id
-            join entry as last_entry
-                on (last_entry.id = bank_account_last_entry_id.entry_id)
-        group by currency
-        order by currency";
-    let rows = conn.query(sql, &[&account_id])?;
+                    and entry.deleted = false
+            )
+        from account
+        order by account.id",
+        &[],
+    )?;
     Ok(rows
         .iter()
-        .map(|row| CurrencyInfo {
-            currency: row.get(0),
-            amount: row.get(1),
-            ts: chrono::Utc
-                .datetime_from_str(row.get(2), "%Y-%m-%d %H:%M:%S%.f")
-                .unwrap(),
+        .map(|row| AccountSummary {
+            id: row.get(0),
+            emails: row.get(1),
+            created: parse_db_ts(row.get(2)),
+            disabled: row.get(3),
+            bank_accounts: row.get(4),
+            entries: row.get(5),
         })
         .collect())
 }
+
+/// Delete user account and all its rows: sessions, tokens, emails, bank
+/// accounts with entries and sharing, and prices.
+/// This can't be undone, the rows are really deleted, not marked deleted.
+pub fn delete_account(conn: &mut postgres::Client, account_id: i64) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    delete_account_sessions(&mut transaction, account_id)?;
+    transaction.execute(
+        "
+        delete from bank_account_share
+        where
+            account = $1
+            or bank_account in (select id from bank_account where account = $1)",
+        &[&account_id],
+    )?;
+    transaction.execute(
+        "
+        delete from entry
+        where bank_account in (select id from bank_account where account = $1)",
+        &[&account_id],
+    )?;
+    // Other user accounts may have them shared as default bank account.
+    transaction.execute(
+        "
+        update account_preference
+        set default_bank_account = null
+        where default_bank_account in (select id from bank_account where account = $1)",
+        &[&account_id],
+    )?;
+    transaction.execute(
+        "
+        delete from webhook_delivery
+        where webhook in (select id from webhook where account = $1)",
+        &[&account_id],
+    )?;
+    for table in &[
+        "account_preference",
+        "account_digest",
+        "account_recovery_code",
+        "account_totp",
+        "webhook",
+        "bank_account",
+        "price",
+        "login_token",
+        "api_token",
+        "account_email",
+    ] {
+        let sql = format!("delete from {} where account = $1", table);
+        transaction.execute(sql.as_str(), &[&account_id])?;
+    }
+    transaction.execute("delete from account where id = $1", &[&account_id])?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Insert entry, nothing is inserted if the user account can't change
+/// entries of the bank account.
+pub fn insert_entry(
+    conn: &mut postgres::Client,
+    account_id: &i64,
+    bank_account: &i64,
+    ts: &chrono::DateTime<chrono::Utc>,
+    amount_str: &str,
+    note: Option<&str>,
+) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    match insert_entry_row(
+        &mut transaction,
+        *account_id,
+        *bank_account,
+        ts,
+        amount_str,
+        note,
+    ) {
+        Ok(_) => Ok(transaction.commit()?),
+        Err(e) => Err(DbError::new(&format!("Failed to insert entry: {}", e))),
+    }
+}
+
+/// Insert entry if the user account can change entries of the bank
+/// account, returns number of inserted rows.
+fn insert_entry_row(
+    conn: &mut impl postgres::GenericClient,
+    account_id: i64,
+    bank_account: i64,
+    ts: &chrono::DateTime<chrono::Utc>,
+    amount_str: &str,
+    note: Option<&str>,
+) -> Result<u64, postgres::Error> {
+    let sql = format!(
+        "insert into entry (
+                id,
+                bank_account,
+                ts,
+                amount,
+                note,
+                deleted,
+                created,
+                modified
+        )
+        select
+            nextval('entry_seq'),
+            bank_account.id,   -- bank account
+            to_timestamp(
+                $3,
+                'YYYY-MM DD HH24:MI:SS.US'
+            ),                 -- ts
+            $4::text::numeric, -- amount
+            $5,                -- note
+            false,
+            current_timestamp,
+            current_timestamp
+        from bank_account
+        where
+            bank_account.id = $2
+            and bank_account.deleted = false
+            and {}
+        returning id",
+        can_write_sql("bank_account", "$1")
+    );
+    let rows = conn.query(
+        sql.as_str(),
+        &[
+            &account_id,
+            &bank_account,
+            &ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+            &amount_str,
+            &note,
+        ],
+    )?;
+    for row in &rows {
+        queue_entry_event(conn, row.get(0), WebhookEvent::EntryCreated)?;
+    }
+    Ok(rows.len() as u64)
+}
+
+/// Insert entries of many bank accounts with the same timestamp, all or
+/// nothing. Bank accounts the user account can't change are skipped, like
+/// in `insert_entry`. Returns number of inserted entries.
+pub fn insert_entries(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    ts: &chrono::DateTime<chrono::Utc>,
+    amounts: &[(i64, String)],
+) -> Result<u64, DbError> {
+    let mut transaction = conn.transaction()?;
+    let mut inserted = 0;
+    for (bank_account, amount) in amounts {
+        inserted += insert_entry_row(
+            &mut transaction,
+            account_id,
+            *bank_account,
+            ts,
+            amount,
+            None,
+        )
+        .map_err(|e| DbError::new(format!("Failed to insert entry: {}", e)))?;
+    }
+    transaction.commit()?;
+    Ok(inserted)
+}
+
+pub fn get_entry(
+    conn: &mut postgres::Client,
+    acc_id: i64,
+    entry_id: i64,
+) -> Result<Option<EntryInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            {}
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where
+            {}
+            and entry.id = $2
+            and entry.deleted = false
+            and bank_account.deleted = false
+    ",
+        entry_info_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    let rows = conn.query(sql.as_str(), &[&acc_id, &entry_id])?;
+    Ok(rows.first().map(entry_info_from_row))
+}
+
+pub fn update_entry(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    entry_id: i64,
+    amount: String,
+    note: Option<&str>,
+) -> Result<(), DbError> {
+    let sql = format!(
+        "
+        update entry
+        set
+            amount = $1::text::numeric,
+            note = $4,
+            modified = current_timestamp
+        where
+            id = (
+                select
+                    entry.id
+                from
+                    entry
+                    join bank_account on (bank_account.id = entry.bank_account)
+                where
+                    entry.id = $2
+                    and {}
+                    and entry.deleted = false
+                    and bank_account.deleted = false
+            )
+        returning id
+    ",
+        can_write_sql("bank_account", "$3")
+    );
+    let mut transaction = conn.transaction()?;
+    let rows = transaction.query(sql.as_str(), &[&amount, &entry_id, &account_id, &note])?;
+    for row in &rows {
+        queue_entry_event(&mut transaction, row.get(0), WebhookEvent::EntryUpdated)?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Get CashLog entries for given account id.
+/// Currently amount is String on Rust side - probably should be pair
+/// of ints or something.
+/// Returns only those entries that were not deleted (are not marked deleted).
+pub fn get_entries(
+    conn: &mut postgres::Client,
+    account_id: i64,
+) -> Result<Vec<EntryInfo>, DbError> {
+    get_entries_page(conn, account_id, None, MAX_ENTRIES_PER_PAGE, 0)
+}
+
+/// Get `limit` live entries, newest first, skipping `offset` newer ones.
+/// With `search` only entries with note containing it are returned, case
+/// is ignored.
+pub fn get_entries_page(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    search: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<EntryInfo>, DbError> {
+    let sql = format!(
+        "select
+            {}
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where
+            {}
+            and bank_account.deleted = false
+            and entry.deleted = false
+            and ($4::text is null or strpos(lower(entry.note), lower($4)) > 0)
+        order by entry.ts desc, entry.id desc
+        limit $2
+        offset $3",
+        entry_info_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    match conn.query(sql.as_str(), &[&account_id, &limit, &offset, &search]) {
+        Ok(rows) => Ok(rows.iter().map(entry_info_from_row).collect()),
+        Err(e) => Err(DbError::new(e.to_string())),
+    }
+}
+
+/// Get all live entries of given account, oldest first.
+/// Unlike `get_entries` this is not limited, it's meant for reports that
+/// need the whole history.
+pub fn get_all_entries(
+    conn: &mut postgres::Client,
+    account_id: i64,
+) -> Result<Vec<EntryInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            {}
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where
+            {}
+            and bank_account.deleted = false
+            and entry.deleted = false
+        order by entry.ts, entry.id",
+        entry_info_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    let rows = conn.query(sql.as_str(), &[&account_id])?;
+    Ok(rows.iter().map(entry_info_from_row).collect())
+}
+
+pub fn get_entries_by_bank_account(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    bank_account_id: i64,
+) -> Result<Vec<EntryInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            {}
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where
+            {}
+            and bank_account.id = $2
+            and entry.deleted = false
+            and bank_account.deleted = false
+        order by entry.ts
+        limit 4096",
+        entry_info_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    match conn.query(sql.as_str(), &[&account_id, &bank_account_id]) {
+        Ok(rows) => Ok(rows.iter().map(entry_info_from_row).collect()),
+        Err(err) => Err(DbError::new(err.to_string())),
+    }
+}
+
+/// Get entries of all bank accounts in given group and currency, oldest first.
+pub fn get_entries_by_group(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    group_name: &str,
+    currency: &str,
+) -> Result<Vec<EntryInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            {}
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where
+            {}
+            and bank_account.group_name = $2
+            and bank_account.currency = $3
+            and entry.deleted = false
+            and bank_account.deleted = false
+        order by entry.ts, entry.id",
+        entry_info_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    let rows = conn.query(sql.as_str(), &[&account_id, &group_name, &currency])?;
+    Ok(rows.iter().map(entry_info_from_row).collect())
+}
+
+/// Delete entry.
+/// The account_id is redundant, but we use it for security.
+pub fn delete_entry(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    entry_id: i64,
+) -> Result<(), DbError> {
+    let sql = format!(
+        "update entry
+                 set deleted = true, modified = current_timestamp
+                 where id = $2
+                 and deleted = false
+                 and bank_account in (select id from bank_account where {})
+                 returning id",
+        can_write_sql("bank_account", "$1")
+    );
+    let mut transaction = conn.transaction()?;
+    let rows = transaction.query(sql.as_str(), &[&account_id, &entry_id])?;
+    for row in &rows {
+        queue_entry_event(&mut transaction, row.get(0), WebhookEvent::EntryDeleted)?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Columns read by `bank_account_from_row`, for queries that have the user
+/// account as `$1`.
+fn bank_account_columns() -> String {
+    format!(
+        "
+            bank_account.id,
+            bank_account.name,
+            bank_account.currency,
+            bank_account.group_name,
+            bank_account.kind,
+            bank_account.commodity,
+            {}",
+        role_sql("bank_account", "$1")
+    )
+}
+
+fn bank_account_from_row(row: &postgres::Row) -> BankAccount {
+    BankAccount {
+        id: row.get(0),
+        name: row.get(1),
+        currency: row.get(2),
+        group_name: row.get(3),
+        kind: parse_db_kind(row.get(4)),
+        commodity: row.get(5),
+        role: parse_db_role(row.get(6)),
+    }
+}
+
+/// Get bank accounts the user account owns or that are shared with it.
+pub fn get_bank_accounts(
+    conn: &mut postgres::Client,
+    account_id: i64,
+) -> Result<Vec<BankAccount>, DbError> {
+    let sql = format!(
+        "
+        select
+            {}
+        from bank_account
+        where
+            {}
+            and bank_account.deleted = false
+        order by bank_account.name, bank_account.id",
+        bank_account_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    match conn.query(sql.as_str(), &[&account_id]) {
+        Ok(rows) => Ok(rows.iter().map(bank_account_from_row).collect()),
+        Err(err) => Err(DbError::from(err)),
+    }
+}
+
+pub fn get_bank_account(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    bank_account_id: i64,
+) -> Result<Option<BankAccount>, DbError> {
+    let sql = format!(
+        "
+        select
+            {}
+        from bank_account
+        where
+            {}
+            and bank_account.id = $2
+            and bank_account.deleted = false",
+        bank_account_columns(),
+        can_read_sql("bank_account", "$1")
+    );
+    let rows = conn.query(sql.as_str(), &[&account_id, &bank_account_id])?;
+    Ok(rows.first().map(bank_account_from_row))
+}
+
+/// Update editable bank account details, only owner can do it.
+/// The group is cleared when `group_name` is `None`.
+pub fn update_bank_account(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    bank_account_id: i64,
+    group_name: Option<&str>,
+    kind: BankAccountKind,
+) -> Result<(), DbError> {
+    let sql = "
+        update bank_account
+        set
+            group_name = $1,
+            kind = $2,
+            modified = current_timestamp
+        where
+            account = $3
+            and id = $4
+            and deleted = false
+        returning id";
+    let mut transaction = conn.transaction()?;
+    let rows = transaction.query(
+        sql,
+        &[&group_name, &kind.as_str(), &account_id, &bank_account_id],
+    )?;
+    for row in &rows {
+        queue_bank_account_event(
+            &mut transaction,
+            row.get(0),
+            WebhookEvent::BankAccountUpdated,
+        )?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Format `as_of` timestamp to be passed as `$n::text::timestamp` param,
+/// `None` means now.
+fn as_of_param(as_of: Option<&chrono::DateTime<chrono::Utc>>) -> String {
+    match as_of {
+        Some(ts) => ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+        None => "infinity".to_string(),
+    }
+}
+
+/// Get bank accounts with their last entry at or before `as_of`,
+/// `None` means now.
+pub fn get_bank_account_infos(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    as_of: Option<&chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<BankAccountInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            bank_account_and_last_entry_id.id,
+            bank_account_and_last_entry_id.name,
+            bank_account_and_last_entry_id.group_name,
+            bank_account_and_last_entry_id.kind,
+            last_entry.amount::text,
+            bank_account_and_last_entry_id.currency,
+            to_char(
+                last_entry.ts,
+                'YYYY-MM-DD HH24:MI:SS.US'
+            ),
+            bank_account_and_last_entry_id.commodity,
+            ({})::text,
+            {}
+        from
+            (
+                select
+                    bank_account.*,
+                    (
+                        select entry.id
+                        from entry
+                        where
+                            entry.bank_account = bank_account.id
+                            and entry.deleted = false
+                            and entry.ts <= $2::text::timestamp
+                        order by entry.ts desc
+                        limit 1
+                    ) as last_entry_id
+                from bank_account
+            ) as bank_account_and_last_entry_id
+            left join entry as last_entry on (
+                last_entry.id = bank_account_and_last_entry_id.last_entry_id
+            )
+        where
+            {}
+            and bank_account_and_last_entry_id.deleted = false
+            and bank_account_and_last_entry_id.last_entry_id is not null
+        order by
+            bank_account_and_last_entry_id.name,
+            bank_account_and_last_entry_id.currency
+    ",
+        value_sql(
+            "bank_account_and_last_entry_id",
+            "last_entry.amount",
+            "$2::text::timestamp"
+        ),
+        role_sql("bank_account_and_last_entry_id", "$1"),
+        can_read_sql("bank_account_and_last_entry_id", "$1")
+    );
+    match conn.query(sql.as_str(), &[&account_id, &as_of_param(as_of)]) {
+        Ok(rows) => Ok(rows
+            .iter()
+            .map(|row| BankAccountInfo {
+                id: row.get(0),
+                bank_account: row.get(1),
+                group_name: row.get(2),
+                kind: parse_db_kind(row.get(3)),
+                amount: row.get(4),
+                currency: row.get(5),
+                ts: parse_db_ts(row.get(6)),
+                commodity: row.get(7),
+                value: row.get(8),
+                role: parse_db_role(row.get(9)),
+            })
+            .collect()),
+        Err(e) => Err(DbError::from(e)),
+    }
+}
+
+pub fn insert_bank_account(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    name: &str,
+    currency: &str,
+    group_name: Option<&str>,
+    kind: BankAccountKind,
+    commodity: Option<&str>,
+) -> Result<(), DbError> {
+    let sql = "insert into bank_account (
+        id,
+        account,
+        name,
+        currency,
+        group_name,
+        kind,
+        commodity,
+        created,
+        modified
+    ) values (
+        nextval('bank_account_seq'),
+        $1,
+        $2,
+        $3,
+        $4,
+        $5,
+        $6,
+        current_timestamp,
+        current_timestamp
+    )
+    returning id";
+    let mut transaction = conn.transaction()?;
+    let row = transaction.query_one(
+        sql,
+        &[
+            &account_id,
+            &name,
+            &currency,
+            &group_name,
+            &kind.as_str(),
+            &commodity,
+        ],
+    )?;
+    queue_bank_account_event(
+        &mut transaction,
+        row.get(0),
+        WebhookEvent::BankAccountCreated,
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// SQL query that selects group, currency, kind, last entry ts and value
+/// of each live bank account user account `$1` can see, as of `$2`
+/// timestamp (see `as_of_param`). Used to sum bank accounts in various ways.
+fn bank_account_value_sql() -> String {
+    format!(
+        "
+            select
+                bank_account.group_name,
+                bank_account.currency,
+                bank_account.kind,
+                last_entry.ts,
+                {} as value
+            from
+                bank_account
+                join entry as last_entry on (
+                    last_entry.id = (
+                        select entry.id
+                        from entry
+                        where
+                            entry.bank_account = bank_account.id
+                            and entry.deleted = false
+                            and entry.ts <= $2::text::timestamp
+                        order by entry.ts desc
+                        limit 1
+                    )
+                )
+            where
+                {}
+                and bank_account.deleted = false",
+        value_sql("bank_account", "last_entry.amount", "$2::text::timestamp"),
+        can_read_sql("bank_account", "$1")
+    )
+}
+
+/// Get totals by currency as of given timestamp, `None` means now.
+pub fn get_currency_info(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    as_of: Option<&chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<CurrencyInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            -- Finally, group those selected values to produce summaries.
+            -- Holdings without price have null value and are skipped.
+            currency,
+            coalesce(sum(case when kind = 'liability' then 0 else value end), 0)::text,
+            coalesce(sum(case when kind = 'liability' then value else 0 end), 0)::text,
+            coalesce(sum(case when kind = 'liability' then -value else value end), 0)::text,
+            to_char(
+                max(ts),
+                'YYYY-MM-DD HH24:MI:SS.US'
+            )
+        from ({}) as bank_account_value
+        group by currency
+        order by currency",
+        bank_account_value_sql()
+    );
+    let rows = conn.query(sql.as_str(), &[&account_id, &as_of_param(as_of)])?;
+    Ok(rows
+        .iter()
+        .map(|row| CurrencyInfo {
+            currency: row.get(0),
+            assets: row.get(1),
+            liabilities: row.get(2),
+            amount: row.get(3),
+            ts: parse_db_ts(row.get(4)),
+        })
+        .collect())
+}
+
+/// Like `get_currency_info`, but summed by bank account group and currency.
+/// Bank accounts without group are skipped.
+pub fn get_group_info(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    as_of: Option<&chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<GroupInfo>, DbError> {
+    let sql = format!(
+        "
+        select
+            group_name,
+            currency,
+            coalesce(sum(case when kind = 'liability' then -value else value end), 0)::text,
+            to_char(
+                max(ts),
+                'YYYY-MM-DD HH24:MI:SS.US'
+            )
+        from ({}) as bank_account_value
+        where group_name is not null
+        group by group_name, currency
+        order by group_name, currency",
+        bank_account_value_sql()
+    );
+    let rows = conn.query(sql.as_str(), &[&account_id, &as_of_param(as_of)])?;
+    Ok(rows
+        .iter()
+        .map(|row| GroupInfo {
+            group_name: row.get(0),
+            currency: row.get(1),
+            amount: row.get(2),
+            ts: parse_db_ts(row.get(3)),
+        })
+        .collect())
+}
+
+/// Get unit prices of commodities, most recent first.
+pub fn get_prices(conn: &mut postgres::Client, account_id: i64) -> Result<Vec<PriceInfo>, DbError> {
+    let sql = "
+        select
+            price.id,
+            price.commodity,
+            price.currency,
+            to_char(
+                price.ts,
+                'YYYY-MM-DD HH24:MI:SS.US'
+            ),
+            price.price::text
+        from price
+        where price.account = $1
+        order by price.commodity, price.currency, price.ts desc";
+    let rows = conn.query(sql, &[&account_id])?;
+    Ok(rows
+        .iter()
+        .map(|row| PriceInfo {
+            id: row.get(0),
+            price: Price {
+                commodity: row.get(1),
+                currency: row.get(2),
+                ts: parse_db_ts(row.get(3)),
+                price: row.get(4),
+            },
+        })
+        .collect())
+}
+
+/// Insert prices in one transaction.
+/// Price of the same commodity, currency and ts is replaced.
+pub fn insert_prices(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    prices: &[Price],
+) -> Result<(), DbError> {
+    let sql = "
+        insert into price (
+            id,
+            account,
+            commodity,
+            currency,
+            ts,
+            price,
+            created,
+            modified
+        ) values (
+            nextval('price_seq'),
+            $1,
+            $2,
+            $3,
+            to_timestamp(
+                $4,
+                'YYYY-MM DD HH24:MI:SS.US'
+            ),
+            $5::text::numeric,
+            current_timestamp,
+            current_timestamp
+        )
+        on conflict (account, commodity, currency, ts)
+        do update set
+            price = $5::text::numeric,
+            modified = current_timestamp";
+    let mut transaction = conn.transaction()?;
+    for price in prices {
+        transaction.execute(
+            sql,
+            &[
+                &account_id,
+                &price.commodity,
+                &price.currency,
+                &price.ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+                &price.price,
+            ],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+pub fn delete_price(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    price_id: i64,
+) -> Result<(), DbError> {
+    conn.execute(
+        "delete from price where account = $1 and id = $2",
+        &[&account_id, &price_id],
+    )?;
+    Ok(())
+}
+
+/// Get user accounts the bank account is shared with, only owner can see
+/// them.
+pub fn get_bank_account_shares(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    bank_account_id: i64,
+) -> Result<Vec<BankAccountShare>, DbError> {
+    let sql = "
+        select
+            bank_account_share.id,
+            (
+                select min(account_email.email)
+                from account_email
+                where account_email.account = bank_account_share.account
+            ),
+            bank_account_share.role
+        from
+            bank_account_share
+            join bank_account on (bank_account.id = bank_account_share.bank_account)
+        where
+            bank_account.account = $1
+            and bank_account.id = $2
+        order by bank_account_share.id";
+    let rows = conn.query(sql, &[&account_id, &bank_account_id])?;
+    Ok(rows
+        .iter()
+        .map(|row| BankAccountShare {
+            id: row.get(0),
+            email: row.get::<_, Option<String>>(1).unwrap_or_default(),
+            role: parse_db_role(row.get(2)).unwrap_or(ShareRole::Viewer),
+        })
+        .collect())
+}
+
+/// Share bank account owned by `account_id` with user account that has
+/// given email, role of existing share is replaced.
+/// Returns false if there's no other user account with this email, or the
+/// bank account isn't one of the user account's.
+pub fn share_bank_account(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    bank_account_id: i64,
+    email: &str,
+    role: ShareRole,
+) -> Result<bool, DbError> {
+    let sql = "
+        insert into bank_account_share (
+            id,
+            bank_account,
+            account,
+            role,
+            created,
+            modified
+        )
+        select
+            nextval('bank_account_share_seq'),
+            bank_account.id,
+            account_email.account,
+            $4,
+            current_timestamp,
+            current_timestamp
+        from
+            bank_account
+            join account_email on (account_email.email = $3)
+        where
+            bank_account.account = $1
+            and bank_account.id = $2
+            and bank_account.deleted = false
+            and account_email.account <> $1
+        on conflict (bank_account, account)
+        do update set
+            role = $4,
+            modified = current_timestamp";
+    let n = conn.execute(
+        sql,
+        &[&account_id, &bank_account_id, &email, &role.as_str()],
+    )?;
+    Ok(n > 0)
+}
+
+/// Stop sharing, only owner of the bank account can do it.
+pub fn delete_bank_account_share(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    share_id: i64,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        delete from bank_account_share
+        where
+            id = $2
+            and bank_account in (select id from bank_account where account = $1)",
+        &[&account_id, &share_id],
+    )?;
+    Ok(())
+}
+
+/// Get everything user account owns, see `backup` module.
+pub fn get_backup(conn: &mut postgres::Client, account_id: i64) -> Result<Backup, DbError> {
+    let mut transaction = conn.transaction()?;
+    let emails: Vec<String> = transaction
+        .query(
+            "select email from account_email where account = $1 order by id",
+            &[&account_id],
+        )?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    let mut bank_accounts: Vec<backup::BackupBankAccount> = Vec::new();
+    let mut bank_account_ids: Vec<i64> = Vec::new();
+    let rows = transaction.query(
+        "
+        select
+            id,
+            name,
+            currency,
+            group_name,
+            kind,
+            commodity,
+            coalesce(deleted, false),
+            to_char(created, 'YYYY-MM-DD HH24:MI:SS.US'),
+            to_char(modified, 'YYYY-MM-DD HH24:MI:SS.US')
+        from bank_account
+        where account = $1
+        order by id",
+        &[&account_id],
+    )?;
+    for row in rows {
+        let bank_account_id: i64 = row.get(0);
+        bank_account_ids.push(bank_account_id);
+        let entries = transaction
+            .query(
+                "
+                select
+                    to_char(ts, 'YYYY-MM-DD HH24:MI:SS.US'),
+                    amount::text,
+                    deleted,
+                    to_char(created, 'YYYY-MM-DD HH24:MI:SS.US'),
+                    to_char(modified, 'YYYY-MM-DD HH24:MI:SS.US'),
+                    note
+                from entry
+                where bank_account = $1
+                order by ts, id",
+                &[&bank_account_id],
+            )?
+            .iter()
+            .map(|row| backup::BackupEntry {
+                ts: row.get(0),
+                amount: row.get(1),
+                deleted: row.get(2),
+                created: row.get(3),
+                modified: row.get(4),
+                note: row.get(5),
+            })
+            .collect();
+        bank_accounts.push(backup::BackupBankAccount {
+            name: row.get(1),
+            currency: row.get(2),
+            group_name: row.get(3),
+            kind: row.get(4),
+            commodity: row.get(5),
+            deleted: row.get(6),
+            created: row.get(7),
+            modified: row.get(8),
+            entries,
+        });
+    }
+    let prices = transaction
+        .query(
+            "
+            select
+                commodity,
+                currency,
+                to_char(ts, 'YYYY-MM-DD HH24:MI:SS.US'),
+                price::text
+            from price
+            where account = $1
+            order by commodity, currency, ts",
+            &[&account_id],
+        )?
+        .iter()
+        .map(|row| backup::BackupPrice {
+            commodity: row.get(0),
+            currency: row.get(1),
+            ts: row.get(2),
+            price: row.get(3),
+        })
+        .collect();
+    let preferences = get_preferences(&mut transaction, account_id)?;
+    transaction.commit()?;
+    Ok(Backup {
+        version: backup::VERSION,
+        exported: chrono::Utc::now()
+            .format("%Y-%m-%d %H:%M:%S%.6f")
+            .to_string(),
+        emails,
+        bank_accounts,
+        prices,
+        preferences: Some(backup::BackupPreferences::new(
+            &preferences,
+            &bank_account_ids,
+        )),
+    })
+}
+
+/// Restore backup into user account in one transaction.
+/// Bank accounts are matched by name and currency, entries by ts and
+/// amount, prices by commodity, currency and ts; matched ones are kept as
+/// they are. Preferences replace the current ones. With `dry_run` the
+/// transaction is rolled back, so only the summary is returned.
+pub fn restore_backup(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    backup: &Backup,
+    dry_run: bool,
+) -> Result<RestoreSummary, DbError> {
+    let mut summary = RestoreSummary::default();
+    let mut transaction = conn.transaction()?;
+    for email in &backup.emails {
+        let existing = !transaction
+            .query(
+                "select 1 from account_email where account = $1 and email = $2",
+                &[&account_id, &email],
+            )?
+            .is_empty();
+        if existing {
+            summary.emails_existing += 1;
+        } else {
+            summary.emails_skipped += 1;
+        }
+    }
+    let mut bank_account_ids: Vec<i64> = Vec::new();
+    for ba in &backup.bank_accounts {
+        let existing: Option<i64> = transaction
+            .query(
+                "
+                select id
+                from bank_account
+                where account = $1 and name = $2 and currency = $3",
+                &[&account_id, &ba.name, &ba.currency],
+            )?
+            .first()
+            .map(|row| row.get(0));
+        let bank_account_id: i64 = match existing {
+            Some(id) => {
+                summary.bank_accounts_existing += 1;
+                id
+            }
+            None => {
+                summary.bank_accounts_added += 1;
+                transaction
+                    .query_one(
+                        "
+                        insert into bank_account (
+                            id,
+                            account,
+                            name,
+                            currency,
+                            group_name,
+                            kind,
+                            commodity,
+                            deleted,
+                            created,
+                            modified
+                        ) values (
+                            nextval('bank_account_seq'),
+                            $1,
+                            $2,
+                            $3,
+                            $4,
+                            $5,
+                            $6,
+                            $7,
+                            $8::text::timestamp,
+                            $9::text::timestamp
+                        )
+                        returning id",
+                        &[
+                            &account_id,
+                            &ba.name,
+                            &ba.currency,
+                            &ba.group_name,
+                            &ba.kind,
+                            &ba.commodity,
+                            &ba.deleted,
+                            &ba.created,
+                            &ba.modified,
+                        ],
+                    )?
+                    .get(0)
+            }
+        };
+        bank_account_ids.push(bank_account_id);
+        for e in &ba.entries {
+            let n = transaction.execute(
+                "
+                insert into entry (
+                    id,
+                    bank_account,
+                    ts,
+                    amount,
+                    deleted,
+                    created,
+                    modified,
+                    note
+                )
+                select
+                    nextval('entry_seq'),
+                    $1,
+                    $2::text::timestamp,
+                    $3::text::numeric,
+                    $4,
+                    $5::text::timestamp,
+                    $6::text::timestamp,
+                    $7
+                where not exists (
+                    select 1
+                    from entry
+                    where
+                        bank_account = $1
+                        and ts = $2::text::timestamp
+                        and amount = $3::text::numeric
+                )",
+                &[
+                    &bank_account_id,
+                    &e.ts,
+                    &e.amount,
+                    &e.deleted,
+                    &e.created,
+                    &e.modified,
+                    &e.note,
+                ],
+            )?;
+            if n > 0 {
+                summary.entries_added += 1;
+            } else {
+                summary.entries_existing += 1;
+            }
+        }
+    }
+    for p in &backup.prices {
+        let n = transaction.execute(
+            "
+            insert into price (
+                id,
+                account,
+                commodity,
+                currency,
+                ts,
+                price,
+                created,
+                modified
+            ) values (
+                nextval('price_seq'),
+                $1,
+                $2,
+                $3,
+                $4::text::timestamp,
+                $5::text::numeric,
+                current_timestamp,
+                current_timestamp
+            )
+            on conflict (account, commodity, currency, ts) do nothing",
+            &[&account_id, &p.commodity, &p.currency, &p.ts, &p.price],
+        )?;
+        if n > 0 {
+            summary.prices_added += 1;
+        } else {
+            summary.prices_existing += 1;
+        }
+    }
+    if let Some(ref preferences) = backup.preferences {
+        set_preferences(
+            &mut transaction,
+            account_id,
+            &preferences.to_preferences(&bank_account_ids),
+        )?;
+        summary.preferences_restored = true;
+    }
+    if dry_run {
+        transaction.rollback()?;
+    } else {
+        transaction.commit()?;
+    }
+    Ok(summary)
+}
+
+pub fn insert_api_token(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    name: &str,
+    token: &str,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        insert into api_token (
+            id,
+            account,
+            name,
+            token,
+            last_used,
+            created,
+            modified
+        ) values (
+            nextval('api_token_seq'),
+            $1,
+            $2,
+            $3,
+            null,
+            current_timestamp,
+            current_timestamp
+        )",
+        &[&account_id, &name, &token],
+    )?;
+    Ok(())
+}
+
+pub fn get_api_tokens(
+    conn: &mut postgres::Client,
+    account_id: i64,
+) -> Result<Vec<ApiToken>, DbError> {
+    let rows = conn.query(
+        "
+        select
+            id,
+            name,
+            to_char(created, 'YYYY-MM-DD HH24:MI:SS.US'),
+            to_char(last_used, 'YYYY-MM-DD HH24:MI:SS.US')
+        from api_token
+        where account = $1
+        order by id",
+        &[&account_id],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| ApiToken {
+            id: row.get(0),
+            name: row.get(1),
+            created: parse_db_ts(row.get(2)),
+            last_used: row.get::<_, Option<&str>>(3).map(parse_db_ts),
+        })
+        .collect())
+}
+
+pub fn delete_api_token(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    token_id: i64,
+) -> Result<(), DbError> {
+    conn.execute(
+        "delete from api_token where account = $1 and id = $2",
+        &[&account_id, &token_id],
+    )?;
+    Ok(())
+}
+
+/// Get account id by API token, and note that the token was used.
+pub fn get_api_token_account(
+    conn: &mut postgres::Client,
+    token: &str,
+) -> Result<Option<i64>, DbError> {
+    let rows = conn.query(
+        "
+        update api_token
+        set last_used = current_timestamp
+        where
+            token = $1
+            and account in (select id from account where disabled = false)
+        returning account",
+        &[&token],
+    )?;
+    Ok(rows.first().map(|row| row.get(0)))
+}
+
+/// Get preferences of user account, defaults if they were never saved.
+pub fn get_preferences(
+    conn: &mut impl postgres::GenericClient,
+    account_id: i64,
+) -> Result<Preferences, DbError> {
+    let rows = conn.query(
+        "
+        select
+            theme,
+            landing_page,
+            default_bank_account,
+            entries_per_page,
+            (select frequency from account_digest where account = $1)
+        from account_preference
+        where account = $1",
+        &[&account_id],
+    )?;
+    let defaults = Preferences::default();
+    Ok(match rows.first() {
+        Some(row) => Preferences {
+            theme: Theme::parse(row.get(0)).unwrap_or(defaults.theme),
+            landing_page: LandingPage::parse(row.get(1)).unwrap_or(defaults.landing_page),
+            default_bank_account: row.get(2),
+            entries_per_page: row.get::<_, i32>(3).into(),
+            digest: row
+                .get::<_, Option<&str>>(4)
+                .and_then(DigestFrequency::parse)
+                .unwrap_or(defaults.digest),
+        },
+        None => defaults,
+    })
+}
+
+pub fn set_preferences(
+    conn: &mut impl postgres::GenericClient,
+    account_id: i64,
+    preferences: &Preferences,
+) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    transaction.execute(
+        "
+        insert into account_preference (
+            account,
+            theme,
+            landing_page,
+            default_bank_account,
+            entries_per_page,
+            created,
+            modified
+        )
+        values ($1, $2, $3, $4, $5::bigint::integer, current_timestamp, current_timestamp)
+        on conflict (account) do update set
+            theme = excluded.theme,
+            landing_page = excluded.landing_page,
+            default_bank_account = excluded.default_bank_account,
+            entries_per_page = excluded.entries_per_page,
+            modified = excluded.modified",
+        &[
+            &account_id,
+            &preferences.theme.as_str(),
+            &preferences.landing_page.as_str(),
+            &preferences.default_bank_account,
+            &preferences.entries_per_page,
+        ],
+    )?;
+    if preferences.digest == DigestFrequency::Off {
+        transaction.execute(
+            "delete from account_digest where account = $1",
+            &[&account_id],
+        )?;
+    } else {
+        // Changing frequency keeps when the last digest was sent.
+        transaction.execute(
+            "
+            insert into account_digest (account, frequency, created, modified)
+            values ($1, $2, current_timestamp, current_timestamp)
+            on conflict (account) do update set
+                frequency = excluded.frequency,
+                modified = excluded.modified",
+            &[&account_id, &preferences.digest.as_str()],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Get digest subscriptions of enabled user accounts.
+pub fn get_digest_subscriptions(
+    conn: &mut postgres::Client,
+) -> Result<Vec<DigestSubscription>, DbError> {
+    let rows = conn.query(
+        "
+        select
+            account_digest.account,
+            account_digest.frequency,
+            to_char(account_digest.sent, 'YYYY-MM-DD HH24:MI:SS.US')
+        from
+            account_digest
+            join account on (account.id = account_digest.account)
+        where account.disabled = false
+        order by account_digest.account",
+        &[],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| DigestSubscription {
+            account_id: row.get(0),
+            frequency: DigestFrequency::parse(row.get(1)).unwrap(),
+            sent: row.get::<_, Option<&str>>(2).map(parse_db_ts),
+        })
+        .collect())
+}
+
+/// Note that digest was sent to user account.
+pub fn set_digest_sent(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    sent: &chrono::DateTime<chrono::Utc>,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        update account_digest
+        set sent = $2::text::timestamp
+        where account = $1",
+        &[
+            &account_id,
+            &sent.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Queue deliveries of `payload` to webhooks of user accounts that can
+/// read the bank account.
+fn queue_webhook_event(
+    conn: &mut impl postgres::GenericClient,
+    bank_account_id: i64,
+    event: WebhookEvent,
+    payload: &str,
+) -> Result<u64, postgres::Error> {
+    conn.execute(
+        "
+        insert into webhook_delivery (
+            id,
+            webhook,
+            event,
+            payload,
+            status,
+            attempts,
+            next_attempt,
+            created,
+            modified
+        )
+        select
+            nextval('webhook_delivery_seq'),
+            webhook.id,
+            $2,
+            $3,
+            'pending',
+            0,
+            current_timestamp,
+            current_timestamp,
+            current_timestamp
+        from webhook
+        where webhook.account in (
+            select account from bank_account where id = $1
+            union
+            select account from bank_account_share where bank_account = $1
+        )",
+        &[&bank_account_id, &event.as_str(), &payload],
+    )
+}
+
+/// Queue `event` of entry, deleted or not, see `queue_webhook_event`.
+fn queue_entry_event(
+    conn: &mut impl postgres::GenericClient,
+    entry_id: i64,
+    event: WebhookEvent,
+) -> Result<(), postgres::Error> {
+    let row = conn.query_one(
+        "
+        select
+            entry.id,
+            bank_account.id,
+            bank_account.name,
+            bank_account.currency,
+            entry.amount::text,
+            to_char(entry.ts, 'YYYY-MM-DD HH24:MI:SS.US'),
+            entry.note
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where entry.id = $1",
+        &[&entry_id],
+    )?;
+    let entry = WebhookEntry {
+        id: row.get(0),
+        bank_account_id: row.get(1),
+        bank_account: row.get(2),
+        currency: row.get(3),
+        amount: row.get(4),
+        ts: row.get(5),
+        note: row.get(6),
+    };
+    queue_webhook_event(
+        conn,
+        entry.bank_account_id,
+        event,
+        &webhook::payload(event, &entry),
+    )?;
+    Ok(())
+}
+
+/// Queue `event` of bank account, see `queue_webhook_event`.
+fn queue_bank_account_event(
+    conn: &mut impl postgres::GenericClient,
+    bank_account_id: i64,
+    event: WebhookEvent,
+) -> Result<(), postgres::Error> {
+    let row = conn.query_one(
+        "
+        select id, name, currency, group_name, kind, commodity
+        from bank_account
+        where id = $1",
+        &[&bank_account_id],
+    )?;
+    let bank_account = WebhookBankAccount {
+        id: row.get(0),
+        name: row.get(1),
+        currency: row.get(2),
+        group_name: row.get(3),
+        kind: row.get(4),
+        commodity: row.get(5),
+    };
+    queue_webhook_event(
+        conn,
+        bank_account_id,
+        event,
+        &webhook::payload(event, &bank_account),
+    )?;
+    Ok(())
+}
+
+pub fn insert_webhook(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    url: &str,
+    secret: &str,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        insert into webhook (
+            id,
+            account,
+            url,
+            secret,
+            created,
+            modified
+        ) values (
+            nextval('webhook_seq'),
+            $1,
+            $2,
+            $3,
+            current_timestamp,
+            current_timestamp
+        )",
+        &[&account_id, &url, &secret],
+    )?;
+    Ok(())
+}
+
+pub fn get_webhooks(conn: &mut postgres::Client, account_id: i64) -> Result<Vec<Webhook>, DbError> {
+    let rows = conn.query(
+        "
+        select id, url, to_char(created, 'YYYY-MM-DD HH24:MI:SS.US')
+        from webhook
+        where account = $1
+        order by id",
+        &[&account_id],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| Webhook {
+            id: row.get(0),
+            url: row.get(1),
+            created: parse_db_ts(row.get(2)),
+        })
+        .collect())
+}
+
+/// Delete webhook of user account with its deliveries.
+pub fn delete_webhook(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    webhook_id: i64,
+) -> Result<(), DbError> {
+    let mut transaction = conn.transaction()?;
+    transaction.execute(
+        "
+        delete from webhook_delivery
+        where webhook in (select id from webhook where account = $1 and id = $2)",
+        &[&account_id, &webhook_id],
+    )?;
+    transaction.execute(
+        "delete from webhook where account = $1 and id = $2",
+        &[&account_id, &webhook_id],
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Columns read by `webhook_delivery_from_row`, for queries that join
+/// `webhook_delivery` with `webhook`.
+const WEBHOOK_DELIVERY_COLUMNS: &str = "
+    webhook_delivery.id,
+    webhook.id,
+    webhook.url,
+    webhook_delivery.event,
+    webhook_delivery.payload,
+    webhook_delivery.status,
+    webhook_delivery.attempts,
+    to_char(webhook_delivery.next_attempt, 'YYYY-MM-DD HH24:MI:SS.US'),
+    to_char(webhook_delivery.last_attempt, 'YYYY-MM-DD HH24:MI:SS.US'),
+    webhook_delivery.response_status,
+    webhook_delivery.error,
+    to_char(webhook_delivery.created, 'YYYY-MM-DD HH24:MI:SS.US'),
+    webhook.secret";
+
+fn webhook_delivery_from_row(row: &postgres::Row) -> WebhookDelivery {
+    WebhookDelivery {
+        id: row.get(0),
+        webhook: row.get(1),
+        url: row.get(2),
+        event: row.get(3),
+        payload: row.get(4),
+        status: DeliveryStatus::parse(row.get(5)).unwrap(),
+        attempts: row.get::<_, i32>(6).into(),
+        next_attempt: row.get::<_, Option<&str>>(7).map(parse_db_ts),
+        last_attempt: row.get::<_, Option<&str>>(8).map(parse_db_ts),
+        response_status: row.get::<_, Option<i32>>(9).map(i64::from),
+        error: row.get(10),
+        created: parse_db_ts(row.get(11)),
+    }
+}
+
+/// Get latest deliveries to webhooks of user account, newest first.
+pub fn get_webhook_deliveries(
+    conn: &mut postgres::Client,
+    account_id: i64,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>, DbError> {
+    let sql = format!(
+        "
+        select {}
+        from
+            webhook_delivery
+            join webhook on (webhook.id = webhook_delivery.webhook)
+        where webhook.account = $1
+        order by webhook_delivery.id desc
+        limit $2",
+        WEBHOOK_DELIVERY_COLUMNS
+    );
+    let rows = conn.query(sql.as_str(), &[&account_id, &limit])?;
+    Ok(rows.iter().map(webhook_delivery_from_row).collect())
+}
+
+/// Get pending deliveries due at `now`, oldest first, with secrets of
+/// their webhooks.
+pub fn get_due_webhook_deliveries(
+    conn: &mut postgres::Client,
+    now: &chrono::DateTime<chrono::Utc>,
+    limit: i64,
+) -> Result<Vec<(WebhookDelivery, String)>, DbError> {
+    let sql = format!(
+        "
+        select {}
+        from
+            webhook_delivery
+            join webhook on (webhook.id = webhook_delivery.webhook)
+        where
+            webhook_delivery.status = 'pending'
+            and webhook_delivery.next_attempt <= $1::text::timestamp
+        order by webhook_delivery.next_attempt, webhook_delivery.id
+        limit $2",
+        WEBHOOK_DELIVERY_COLUMNS
+    );
+    let rows = conn.query(
+        sql.as_str(),
+        &[&now.format("%Y-%m-%d %H:%M:%S%.6f").to_string(), &limit],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| (webhook_delivery_from_row(row), row.get(12)))
+        .collect())
+}
+
+/// Save result of delivery attempt, `next_attempt` is set for retries.
+pub fn record_webhook_attempt(
+    conn: &mut postgres::Client,
+    delivery_id: i64,
+    status: DeliveryStatus,
+    response_status: Option<i64>,
+    error: Option<&str>,
+    next_attempt: Option<&chrono::DateTime<chrono::Utc>>,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        update webhook_delivery
+        set
+            status = $2,
+            attempts = attempts + 1,
+            response_status = $3::bigint::integer,
+            error = $4,
+            next_attempt = $5::text::timestamp,
+            last_attempt = current_timestamp,
+            modified = current_timestamp
+        where id = $1",
+        &[
+            &delivery_id,
+            &status.as_str(),
+            &response_status,
+            &error,
+            &next_attempt.map(|ts| ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string()),
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+//! Email digest of balances, weekly or monthly, for user accounts that
+//! turn it on in settings.
+//!
+//! The worker started by `start_worker` checks every `CHECK_INTERVAL`
+//! which digests are due and sends them to all emails of the user account
+//! as text and HTML alternatives. Digest has net worth by currency, change
+//! of every bank account since the previous digest (or since one period
+//! ago for the first one) and bank accounts not updated for `STALE_DAYS`.
+
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Months;
+use chrono::Utc;
+
+use crate::common;
+use crate::db::DbError;
+use crate::model::BankAccountInfo;
+use crate::model::Config;
+use crate::model::CurrencyInfo;
+use crate::model::DigestFrequency;
+use crate::model::DigestSubscription;
+use crate::page::bank_accounts::bank_account_balances;
+use crate::report;
+use crate::report::DiffRow;
+use crate::storage::Storage;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::util;
+
+/// How often the worker looks for due digests.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Bank accounts without entry for this long are listed as not updated.
+pub const STALE_DAYS: i64 = 30;
+
+/// Everything shown in one digest.
+pub struct Digest {
+    pub frequency: DigestFrequency,
+    /// Changes are counted since then.
+    pub since: DateTime<Utc>,
+    pub currencies: Vec<CurrencyInfo>,
+    /// Changes of bank accounts, holdings without price are left out.
+    pub changes: Vec<DiffRow>,
+    /// Bank accounts not updated for `STALE_DAYS`, oldest first.
+    pub stale: Vec<BankAccountInfo>,
+}
+
+/// Start of the period that ends at `ts`.
+fn period_start(frequency: DigestFrequency, ts: DateTime<Utc>) -> DateTime<Utc> {
+    match frequency {
+        DigestFrequency::Monthly => ts.checked_sub_months(Months::new(1)).unwrap_or(ts),
+        _ => ts - chrono::Duration::days(7),
+    }
+}
+
+/// Whether digest of the subscription should be sent at `now`.
+pub fn is_due(subscription: &DigestSubscription, now: DateTime<Utc>) -> bool {
+    match subscription.sent {
+        Some(sent) => sent <= period_start(subscription.frequency, now),
+        None => true,
+    }
+}
+
+/// Build digest of user account as of `now`.
+pub fn build_digest(
+    conn: &mut dyn Storage,
+    subscription: &DigestSubscription,
+    now: DateTime<Utc>,
+) -> Result<Digest, DbError> {
+    let account_id = subscription.account_id;
+    let since = subscription
+        .sent
+        .unwrap_or_else(|| period_start(subscription.frequency, now));
+    let before = conn.get_bank_account_infos(account_id, Some(&since))?;
+    let mut after = conn.get_bank_account_infos(account_id, None)?;
+    let changes = report::diff_balances(
+        &bank_account_balances(&before),
+        &bank_account_balances(&after),
+    );
+    let stale_before = now - chrono::Duration::days(STALE_DAYS);
+    after.retain(|i| i.ts < stale_before);
+    after.sort_by_key(|i| i.ts);
+    Ok(Digest {
+        frequency: subscription.frequency,
+        since,
+        currencies: conn.get_currency_info(account_id, None)?,
+        changes,
+        stale: after,
+    })
+}
+
+/// Plain text version of the digest.
+pub fn format_text(digest: &Digest, base_url: &str) -> String {
+    let mut text = format!(
+        "CashLog {} digest, changes since {}.\n",
+        digest.frequency.label().to_lowercase(),
+        util::format_ts(digest.since)
+    );
+    text.push_str("\nNet worth by currency:\n");
+    for c in &digest.currencies {
+        text.push_str(&format!("  {} {}\n", c.amount, c.currency));
+    }
+    text.push_str("\nChanges by account:\n");
+    for row in &digest.changes {
+        text.push_str(&format!(
+            "  {}: {} {} ({:+.2})\n",
+            row.name,
+            row.after.map(util::format_amount).unwrap_or_default(),
+            row.currency,
+            row.change
+        ));
+    }
+    if !digest.stale.is_empty() {
+        text.push_str(&format!("\nNot updated for {} days:\n", STALE_DAYS));
+        for i in &digest.stale {
+            text.push_str(&format!(
+                "  {}, last entry {}\n",
+                i.bank_account,
+                util::format_ts(i.ts)
+            ));
+        }
+    }
+    text.push_str(&format!(
+        "\nTurn the digest off in settings: {}/settings\n",
+        base_url
+    ));
+    text
+}
+
+/// Send digest of the subscription, false if the user account has no
+/// entries, it's still marked sent then.
+fn send_digest(
+    pool: &StoragePool,
+    conf: &Config,
+    subscription: &DigestSubscription,
+    now: DateTime<Utc>,
+) -> Result<bool, DbError> {
+    let account_id = subscription.account_id;
+    let (digest, emails) = {
+        let mut conn = pool.get()?;
+        let digest = build_digest(&mut *conn, subscription, now)?;
+        (digest, conn.get_user_account_emails(account_id)?)
+    };
+    // Connection isn't held while sending emails.
+    let has_entries = !digest.currencies.is_empty();
+    if has_entries {
+        let subject = format!("CashLog {} Digest", digest.frequency.label());
+        let text = format_text(&digest, &conf.base_url);
+        let html = tmpl::digest::tmpl_digest(&digest, &conf.base_url).into_string();
+        for email in emails.iter() {
+            // Failure is logged, the next digest will cover the period.
+            let _ = common::send_html_email(email, &subject, &text, &html, conf.use_email);
+        }
+    }
+    pool.get()?.set_digest_sent(account_id, &now)?;
+    Ok(has_entries)
+}
+
+/// Send due digests, returns how many were sent. Digest isn't sent to user
+/// accounts without entries, but it's still marked sent. Errors of one
+/// user account are logged and its digest is tried again on next check.
+pub fn send_due(pool: &StoragePool, conf: &Config) -> Result<usize, DbError> {
+    let now = Utc::now();
+    let subscriptions = pool.get()?.get_digest_subscriptions()?;
+    let mut sent = 0;
+    for subscription in subscriptions.iter().filter(|s| is_due(s, now)) {
+        match send_digest(pool, conf, subscription, now) {
+            Ok(true) => sent += 1,
+            Ok(false) => (),
+            Err(e) => error!(
+                "Failed to send email digest of user account {}: {}.",
+                subscription.account_id, e
+            ),
+        }
+    }
+    Ok(sent)
+}
+
+/// Start thread that sends due digests until the process exits.
+pub fn start_worker(pool: StoragePool, conf: Config) {
+    std::thread::spawn(move || loop {
+        match send_due(&pool, &conf) {
+            Ok(0) => (),
+            Ok(n) => info!("Sent {} email digests.", n),
+            Err(e) => error!("Failed to send email digests: {}.", e),
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
@@ -0,0 +1,293 @@
+//! Balance projection.
+//!
+//! Fits a trend to balance history and extends it into the future. This is
+//! pure computation on points, so it does not touch the database.
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Months;
+use chrono::Utc;
+
+use crate::report::Snapshot;
+
+/// Average month length in days, used for the implied monthly change.
+const DAYS_PER_MONTH: f64 = 365.2425 / 12.0;
+
+/// Shape of the trend.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Model {
+    /// Value changes by the same amount every day.
+    Linear,
+    /// Value changes by the same ratio every day, e.g. compound interest.
+    Exponential,
+}
+
+impl Model {
+    pub fn parse(s: &str) -> Option<Model> {
+        match s {
+            "linear" => Some(Model::Linear),
+            "exp" => Some(Model::Exponential),
+            _ => None,
+        }
+    }
+}
+
+/// When the trend reaches a goal.
+#[derive(Debug, PartialEq)]
+pub enum GoalDate {
+    /// The trend is already past the goal at the last fitted point.
+    Reached,
+    At(DateTime<Utc>),
+    /// The trend goes the other way, or gets there too far in the future.
+    Never,
+}
+
+/// Fitted trend.
+/// For linear model value is `a + b * t`, for exponential it's
+/// `a * exp(b * t)`, where `t` is days since `t0`.
+pub struct Fit {
+    pub model: Model,
+    pub a: f64,
+    pub b: f64,
+    pub t0: DateTime<Utc>,
+    /// Ts of the last fitted point, projections start there.
+    pub last: DateTime<Utc>,
+}
+
+fn days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 / (24.0 * 3600.0 * 1000.0)
+}
+
+/// Least squares fit of `y = a + b * x`.
+fn least_squares(xy: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = xy.len() as f64;
+    let mean_x = xy.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = xy.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = xy.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = xy.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    if sxx == 0.0 {
+        // All points at the same ts, there's no trend.
+        return None;
+    }
+    let b = sxy / sxx;
+    Some((mean_y - b * mean_x, b))
+}
+
+/// Fit trend to points ordered by ts.
+/// Returns `None` when there's not enough data: less than two distinct
+/// timestamps, or non-positive values for exponential model.
+pub fn fit(points: &[Snapshot], model: Model) -> Option<Fit> {
+    let t0 = points.first()?.0;
+    let last = points.last()?.0;
+    let xy: Vec<(f64, f64)> = points
+        .iter()
+        .map(|&(ts, v)| (days_between(t0, ts), v))
+        .collect();
+    match model {
+        Model::Linear => {
+            let (a, b) = least_squares(&xy)?;
+            Some(Fit {
+                model,
+                a,
+                b,
+                t0,
+                last,
+            })
+        }
+        Model::Exponential => {
+            if xy.iter().any(|p| p.1 <= 0.0) {
+                return None;
+            }
+            let log_xy: Vec<(f64, f64)> = xy.iter().map(|&(x, y)| (x, y.ln())).collect();
+            let (ln_a, b) = least_squares(&log_xy)?;
+            Some(Fit {
+                model,
+                a: ln_a.exp(),
+                b,
+                t0,
+                last,
+            })
+        }
+    }
+}
+
+impl Fit {
+    pub fn value_at(&self, ts: DateTime<Utc>) -> f64 {
+        let t = days_between(self.t0, ts);
+        match self.model {
+            Model::Linear => self.a + self.b * t,
+            Model::Exponential => self.a * (self.b * t).exp(),
+        }
+    }
+
+    /// Change over one average month following the last fitted point.
+    pub fn monthly_change(&self) -> f64 {
+        let month_ms = (DAYS_PER_MONTH * 24.0 * 3600.0 * 1000.0) as i64;
+        self.value_at(self.last + Duration::milliseconds(month_ms)) - self.value_at(self.last)
+    }
+
+    /// Monthly growth in percent, only meaningful for exponential model.
+    pub fn monthly_rate_pct(&self) -> Option<f64> {
+        match self.model {
+            Model::Linear => None,
+            Model::Exponential => Some(((self.b * DAYS_PER_MONTH).exp() - 1.0) * 100.0),
+        }
+    }
+
+    /// When the trend reaches `goal`, at or after the last fitted point.
+    /// Goal is reached already if the trend at the last point is past it in
+    /// the direction the trend goes, or at it for a flat trend.
+    pub fn goal_date(&self, goal: f64) -> GoalDate {
+        let current = self.value_at(self.last);
+        let reached = if self.b > 0.0 {
+            current >= goal
+        } else if self.b < 0.0 {
+            current <= goal
+        } else {
+            current == goal
+        };
+        if reached {
+            return GoalDate::Reached;
+        }
+        let t = match self.model {
+            Model::Linear if self.b != 0.0 => (goal - self.a) / self.b,
+            Model::Exponential if self.b != 0.0 && goal > 0.0 => (goal / self.a).ln() / self.b,
+            _ => return GoalDate::Never,
+        };
+        if !t.is_finite() || t < days_between(self.t0, self.last) {
+            return GoalDate::Never;
+        }
+        let ms = t * 24.0 * 3600.0 * 1000.0;
+        if ms > Duration::days(100 * 366).num_milliseconds() as f64 {
+            // Far beyond anything useful, and beyond what DateTime can hold.
+            return GoalDate::Never;
+        }
+        GoalDate::At(self.t0 + Duration::milliseconds(ms as i64))
+    }
+
+    /// Points of the trend from the last fitted point, one per month,
+    /// `horizon` months ahead.
+    pub fn project(&self, horizon: u32) -> Vec<Snapshot> {
+        (0..=horizon)
+            .filter_map(|m| self.last.checked_add_months(Months::new(m)))
+            .map(|ts| (ts, self.value_at(ts)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        crate::util::parse_ts(s).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    /// 100 on Jan 1st, 200 ten days later.
+    fn points() -> Vec<Snapshot> {
+        vec![
+            (ts("2024-01-01 00:00:00"), 100.0),
+            (ts("2024-01-11 00:00:00"), 200.0),
+        ]
+    }
+
+    #[test]
+    fn fit_linear() {
+        let fit = fit(&points(), Model::Linear).unwrap();
+        assert!(close(fit.a, 100.0));
+        assert!(close(fit.b, 10.0));
+        assert_eq!(fit.last, ts("2024-01-11 00:00:00"));
+        assert!(close(fit.value_at(ts("2024-01-21 00:00:00")), 300.0));
+        assert!(close(fit.monthly_change(), 10.0 * DAYS_PER_MONTH));
+        assert_eq!(fit.monthly_rate_pct(), None);
+    }
+
+    #[test]
+    fn fit_exponential() {
+        let fit = fit(&points(), Model::Exponential).unwrap();
+        assert!(close(fit.a, 100.0));
+        assert!(close(fit.b, 2f64.ln() / 10.0));
+        assert!(close(fit.value_at(ts("2024-01-21 00:00:00")), 400.0));
+        let rate = 2f64.powf(DAYS_PER_MONTH / 10.0);
+        assert!(close(fit.monthly_change(), 200.0 * (rate - 1.0)));
+        assert!(close(fit.monthly_rate_pct().unwrap(), (rate - 1.0) * 100.0));
+    }
+
+    #[test]
+    fn fit_not_enough_data() {
+        assert!(fit(&[], Model::Linear).is_none());
+        let t = ts("2024-01-01 00:00:00");
+        assert!(fit(&[(t, 100.0)], Model::Linear).is_none());
+        assert!(fit(&[(t, 100.0), (t, 200.0)], Model::Linear).is_none());
+        let mut zero = points();
+        zero[0].1 = 0.0;
+        assert!(fit(&zero, Model::Exponential).is_none());
+        zero[0].1 = -10.0;
+        assert!(fit(&zero, Model::Exponential).is_none());
+        assert!(fit(&zero, Model::Linear).is_some());
+    }
+
+    #[test]
+    fn goal_dates() {
+        let linear = fit(&points(), Model::Linear).unwrap();
+        assert_eq!(
+            linear.goal_date(300.0),
+            GoalDate::At(ts("2024-01-21 00:00:00"))
+        );
+        // Balance is already past the goal.
+        assert_eq!(linear.goal_date(150.0), GoalDate::Reached);
+        assert_eq!(linear.goal_date(200.0), GoalDate::Reached);
+        let exp = fit(&points(), Model::Exponential).unwrap();
+        assert_eq!(
+            exp.goal_date(400.0),
+            GoalDate::At(ts("2024-01-21 00:00:00"))
+        );
+        assert_eq!(exp.goal_date(-5.0), GoalDate::Reached);
+        // Falling trend, e.g. paying off a loan, reaches lower goals.
+        let falling = vec![
+            (ts("2024-01-01 00:00:00"), 5000.0),
+            (ts("2024-01-11 00:00:00"), 4000.0),
+        ];
+        let loan = fit(&falling, Model::Linear).unwrap();
+        assert_eq!(loan.goal_date(4500.0), GoalDate::Reached);
+        assert_eq!(
+            loan.goal_date(3000.0),
+            GoalDate::At(ts("2024-01-21 00:00:00"))
+        );
+        // Exponential decay never gets to zero.
+        let decay = fit(&falling, Model::Exponential).unwrap();
+        assert_eq!(decay.goal_date(0.0), GoalDate::Never);
+        // Flat trend reaches only what it's at.
+        let flat = vec![
+            (ts("2024-01-01 00:00:00"), 100.0),
+            (ts("2024-01-11 00:00:00"), 100.0),
+        ];
+        let flat = fit(&flat, Model::Linear).unwrap();
+        assert_eq!(flat.goal_date(100.0), GoalDate::Reached);
+        assert_eq!(flat.goal_date(200.0), GoalDate::Never);
+        // Too far to be useful.
+        assert_eq!(linear.goal_date(1e12), GoalDate::Never);
+    }
+
+    #[test]
+    fn project_months() {
+        let fit = fit(&points(), Model::Linear).unwrap();
+        let projected = fit.project(2);
+        let dates: Vec<DateTime<Utc>> = projected.iter().map(|p| p.0).collect();
+        assert_eq!(
+            dates,
+            vec![
+                ts("2024-01-11 00:00:00"),
+                ts("2024-02-11 00:00:00"),
+                ts("2024-03-11 00:00:00")
+            ]
+        );
+        assert!(close(projected[0].1, 200.0));
+        assert!(close(projected[1].1, 200.0 + 31.0 * 10.0));
+        assert_eq!(fit.project(0).len(), 1);
+    }
+}
@@ -0,0 +1,42 @@
+//! CashLog, shared by the web app and the command line client.
+
+// #![feature(plugin)]
+// #![plugin(maud_macros)]
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate actix_rt;
+extern crate env_logger;
+extern crate lettre;
+extern crate maud;
+extern crate postgres;
+extern crate r2d2;
+extern crate r2d2_postgres;
+extern crate toml;
+extern crate url;
+extern crate uuid;
+
+pub mod admin;
+pub mod api;
+pub mod backup;
+pub mod common;
+pub mod currency;
+pub mod db;
+pub mod digest;
+pub mod forecast;
+pub mod logging;
+pub mod login_limit;
+pub mod model;
+pub mod oidc;
+pub mod page;
+pub mod registration;
+pub mod report;
+pub mod storage;
+pub mod tmpl;
+pub mod totp;
+pub mod util;
+pub mod webhook;
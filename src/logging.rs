@@ -9,6 +9,12 @@ use log::LevelFilter;
 /// Does not return error, instead just panics on error,
 /// since logging is pretty essential.
 pub fn env_logger_init() {
+    env_logger_init_with_level(LevelFilter::Debug)
+}
+
+/// Like `env_logger_init`, but with given default level, e.g. command line
+/// client only wants warnings.
+pub fn env_logger_init_with_level(level: LevelFilter) {
     let format = |buf: &mut env_logger::fmt::Formatter, record: &log::Record| {
         let ts = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S");
         writeln!(
@@ -21,7 +27,7 @@ pub fn env_logger_init() {
         )
     };
     let mut builder = Builder::new();
-    builder.format(format).filter(None, LevelFilter::Debug);
+    builder.format(format).filter(None, level);
     if env::var("RUST_LOG").is_ok() {
         builder.parse_env(&env::var("RUST_LOG").unwrap());
     }
@@ -0,0 +1,133 @@
+//! Rate limits of login emails, by email and by client IP address.
+//!
+//! Storage checks them in the same transaction that records the login
+//! request, see `Storage::insert_login_request`, so concurrent logins
+//! can't get past them.
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use crate::model::LoginRequest;
+
+/// Minimum time between login emails to the same address.
+const EMAIL_COOLDOWN_SECS: i64 = 60;
+
+/// Login emails per address per hour.
+const MAX_REQUESTS_PER_EMAIL: usize = 5;
+
+/// Login emails per IP address per hour.
+const MAX_REQUESTS_PER_IP: usize = 20;
+
+/// Email as login requests are recorded and counted, so that case
+/// variants of one address share its limit.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Requests since then count towards the limits.
+pub fn window_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    now - Duration::hours(1)
+}
+
+/// When the next login email can be sent, `None` if it can be sent now.
+/// `requests` are the ones since `window_start`, oldest first, `email` is
+/// normalized by `normalize_email`.
+pub fn retry_at(
+    requests: &[LoginRequest],
+    email: &str,
+    ip: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let hour = Duration::hours(1);
+    let by_email: Vec<&LoginRequest> = requests.iter().filter(|r| r.email == email).collect();
+    let by_ip: Vec<&LoginRequest> = requests
+        .iter()
+        .filter(|r| ip.is_some() && r.ip.as_deref() == ip)
+        .collect();
+    let mut retry = Vec::new();
+    if let Some(last) = by_email.last() {
+        retry.push(last.created + Duration::seconds(EMAIL_COOLDOWN_SECS));
+    }
+    if by_email.len() >= MAX_REQUESTS_PER_EMAIL {
+        retry.push(by_email[by_email.len() - MAX_REQUESTS_PER_EMAIL].created + hour);
+    }
+    if by_ip.len() >= MAX_REQUESTS_PER_IP {
+        retry.push(by_ip[by_ip.len() - MAX_REQUESTS_PER_IP].created + hour);
+    }
+    retry.into_iter().max().filter(|t| *t > now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(email: &str, ip: Option<&str>, created: DateTime<Utc>) -> LoginRequest {
+        LoginRequest {
+            email: email.to_string(),
+            ip: ip.map(str::to_string),
+            created,
+        }
+    }
+
+    #[test]
+    fn emails() {
+        assert_eq!(
+            normalize_email(" Jane.Doe@Example.COM "),
+            "jane.doe@example.com"
+        );
+    }
+
+    #[test]
+    fn email_cooldown() {
+        let now = Utc::now();
+        let email = "jane@example.com";
+        assert_eq!(retry_at(&[], email, Some("10.0.0.1"), now), None);
+        let requests = [request(email, None, now - Duration::seconds(10))];
+        assert_eq!(
+            retry_at(&requests, email, None, now),
+            Some(now + Duration::seconds(50))
+        );
+        assert_eq!(retry_at(&requests, "john@example.com", None, now), None);
+        let requests = [request(email, None, now - Duration::seconds(60))];
+        assert_eq!(retry_at(&requests, email, None, now), None);
+    }
+
+    #[test]
+    fn email_limit() {
+        let now = Utc::now();
+        let email = "jane@example.com";
+        let requests: Vec<LoginRequest> = (0..MAX_REQUESTS_PER_EMAIL as i64)
+            .map(|i| request(email, None, now - Duration::minutes(50 - i * 10)))
+            .collect();
+        // Oldest of the last five leaves the window in 10 minutes.
+        assert_eq!(
+            retry_at(&requests, email, None, now),
+            Some(now + Duration::minutes(10))
+        );
+        assert_eq!(retry_at(&requests[1..], email, None, now), None);
+    }
+
+    #[test]
+    fn ip_limit() {
+        let now = Utc::now();
+        let ip = Some("10.0.0.1");
+        let requests: Vec<LoginRequest> = (0..MAX_REQUESTS_PER_IP as i64)
+            .map(|i| {
+                let email = format!("user{}@example.com", i);
+                request(&email, ip, now - Duration::minutes(30))
+            })
+            .collect();
+        assert_eq!(
+            retry_at(&requests, "jane@example.com", ip, now),
+            Some(now + Duration::minutes(30))
+        );
+        assert_eq!(
+            retry_at(&requests, "jane@example.com", Some("10.0.0.2"), now),
+            None
+        );
+        // Requests without address don't count together.
+        assert_eq!(retry_at(&requests, "jane@example.com", None, now), None);
+        assert_eq!(retry_at(&requests[1..], "jane@example.com", ip, now), None);
+    }
+}
@@ -1,74 +1,18 @@
-// #![feature(plugin)]
-// #![plugin(maud_macros)]
-
+extern crate cashlog;
 #[macro_use]
 extern crate log;
-#[macro_use]
-extern crate serde_derive;
-#[macro_use]
-extern crate serde_json;
-extern crate actix_rt;
-extern crate env_logger;
-extern crate lettre;
-extern crate maud;
-extern crate postgres;
-extern crate r2d2;
-extern crate r2d2_postgres;
-extern crate toml;
-extern crate url;
-extern crate uuid;
-
-use std::io::Read;
 
 use actix_web::App;
 use actix_web::HttpResponse;
 use actix_web::HttpServer;
 
-mod common;
-mod db;
-mod logging;
-mod model;
-mod page;
-mod tmpl;
-mod util;
-
-/// Load config or exit.
-fn load_config_or_exit() -> model::Config {
-    let config_filename = "cashlog.toml";
-    let mut toml_source = String::new();
-    {
-        let mut f = match std::fs::File::open(config_filename) {
-            Ok(f) => f,
-            Err(e) => {
-                error!("Failed to open config file {}: {}.", config_filename, e);
-                debug!(
-                    "Current directory: {}.",
-                    std::env::current_dir()
-                        .map(|d| d.to_str().unwrap_or("<unknown>").to_string())
-                        .unwrap_or("<unknown>".to_string())
-                );
-                std::process::exit(1);
-            }
-        };
-        if let Err(e) = f.read_to_string(&mut toml_source) {
-            error!("Failed to read config file: {}.", e);
-            std::process::exit(1);
-        }
-    }
-    // This inline struct is only so that I can have top level "config" key
-    // in toml config file, without having to go through Value object.
-    #[derive(Debug, Deserialize)]
-    struct ConfigWrapper {
-        config: model::Config,
-    }
-    match toml::from_str::<ConfigWrapper>(&toml_source) {
-        Err(decode_error) => {
-            error!("Decode error while parsing config: {}.", decode_error);
-            std::process::exit(1);
-        }
-        Ok(conf) => conf.config,
-    }
-}
+use cashlog::admin;
+use cashlog::common;
+use cashlog::digest;
+use cashlog::logging;
+use cashlog::page;
+use cashlog::storage;
+use cashlog::webhook;
 
 async fn handle_favicon() -> HttpResponse {
     let response_body = include_bytes!("../bank.png");
@@ -79,17 +23,34 @@ async fn handle_favicon() -> HttpResponse {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("admin") {
+        logging::env_logger_init_with_level(log::LevelFilter::Warn);
+        let conf = common::load_config_or_exit("cashlog.toml");
+        if let Err(e) = admin::run(&conf, &args[1..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     logging::env_logger_init();
-    let conf = load_config_or_exit();
+    let conf = common::load_config_or_exit("cashlog.toml");
     debug!("Config loaded:\n{:?}", conf);
     let addr = format!("{}:{}", "localhost", conf.port.unwrap());
-    let pool = common::create_database_pool(
-        &conf.db_host,
-        conf.db_port,
-        &conf.db_name,
-        &conf.db_username,
-        &conf.db_password,
-    );
+    let pool = match storage::open_pool(&conf) {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Failed to open storage: {}.", e);
+            std::process::exit(1);
+        }
+    };
+    // Database calls run on actix blocking thread pool, one thread per
+    // connection lets all of them be used at once.
+    if std::env::var_os("ACTIX_THREADPOOL").is_none() {
+        std::env::set_var("ACTIX_THREADPOOL", pool.max_size().to_string());
+    }
+    webhook::start_worker(pool.clone(), conf.clone());
+    digest::start_worker(pool.clone(), conf.clone());
     HttpServer::new(move || {
         use actix_web::web::get;
         use actix_web::web::post;
@@ -98,6 +59,7 @@ async fn main() -> std::io::Result<()> {
             .data(pool.clone())
             .data(conf.clone())
             .route("/", get().to(page::main::handle_main))
+            .route("/entries", get().to(page::main::handle_entries))
             .route("/favicon.ico", get().to(handle_favicon))
             .route("/about", get().to(page::about::handle_about))
             .route("/profile", get().to(page::profile::handle_profile))
@@ -115,8 +77,38 @@ async fn main() -> std::io::Result<()> {
                 "/add-bank-account",
                 post().to(page::add_bank_account::handle_post_add_bank_account),
             )
+            .route(
+                "/edit-bank-account",
+                get().to(page::edit_bank_account::handle_get_edit_bank_account),
+            )
+            .route(
+                "/edit-bank-account",
+                post().to(page::edit_bank_account::handle_post_edit_bank_account),
+            )
+            .route(
+                "/share-bank-account",
+                post().to(page::edit_bank_account::handle_post_share_bank_account),
+            )
+            .route(
+                "/unshare-bank-account",
+                get().to(page::edit_bank_account::handle_unshare_bank_account),
+            )
+            .route("/check-in", get().to(page::check_in::handle_check_in))
+            .route("/check-in", post().to(page::check_in::handle_post_check_in))
             .route("/currency", get().to(page::currency::handle_currency))
             .route("/delete", get().to(page::delete::handle_delete))
+            .route(
+                "/delete-account",
+                post().to(page::delete_account::handle_post_delete_account),
+            )
+            .route(
+                "/delete-account/{token}",
+                get().to(page::delete_account::handle_get_delete_account_with_token),
+            )
+            .route(
+                "/delete-account/{token}",
+                post().to(page::delete_account::handle_post_delete_account_with_token),
+            )
             .route("/edit", get().to(page::edit::handle_edit))
             .route("/edit", post().to(page::edit::handle_post_edit))
             .route(
@@ -131,6 +123,15 @@ async fn main() -> std::io::Result<()> {
                 "/new-session/{token}",
                 get().to(page::new_session::handle_get_new_session_with_token),
             )
+            .route(
+                "/new-session-code",
+                post().to(page::new_session::handle_post_new_session_code),
+            )
+            .route("/oidc/login", get().to(page::oidc::handle_oidc_login))
+            .route(
+                "/oidc/callback",
+                get().to(page::oidc::handle_oidc_callback),
+            )
             .route(
                 "/logout",
                 actix_web::web::get().to(page::logout::handle_get_logout),
@@ -140,6 +141,76 @@ async fn main() -> std::io::Result<()> {
                 actix_web::web::post().to(page::logout::handle_post_logout),
             )
             .route("/graph", get().to(page::graph::handle_graph))
+            .route("/prices", get().to(page::prices::handle_prices))
+            .route("/prices", post().to(page::prices::handle_post_price))
+            .route(
+                "/prices/import",
+                post().to(page::prices::handle_post_import_prices),
+            )
+            .route(
+                "/prices/delete",
+                get().to(page::prices::handle_delete_price),
+            )
+            .route("/quick/{id}", get().to(page::quick::handle_quick))
+            .route("/quick/{id}", post().to(page::quick::handle_post_quick))
+            .route("/report", get().to(page::report::handle_report))
+            .route("/settings", get().to(page::settings::handle_settings))
+            .route("/settings", post().to(page::settings::handle_post_settings))
+            .route(
+                "/report/{filename}",
+                get().to(page::report::handle_report_file),
+            )
+            .route("/backup", get().to(page::backup::handle_backup))
+            // Restore form carries the whole backup, way over default limit.
+            .service(
+                actix_web::web::resource("/backup/restore")
+                    .app_data(
+                        actix_web::web::FormConfig::default().limit(page::backup::MAX_RESTORE_SIZE),
+                    )
+                    .route(post().to(page::backup::handle_post_restore)),
+            )
+            .route(
+                "/backup/{filename}",
+                get().to(page::backup::handle_backup_file),
+            )
+            .route("/api/accounts", get().to(page::api::handle_api_accounts))
+            .route("/api/entries", get().to(page::api::handle_api_entries))
+            .route("/api/entries", post().to(page::api::handle_api_post_entry))
+            .route("/api/backup", get().to(page::api::handle_api_backup))
+            .service(
+                actix_web::web::resource("/api/restore")
+                    .app_data(
+                        actix_web::web::PayloadConfig::default()
+                            .limit(page::backup::MAX_RESTORE_SIZE),
+                    )
+                    .route(post().to(page::api::handle_api_restore)),
+            )
+            .route(
+                "/api-tokens",
+                post().to(page::api_tokens::handle_post_api_token),
+            )
+            .route(
+                "/api-tokens/revoke",
+                get().to(page::api_tokens::handle_revoke_api_token),
+            )
+            .route("/totp/setup", post().to(page::totp::handle_post_totp_setup))
+            .route(
+                "/totp/enable",
+                post().to(page::totp::handle_post_totp_enable),
+            )
+            .route(
+                "/totp/disable",
+                post().to(page::totp::handle_post_totp_disable),
+            )
+            .route("/webhooks", post().to(page::webhooks::handle_post_webhook))
+            .route(
+                "/webhooks/delete",
+                get().to(page::webhooks::handle_delete_webhook),
+            )
+            .route(
+                "/webhooks/deliveries",
+                get().to(page::webhooks::handle_webhook_deliveries),
+            )
             .route(
                 "/export",
                 actix_web::web::get().to(page::export::handle_export),
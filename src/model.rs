@@ -1,23 +1,281 @@
 use chrono::DateTime;
 use chrono::Utc;
 
+/// Asset bank account holds money, liability (credit card, loan) owes it.
+/// Liability amounts are positive and are subtracted in totals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BankAccountKind {
+    Asset,
+    Liability,
+}
+
+impl BankAccountKind {
+    pub fn parse(s: &str) -> Option<BankAccountKind> {
+        match s {
+            "asset" => Some(BankAccountKind::Asset),
+            "liability" => Some(BankAccountKind::Liability),
+            _ => None,
+        }
+    }
+
+    /// Name as stored in database and used in forms and exports.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BankAccountKind::Asset => "asset",
+            BankAccountKind::Liability => "liability",
+        }
+    }
+
+    /// Multiplier of the amount when adding it to totals.
+    pub fn sign(self) -> f64 {
+        match self {
+            BankAccountKind::Asset => 1.0,
+            BankAccountKind::Liability => -1.0,
+        }
+    }
+}
+
+/// Role of user account in a bank account shared with it by the owner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShareRole {
+    /// Sees the bank account and its entries.
+    Viewer,
+    /// Also adds, edits and deletes entries.
+    Editor,
+}
+
+impl ShareRole {
+    pub fn parse(s: &str) -> Option<ShareRole> {
+        match s {
+            "viewer" => Some(ShareRole::Viewer),
+            "editor" => Some(ShareRole::Editor),
+            _ => None,
+        }
+    }
+
+    /// Name as stored in database and used in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShareRole::Viewer => "viewer",
+            ShareRole::Editor => "editor",
+        }
+    }
+
+    /// Whether user account with given role, `None` for owner, can change
+    /// entries.
+    pub fn can_edit(role: Option<ShareRole>) -> bool {
+        role != Some(ShareRole::Viewer)
+    }
+}
+
+/// Colors of the pages, see `tmpl::common`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Theme {
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Light, Theme::Dark, Theme::HighContrast];
+
+    pub fn parse(s: &str) -> Option<Theme> {
+        Theme::ALL.iter().copied().find(|t| t.as_str() == s)
+    }
+
+    /// Name as stored in database and used in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+}
+
+/// Page shown at `/`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LandingPage {
+    Entries,
+    Accounts,
+    Currency,
+    CheckIn,
+    Report,
+}
+
+impl LandingPage {
+    pub const ALL: [LandingPage; 5] = [
+        LandingPage::Entries,
+        LandingPage::Accounts,
+        LandingPage::Currency,
+        LandingPage::CheckIn,
+        LandingPage::Report,
+    ];
+
+    pub fn parse(s: &str) -> Option<LandingPage> {
+        LandingPage::ALL.iter().copied().find(|p| p.as_str() == s)
+    }
+
+    /// Name as stored in database and used in forms, it's also the path
+    /// of the page.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LandingPage::Entries => "entries",
+            LandingPage::Accounts => "accounts",
+            LandingPage::Currency => "currency",
+            LandingPage::CheckIn => "check-in",
+            LandingPage::Report => "report",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LandingPage::Entries => "Entries",
+            LandingPage::Accounts => "Accounts",
+            LandingPage::Currency => "Currency",
+            LandingPage::CheckIn => "Check-in",
+            LandingPage::Report => "Report",
+        }
+    }
+}
+
+/// How often email digest of balances is sent, see `digest` module.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestFrequency {
+    Off,
+    Weekly,
+    Monthly,
+}
+
+impl DigestFrequency {
+    pub const ALL: [DigestFrequency; 3] = [
+        DigestFrequency::Off,
+        DigestFrequency::Weekly,
+        DigestFrequency::Monthly,
+    ];
+
+    pub fn parse(s: &str) -> Option<DigestFrequency> {
+        DigestFrequency::ALL
+            .iter()
+            .copied()
+            .find(|f| f.as_str() == s)
+    }
+
+    /// Name as stored in database and used in forms, off isn't stored.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DigestFrequency::Off => "off",
+            DigestFrequency::Weekly => "weekly",
+            DigestFrequency::Monthly => "monthly",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DigestFrequency::Off => "Off",
+            DigestFrequency::Weekly => "Weekly",
+            DigestFrequency::Monthly => "Monthly",
+        }
+    }
+}
+
+/// Settings of user account, read once per request and passed to
+/// templates. Defaults are used until the user saves settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preferences {
+    pub theme: Theme,
+    pub landing_page: LandingPage,
+    /// Bank account selected on the add page.
+    pub default_bank_account: Option<i64>,
+    pub entries_per_page: i64,
+    pub digest: DigestFrequency,
+}
+
+/// Most entries per page, also the limit when they weren't paged.
+pub const MAX_ENTRIES_PER_PAGE: i64 = 1024;
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            theme: Theme::Light,
+            landing_page: LandingPage::Entries,
+            default_bank_account: None,
+            entries_per_page: 100,
+            digest: DigestFrequency::Off,
+        }
+    }
+}
+
 /// Just a Bank Account.
 pub struct BankAccount {
     pub id: i64,
     pub name: String,
     pub currency: String,
+    pub group_name: Option<String>,
+    pub kind: BankAccountKind,
+    /// Set for holdings, then entry amounts are quantities of commodity.
+    pub commodity: Option<String>,
+    /// Role of current user account if the bank account is shared with it,
+    /// `None` for owner.
+    pub role: Option<ShareRole>,
+}
+
+/// Same as `bank_account.name` column size.
+pub const MAX_BANK_ACCOUNT_NAME_LENGTH: usize = 32;
+
+/// Same as `bank_account.group_name` column size.
+pub const MAX_GROUP_NAME_LENGTH: usize = 32;
+
+/// Same as `bank_account.commodity` and `price.commodity` column size.
+pub const MAX_COMMODITY_LENGTH: usize = 32;
+
+/// Bank account shared with another user account.
+pub struct BankAccountShare {
+    pub id: i64,
+    /// Email of the user account the bank account is shared with.
+    pub email: String,
+    pub role: ShareRole,
 }
 
 /// Bank Account with some other joins.
 pub struct BankAccountInfo {
+    pub id: i64,
     pub bank_account: String,
+    pub group_name: Option<String>,
+    pub kind: BankAccountKind,
     pub amount: String,
     pub currency: String,
     pub ts: DateTime<Utc>,
+    pub commodity: Option<String>,
+    /// Amount in currency, for holdings it's quantity times latest price,
+    /// `None` if there's no price.
+    pub value: Option<String>,
+    /// See `BankAccount::role`.
+    pub role: Option<ShareRole>,
 }
 
 /// Details by currency.
+/// The `amount` is the net worth, that is `assets` minus `liabilities`.
 pub struct CurrencyInfo {
+    pub currency: String,
+    pub assets: String,
+    pub liabilities: String,
+    pub amount: String,
+    pub ts: DateTime<Utc>,
+}
+
+/// Details by bank account group and currency, liabilities are subtracted
+/// from the amount.
+pub struct GroupInfo {
+    pub group_name: String,
     pub currency: String,
     pub amount: String,
     pub ts: DateTime<Utc>,
@@ -26,10 +284,38 @@ pub struct CurrencyInfo {
 /// Bank account balance log entry.
 pub struct EntryInfo {
     pub amount: String,
+    /// Bank account name, for display. Shared bank accounts can have the
+    /// same name and currency as own ones, so match them by id.
     pub bank_account: String,
+    pub bank_account_id: i64,
     pub currency: String,
     pub id: i64,
     pub ts: DateTime<Utc>,
+    pub kind: BankAccountKind,
+    pub commodity: Option<String>,
+    /// Amount in currency, for holdings it's quantity times price at
+    /// entry's ts, `None` if there's no price.
+    pub value: Option<String>,
+    /// See `BankAccount::role`.
+    pub role: Option<ShareRole>,
+    /// Free-text note, at most `MAX_NOTE_LENGTH` characters.
+    pub note: Option<String>,
+}
+
+pub const MAX_NOTE_LENGTH: usize = 1024;
+
+/// Unit price of commodity in currency.
+pub struct Price {
+    pub commodity: String,
+    pub currency: String,
+    pub ts: DateTime<Utc>,
+    pub price: String,
+}
+
+/// Stored price.
+pub struct PriceInfo {
+    pub id: i64,
+    pub price: Price,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -37,11 +323,224 @@ pub struct Config {
     pub base_url: String,
     pub use_email: bool,
     pub port: Option<i32>,
+    /// Either "postgres" (the default) or "sqlite", see `storage::open_pool`.
+    pub storage: Option<String>,
+    /// SQLite database file, used with `storage = "sqlite"`.
+    pub sqlite_path: Option<String>,
+    #[serde(default)]
     pub db_host: String,
+    #[serde(default)]
     pub db_port: u16,
+    #[serde(default)]
     pub db_name: String,
+    #[serde(default)]
     pub db_username: String,
+    #[serde(default)]
     pub db_password: String,
+    /// Maximum number of database connections, 100 for PostgreSQL and 8
+    /// for SQLite by default. It's also the number of threads running
+    /// database queries, unless `ACTIX_THREADPOOL` env var is set.
+    pub db_pool_size: Option<u32>,
+    /// Seconds to wait for a free database connection, 30 by default.
+    pub db_connection_timeout: Option<u64>,
+    /// Seconds after which database connection is closed, 60 by default.
+    pub db_max_lifetime: Option<u64>,
+    /// Seconds unused database connection is kept open, 10 by default.
+    pub db_idle_timeout: Option<u64>,
+    /// Take client IP address from `Forwarded` or `X-Forwarded-For` header,
+    /// set it when running behind a reverse proxy. Used for rate limiting.
+    #[serde(default)]
+    pub behind_proxy: bool,
+    /// OpenID Connect login next to login links, see `oidc` module.
+    pub oidc: Option<OidcConfig>,
+    /// Who can create user accounts by logging in, see `registration`
+    /// module.
+    #[serde(default)]
+    pub registration: RegistrationMode,
+    /// Email domains, e.g. "example.com", for `registration = "domains"`.
+    #[serde(default)]
+    pub registration_domains: Vec<String>,
+    /// Hosts webhooks can call though they resolve to loopback or private
+    /// addresses, see `webhook::check_url`.
+    #[serde(default)]
+    pub webhook_allowed_hosts: Vec<String>,
+}
+
+/// How new user accounts can be created. Existing accounts can always log
+/// in, and `cashlog admin create-user` works in any mode.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationMode {
+    /// Any email.
+    #[default]
+    Open,
+    /// Emails at `registration_domains`.
+    Domains,
+    /// Emails with invite code, see `cashlog admin create-invite`.
+    Invite,
+    /// No new accounts.
+    Closed,
+}
+
+/// Invite code for `registration = "invite"`.
+pub struct Invite {
+    pub code: String,
+    pub note: Option<String>,
+    pub created: DateTime<Utc>,
+    /// When user account was created with the code, codes work once.
+    pub used: Option<DateTime<Utc>>,
+}
+
+/// OpenID Connect provider, the `[config.oidc]` table.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcConfig {
+    /// Provider endpoints are discovered from
+    /// `{issuer}/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Redirect URL registered with the provider, `{base_url}/oidc/callback`
+    /// by default.
+    pub redirect_url: Option<String>,
+    /// Provider name on the login page, e.g. "Company SSO".
+    pub name: Option<String>,
+}
+
+impl OidcConfig {
+    pub fn redirect_url(&self, base_url: &str) -> String {
+        match &self.redirect_url {
+            Some(url) => url.clone(),
+            None => format!("{}/oidc/callback", base_url.trim_end_matches('/')),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("OpenID Connect")
+    }
+}
+
+/// Personal token for the JSON API, the token itself is only shown once
+/// when created.
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub created: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// TOTP second factor of user account, see `totp` module.
+pub struct Totp {
+    pub secret: String,
+    /// False while enrolling, until the first code is confirmed.
+    pub enabled: bool,
+    /// Time step of the last accepted code.
+    pub last_step: Option<i64>,
+    /// Number of unused recovery codes.
+    pub recovery_codes: i64,
+}
+
+/// Login form submission, used for rate limiting.
+pub struct LoginRequest {
+    pub email: String,
+    pub ip: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+/// Change that webhooks are called for, see `webhook` module.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WebhookEvent {
+    EntryCreated,
+    EntryUpdated,
+    EntryDeleted,
+    BankAccountCreated,
+    BankAccountUpdated,
+}
+
+impl WebhookEvent {
+    /// Name as stored in database and sent in payload.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::EntryCreated => "entry.created",
+            WebhookEvent::EntryUpdated => "entry.updated",
+            WebhookEvent::EntryDeleted => "entry.deleted",
+            WebhookEvent::BankAccountCreated => "bank_account.created",
+            WebhookEvent::BankAccountUpdated => "bank_account.updated",
+        }
+    }
+}
+
+/// Endpoint of user account that gets signed JSON payloads, the secret
+/// is only shown once when created.
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeliveryStatus {
+    /// Not sent yet, or failed and will be retried.
+    Pending,
+    Delivered,
+    /// Out of attempts.
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn parse(s: &str) -> Option<DeliveryStatus> {
+        match s {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// Name as stored in database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Webhook call in the outbox, with the result of its last attempt.
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook: i64,
+    pub url: String,
+    /// See `WebhookEvent::as_str`.
+    pub event: String,
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    /// Set while pending.
+    pub next_attempt: Option<DateTime<Utc>>,
+    pub last_attempt: Option<DateTime<Utc>>,
+    /// HTTP status of the last response, if there was one.
+    pub response_status: Option<i64>,
+    pub error: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+/// User account that gets email digest.
+pub struct DigestSubscription {
+    pub account_id: i64,
+    pub frequency: DigestFrequency,
+    /// When the last digest was sent, `None` before the first one.
+    pub sent: Option<DateTime<Utc>>,
+}
+
+/// User account as listed by `cashlog admin list-users`.
+pub struct AccountSummary {
+    pub id: i64,
+    pub emails: Vec<String>,
+    pub created: DateTime<Utc>,
+    pub disabled: bool,
+    pub bank_accounts: i64,
+    pub entries: i64,
 }
 
 /// Details about user account,
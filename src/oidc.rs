@@ -0,0 +1,337 @@
+//! OpenID Connect login, authorization code flow with PKCE.
+//!
+//! `page::oidc` redirects the browser to the provider with random state,
+//! nonce and PKCE challenge, kept in session table under the state until
+//! the provider redirects back. The callback exchanges the code for ID
+//! token at the token endpoint. The token comes straight from the provider
+//! over TLS, so its claims are checked but not its signature, as OpenID
+//! Connect Core 3.1.3.7 allows.
+//!
+//! The identity is linked to user account by email, so the provider has to
+//! say the email is verified.
+//!
+//! To try it without a real provider, run the mock one:
+//!
+//! ```text
+//! cargo run --example mock_oidc -- 14081
+//! ```
+//!
+//! and set `issuer = "http://localhost:14081"` with any client id and
+//! secret in `[config.oidc]`.
+
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::model::OidcConfig;
+
+/// Timeout of requests to the provider.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accepted clock difference with the provider, in seconds.
+const CLOCK_SKEW_SECS: i64 = 60;
+
+/// Provider endpoints, from discovery document.
+#[derive(Deserialize)]
+pub struct Provider {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: String,
+}
+
+/// ID token claims we check or use.
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    /// Either a string or an array of strings.
+    aud: serde_json::Value,
+    exp: i64,
+    nonce: Option<String>,
+    email: Option<String>,
+    /// Some providers send it as string.
+    email_verified: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    email: Option<String>,
+    email_verified: Option<serde_json::Value>,
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(TIMEOUT).build()
+}
+
+/// Random URL-safe string, for state, nonce and PKCE verifier.
+pub fn random_token() -> String {
+    let mut buf = [0u8; 32];
+    getrandom::getrandom(&mut buf).expect("Error getting random bytes");
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+/// PKCE S256 challenge of the verifier.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn same_issuer(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+/// Get provider endpoints. This blocks, call it from `web::block`.
+pub fn discover(conf: &OidcConfig) -> Result<Provider, String> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        conf.issuer.trim_end_matches('/')
+    );
+    let provider = agent()
+        .get(&url)
+        .call()
+        .map_err(|e| format!("Discovery failed: {}", e))?
+        .into_json()
+        .map_err(|e| format!("Invalid discovery document: {}", e))?;
+    check_provider(&provider, conf)?;
+    Ok(provider)
+}
+
+/// Discovery document has to be of the configured issuer, ID tokens are
+/// checked against its issuer.
+fn check_provider(provider: &Provider, conf: &OidcConfig) -> Result<(), String> {
+    if !same_issuer(&provider.issuer, &conf.issuer) {
+        return Err(format!(
+            "Discovery document of unexpected issuer {}",
+            provider.issuer
+        ));
+    }
+    Ok(())
+}
+
+/// URL of the provider login page.
+pub fn authorization_url(
+    provider: &Provider,
+    conf: &OidcConfig,
+    redirect_url: &str,
+    state: &str,
+    nonce: &str,
+    verifier: &str,
+) -> Result<String, String> {
+    let mut url = url::Url::parse(&provider.authorization_endpoint)
+        .map_err(|e| format!("Invalid authorization endpoint: {}", e))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &conf.client_id)
+        .append_pair("redirect_uri", redirect_url)
+        .append_pair("scope", "openid email")
+        .append_pair("state", state)
+        .append_pair("nonce", nonce)
+        .append_pair("code_challenge", &pkce_challenge(verifier))
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.to_string())
+}
+
+fn is_true(v: &Option<serde_json::Value>) -> bool {
+    match v {
+        Some(serde_json::Value::Bool(b)) => *b,
+        Some(serde_json::Value::String(s)) => s == "true",
+        _ => false,
+    }
+}
+
+/// Decode ID token payload, the signature is not checked, see module docs.
+fn decode_claims(id_token: &str) -> Result<Claims, String> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| "ID token is not a JWT".to_string())?;
+    let json = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|e| format!("Invalid ID token encoding: {}", e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Invalid ID token claims: {}", e))
+}
+
+fn check_claims(
+    claims: &Claims,
+    provider: &Provider,
+    conf: &OidcConfig,
+    nonce: &str,
+) -> Result<(), String> {
+    if !same_issuer(&claims.iss, &provider.issuer) {
+        return Err(format!("ID token from unexpected issuer {}", claims.iss));
+    }
+    let audience_ok = match &claims.aud {
+        serde_json::Value::String(aud) => *aud == conf.client_id,
+        serde_json::Value::Array(auds) => auds.iter().any(|a| a.as_str() == Some(&conf.client_id)),
+        _ => false,
+    };
+    if !audience_ok {
+        return Err("ID token is for another client".to_string());
+    }
+    if claims.exp + CLOCK_SKEW_SECS < Utc::now().timestamp() {
+        return Err("ID token expired".to_string());
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err("ID token nonce doesn't match".to_string());
+    }
+    Ok(())
+}
+
+/// Exchange authorization code for the verified email of the user. This
+/// blocks, call it from `web::block`.
+pub fn verified_email(
+    provider: &Provider,
+    conf: &OidcConfig,
+    redirect_url: &str,
+    code: &str,
+    verifier: &str,
+    nonce: &str,
+) -> Result<String, String> {
+    let agent = agent();
+    let token: TokenResponse = agent
+        .post(&provider.token_endpoint)
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_url),
+            ("client_id", &conf.client_id),
+            ("client_secret", &conf.client_secret),
+            ("code_verifier", verifier),
+        ])
+        .map_err(|e| format!("Token request failed: {}", e))?
+        .into_json()
+        .map_err(|e| format!("Invalid token response: {}", e))?;
+    let claims = decode_claims(&token.id_token)?;
+    check_claims(&claims, provider, conf, nonce)?;
+    let (email, verified) = match (&claims.email, &provider.userinfo_endpoint) {
+        (Some(email), _) => (email.clone(), is_true(&claims.email_verified)),
+        (None, Some(userinfo_endpoint)) => {
+            let info: UserInfo = agent
+                .get(userinfo_endpoint)
+                .set("Authorization", &format!("Bearer {}", token.access_token))
+                .call()
+                .map_err(|e| format!("User info request failed: {}", e))?
+                .into_json()
+                .map_err(|e| format!("Invalid user info: {}", e))?;
+            let verified = is_true(&info.email_verified);
+            (info.email.unwrap_or_default(), verified)
+        }
+        (None, None) => (String::new(), false),
+    };
+    if email.is_empty() {
+        return Err("Provider didn't return email".to_string());
+    }
+    if !verified {
+        return Err(format!("Email {} is not verified by provider", email));
+    }
+    Ok(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(issuer: &str) -> Provider {
+        Provider {
+            issuer: issuer.to_string(),
+            authorization_endpoint: format!("{}/authorize", issuer),
+            token_endpoint: format!("{}/token", issuer),
+            userinfo_endpoint: None,
+        }
+    }
+
+    fn conf() -> OidcConfig {
+        OidcConfig {
+            issuer: "https://sso.example.com/".to_string(),
+            client_id: "cashlog".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: None,
+            name: None,
+        }
+    }
+
+    fn claims() -> Claims {
+        Claims {
+            iss: "https://sso.example.com".to_string(),
+            aud: serde_json::json!("cashlog"),
+            exp: Utc::now().timestamp() + 300,
+            nonce: Some("nonce".to_string()),
+            email: Some("jane@example.com".to_string()),
+            email_verified: Some(serde_json::json!(true)),
+        }
+    }
+
+    #[test]
+    fn providers() {
+        assert!(check_provider(&provider("https://sso.example.com"), &conf()).is_ok());
+        assert!(check_provider(&provider("https://evil.example.com"), &conf()).is_err());
+    }
+
+    #[test]
+    fn valid_claims() {
+        let provider = provider("https://sso.example.com/");
+        assert!(check_claims(&claims(), &provider, &conf(), "nonce").is_ok());
+        let claims = Claims {
+            aud: serde_json::json!(["other", "cashlog"]),
+            exp: Utc::now().timestamp() - CLOCK_SKEW_SECS / 2,
+            ..claims()
+        };
+        assert!(check_claims(&claims, &provider, &conf(), "nonce").is_ok());
+    }
+
+    #[test]
+    fn invalid_claims() {
+        let provider = provider("https://sso.example.com");
+        let check = |claims: Claims| check_claims(&claims, &provider, &conf(), "nonce");
+        let iss = "https://evil.example.com".to_string();
+        assert!(check(Claims { iss, ..claims() }).is_err());
+        assert!(check(Claims {
+            aud: serde_json::json!("other"),
+            ..claims()
+        })
+        .is_err());
+        assert!(check(Claims {
+            aud: serde_json::json!(["other"]),
+            ..claims()
+        })
+        .is_err());
+        assert!(check(Claims {
+            aud: serde_json::json!(null),
+            ..claims()
+        })
+        .is_err());
+        assert!(check(Claims {
+            exp: Utc::now().timestamp() - CLOCK_SKEW_SECS - 1,
+            ..claims()
+        })
+        .is_err());
+        assert!(check(Claims {
+            nonce: Some("other".to_string()),
+            ..claims()
+        })
+        .is_err());
+        assert!(check(Claims {
+            nonce: None,
+            ..claims()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn email_verified() {
+        assert!(is_true(&Some(serde_json::json!(true))));
+        assert!(is_true(&Some(serde_json::json!("true"))));
+        assert!(!is_true(&Some(serde_json::json!(false))));
+        assert!(!is_true(&Some(serde_json::json!("false"))));
+        assert!(!is_true(&None));
+    }
+}
@@ -1,7 +1,18 @@
+use actix_web::HttpMessage;
+
+use crate::model::Preferences;
+use crate::storage::StoragePool;
 use crate::tmpl;
 
-pub async fn handle_about() -> impl actix_web::Responder {
-    let content = tmpl::about::tmpl_about().into_string();
+pub async fn handle_about(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let prefs = match request.cookie("session") {
+        Some(c) => pool.get_sess_preferences(c.value()).await,
+        None => Preferences::default(),
+    };
+    let content = tmpl::about::tmpl_about(&prefs).into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(content)
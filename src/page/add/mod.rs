@@ -1,8 +1,10 @@
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
+use crate::model::BankAccount;
+use crate::model::ShareRole;
+use crate::storage::StoragePool;
 use crate::tmpl::add::tmpl_add;
+use crate::util::parse_note;
 use crate::util::parse_ts;
 
 /// POST params to add an entry.
@@ -11,21 +13,36 @@ pub struct AddPostParams {
     pub ts: String,
     pub amount: String,
     pub bank_account: i64,
+    pub note: Option<String>,
 }
 
 pub async fn handle_add(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let sess_cookie = request.cookie("session").unwrap();
     let sess_key = sess_cookie.value();
-    let acc_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let acc_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    let bank_accounts = db::get_bank_accounts(&mut conn, acc_id).unwrap();
-    let resp_html = tmpl_add("Add", &bank_accounts).into_string();
+    // Entries can't be added to bank accounts shared read only.
+    let (prefs, bank_accounts) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_bank_accounts(acc_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let bank_accounts: Vec<BankAccount> = bank_accounts
+        .into_iter()
+        .filter(|ba| ShareRole::can_edit(ba.role))
+        .collect();
+    let resp_html = tmpl_add(&prefs, "Add", &bank_accounts).into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(resp_html)
@@ -33,25 +50,30 @@ pub async fn handle_add(
 
 pub async fn handle_post_add(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     params: actix_web::web::Form<AddPostParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let sess_cookie = request.cookie("session").unwrap();
     let sess_key = sess_cookie.value();
-    let acc_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let acc_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    db::insert_entry(
-        &mut conn,
-        &acc_id,
-        &params.bank_account,
-        &parse_ts(&params.ts).unwrap(),
-        &params.amount,
-    )
+    let bank_account = params.bank_account;
+    let ts = parse_ts(&params.ts).unwrap();
+    let amount = params.amount.clone();
+    let note = match parse_note(params.note.as_deref()) {
+        Ok(note) => note,
+        Err(e) => return actix_web::HttpResponse::BadRequest().body(e),
+    };
+    pool.run(move |conn| {
+        conn.insert_entry(&acc_id, &bank_account, &ts, &amount, note.as_deref())
+    })
+    .await
     .unwrap();
     actix_web::HttpResponse::SeeOther()
-        .header("Location", ".")
+        .header("Location", "entries")
         .body("Redirecting...")
 }
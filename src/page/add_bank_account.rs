@@ -1,7 +1,12 @@
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
+use crate::currency;
+use crate::model::BankAccountKind;
+use crate::model::Preferences;
+use crate::model::MAX_BANK_ACCOUNT_NAME_LENGTH;
+use crate::model::MAX_COMMODITY_LENGTH;
+use crate::model::MAX_GROUP_NAME_LENGTH;
+use crate::storage::StoragePool;
 use crate::tmpl;
 use crate::tmpl::add_bank_account::AddBankAccountTmplData;
 
@@ -9,36 +14,90 @@ use crate::tmpl::add_bank_account::AddBankAccountTmplData;
 pub struct AddBankAccountParams {
     pub name: String,
     pub currency: String,
+    pub group_name: Option<String>,
+    pub kind: Option<String>,
+    pub commodity: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct AddBankAccountParamsValidationResult {
     pub name_err: Option<String>,
     pub currency_err: Option<String>,
+    pub group_err: Option<String>,
+    pub commodity_err: Option<String>,
 }
 
-// /// Take the raw form values data and return validation errors, if any.
-// fn validate(params: AddBankAccountParams) -> AddBankAccountParamsValidationResult {
-//     let mut v = AddBankAccountParamsValidationResult {
-//         name_err: None,
-//         currency_err: None,
-//     };
-//     if params.name.trim().is_empty() {
-//         v.name_err = Some("Name is required".to_string());
-//     }
-//     if params.currency.trim().is_empty() {
-//         v.currency_err = Some("Currency is required".to_string());
-//     }
-//     v
-// }
+impl AddBankAccountParamsValidationResult {
+    fn is_ok(&self) -> bool {
+        self.name_err.is_none()
+            && self.currency_err.is_none()
+            && self.group_err.is_none()
+            && self.commodity_err.is_none()
+    }
+}
+
+/// Take the raw form values data and return validation errors, if any.
+fn validate(params: &AddBankAccountParams) -> AddBankAccountParamsValidationResult {
+    let mut v = AddBankAccountParamsValidationResult {
+        name_err: None,
+        currency_err: None,
+        group_err: None,
+        commodity_err: None,
+    };
+    let name = params.name.trim();
+    if name.is_empty() {
+        v.name_err = Some("Name is required".to_string());
+    } else if name.chars().count() > MAX_BANK_ACCOUNT_NAME_LENGTH {
+        v.name_err = Some(format!(
+            "Name is longer than {} characters",
+            MAX_BANK_ACCOUNT_NAME_LENGTH
+        ));
+    }
+    let currency = params.currency.trim();
+    if currency.is_empty() {
+        v.currency_err = Some("Currency is required".to_string());
+    } else if currency::find(currency).is_none() {
+        v.currency_err = Some(format!("Unknown currency {}", currency));
+    }
+    let group_name = params.group_name.as_deref().unwrap_or("").trim();
+    if group_name.chars().count() > MAX_GROUP_NAME_LENGTH {
+        v.group_err = Some(format!(
+            "Group is longer than {} characters",
+            MAX_GROUP_NAME_LENGTH
+        ));
+    }
+    let commodity = params.commodity.as_deref().unwrap_or("").trim();
+    if commodity.chars().count() > MAX_COMMODITY_LENGTH {
+        v.commodity_err = Some(format!(
+            "Commodity is longer than {} characters",
+            MAX_COMMODITY_LENGTH
+        ));
+    }
+    v
+}
 
-pub async fn handle_get_add_bank_account() -> impl actix_web::Responder {
-    let content = tmpl::add_bank_account::add_bank_account(&AddBankAccountTmplData {
-        name: "".into(),
-        name_err: "".into(),
-        curr: "".into(),
-        curr_err: "".into(),
-    })
+pub async fn handle_get_add_bank_account(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let prefs = match request.cookie("session") {
+        Some(c) => pool.get_sess_preferences(c.value()).await,
+        None => Preferences::default(),
+    };
+    let content = tmpl::add_bank_account::add_bank_account(
+        &prefs,
+        &AddBankAccountTmplData {
+            name: "".into(),
+            name_err: "".into(),
+            curr: "".into(),
+            curr_err: "".into(),
+            group_name: "".into(),
+            group_err: "".into(),
+            kind: BankAccountKind::Asset,
+            commodity: "".into(),
+            commodity_err: "".into(),
+        },
+    )
     .into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
@@ -47,10 +106,9 @@ pub async fn handle_get_add_bank_account() -> impl actix_web::Responder {
 
 pub async fn handle_post_add_bank_account(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     params: actix_web::web::Form<AddBankAccountParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = match request.cookie("session") {
         Some(c) => c,
         None => {
@@ -60,10 +118,88 @@ pub async fn handle_post_add_bank_account(
         }
     };
     let sess_key = cookie.value();
-    let account_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let account_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    db::insert_bank_account(&mut conn, account_id, &params.name, &params.currency).unwrap();
-    actix_web::HttpResponse::SeeOther().header("Location", ".").body("Redirecting...")
+    let group_name = params
+        .group_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .map(String::from);
+    let kind = params
+        .kind
+        .as_deref()
+        .and_then(BankAccountKind::parse)
+        .unwrap_or(BankAccountKind::Asset);
+    let commodity = params
+        .commodity
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from);
+    let mut v = validate(&params);
+    let name = params.name.trim().to_string();
+    let currency = params.currency.trim().to_uppercase();
+    if v.is_ok() {
+        let (n, c) = (name.clone(), currency.clone());
+        let bank_accounts = match pool
+            .run(move |conn| conn.get_bank_accounts(account_id))
+            .await
+        {
+            Ok(bank_accounts) => bank_accounts,
+            Err(_) => {
+                return actix_web::HttpResponse::InternalServerError()
+                    .body("Failed to query bank accounts")
+            }
+        };
+        if bank_accounts
+            .iter()
+            .any(|b| b.role.is_none() && b.name == n && b.currency == c)
+        {
+            v.name_err = Some(format!("Bank account {} in {} already exists", n, c));
+        }
+    }
+    if !v.is_ok() {
+        let prefs = pool.get_sess_preferences(sess_key).await;
+        let content = tmpl::add_bank_account::add_bank_account(
+            &prefs,
+            &AddBankAccountTmplData {
+                name: params.name.clone(),
+                name_err: v.name_err.unwrap_or_default(),
+                curr: currency,
+                curr_err: v.currency_err.unwrap_or_default(),
+                group_name: group_name.unwrap_or_default(),
+                group_err: v.group_err.unwrap_or_default(),
+                kind,
+                commodity: commodity.unwrap_or_default(),
+                commodity_err: v.commodity_err.unwrap_or_default(),
+            },
+        )
+        .into_string();
+        return actix_web::HttpResponse::Ok()
+            .content_type("text/html")
+            .body(content);
+    }
+    let inserted = pool
+        .run(move |conn| {
+            conn.insert_bank_account(
+                account_id,
+                &name,
+                &currency,
+                group_name.as_deref(),
+                kind,
+                commodity.as_deref(),
+            )
+        })
+        .await;
+    if inserted.is_err() {
+        return actix_web::HttpResponse::InternalServerError().body("Failed to add bank account");
+    }
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", ".")
+        .body("Redirecting...")
 }
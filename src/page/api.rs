@@ -0,0 +1,156 @@
+//! JSON API for scripts and `cashlog-cli`, see `api` module.
+use actix_web::web::Data;
+
+use crate::api;
+use crate::api::ApiEntry;
+use crate::api::ApiNewEntry;
+use crate::backup;
+use crate::model::ShareRole;
+use crate::storage::StoragePool;
+use crate::util;
+
+#[derive(Deserialize)]
+pub struct EntriesParams {
+    /// Date or timestamp, only entries at or after it are returned.
+    pub since: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreParams {
+    pub dry_run: Option<bool>,
+}
+
+/// Get account id from `Authorization: Bearer <token>` header.
+async fn api_account(pool: &StoragePool, request: &actix_web::HttpRequest) -> Option<i64> {
+    let header = request.headers().get("Authorization")?.to_str().ok()?;
+    let token = header.strip_prefix("Bearer ")?.trim().to_string();
+    pool.run(move |conn| conn.get_api_token_account(&token))
+        .await
+        .unwrap()
+}
+
+fn unauthorized() -> actix_web::HttpResponse {
+    actix_web::HttpResponse::Unauthorized().json(json!({"error": "Invalid API token"}))
+}
+
+fn bad_request(error: &str) -> actix_web::HttpResponse {
+    actix_web::HttpResponse::BadRequest().json(json!({ "error": error }))
+}
+
+pub async fn handle_api_accounts(
+    request: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let account_id = match api_account(&pool, &request).await {
+        Some(account_id) => account_id,
+        None => return unauthorized(),
+    };
+    let bank_accounts = pool
+        .run(move |conn| api::get_bank_accounts(conn, account_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::Ok().json(bank_accounts)
+}
+
+pub async fn handle_api_entries(
+    request: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+    params: actix_web::web::Query<EntriesParams>,
+) -> impl actix_web::Responder {
+    let account_id = match api_account(&pool, &request).await {
+        Some(account_id) => account_id,
+        None => return unauthorized(),
+    };
+    let since = match params.since.as_deref().map(util::parse_since) {
+        Some(Ok(since)) => Some(since),
+        Some(Err(e)) => return bad_request(&e),
+        None => None,
+    };
+    let entries: Vec<ApiEntry> = pool
+        .run(move |conn| conn.get_all_entries(account_id))
+        .await
+        .unwrap()
+        .iter()
+        .filter(|e| since.is_none_or(|since| e.ts >= since))
+        .map(ApiEntry::new)
+        .collect();
+    actix_web::HttpResponse::Ok().json(entries)
+}
+
+pub async fn handle_api_post_entry(
+    request: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+    entry: actix_web::web::Json<ApiNewEntry>,
+) -> impl actix_web::Responder {
+    let account_id = match api_account(&pool, &request).await {
+        Some(account_id) => account_id,
+        None => return unauthorized(),
+    };
+    let ts = match entry.ts.as_deref().map(api::parse_api_ts) {
+        Some(Ok(ts)) => ts,
+        Some(Err(e)) => return bad_request(&e),
+        None => chrono::Utc::now(),
+    };
+    if entry.amount.trim().parse::<f64>().is_err() {
+        return bad_request(&format!("Invalid amount: {}", entry.amount));
+    }
+    let bank_account_id = entry.bank_account;
+    match pool
+        .run(move |conn| conn.get_bank_account(account_id, bank_account_id))
+        .await
+        .unwrap()
+    {
+        Some(bank_account) if ShareRole::can_edit(bank_account.role) => (),
+        Some(_) => return bad_request("Bank account is shared read only"),
+        None => return bad_request("No such bank account"),
+    }
+    let note = match util::parse_note(entry.note.as_deref()) {
+        Ok(note) => note,
+        Err(e) => return bad_request(&e),
+    };
+    let amount = entry.amount.trim().to_string();
+    pool.run(move |conn| {
+        conn.insert_entry(&account_id, &bank_account_id, &ts, &amount, note.as_deref())
+    })
+    .await
+    .unwrap();
+    actix_web::HttpResponse::Created().json(json!({}))
+}
+
+pub async fn handle_api_backup(
+    request: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let account_id = match api_account(&pool, &request).await {
+        Some(account_id) => account_id,
+        None => return unauthorized(),
+    };
+    let backup = pool
+        .run(move |conn| conn.get_backup(account_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::Ok().json(backup)
+}
+
+/// Restore backup sent as request body, see `db::restore_backup`.
+pub async fn handle_api_restore(
+    request: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+    params: actix_web::web::Query<RestoreParams>,
+    body: String,
+) -> impl actix_web::Responder {
+    let account_id = match api_account(&pool, &request).await {
+        Some(account_id) => account_id,
+        None => return unauthorized(),
+    };
+    let backup = match backup::parse(&body) {
+        Ok(backup) => backup,
+        Err(e) => return bad_request(&e),
+    };
+    let dry_run = params.dry_run.unwrap_or(false);
+    let summary = pool
+        .run(move |conn| conn.restore_backup(account_id, &backup, dry_run))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::Ok().json(summary)
+}
@@ -0,0 +1,82 @@
+//! Personal tokens for the JSON API, managed on profile page.
+use actix_web::HttpMessage;
+
+use crate::storage::StoragePool;
+use crate::tmpl;
+
+#[derive(Deserialize)]
+pub struct CreateApiTokenParams {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeApiTokenParams {
+    pub id: i64,
+}
+
+/// Create token and show it, this is the only time it's shown.
+pub async fn handle_post_api_token(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<CreateApiTokenParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let name = params.name.trim();
+    if name.is_empty() {
+        return actix_web::HttpResponse::BadRequest().body("Name is required");
+    }
+    let token: String = uuid::Uuid::new_v4().to_string();
+    let (name_arg, token_arg) = (name.to_string(), token.clone());
+    let prefs = pool
+        .run(move |conn| {
+            conn.insert_api_token(acc_id, &name_arg, &token_arg)?;
+            conn.get_preferences(acc_id)
+        })
+        .await
+        .unwrap();
+    let content = tmpl::api_tokens::tmpl_api_token_created(&prefs, name, &token).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+pub async fn handle_revoke_api_token(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<RevokeApiTokenParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let token_id = params.id;
+    pool.run(move |conn| conn.delete_api_token(acc_id, token_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "/profile")
+        .body("Redirecting...")
+}
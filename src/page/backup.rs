@@ -0,0 +1,127 @@
+//! Full backup of user account as JSON, and restore of it.
+use actix_web::web::Data;
+use actix_web::HttpMessage;
+
+use crate::backup;
+use crate::model;
+use crate::storage::StoragePool;
+use crate::tmpl;
+
+/// Largest backup that can be restored, in bytes.
+pub const MAX_RESTORE_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct BackupFileParams {
+    pub filename: String,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreParams {
+    pub json: String,
+    /// Checkbox, present when checked.
+    pub dry_run: Option<String>,
+}
+
+pub async fn handle_backup(
+    request: actix_web::HttpRequest,
+    config: Data<model::Config>,
+    pool: Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(account_id) => account_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let prefs = pool
+        .run(move |conn| conn.get_preferences(account_id))
+        .await
+        .unwrap();
+    let content = tmpl::backup::tmpl_backup(&prefs, &config.base_url, None, None).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+pub async fn handle_backup_file(
+    request: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+    path: actix_web::web::Path<BackupFileParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(account_id) => account_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let backup = pool
+        .run(move |conn| conn.get_backup(account_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::Ok()
+        .content_type("application/json")
+        .header("Content-Disposition", path.filename.to_string())
+        .body(serde_json::to_string_pretty(&backup).unwrap())
+}
+
+pub async fn handle_post_restore(
+    request: actix_web::HttpRequest,
+    config: Data<model::Config>,
+    pool: Data<StoragePool>,
+    params: actix_web::web::Form<RestoreParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(account_id) => account_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let prefs = pool
+        .run(move |conn| conn.get_preferences(account_id))
+        .await
+        .unwrap();
+    let dry_run = params.dry_run.is_some();
+    let content = match backup::parse(&params.json) {
+        Ok(backup) => {
+            let summary = pool
+                .run(move |conn| conn.restore_backup(account_id, &backup, dry_run))
+                .await
+                .unwrap();
+            tmpl::backup::tmpl_backup(&prefs, &config.base_url, None, Some((&summary, dry_run)))
+        }
+        Err(e) => tmpl::backup::tmpl_backup(&prefs, &config.base_url, Some(&e), None),
+    };
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content.into_string())
+}
@@ -1,14 +1,80 @@
 use actix_web::HttpMessage;
+use chrono::DateTime;
+use chrono::Utc;
 
-use crate::common;
-use crate::db;
+use crate::model::BankAccountInfo;
+use crate::model::CurrencyInfo;
+use crate::report;
+use crate::storage::StoragePool;
 use crate::tmpl;
+use crate::tmpl::bank_accounts::AsOf;
+use crate::util;
+
+/// Point-in-time params of Accounts and Currency pages.
+#[derive(Deserialize)]
+pub struct AsOfParams {
+    /// Date or timestamp, show balances as of then instead of now.
+    pub as_of: Option<String>,
+    /// Second date, when given the page compares balances at both dates.
+    pub compare: Option<String>,
+}
+
+impl AsOfParams {
+    pub fn parse(&self) -> Result<AsOf, String> {
+        let parse = |s: &Option<String>| -> Result<(Option<DateTime<Utc>>, String), String> {
+            match s.as_deref().map(str::trim) {
+                Some(s) if !s.is_empty() => Ok((Some(util::parse_as_of(s)?), s.to_string())),
+                _ => Ok((None, String::new())),
+            }
+        };
+        let (as_of, as_of_str) = parse(&self.as_of)?;
+        let (compare, compare_str) = parse(&self.compare)?;
+        Ok(AsOf {
+            as_of,
+            compare,
+            as_of_str,
+            compare_str,
+        })
+    }
+}
+
+/// Balances to compare bank accounts, holdings without price are skipped.
+pub fn bank_account_balances(infos: &[BankAccountInfo]) -> Vec<report::KeyedBalance> {
+    infos
+        .iter()
+        .filter_map(|i| {
+            i.value.as_ref().map(|v| {
+                (
+                    Some(i.id),
+                    i.bank_account.clone(),
+                    i.currency.clone(),
+                    v.parse().unwrap(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Balances to compare currencies.
+pub fn currency_balances(infos: &[CurrencyInfo]) -> Vec<report::KeyedBalance> {
+    infos
+        .iter()
+        .map(|i| {
+            (
+                None,
+                i.currency.clone(),
+                i.currency.clone(),
+                i.amount.parse().unwrap(),
+            )
+        })
+        .collect()
+}
 
 pub async fn handle_bank_accounts(
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     request: actix_web::HttpRequest,
+    params: actix_web::web::Query<AsOfParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = match request.cookie("session") {
         Some(c) => c,
         None => {
@@ -18,12 +84,55 @@ pub async fn handle_bank_accounts(
         }
     };
     let sess_key = cookie.value();
-    let acc_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let acc_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    let bank_accounts = db::get_bank_account_infos(&mut conn, acc_id).unwrap();
-    let content = tmpl::bank_accounts::tmpl_bank_accounts(&bank_accounts).into_string();
+    let as_of = match params.parse() {
+        Ok(as_of) => as_of,
+        Err(e) => return actix_web::HttpResponse::BadRequest().body(e),
+    };
+    let (at, compare) = (as_of.as_of, as_of.compare);
+    let (prefs, bank_accounts) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_bank_account_infos(acc_id, at.as_ref())?,
+            ))
+        })
+        .await
+        .unwrap();
+    let content = if as_of.is_diff() {
+        let (compared, currency_info, compared_currency_info) = pool
+            .run(move |conn| {
+                Ok((
+                    conn.get_bank_account_infos(acc_id, compare.as_ref())?,
+                    conn.get_currency_info(acc_id, at.as_ref())?,
+                    conn.get_currency_info(acc_id, compare.as_ref())?,
+                ))
+            })
+            .await
+            .unwrap();
+        let rows = report::diff_balances(
+            &bank_account_balances(&bank_accounts),
+            &bank_account_balances(&compared),
+        );
+        let currency_rows = report::diff_balances(
+            &currency_balances(&currency_info),
+            &currency_balances(&compared_currency_info),
+        );
+        tmpl::bank_accounts::tmpl_bank_accounts_diff(&prefs, &as_of, &rows, &currency_rows)
+            .into_string()
+    } else {
+        let groups = pool
+            .run(move |conn| conn.get_group_info(acc_id, at.as_ref()))
+            .await
+            .unwrap();
+        tmpl::bank_accounts::tmpl_bank_accounts(&prefs, &bank_accounts, &groups, &as_of)
+            .into_string()
+    };
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(content)
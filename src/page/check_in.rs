@@ -0,0 +1,139 @@
+//! Monthly check-in, new balances of all bank accounts at once.
+use std::collections::HashMap;
+
+use actix_web::HttpMessage;
+
+use crate::model::Preferences;
+use crate::model::ShareRole;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::tmpl::check_in::CheckInRow;
+use crate::util::parse_ts;
+
+/// Preferences of the user account and bank accounts it can add entries
+/// to, with their last entries.
+async fn check_in_rows(pool: &StoragePool, account_id: i64) -> (Preferences, Vec<CheckInRow>) {
+    let (prefs, bank_accounts, infos) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(account_id)?,
+                conn.get_bank_accounts(account_id)?,
+                conn.get_bank_account_infos(account_id, None)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let rows = bank_accounts
+        .into_iter()
+        .filter(|ba| ShareRole::can_edit(ba.role))
+        .map(|ba| {
+            let last = infos.iter().find(|i| i.id == ba.id);
+            CheckInRow {
+                last_amount: last.map(|i| i.amount.clone()),
+                last_ts: last.map(|i| i.ts),
+                amount: String::new(),
+                bank_account: ba,
+            }
+        })
+        .collect();
+    (prefs, rows)
+}
+
+fn render_check_in(
+    prefs: &Preferences,
+    ts: &str,
+    rows: &[CheckInRow],
+    error: Option<&str>,
+) -> actix_web::HttpResponse {
+    let content = tmpl::check_in::tmpl_check_in(prefs, ts, rows, error).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+pub async fn handle_check_in(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let (prefs, rows) = check_in_rows(&pool, account_id).await;
+    render_check_in(&prefs, &now, &rows, None)
+}
+
+/// Form has `ts` and `amount-<bank account id>` params, entries are saved
+/// for non-blank amounts, or none if any is invalid.
+pub async fn handle_post_check_in(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<HashMap<String, String>>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let ts_str = params.get("ts").map(String::as_str).unwrap_or("").trim();
+    let (prefs, mut rows) = check_in_rows(&pool, account_id).await;
+    let mut amounts = Vec::new();
+    let mut errors = Vec::new();
+    for row in rows.iter_mut() {
+        let key = format!("amount-{}", row.bank_account.id);
+        row.amount = params
+            .get(&key)
+            .map(|a| a.trim().to_string())
+            .unwrap_or_default();
+        if row.amount.is_empty() {
+            continue;
+        }
+        if row.amount.parse::<f64>().is_err() {
+            errors.push(format!(
+                "Invalid amount of {}: {}.",
+                row.bank_account.name, row.amount
+            ));
+        }
+        amounts.push((row.bank_account.id, row.amount.clone()));
+    }
+    let ts = match parse_ts(ts_str) {
+        Ok(ts) => Some(ts),
+        Err(e) => {
+            errors.insert(0, format!("{}.", e));
+            None
+        }
+    };
+    if amounts.is_empty() {
+        errors.push("No amounts entered.".to_string());
+    }
+    let ts = match ts {
+        Some(ts) if errors.is_empty() => ts,
+        _ => return render_check_in(&prefs, ts_str, &rows, Some(&errors.join(" "))),
+    };
+    pool.run(move |conn| conn.insert_entries(account_id, &ts, &amounts))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "entries")
+        .body("Redirecting...")
+}
@@ -2,23 +2,57 @@
 use actix_web;
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
+use crate::page::bank_accounts::currency_balances;
+use crate::page::bank_accounts::AsOfParams;
+use crate::report;
+use crate::storage::StoragePool;
 use crate::tmpl::currency::tmpl_currency;
+use crate::tmpl::currency::tmpl_currency_diff;
 
 pub async fn handle_currency(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<AsOfParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let sess_cookie = request.cookie("session").unwrap();
     let sess_key = sess_cookie.value();
-    let acc_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let acc_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    let currency_info = db::get_currency_info(&mut conn, acc_id).unwrap();
-    let content = tmpl_currency(currency_info).into_string();
+    let as_of = match params.parse() {
+        Ok(as_of) => as_of,
+        Err(e) => return actix_web::HttpResponse::BadRequest().body(e),
+    };
+    let (at, compare) = (as_of.as_of, as_of.compare);
+    let (prefs, currency_info) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_currency_info(acc_id, at.as_ref())?,
+            ))
+        })
+        .await
+        .unwrap();
+    let content = if as_of.is_diff() {
+        let compared = pool
+            .run(move |conn| conn.get_currency_info(acc_id, compare.as_ref()))
+            .await
+            .unwrap();
+        let rows = report::diff_balances(
+            &currency_balances(&currency_info),
+            &currency_balances(&compared),
+        );
+        tmpl_currency_diff(&prefs, &as_of, &rows).into_string()
+    } else {
+        let groups = pool
+            .run(move |conn| conn.get_group_info(acc_id, at.as_ref()))
+            .await
+            .unwrap();
+        tmpl_currency(&prefs, currency_info, &groups, &as_of).into_string()
+    };
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(content)
@@ -1,8 +1,7 @@
 use actix_web;
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
+use crate::storage::StoragePool;
 
 #[derive(Deserialize)]
 pub struct DeletePostParams {
@@ -10,11 +9,10 @@ pub struct DeletePostParams {
 }
 
 pub async fn handle_delete(
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     request: actix_web::HttpRequest,
     params: actix_web::web::Query<DeletePostParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = match request.cookie("session") {
         Some(c) => c,
         None => {
@@ -24,12 +22,17 @@ pub async fn handle_delete(
         }
     };
     let sess_key = cookie.value();
-    let acc_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let acc_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    db::delete_entry(&mut conn, acc_id, params.id).unwrap();
+    let entry_id = params.id;
+    pool.run(move |conn| conn.delete_entry(acc_id, entry_id))
+        .await
+        .unwrap();
     actix_web::HttpResponse::SeeOther()
-        .header("Location", ".")
+        .header("Location", "entries")
         .body("Redirecting...")
 }
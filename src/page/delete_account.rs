@@ -0,0 +1,112 @@
+//! Self-service account deletion.
+//! User asks for it on profile page, confirms it by the link sent by email,
+//! and then all rows of the account are deleted.
+use actix_web::HttpMessage;
+
+use crate::common;
+use crate::model;
+use crate::model::Preferences;
+use crate::storage::StoragePool;
+use crate::tmpl;
+
+#[derive(Deserialize)]
+pub struct DeleteAccountTokenParams {
+    pub token: String,
+}
+
+/// Send confirmation link to all emails of the account.
+pub async fn handle_post_delete_account(
+    request: actix_web::HttpRequest,
+    config: actix_web::web::Data<model::Config>,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(acc_id) => acc_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let token: String = uuid::Uuid::new_v4().to_string();
+    let token_arg = token.clone();
+    let (prefs, emails) = pool
+        .run(move |conn| {
+            conn.insert_delete_account_token(acc_id, &token_arg)?;
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_user_account_emails(acc_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    for email in emails.iter() {
+        common::send_delete_account_email(&config.base_url, email, &token, config.use_email)
+            .unwrap();
+    }
+    let content = tmpl::delete_account::tmpl_delete_account_email_sent(&prefs).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+/// User clicked the link in the email, ask once more.
+pub async fn handle_get_delete_account_with_token(
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Path<DeleteAccountTokenParams>,
+) -> impl actix_web::Responder {
+    let token = params.token.clone();
+    let prefs = match pool
+        .run(
+            move |conn| match conn.get_delete_account_token_account(&token)? {
+                Some(acc_id) => Ok(Some(conn.get_preferences(acc_id)?)),
+                None => Ok(None),
+            },
+        )
+        .await
+        .unwrap()
+    {
+        Some(prefs) => prefs,
+        None => return actix_web::HttpResponse::BadRequest().body("Invalid or expired token"),
+    };
+    let content = tmpl::delete_account::tmpl_delete_account_confirm(&prefs).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+/// User confirmed, delete everything and log out.
+pub async fn handle_post_delete_account_with_token(
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Path<DeleteAccountTokenParams>,
+) -> impl actix_web::Responder {
+    let token = params.token.clone();
+    let acc_id = match pool
+        .run(move |conn| conn.get_delete_account_token_account(&token))
+        .await
+        .unwrap()
+    {
+        Some(acc_id) => acc_id,
+        None => return actix_web::HttpResponse::BadRequest().body("Invalid or expired token"),
+    };
+    pool.run(move |conn| conn.delete_account(acc_id))
+        .await
+        .unwrap();
+    info!("Deleted account {}.", acc_id);
+    // Sessions are gone from db already, this just cleans up the browser.
+    let cookie = actix_web::http::Cookie::build("session", "")
+        .path("/")
+        .finish();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .del_cookie(&cookie)
+        .body(tmpl::delete_account::tmpl_delete_account_done(&Preferences::default()).into_string())
+}
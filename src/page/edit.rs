@@ -2,10 +2,11 @@ use actix_web;
 use actix_web::HttpMessage;
 use actix_web::Responder;
 
-use crate::common;
-use crate::db;
+use crate::model::ShareRole;
+use crate::storage::StoragePool;
 use crate::tmpl::edit::tmpl_edit;
 use crate::tmpl::edit::FormData;
+use crate::util::parse_note;
 
 /// The query or post params of the edit page.
 #[derive(Deserialize)]
@@ -19,17 +20,17 @@ pub struct EditParams {
 pub struct EditPostParams {
     id: String,
     amount: String,
+    note: Option<String>,
 }
 
 pub async fn handle_edit(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     params: actix_web::web::Query<EditParams>,
 ) -> impl Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = request.cookie("session").unwrap();
     let sess_key = cookie.value();
-    let account_id = match db::get_sess_val(&mut conn, sess_key, "account") {
+    let account_id: i64 = match pool.get_sess_val(sess_key, "account").await {
         Some(account_id) => account_id.parse().unwrap(),
         None => {
             return actix_web::HttpResponse::SeeOther()
@@ -37,46 +38,80 @@ pub async fn handle_edit(
                 .body("Redirecting to new session form");
         }
     };
-    let entry = match db::get_entry(&mut conn, account_id, params.id.parse().unwrap()).unwrap() {
+    let entry_id: i64 = params.id.parse().unwrap();
+    let (prefs, entry) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(account_id)?,
+                conn.get_entry(account_id, entry_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let entry = match entry {
         Some(entry) => entry,
         None => {
             return actix_web::HttpResponse::InternalServerError().body("No such entry");
         }
     };
+    if !ShareRole::can_edit(entry.role) {
+        return actix_web::HttpResponse::Forbidden().body("Bank account is shared read only");
+    }
     let form_data = FormData {
         id: entry.id,
         amount: (String::from(entry.amount), None),
+        note: (entry.note.unwrap_or_default(), None),
     };
-    let resp_body = tmpl_edit(&form_data).into_string();
+    let resp_body = tmpl_edit(&prefs, &form_data).into_string();
     actix_web::HttpResponse::Ok().body(resp_body)
 }
 
 pub async fn handle_post_edit(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     params: actix_web::web::Form<EditPostParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = request.cookie("session").unwrap();
     let sess_key = cookie.value();
-    let account_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let account_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    if db::get_entry(&mut conn, account_id, params.id.parse().unwrap())
+    let entry_id: i64 = params.id.parse().unwrap();
+    let note = match parse_note(params.note.as_deref()) {
+        Ok(note) => note,
+        Err(e) => {
+            let prefs = pool
+                .run(move |conn| conn.get_preferences(account_id))
+                .await
+                .unwrap();
+            let form_data = FormData {
+                id: entry_id,
+                amount: (params.amount.clone(), None),
+                note: (params.note.clone().unwrap_or_default(), Some(e)),
+            };
+            return actix_web::HttpResponse::BadRequest()
+                .body(tmpl_edit(&prefs, &form_data).into_string());
+        }
+    };
+    match pool
+        .run(move |conn| conn.get_entry(account_id, entry_id))
+        .await
         .unwrap()
-        .is_none()
     {
-        return actix_web::HttpResponse::NotFound().body("Not found");
+        Some(entry) if !ShareRole::can_edit(entry.role) => {
+            return actix_web::HttpResponse::Forbidden().body("Bank account is shared read only");
+        }
+        Some(_) => (),
+        None => return actix_web::HttpResponse::NotFound().body("Not found"),
     };
-    db::update_entry_amount(
-        &mut conn,
-        account_id,
-        params.id.parse().unwrap(),
-        params.amount.clone(),
-    )
-    .unwrap();
+    let amount = params.amount.clone();
+    pool.run(move |conn| conn.update_entry(account_id, entry_id, amount, note.as_deref()))
+        .await
+        .unwrap();
     actix_web::HttpResponse::SeeOther()
-        .header("Location", ".")
+        .header("Location", "entries")
         .body("Redirecting...")
 }
@@ -0,0 +1,202 @@
+//! Edit bank account, currently only its group and type can be changed,
+//! and share it with other users. Only the owner can do it.
+use actix_web::HttpMessage;
+
+use crate::model::BankAccountKind;
+use crate::model::ShareRole;
+use crate::model::MAX_GROUP_NAME_LENGTH;
+use crate::storage::StoragePool;
+use crate::tmpl;
+
+#[derive(Deserialize)]
+pub struct EditBankAccountParams {
+    pub id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct EditBankAccountPostParams {
+    pub id: i64,
+    pub group_name: String,
+    pub kind: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShareBankAccountParams {
+    pub id: i64,
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct UnshareBankAccountParams {
+    /// Bank account id.
+    pub id: i64,
+    pub share_id: i64,
+}
+
+/// Render edit page of bank account owned by the user account, or not found.
+async fn render_edit_bank_account(
+    pool: &StoragePool,
+    account_id: i64,
+    bank_account_id: i64,
+    error: Option<&str>,
+) -> actix_web::HttpResponse {
+    let (prefs, bank_account, shares) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(account_id)?,
+                conn.get_bank_account(account_id, bank_account_id)?,
+                conn.get_bank_account_shares(account_id, bank_account_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let bank_account = match bank_account {
+        Some(bank_account) if bank_account.role.is_none() => bank_account,
+        _ => return actix_web::HttpResponse::NotFound().body("Not found"),
+    };
+    let content =
+        tmpl::edit_bank_account::tmpl_edit_bank_account(&prefs, &bank_account, &shares, error)
+            .into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+pub async fn handle_get_edit_bank_account(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<EditBankAccountParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    render_edit_bank_account(&pool, account_id, params.id, None).await
+}
+
+pub async fn handle_post_edit_bank_account(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<EditBankAccountPostParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let group_name = Some(params.group_name.trim())
+        .filter(|g| !g.is_empty())
+        .map(String::from);
+    let kind = match BankAccountKind::parse(&params.kind) {
+        Some(kind) => kind,
+        None => return actix_web::HttpResponse::BadRequest().body("Invalid type"),
+    };
+    if group_name
+        .as_ref()
+        .is_some_and(|g| g.chars().count() > MAX_GROUP_NAME_LENGTH)
+    {
+        let error = format!("Group is longer than {} characters.", MAX_GROUP_NAME_LENGTH);
+        return render_edit_bank_account(&pool, account_id, params.id, Some(&error)).await;
+    }
+    let bank_account_id = params.id;
+    let updated = pool
+        .run(move |conn| {
+            conn.update_bank_account(account_id, bank_account_id, group_name.as_deref(), kind)
+        })
+        .await;
+    if updated.is_err() {
+        return actix_web::HttpResponse::InternalServerError()
+            .body("Failed to update bank account");
+    }
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "accounts")
+        .body("Redirecting...")
+}
+
+pub async fn handle_post_share_bank_account(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<ShareBankAccountParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let role = match ShareRole::parse(&params.role) {
+        Some(role) => role,
+        None => return actix_web::HttpResponse::BadRequest().body("Invalid role"),
+    };
+    let email = params.email.trim().to_string();
+    let (bank_account_id, email_arg) = (params.id, email.clone());
+    if !pool
+        .run(move |conn| conn.share_bank_account(account_id, bank_account_id, &email_arg, role))
+        .await
+        .unwrap()
+    {
+        // Same message whether the email is registered or not, so it can't
+        // be used to find out.
+        let error = format!("Can't share the bank account with {}.", email);
+        return render_edit_bank_account(&pool, account_id, params.id, Some(&error)).await;
+    }
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", format!("edit-bank-account?id={}", params.id))
+        .body("Redirecting...")
+}
+
+pub async fn handle_unshare_bank_account(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<UnshareBankAccountParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let share_id = params.share_id;
+    pool.run(move |conn| conn.delete_bank_account_share(account_id, share_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", format!("edit-bank-account?id={}", params.id))
+        .body("Redirecting...")
+}
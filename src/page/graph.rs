@@ -1,21 +1,119 @@
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
-use crate::model::EntryInfo;
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::forecast;
+use crate::report;
+use crate::storage::StoragePool;
 use crate::tmpl;
+use crate::tmpl::graph::GraphForm;
+use crate::tmpl::graph::Projection;
+use crate::tmpl::graph::ProjectionForm;
+use crate::tmpl::graph::Scale;
+use crate::tmpl::graph::Series;
+use crate::util;
 
+/// Lines are chosen by repeated params, see `lines`: `id` of bank
+/// accounts, and `currency` to graph the sum of bank accounts in the
+/// currency, in `group` only if given. `from` and `to` limit the dates,
+/// `scale` is "absolute" or "normalized" to start every line at 100.
+/// Optional `projection` ("linear" or "exp") overlays trend `horizon`
+/// months ahead, with date when `goal` amount is reached.
 #[derive(Deserialize)]
 pub struct GraphParams {
-    pub account: String,
+    pub group: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub scale: Option<String>,
+    pub projection: Option<String>,
+    pub horizon: Option<String>,
+    pub goal: Option<String>,
+}
+
+/// Default projection horizon in months.
+const DEFAULT_HORIZON: u32 = 12;
+
+/// Longest projection horizon in months.
+const MAX_HORIZON: u32 = 1200;
+
+/// Bank account ids and currencies from repeated `id` and `currency`
+/// params, which `GraphParams` can't hold.
+fn lines(query: &str) -> Result<(Vec<i64>, Vec<String>), String> {
+    let (mut ids, mut currencies) = (Vec::new(), Vec::new());
+    for (name, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match name.as_ref() {
+            "id" => {
+                let id: i64 = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid bank account id: {}", value))?;
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            "currency" if !currencies.contains(&value.to_string()) => {
+                currencies.push(value.to_string())
+            }
+            _ => {}
+        }
+    }
+    Ok((ids, currencies))
+}
+
+/// Start and end of graphed dates, unlimited if `None`.
+type Range = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// Dates of the range, `from` is the start and `to` the end of the day.
+fn range(form: &GraphForm) -> Result<Range, String> {
+    let from = match form.from.as_str() {
+        "" => None,
+        s => Some(util::parse_since(s)?),
+    };
+    let to = match form.to.as_str() {
+        "" => None,
+        s => Some(util::parse_as_of(s)?),
+    };
+    Ok((from, to))
+}
+
+/// Link to the graph of one line, in the same range and scale.
+fn line_link(form: &GraphForm, name: &str, value: &str) -> String {
+    let mut params = vec![(name, value)];
+    if name == "currency" && !form.group.is_empty() {
+        params.push(("group", &form.group));
+    }
+    for (name, value) in &[("from", &form.from), ("to", &form.to)] {
+        if !value.is_empty() {
+            params.push((name, value));
+        }
+    }
+    if form.scale != Scale::Absolute {
+        params.push(("scale", form.scale.name()));
+    }
+    let query: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, util::url_encode(value)))
+        .collect();
+    format!("graph?{}", query.join("&"))
+}
+
+fn projection(points: &[report::Snapshot], form: &ProjectionForm) -> Option<Projection> {
+    let fit = forecast::fit(points, form.model?)?;
+    let goal = form.goal.trim().parse::<f64>().ok();
+    Some(Projection {
+        points: fit.project(form.horizon),
+        monthly_change: fit.monthly_change(),
+        monthly_rate_pct: fit.monthly_rate_pct(),
+        goal_date: goal.map(|goal| fit.goal_date(goal)),
+    })
 }
 
 pub async fn handle_graph(
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
     request: actix_web::HttpRequest,
     params: actix_web::web::Query<GraphParams>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = match request.cookie("session") {
         Some(c) => c,
         None => {
@@ -25,13 +123,148 @@ pub async fn handle_graph(
         }
     };
     let sess_key = cookie.value();
-    let account_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let account_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    let entries: Vec<EntryInfo> =
-        db::get_entries_by_bank_account(&mut conn, account_id, &params.account).unwrap();
-    let resp_html = tmpl::graph::tmpl_graph(&entries).into_string();
+    let form = ProjectionForm {
+        model: params
+            .projection
+            .as_deref()
+            .and_then(forecast::Model::parse),
+        horizon: params
+            .horizon
+            .as_deref()
+            .and_then(|h| h.trim().parse().ok())
+            .map(|h: u32| h.min(MAX_HORIZON))
+            .unwrap_or(DEFAULT_HORIZON),
+        goal: params.goal.clone().unwrap_or_default(),
+    };
+    let (ids, currencies) = match lines(request.query_string()) {
+        Ok(lines) => lines,
+        Err(e) => return actix_web::HttpResponse::BadRequest().body(e),
+    };
+    let graph_form = GraphForm {
+        ids,
+        currencies,
+        group: params.group.clone().unwrap_or_default(),
+        from: params.from.clone().unwrap_or_default().trim().to_string(),
+        to: params.to.clone().unwrap_or_default().trim().to_string(),
+        scale: params
+            .scale
+            .as_deref()
+            .and_then(Scale::parse)
+            .unwrap_or(Scale::Absolute),
+    };
+    let (from, to) = match range(&graph_form) {
+        Ok(range) => range,
+        Err(e) => return actix_web::HttpResponse::BadRequest().body(e),
+    };
+    let (ids, currencies, group) = (
+        graph_form.ids.clone(),
+        graph_form.currencies.clone(),
+        graph_form.group.clone(),
+    );
+    let (prefs, bank_accounts, account_points, currency_points) = pool
+        .run(move |conn| {
+            let bank_accounts = conn.get_bank_accounts(account_id)?;
+            let mut account_points = Vec::new();
+            for id in ids {
+                let entries = conn.get_entries_by_bank_account(account_id, id)?;
+                // Holdings are graphed by value, points without price are skipped.
+                let points: Vec<report::Snapshot> = entries
+                    .iter()
+                    .filter_map(|e| e.value.as_ref().map(|v| (e.ts, v.parse().unwrap())))
+                    .collect();
+                account_points.push((id, points));
+            }
+            let all = match group.is_empty() && !currencies.is_empty() {
+                true => conn.get_all_entries(account_id)?,
+                false => Vec::new(),
+            };
+            let mut currency_points = Vec::new();
+            for currency in currencies {
+                let points = match group.is_empty() {
+                    true => report::sum_series(all.iter().filter(|e| e.currency == currency)),
+                    false => report::sum_series(
+                        &conn.get_entries_by_group(account_id, &group, &currency)?,
+                    ),
+                };
+                currency_points.push((currency, points));
+            }
+            Ok((
+                conn.get_preferences(account_id)?,
+                bank_accounts,
+                account_points,
+                currency_points,
+            ))
+        })
+        .await
+        .unwrap();
+    let mut lines: Vec<Series> = Vec::new();
+    for (id, points) in account_points {
+        let bank_account = match bank_accounts.iter().find(|b| b.id == id) {
+            Some(bank_account) => bank_account,
+            None => return actix_web::HttpResponse::NotFound().body("Not found"),
+        };
+        lines.push(Series {
+            name: format!("{} ({})", bank_account.name, bank_account.currency),
+            link: line_link(&graph_form, "id", &id.to_string()),
+            points,
+        });
+    }
+    for (currency, points) in currency_points {
+        lines.push(Series {
+            name: match graph_form.group.as_str() {
+                "" => format!("{} (all accounts)", currency),
+                group => format!("{} ({})", group, currency),
+            },
+            link: line_link(&graph_form, "currency", &currency),
+            points,
+        });
+    }
+    let mut notes: Vec<String> = Vec::new();
+    for line in lines.iter_mut() {
+        line.points = report::clip_series(&line.points, from, to);
+    }
+    if graph_form.scale == Scale::Normalized {
+        lines.retain_mut(|line| match report::normalize_series(&line.points) {
+            Some(points) => {
+                line.points = points;
+                true
+            }
+            None if line.points.is_empty() => true,
+            None => {
+                notes.push(format!(
+                    "{} starts at zero, it can't be normalized.",
+                    line.name
+                ));
+                false
+            }
+        });
+    }
+    let title: Vec<&str> = lines.iter().map(|l| l.name.as_str()).collect();
+    // Trend and goal are in amounts, they make no sense on normalized lines.
+    let projection = match (lines.as_slice(), graph_form.scale) {
+        ([line], Scale::Absolute) => projection(&line.points, &form),
+        _ => None,
+    };
+    if form.model.is_some() && graph_form.scale == Scale::Normalized {
+        notes.push("Trend is only projected on absolute scale.".to_string());
+    }
+    let resp_html = tmpl::graph::tmpl_graph(
+        &prefs,
+        &title.join(", "),
+        &lines,
+        &notes,
+        &bank_accounts,
+        &graph_form,
+        &form,
+        projection.as_ref(),
+    )
+    .into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(resp_html)
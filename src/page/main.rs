@@ -1,32 +1,113 @@
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
 use crate::model::EntryInfo;
+use crate::model::LandingPage;
+use crate::model::Preferences;
+use crate::storage::StoragePool;
 use crate::tmpl;
 
+/// Query params of the entries page.
+#[derive(Deserialize)]
+pub struct EntriesParams {
+    /// Page number, counts from 1.
+    pub page: Option<i64>,
+    /// Show only entries with note containing this.
+    pub q: Option<String>,
+}
+
+/// Render page of entries, `entries_per_page` of preferences of them.
+async fn render_entries(
+    pool: &StoragePool,
+    prefs: &Preferences,
+    account_id: i64,
+    page: i64,
+    search: &str,
+) -> actix_web::HttpResponse {
+    let per_page = prefs.entries_per_page;
+    let offset = (page - 1).saturating_mul(per_page);
+    let search_param = Some(search.to_string()).filter(|s| !s.is_empty());
+    // One more to know if there's an older page.
+    let mut entries: Vec<EntryInfo> = pool
+        .run(move |conn| {
+            conn.get_entries_page(account_id, search_param.as_deref(), per_page + 1, offset)
+        })
+        .await
+        .unwrap();
+    let has_more = entries.len() as i64 > per_page;
+    entries.truncate(per_page as usize);
+    let resp_html =
+        tmpl::main::tmpl_main(prefs, "Main", &entries, search, page, has_more).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(resp_html)
+}
+
+/// Front page, shows entries or redirects to the landing page chosen in
+/// settings.
 pub async fn handle_main(
     req: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let sess_cookie = match req.cookie("session") {
         Some(c) => c,
-        None => return actix_web::HttpResponse::SeeOther().header("Location", "new-session").body("Redirecting..."),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
     };
     let sess_key = sess_cookie.value();
-    let o_account_id = db::get_sess_val(&mut conn, sess_key, "account");
+    let o_account_id = pool.get_sess_val(sess_key, "account").await;
     if let Some(account_id) = o_account_id {
-        let entries: Vec<EntryInfo> =
-            db::get_entries(&mut conn, account_id.parse().unwrap()).unwrap();
-        let resp_html = tmpl::main::tmpl_main("Main", &entries).into_string();
-        let ct = "text/html";
-        actix_web::HttpResponse::Ok()
-            .content_type(ct)
-            .body(resp_html)
+        let account_id: i64 = account_id.parse().unwrap();
+        let prefs = pool
+            .run(move |conn| conn.get_preferences(account_id))
+            .await
+            .unwrap();
+        if prefs.landing_page != LandingPage::Entries {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", prefs.landing_page.as_str())
+                .body("Redirecting...");
+        }
+        render_entries(&pool, &prefs, account_id, 1, "").await
     } else {
         actix_web::HttpResponse::SeeOther()
             .header("Location", "new-session")
             .body("Redirecting to new session form")
     }
 }
+
+pub async fn handle_entries(
+    req: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<EntriesParams>,
+) -> impl actix_web::Responder {
+    let sess_cookie = match req.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = match pool.get_sess_val(sess_cookie.value(), "account").await {
+        Some(account_id) => account_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let prefs = pool
+        .run(move |conn| conn.get_preferences(account_id))
+        .await
+        .unwrap();
+    render_entries(
+        &pool,
+        &prefs,
+        account_id,
+        params.page.unwrap_or(1).max(1),
+        params.q.as_deref().unwrap_or("").trim(),
+    )
+    .await
+}
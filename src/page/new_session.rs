@@ -1,10 +1,22 @@
+use actix_web::HttpMessage;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
 use uuid;
 
-use crate::db;
-use crate::tmpl::new_session::tmpl_new_session;
-use crate::tmpl::new_session::tmpl_new_session_result;
 use crate::common;
+use crate::db::DbError;
+use crate::model::Config;
+use crate::model::Preferences;
+use crate::model::RegistrationMode;
+use crate::registration;
+use crate::storage::Storage;
+use crate::storage::StoragePool;
+use crate::tmpl::new_session::tmpl_new_session;
+use crate::tmpl::new_session::tmpl_new_session_code;
 use crate::tmpl::new_session::tmpl_new_session_email_sent;
+use crate::tmpl::new_session::tmpl_new_session_result;
+use crate::totp;
 
 #[derive(Deserialize)]
 pub struct GetNewSessionWithTokenParams {
@@ -15,96 +27,458 @@ pub struct GetNewSessionWithTokenParams {
 #[derive(Deserialize)]
 pub struct PostNewSessionParams {
     pub email: String,
+    pub invite: Option<String>,
+}
+
+/// Seconds to enter TOTP code after clicking the login link.
+const PENDING_LOGIN_SECS: i64 = 10 * 60;
+
+/// Wrong TOTP codes allowed per login link.
+const MAX_CODE_ATTEMPTS: i64 = 5;
+
+/// Cookie of login waiting for TOTP code, see `start_code_step`.
+const PENDING_LOGIN_COOKIE: &str = "pending-login";
+
+/// Same as `account_email.email` column size.
+const MAX_EMAIL_LENGTH: usize = 128;
+
+#[derive(Deserialize)]
+pub struct PostNewSessionCodeParams {
+    pub code: String,
 }
 
 /// The entry page for the new session flow, shows the basic form.
-pub async fn handle_new_session() -> impl actix_web::Responder {
-    let resp_html = tmpl_new_session().into_string();
+pub async fn handle_new_session(config: actix_web::web::Data<Config>) -> impl actix_web::Responder {
+    let resp_html = tmpl_new_session(
+        &Preferences::default(),
+        "",
+        invite_field(&config, ""),
+        "",
+        oidc_name(&config),
+    )
+    .into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(resp_html)
 }
 
+/// Name of OpenID Connect provider for login page, if configured.
+pub fn oidc_name(config: &Config) -> Option<&str> {
+    config.oidc.as_ref().map(|o| o.name())
+}
+
+/// Invite code for login page, if registration needs one.
+fn invite_field<'a>(config: &Config, invite: &'a str) -> Option<&'a str> {
+    match config.registration {
+        RegistrationMode::Invite => Some(invite),
+        _ => None,
+    }
+}
+
+/// Client IP address, from proxy headers only if configured to trust them.
+fn client_ip(config: &Config, request: &actix_web::HttpRequest) -> Option<String> {
+    let ip = if config.behind_proxy {
+        request
+            .connection_info()
+            .realip_remote_addr()
+            .map(String::from)
+    } else {
+        request.peer_addr().map(|a| a.ip().to_string())
+    };
+    ip.map(|ip| ip.chars().take(64).collect())
+}
+
+/// Why login email isn't sent.
+enum Refusal {
+    /// Over the rate limits, login email can be sent again then.
+    Limited(DateTime<Utc>),
+    Rejected(registration::Rejection),
+}
+
 /// Use clicked "yes" on login via email form.
 /// We have to:
-/// - check if email belongs to existing account,
-///   - if not, create empty account with new id,
-///   - if yes, then get account id we're trying to log in,
-///   - the DB constraint prevent race here,
 /// - generate the login token,
-/// - store it in login token table,
+/// - check if email belongs to existing account,
+///   - if yes, record the login request and store the token in login
+///     token table,
+///   - if not, check the registration policy and store the token with the
+///     login request, the account is created only when the link is
+///     clicked, so unused addresses don't fill the account table,
+/// - recording login request fails if the email or IP address is over the
+///   rate limits, see `login_limit`,
 /// - send an email to given user
 ///   - if user is in fact able to read email, then they'll be able to
 ///     setup the session.
 pub async fn handle_post_new_session(
-    config: actix_web::web::Data<crate::model::Config>,
-    pool: actix_web::web::Data<common::DatabasePool>,
-    params: actix_web::web::Form<PostNewSessionParams>) -> impl actix_web::Responder {
-    let mut conn = pool.get().expect("Error getting db conn from pool");
-    let acc_id: i64 = match db::get_acc_id_by_email(&mut conn, &params.email) {
-        Ok(oa) => match oa {
-            Some(a) => a,
-            None => db::create_acc_with_email(&mut conn, &params.email).expect("Error creating an account"),
-        },
-        Err(_) => {
-            return actix_web::HttpResponse::InternalServerError().body(
-                "Failed to query account")
-        }
+    request: actix_web::HttpRequest,
+    config: actix_web::web::Data<Config>,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<PostNewSessionParams>,
+) -> impl actix_web::Responder {
+    let prefs = Preferences::default();
+    let email = params.email.trim().to_string();
+    let invite_str = params.invite.as_deref().unwrap_or("").trim().to_string();
+    let invite = match invite_str.is_empty() {
+        true => None,
+        false => Some(registration::normalize_invite(&invite_str)),
     };
+    if !email.contains('@') || email.chars().count() > MAX_EMAIL_LENGTH {
+        let resp_html = tmpl_new_session(
+            &prefs,
+            &email,
+            invite_field(&config, &invite_str),
+            "Invalid email address",
+            oidc_name(&config),
+        )
+        .into_string();
+        return actix_web::HttpResponse::BadRequest()
+            .content_type("text/html")
+            .body(resp_html);
+    }
+    let ip = client_ip(&config, &request);
+    let now = Utc::now();
     let token: String = uuid::Uuid::new_v4().to_string();
-    let use_email= config.use_email;
-    db::insert_login_token(&mut conn, &acc_id, &token).expect("Error inserting login token");
-    common::send_email_login_email(
-        &config.base_url, &params.email, &token, use_email
-    ).unwrap();
-    let resp_html = tmpl_new_session_email_sent().into_string();
+    let (e, t, conf) = (email.clone(), token.clone(), config.clone());
+    let stored = pool
+        .run(move |conn| match conn.get_acc_id_by_email(&e)? {
+            Some(acc_id) => match conn.insert_login_request(&e, ip.as_deref(), None, None, &now)? {
+                Some(retry) => Ok(Some(Refusal::Limited(retry))),
+                None => {
+                    conn.insert_login_token(&acc_id, &t)?;
+                    Ok(None)
+                }
+            },
+            None => match registration::check(conn, &conf, &e, invite.as_deref())? {
+                Some(rejection) => Ok(Some(Refusal::Rejected(rejection))),
+                None => Ok(conn
+                    .insert_login_request(&e, ip.as_deref(), Some(&t), invite.as_deref(), &now)?
+                    .map(Refusal::Limited)),
+            },
+        })
+        .await;
+    match stored {
+        Ok(None) => {}
+        Ok(Some(Refusal::Limited(retry))) => {
+            let minutes = ((retry - now).num_seconds() + 59) / 60;
+            let err = format!(
+                "Too many login emails, try again in {} minute{}.",
+                minutes,
+                if minutes == 1 { "" } else { "s" }
+            );
+            let resp_html = tmpl_new_session(
+                &prefs,
+                &email,
+                invite_field(&config, &invite_str),
+                &err,
+                oidc_name(&config),
+            )
+            .into_string();
+            return actix_web::HttpResponse::TooManyRequests()
+                .header(
+                    "Retry-After",
+                    format!("{}", (retry - now).num_seconds() + 1),
+                )
+                .content_type("text/html")
+                .body(resp_html);
+        }
+        Ok(Some(Refusal::Rejected(rejection))) => {
+            let resp_html = tmpl_new_session(
+                &prefs,
+                &email,
+                invite_field(&config, &invite_str),
+                &rejection.message(&config),
+                oidc_name(&config),
+            )
+            .into_string();
+            return actix_web::HttpResponse::Forbidden()
+                .content_type("text/html")
+                .body(resp_html);
+        }
+        Err(_) => {
+            return actix_web::HttpResponse::InternalServerError()
+                .body("Failed to store login token")
+        }
+    }
+    common::send_email_login_email(&config.base_url, &email, &token, config.use_email).unwrap();
+    let resp_html = tmpl_new_session_email_sent(&prefs).into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
-        .body( resp_html)
+        .body(resp_html)
+}
+
+/// Session values of login to the account: `account` that other pages
+/// look for, or with TOTP enabled the pending login waiting for the code,
+/// see `handle_post_new_session_code`.
+fn login_values(
+    acc_id: i64,
+    totp_enabled: bool,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let id = format!("{}", acc_id);
+    if totp_enabled {
+        vec![
+            ("totp_account", id),
+            ("totp_started", now.to_rfc3339()),
+            ("totp_attempts", "0".to_string()),
+        ]
+    } else {
+        vec![("account", id)]
+    }
+}
+
+fn set_login_values(
+    conn: &mut dyn Storage,
+    session_key: &str,
+    acc_id: i64,
+    totp_enabled: bool,
+    now: DateTime<Utc>,
+) -> Result<(), DbError> {
+    for (name, value) in login_values(acc_id, totp_enabled, now) {
+        conn.set_session_value(session_key, name, &value)?;
+    }
+    Ok(())
+}
+
+/// Login started by login link.
+struct Login {
+    acc_id: i64,
+    /// TOTP code is needed to finish it.
+    needs_code: bool,
+}
+
+/// Start login with the login link token as session `session_key`. Tokens
+/// can be used once.
+/// Tokens of login requests for emails without an account create it,
+/// unless it was created meanwhile. The registration policy is checked
+/// again, the invite may be used up by now.
+fn login_by_token(
+    conn: &mut dyn Storage,
+    config: &Config,
+    token: &str,
+    session_key: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<Result<Login, registration::Rejection>>, DbError> {
+    if let Some(acc_id) = conn.get_login_token_account(token)? {
+        let totp_enabled = conn.get_totp(acc_id)?.is_some_and(|t| t.enabled);
+        let values = login_values(acc_id, totp_enabled, now);
+        let values: Vec<(&str, &str)> = values.iter().map(|(n, v)| (*n, v.as_str())).collect();
+        // False if the link was clicked again meanwhile.
+        if !conn.use_login_token(token, session_key, &values)? {
+            return Ok(None);
+        }
+        return Ok(Some(Ok(Login {
+            acc_id,
+            needs_code: totp_enabled,
+        })));
+    }
+    // Login request token is used up here, so it logs in once too.
+    let (email, invite) = match conn.use_login_request_token(token)? {
+        Some(request) => request,
+        None => return Ok(None),
+    };
+    let acc_id = match conn.get_acc_id_by_email(&email)? {
+        Some(acc_id) if conn.is_account_disabled(acc_id)? => return Ok(None),
+        Some(acc_id) => acc_id,
+        None => match registration::create_account(conn, config, &email, invite.as_deref())? {
+            Ok(acc_id) => acc_id,
+            Err(rejection) => return Ok(Some(Err(rejection))),
+        },
+    };
+    let totp_enabled = conn.get_totp(acc_id)?.is_some_and(|t| t.enabled);
+    set_login_values(conn, session_key, acc_id, totp_enabled, now)?;
+    Ok(Some(Ok(Login {
+        acc_id,
+        needs_code: totp_enabled,
+    })))
 }
 
 /// User clicks on the login link in the new-session email.
 /// URL contains the token.
-/// If the token looks good, then we'll give user's browser the session cookie.
-/// We also need to mark token as consumed.
+/// If the token looks good, then we'll give user's browser the session cookie,
+/// or the pending login cookie if TOTP code is needed.
+/// The token is marked as consumed with the session, so the link works once.
 /// This is a GET link, which kind of breaks the HTTP proto, maybe we should present a web page
 /// where the user has a chance to consume the token by clicking a button?
 pub async fn handle_get_new_session_with_token(
-    pool: actix_web::web::Data<common::DatabasePool>,
-    params: actix_web::web::Path<GetNewSessionWithTokenParams>) -> impl actix_web::Responder {
-    use std::convert::TryInto;
-    let mut conn = pool.get().expect("Error getting database conn from pool");
+    config: actix_web::web::Data<Config>,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Path<GetNewSessionWithTokenParams>,
+) -> impl actix_web::Responder {
     debug!("Logging in with key {}.", &params.token);
-    match db::get_login_token_account(&mut conn, &params.token) {
-        Err(_) => {
-            return actix_web::HttpResponse::InternalServerError().body("Failed to check token");
+    let session_key: String = uuid::Uuid::new_v4().to_string();
+    let (token, conf, key) = (params.token.clone(), config.clone(), session_key.clone());
+    let now = Utc::now();
+    match pool
+        .run(move |conn| login_by_token(conn, &conf, &token, &key, now))
+        .await
+    {
+        Err(_) => actix_web::HttpResponse::InternalServerError().body("Failed to check token"),
+        Ok(None) => actix_web::HttpResponse::BadRequest().body("Invalid token"),
+        Ok(Some(Err(rejection))) => {
+            let resp_html = tmpl_new_session(
+                &Preferences::default(),
+                "",
+                invite_field(&config, ""),
+                &rejection.message(&config),
+                oidc_name(&config),
+            )
+            .into_string();
+            actix_web::HttpResponse::Forbidden()
+                .content_type("text/html")
+                .body(resp_html)
         }
-        Ok(oa) => {
-            match oa {
-                None => actix_web::HttpResponse::BadRequest().body("Invalid token"),
-                Some(acc_id) => {
-                    // Yeah, token is ok.
-                    // TODO: mark token as used.
-                    let session_key: String = uuid::Uuid::new_v4().to_string();
-                    db::set_session_value(
-                        &mut conn,
-                        &session_key,
-                        "account",
-                        &format!("{}", acc_id)
-                    ).unwrap();
-                    let cookie = actix_web::http::Cookie::build("session", session_key)
-                        .path("/")
-                        .secure(true)
-                        .max_age(std::time::Duration::from_secs(60 * 60 * 24 * 365 * 2).try_into().expect("Error converting durations"))
-                        .finish();
-                    let resp = actix_web::HttpResponse::Ok()
-                        .content_type("text/html")
-                        .cookie(cookie)
-                        .body(tmpl_new_session_result(true).into_string());
-                    resp
+        Ok(Some(Ok(login))) if login.needs_code => code_step_response(session_key),
+        Ok(Some(Ok(login))) => session_response(&pool, login.acc_id, session_key).await,
+    }
+}
+
+/// Log in: give user's browser the session cookie for the account, and
+/// clean up the pending login cookie if there's one.
+pub async fn start_session(pool: &StoragePool, acc_id: i64) -> actix_web::HttpResponse {
+    let session_key: String = uuid::Uuid::new_v4().to_string();
+    let key = session_key.clone();
+    pool.run(move |conn| set_login_values(conn, &key, acc_id, false, Utc::now()))
+        .await
+        .unwrap();
+    session_response(pool, acc_id, session_key).await
+}
+
+/// Session cookie of logged in session and the login result page.
+async fn session_response(
+    pool: &StoragePool,
+    acc_id: i64,
+    session_key: String,
+) -> actix_web::HttpResponse {
+    use std::convert::TryInto;
+    let prefs = pool
+        .run(move |conn| conn.get_preferences(acc_id))
+        .await
+        .unwrap();
+    let cookie = actix_web::http::Cookie::build("session", session_key)
+        .path("/")
+        .secure(true)
+        .max_age(
+            std::time::Duration::from_secs(60 * 60 * 24 * 365 * 2)
+                .try_into()
+                .expect("Error converting durations"),
+        )
+        .finish();
+    let pending_cookie = actix_web::http::Cookie::build(PENDING_LOGIN_COOKIE, "")
+        .path("/")
+        .finish();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .cookie(cookie)
+        .del_cookie(&pending_cookie)
+        .body(tmpl_new_session_result(&prefs, true).into_string())
+}
+
+/// Account has TOTP enabled, so instead of session the browser gets the
+/// pending login cookie and the code form. Pending login is kept in
+/// session table, without the `account` key other pages look for.
+pub async fn start_code_step(pool: &StoragePool, acc_id: i64) -> actix_web::HttpResponse {
+    let pending_key: String = uuid::Uuid::new_v4().to_string();
+    let key = pending_key.clone();
+    pool.run(move |conn| set_login_values(conn, &key, acc_id, true, Utc::now()))
+        .await
+        .unwrap();
+    code_step_response(pending_key)
+}
+
+/// Pending login cookie and the code form.
+fn code_step_response(pending_key: String) -> actix_web::HttpResponse {
+    use std::convert::TryInto;
+    let cookie = actix_web::http::Cookie::build(PENDING_LOGIN_COOKIE, pending_key)
+        .path("/")
+        .secure(true)
+        .http_only(true)
+        .max_age(
+            std::time::Duration::from_secs(PENDING_LOGIN_SECS as u64)
+                .try_into()
+                .expect("Error converting durations"),
+        )
+        .finish();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .cookie(cookie)
+        .body(tmpl_new_session_code(&Preferences::default(), "").into_string())
+}
+
+/// Outcome of a TOTP code entered for a pending login.
+enum CodeCheck {
+    /// Pending login expired, unknown or out of attempts, it's deleted.
+    Rejected,
+    Invalid,
+    Valid(i64),
+}
+
+/// User entered TOTP or recovery code after clicking the login link.
+pub async fn handle_post_new_session_code(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<PostNewSessionCodeParams>,
+) -> impl actix_web::Responder {
+    let prefs = Preferences::default();
+    let pending_key = match request.cookie(PENDING_LOGIN_COOKIE) {
+        Some(c) => c.value().to_string(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id = pool
+        .get_sess_val(&pending_key, "totp_account")
+        .await
+        .and_then(|a| a.parse::<i64>().ok());
+    let started = pool
+        .get_sess_val(&pending_key, "totp_started")
+        .await
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok());
+    let now = Utc::now();
+    let acc_id = match (acc_id, started) {
+        (Some(acc_id), Some(started))
+            if now.signed_duration_since(started) < Duration::seconds(PENDING_LOGIN_SECS) =>
+        {
+            Some(acc_id)
+        }
+        _ => None,
+    };
+    let code = params.code.clone();
+    let key = pending_key.clone();
+    let check = pool
+        .run(move |conn| {
+            // The attempt is counted before the code is checked, concurrent
+            // requests can't get past the limit.
+            let attempts = conn.increment_session_value(&key, "totp_attempts")?;
+            let acc_id = match (acc_id, attempts) {
+                (Some(acc_id), Some(attempts)) if attempts <= MAX_CODE_ATTEMPTS => acc_id,
+                _ => {
+                    conn.delete_session(&key)?;
+                    return Ok(CodeCheck::Rejected);
                 }
+            };
+            if !totp::check_code(conn, acc_id, &code, now)? {
+                return Ok(CodeCheck::Invalid);
             }
+            conn.delete_session(&key)?;
+            Ok(CodeCheck::Valid(acc_id))
+        })
+        .await
+        .unwrap();
+    let acc_id = match check {
+        CodeCheck::Valid(acc_id) => acc_id,
+        CodeCheck::Invalid => {
+            let resp_html = tmpl_new_session_code(&prefs, "Invalid code").into_string();
+            return actix_web::HttpResponse::BadRequest()
+                .content_type("text/html")
+                .body(resp_html);
         }
-    }
+        CodeCheck::Rejected => {
+            return actix_web::HttpResponse::BadRequest()
+                .content_type("text/html")
+                .body(tmpl_new_session_result(&prefs, false).into_string());
+        }
+    };
+    start_session(&pool, acc_id).await
 }
-
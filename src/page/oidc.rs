@@ -0,0 +1,196 @@
+//! OpenID Connect login, see `oidc` module.
+use actix_web::error::BlockingError;
+use actix_web::HttpMessage;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use crate::db::DbError;
+use crate::model::Config;
+use crate::model::Preferences;
+use crate::oidc;
+use crate::page::new_session::oidc_name;
+use crate::page::new_session::start_code_step;
+use crate::page::new_session::start_session;
+use crate::registration;
+use crate::storage::Storage;
+use crate::storage::StoragePool;
+use crate::tmpl::new_session::tmpl_new_session;
+
+/// Seconds to log in at the provider.
+const PENDING_LOGIN_SECS: i64 = 10 * 60;
+
+/// Cookie with state of login in progress, ties the callback to the
+/// browser that started the login.
+const OIDC_LOGIN_COOKIE: &str = "oidc-login";
+
+#[derive(Deserialize)]
+pub struct OidcCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Login form with the error, so user can try again.
+fn login_failed(config: &Config, error: &str) -> actix_web::HttpResponse {
+    let resp_html = tmpl_new_session(
+        &Preferences::default(),
+        "",
+        None,
+        &format!("Login failed: {}", error),
+        oidc_name(config),
+    )
+    .into_string();
+    actix_web::HttpResponse::BadRequest()
+        .content_type("text/html")
+        .body(resp_html)
+}
+
+/// Get account id by email verified by provider, create the account if
+/// there's none and the registration policy allows it, like login links
+/// do. There's no invite code here, so invite-only instances need the
+/// account created first.
+fn oidc_account(
+    conn: &mut dyn Storage,
+    config: &Config,
+    email: &str,
+) -> Result<Result<i64, String>, DbError> {
+    match conn.get_acc_id_by_email(email)? {
+        Some(acc_id) if conn.is_account_disabled(acc_id)? => {
+            Ok(Err("user account is disabled".to_string()))
+        }
+        Some(acc_id) => Ok(Ok(acc_id)),
+        None => Ok(registration::create_account(conn, config, email, None)?
+            .map_err(|rejection| rejection.message(config))),
+    }
+}
+
+/// Redirect to provider login page.
+pub async fn handle_oidc_login(
+    config: actix_web::web::Data<Config>,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    use std::convert::TryInto;
+    let conf = match &config.oidc {
+        Some(conf) => conf.clone(),
+        None => return actix_web::HttpResponse::NotFound().body("Not found"),
+    };
+    let (state, nonce, verifier) = (
+        oidc::random_token(),
+        oidc::random_token(),
+        oidc::random_token(),
+    );
+    let redirect_url = conf.redirect_url(&config.base_url);
+    let (s, n, v) = (state.clone(), nonce.clone(), verifier.clone());
+    let url = actix_web::web::block(move || {
+        let provider = oidc::discover(&conf)?;
+        oidc::authorization_url(&provider, &conf, &redirect_url, &s, &n, &v)
+    })
+    .await;
+    let url = match url {
+        Ok(url) => url,
+        Err(e) => {
+            warn!("OpenID Connect login failed: {}.", e);
+            return login_failed(&config, "provider is not available");
+        }
+    };
+    let key = state.clone();
+    pool.run(move |conn| {
+        conn.set_session_value(&key, "oidc_nonce", &nonce)?;
+        conn.set_session_value(&key, "oidc_verifier", &verifier)?;
+        conn.set_session_value(&key, "oidc_started", &Utc::now().to_rfc3339())
+    })
+    .await
+    .unwrap();
+    let cookie = actix_web::http::Cookie::build(OIDC_LOGIN_COOKIE, state)
+        .path("/")
+        .secure(true)
+        .http_only(true)
+        .max_age(
+            std::time::Duration::from_secs(PENDING_LOGIN_SECS as u64)
+                .try_into()
+                .expect("Error converting durations"),
+        )
+        .finish();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", url)
+        .cookie(cookie)
+        .body("Redirecting...")
+}
+
+/// Provider redirects back here with the authorization code.
+pub async fn handle_oidc_callback(
+    request: actix_web::HttpRequest,
+    config: actix_web::web::Data<Config>,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<OidcCallbackParams>,
+) -> impl actix_web::Responder {
+    let conf = match &config.oidc {
+        Some(conf) => conf.clone(),
+        None => return actix_web::HttpResponse::NotFound().body("Not found"),
+    };
+    let state = match (request.cookie(OIDC_LOGIN_COOKIE), &params.state) {
+        (Some(c), Some(state)) if c.value() == state => state.clone(),
+        _ => return login_failed(&config, "login was not started in this browser"),
+    };
+    let nonce = pool.get_sess_val(&state, "oidc_nonce").await;
+    let verifier = pool.get_sess_val(&state, "oidc_verifier").await;
+    let started = pool
+        .get_sess_val(&state, "oidc_started")
+        .await
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok());
+    let key = state.clone();
+    pool.run(move |conn| conn.delete_session(&key))
+        .await
+        .unwrap();
+    let (nonce, verifier) = match (nonce, verifier, started) {
+        (Some(nonce), Some(verifier), Some(started))
+            if Utc::now().signed_duration_since(started)
+                < Duration::seconds(PENDING_LOGIN_SECS) =>
+        {
+            (nonce, verifier)
+        }
+        _ => return login_failed(&config, "login expired"),
+    };
+    if let Some(error) = &params.error {
+        let description = params.error_description.as_deref().unwrap_or(error);
+        return login_failed(&config, description);
+    }
+    let code = match &params.code {
+        Some(code) => code.clone(),
+        None => return login_failed(&config, "provider didn't return code"),
+    };
+    let redirect_url = conf.redirect_url(&config.base_url);
+    let email = actix_web::web::block(move || {
+        let provider = oidc::discover(&conf)?;
+        oidc::verified_email(&provider, &conf, &redirect_url, &code, &verifier, &nonce)
+    })
+    .await;
+    let email = match email {
+        Ok(email) => email,
+        Err(BlockingError::Error(e)) => {
+            warn!("OpenID Connect login failed: {}.", e);
+            return login_failed(&config, &e);
+        }
+        Err(BlockingError::Canceled) => {
+            return actix_web::HttpResponse::InternalServerError().body("Thread pool is gone")
+        }
+    };
+    let conf = config.clone();
+    let (acc_id, totp_enabled) = pool
+        .run(move |conn| match oidc_account(conn, &conf, &email)? {
+            Ok(acc_id) => Ok((
+                Ok(acc_id),
+                conn.get_totp(acc_id)?.is_some_and(|t| t.enabled),
+            )),
+            Err(error) => Ok((Err(error), false)),
+        })
+        .await
+        .unwrap();
+    match acc_id {
+        Err(error) => login_failed(&config, &error),
+        Ok(acc_id) if totp_enabled => start_code_step(&pool, acc_id).await,
+        Ok(acc_id) => start_session(&pool, acc_id).await,
+    }
+}
@@ -0,0 +1,264 @@
+//! Unit prices of commodities held in holdings.
+use actix_web::HttpMessage;
+
+use crate::currency;
+use crate::model::Price;
+use crate::model::MAX_COMMODITY_LENGTH;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::util::parse_ts;
+
+#[derive(Deserialize)]
+pub struct AddPriceParams {
+    pub commodity: String,
+    pub currency: String,
+    pub ts: String,
+    pub price: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImportPricesParams {
+    pub csv: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeletePriceParams {
+    pub id: i64,
+}
+
+/// Validate single price, `line` is used in error messages if given.
+fn parse_price(
+    commodity: &str,
+    currency: &str,
+    ts: &str,
+    price: &str,
+    line: Option<usize>,
+) -> Result<Price, String> {
+    let prefix = match line {
+        Some(line) => format!("Line {}: ", line),
+        None => String::new(),
+    };
+    let commodity = commodity.trim();
+    let currency = currency.trim().to_uppercase();
+    if commodity.is_empty() {
+        return Err(format!("{}Commodity is required", prefix));
+    }
+    if commodity.chars().count() > MAX_COMMODITY_LENGTH {
+        return Err(format!(
+            "{}Commodity is longer than {} characters",
+            prefix, MAX_COMMODITY_LENGTH
+        ));
+    }
+    if currency.is_empty() {
+        return Err(format!("{}Currency is required", prefix));
+    }
+    if currency::find(&currency).is_none() {
+        return Err(format!("{}Unknown currency {}", prefix, currency));
+    }
+    let ts = parse_ts(ts.trim()).map_err(|e| format!("{}{}", prefix, e))?;
+    let price = price.trim();
+    if !price.parse::<f64>().is_ok_and(f64::is_finite) {
+        return Err(format!("{}Invalid price: {}", prefix, price));
+    }
+    Ok(Price {
+        commodity: commodity.to_string(),
+        currency,
+        ts,
+        price: price.to_string(),
+    })
+}
+
+/// Parse CSV with `commodity,currency,ts,price` lines.
+/// Header line and empty lines are skipped.
+fn parse_prices_csv(csv: &str) -> Result<Vec<Price>, String> {
+    let mut prices = Vec::new();
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line.starts_with("commodity")) {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() != 4 {
+            return Err(format!(
+                "Line {}: expected commodity,currency,ts,price",
+                i + 1
+            ));
+        }
+        prices.push(parse_price(
+            fields[0],
+            fields[1],
+            fields[2],
+            fields[3],
+            Some(i + 1),
+        )?);
+    }
+    Ok(prices)
+}
+
+/// Prices page, with status 400 if there's an `error`.
+async fn render_prices(
+    pool: &StoragePool,
+    account_id: i64,
+    error: Option<&str>,
+) -> actix_web::HttpResponse {
+    let (prefs, prices) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(account_id)?,
+                conn.get_prices(account_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let content = tmpl::prices::tmpl_prices(&prefs, &prices, error).into_string();
+    let mut response = match error {
+        Some(_) => actix_web::HttpResponse::BadRequest(),
+        None => actix_web::HttpResponse::Ok(),
+    };
+    response.content_type("text/html").body(content)
+}
+
+pub async fn handle_prices(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    render_prices(&pool, account_id, None).await
+}
+
+pub async fn handle_post_price(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<AddPriceParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    match parse_price(
+        &params.commodity,
+        &params.currency,
+        &params.ts,
+        &params.price,
+        None,
+    ) {
+        Ok(price) => {
+            pool.run(move |conn| conn.insert_prices(account_id, &[price]))
+                .await
+                .unwrap();
+            actix_web::HttpResponse::SeeOther()
+                .header("Location", "prices")
+                .body("Redirecting...")
+        }
+        Err(e) => render_prices(&pool, account_id, Some(&e)).await,
+    }
+}
+
+pub async fn handle_post_import_prices(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<ImportPricesParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    match parse_prices_csv(&params.csv) {
+        Ok(prices) => {
+            pool.run(move |conn| conn.insert_prices(account_id, &prices))
+                .await
+                .unwrap();
+            actix_web::HttpResponse::SeeOther()
+                .header("Location", "prices")
+                .body("Redirecting...")
+        }
+        Err(e) => render_prices(&pool, account_id, Some(&e)).await,
+    }
+}
+
+pub async fn handle_delete_price(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<DeletePriceParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let price_id = params.id;
+    pool.run(move |conn| conn.delete_price(account_id, price_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "prices")
+        .body("Redirecting...")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices() {
+        let price = parse_price(" VWCE ", "EUR", "2024-01-02 00:00:00", "101.5", None).unwrap();
+        assert_eq!(price.commodity, "VWCE");
+        assert_eq!(price.price, "101.5");
+        let long = "X".repeat(MAX_COMMODITY_LENGTH + 1);
+        assert_eq!(
+            parse_price(&long, "EUR", "2024-01-02 00:00:00", "1", Some(2)).err(),
+            Some("Line 2: Commodity is longer than 32 characters".to_string())
+        );
+        let price = parse_price("VWCE", " eur", "2024-01-02 00:00:00", "1", None).unwrap();
+        assert_eq!(price.currency, "EUR");
+        assert_eq!(
+            parse_price("VWCE", "EURO", "2024-01-02 00:00:00", "1", None).err(),
+            Some("Unknown currency EURO".to_string())
+        );
+        assert!(parse_price("VWCE", "XYZ", "2024-01-02 00:00:00", "1", None).is_err());
+        assert!(parse_price("VWCE", "EUR", "2024-01-02 00:00:00", "inf", None).is_err());
+        assert!(parse_price("VWCE", "EUR", "2024-01-02 00:00:00", "NaN", None).is_err());
+    }
+}
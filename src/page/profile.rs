@@ -1,15 +1,13 @@
 //! The profile page.
 use actix_web::HttpMessage;
 
-use crate::common;
-use crate::db;
+use crate::storage::StoragePool;
 use crate::tmpl;
 
 pub async fn handle_profile(
     request: actix_web::HttpRequest,
-    pool: actix_web::web::Data<common::DatabasePool>,
+    pool: actix_web::web::Data<StoragePool>,
 ) -> impl actix_web::Responder {
-    let mut conn = pool.get().unwrap();
     let cookie = match request.cookie("session") {
         Some(c) => c,
         None => {
@@ -19,14 +17,28 @@ pub async fn handle_profile(
         }
     };
     let sess_key = cookie.value();
-    let acc_id = db::get_sess_val(&mut conn, sess_key, "account")
+    let acc_id: i64 = pool
+        .get_sess_val(sess_key, "account")
+        .await
         .unwrap()
         .parse()
         .unwrap();
-    let acc_info = db::get_user_account_info(&mut conn, acc_id)
-        .unwrap()
+    let (prefs, acc_info, api_tokens, webhooks, totp) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_user_account_info(acc_id)?,
+                conn.get_api_tokens(acc_id)?,
+                conn.get_webhooks(acc_id)?,
+                conn.get_totp(acc_id)?,
+            ))
+        })
+        .await
         .unwrap();
-    let content = tmpl::profile::tmpl_profile(&acc_info).into_string();
+    let acc_info = acc_info.unwrap();
+    let content =
+        tmpl::profile::tmpl_profile(&prefs, &acc_info, &api_tokens, &webhooks, totp.as_ref())
+            .into_string();
     actix_web::HttpResponse::Ok()
         .content_type("text/html")
         .body(content)
@@ -0,0 +1,119 @@
+//! Quick-add page of one bank account, to bookmark on a phone.
+use actix_web::HttpMessage;
+
+use crate::model::ShareRole;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::util::parse_ts;
+
+#[derive(Deserialize)]
+pub struct QuickPathParams {
+    /// Bank account id.
+    pub id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct QuickPostParams {
+    pub amount: String,
+    /// Now if blank.
+    pub ts: String,
+}
+
+/// Render the page, or not found if the user account can't add entries to
+/// the bank account.
+async fn render_quick(
+    pool: &StoragePool,
+    account_id: i64,
+    bank_account_id: i64,
+    amount: &str,
+    error: Option<&str>,
+) -> actix_web::HttpResponse {
+    let (prefs, bank_account, infos) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(account_id)?,
+                conn.get_bank_account(account_id, bank_account_id)?,
+                conn.get_bank_account_infos(account_id, None)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let bank_account = match bank_account {
+        Some(bank_account) if ShareRole::can_edit(bank_account.role) => bank_account,
+        _ => return actix_web::HttpResponse::NotFound().body("Not found"),
+    };
+    let last = infos.iter().find(|i| i.id == bank_account_id);
+    let content = tmpl::quick::tmpl_quick(&prefs, &bank_account, last, amount, error).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+pub async fn handle_quick(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    path: actix_web::web::Path<QuickPathParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(account_id) => account_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    render_quick(&pool, account_id, path.id, "", None).await
+}
+
+/// Save the entry and show the page again with the new balance.
+pub async fn handle_post_quick(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    path: actix_web::web::Path<QuickPathParams>,
+    params: actix_web::web::Form<QuickPostParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(account_id) => account_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let amount = params.amount.trim().to_string();
+    // "NaN" and "inf" parse too, but aren't amounts.
+    if !amount.parse::<f64>().is_ok_and(f64::is_finite) {
+        let error = format!("Invalid amount: {}", amount);
+        return render_quick(&pool, account_id, path.id, &amount, Some(&error)).await;
+    }
+    let ts = match params.ts.trim() {
+        "" => chrono::Utc::now(),
+        ts => match parse_ts(ts) {
+            Ok(ts) => ts,
+            Err(e) => return render_quick(&pool, account_id, path.id, &amount, Some(&e)).await,
+        },
+    };
+    let bank_account_id = path.id;
+    pool.run(move |conn| conn.insert_entry(&account_id, &bank_account_id, &ts, &amount, None))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", format!("/quick/{}", bank_account_id))
+        .body("Redirecting...")
+}
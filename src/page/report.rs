@@ -0,0 +1,141 @@
+//! Period-change report.
+use actix_web::web::Data;
+use actix_web::HttpMessage;
+use actix_web::Responder;
+
+use crate::report;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::util::csv_field;
+use crate::util::format_amount;
+
+#[derive(Deserialize)]
+pub struct ReportParams {
+    /// Either "month" or "year", defaults to month.
+    pub period: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HandleReportFileParams {
+    pub filename: String,
+}
+
+fn report_to_csv(report: &report::Report) -> String {
+    let mut csv = String::new();
+    csv.push_str("period,type,account,currency,balance,change,change_pct\n");
+    for (kind, rows) in &[("account", &report.rows), ("total", &report.totals)] {
+        for r in rows.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                r.period,
+                kind,
+                csv_field(r.bank_account.as_deref().unwrap_or("")),
+                r.currency,
+                format_amount(r.balance),
+                r.change.map(format_amount).unwrap_or_default(),
+                r.change_pct.map(format_amount).unwrap_or_default()
+            ));
+        }
+    }
+    csv
+}
+
+fn get_period(params: &ReportParams) -> report::Period {
+    params
+        .period
+        .as_deref()
+        .and_then(report::Period::parse)
+        .unwrap_or(report::Period::Month)
+}
+
+/// Show the report page.
+pub async fn handle_report(
+    req: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+    params: actix_web::web::Query<ReportParams>,
+) -> impl Responder {
+    let cookie = match req.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(acc_id) => acc_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let period = get_period(&params);
+    let (prefs, entries) = pool
+        .run(move |conn| Ok((conn.get_preferences(acc_id)?, conn.get_all_entries(acc_id)?)))
+        .await
+        .unwrap();
+    let report = report::build_report(&entries, period, chrono::Utc::now());
+    let content = tmpl::report::tmpl_report(&prefs, period, &report).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+/// Generate the report as CSV file.
+pub async fn handle_report_file(
+    req: actix_web::HttpRequest,
+    pool: Data<StoragePool>,
+    path: actix_web::web::Path<HandleReportFileParams>,
+    params: actix_web::web::Query<ReportParams>,
+) -> impl Responder {
+    let cookie = match req.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("New session")
+        }
+    };
+    let acc_id: i64 = match pool.get_sess_val(cookie.value(), "account").await {
+        Some(acc_id) => acc_id.parse().unwrap(),
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("New session")
+        }
+    };
+    let entries = pool
+        .run(move |conn| conn.get_all_entries(acc_id))
+        .await
+        .unwrap();
+    let report = report::build_report(&entries, get_period(&params), chrono::Utc::now());
+    actix_web::HttpResponse::Ok()
+        .content_type("text/csv")
+        .header("Content-Disposition", path.filename.to_string())
+        .body(report_to_csv(&report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_names() {
+        let report = report::Report {
+            rows: vec![report::ReportRow {
+                period: "2024-01".to_string(),
+                bank_account: Some("Savings, \"joint\"".to_string()),
+                currency: "PLN".to_string(),
+                balance: 1.0,
+                change: None,
+                change_pct: None,
+            }],
+            totals: Vec::new(),
+        };
+        assert_eq!(
+            report_to_csv(&report).lines().nth(1),
+            Some("2024-01,account,\"Savings, \"\"joint\"\"\",PLN,1.00,,")
+        );
+    }
+}
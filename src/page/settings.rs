@@ -0,0 +1,160 @@
+//! Per-user settings: theme, landing page, add page default, paging and
+//! email digest.
+use actix_web::HttpMessage;
+
+use crate::model::BankAccount;
+use crate::model::DigestFrequency;
+use crate::model::LandingPage;
+use crate::model::Preferences;
+use crate::model::ShareRole;
+use crate::model::Theme;
+use crate::model::MAX_ENTRIES_PER_PAGE;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::tmpl::settings::SettingsForm;
+
+#[derive(Deserialize)]
+pub struct SettingsParams {
+    pub theme: String,
+    pub landing_page: String,
+    /// Bank account id, blank for none.
+    pub default_bank_account: String,
+    pub entries_per_page: String,
+    pub digest: String,
+}
+
+/// Preferences and the bank accounts entries can be added to.
+async fn get_settings(pool: &StoragePool, account_id: i64) -> (Preferences, Vec<BankAccount>) {
+    let (prefs, bank_accounts) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(account_id)?,
+                conn.get_bank_accounts(account_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let bank_accounts = bank_accounts
+        .into_iter()
+        .filter(|ba| ShareRole::can_edit(ba.role))
+        .collect();
+    (prefs, bank_accounts)
+}
+
+fn render_settings(
+    prefs: &Preferences,
+    form: &SettingsForm,
+    bank_accounts: &[BankAccount],
+    error: Option<&str>,
+) -> actix_web::HttpResponse {
+    let content = tmpl::settings::tmpl_settings(prefs, form, bank_accounts, error).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+/// Parse settings form, the default bank account has to be one of
+/// `bank_accounts`.
+fn parse_settings(
+    params: &SettingsParams,
+    bank_accounts: &[BankAccount],
+) -> Result<Preferences, String> {
+    let theme =
+        Theme::parse(&params.theme).ok_or_else(|| format!("Unknown theme: {}.", params.theme))?;
+    let landing_page = LandingPage::parse(&params.landing_page)
+        .ok_or_else(|| format!("Unknown landing page: {}.", params.landing_page))?;
+    let default_bank_account = match params.default_bank_account.trim() {
+        "" => None,
+        id => match id.parse::<i64>() {
+            Ok(id) if bank_accounts.iter().any(|ba| ba.id == id) => Some(id),
+            _ => return Err(format!("Unknown bank account: {}.", id)),
+        },
+    };
+    let entries_per_page = match params.entries_per_page.trim().parse::<i64>() {
+        Ok(n) if (1..=MAX_ENTRIES_PER_PAGE).contains(&n) => n,
+        _ => {
+            return Err(format!(
+                "Entries per page must be a number from 1 to {}.",
+                MAX_ENTRIES_PER_PAGE
+            ))
+        }
+    };
+    let digest = DigestFrequency::parse(&params.digest)
+        .ok_or_else(|| format!("Unknown digest frequency: {}.", params.digest))?;
+    Ok(Preferences {
+        theme,
+        landing_page,
+        default_bank_account,
+        entries_per_page,
+        digest,
+    })
+}
+
+pub async fn handle_settings(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let (prefs, bank_accounts) = get_settings(&pool, account_id).await;
+    render_settings(
+        &prefs,
+        &SettingsForm::from_preferences(&prefs),
+        &bank_accounts,
+        None,
+    )
+}
+
+pub async fn handle_post_settings(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<SettingsParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "new-session")
+                .body("Redirecting...")
+        }
+    };
+    let account_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let (prefs, bank_accounts) = get_settings(&pool, account_id).await;
+    let new_prefs = match parse_settings(&params, &bank_accounts) {
+        Ok(new_prefs) => new_prefs,
+        Err(e) => {
+            let form = SettingsForm {
+                theme: Theme::parse(&params.theme).unwrap_or(prefs.theme),
+                landing_page: LandingPage::parse(&params.landing_page)
+                    .unwrap_or(prefs.landing_page),
+                default_bank_account: params.default_bank_account.trim().parse().ok(),
+                entries_per_page: params.entries_per_page.clone(),
+                digest: DigestFrequency::parse(&params.digest).unwrap_or(prefs.digest),
+            };
+            return render_settings(&prefs, &form, &bank_accounts, Some(&e));
+        }
+    };
+    pool.run(move |conn| conn.set_preferences(account_id, &new_prefs))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "settings")
+        .body("Redirecting...")
+}
@@ -0,0 +1,163 @@
+//! TOTP two-factor authentication setup, managed on profile page.
+use actix_web::HttpMessage;
+use chrono::Utc;
+
+use crate::model::Preferences;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::totp;
+
+#[derive(Deserialize)]
+pub struct TotpCodeParams {
+    pub code: String,
+}
+
+/// Setup page for the secret stored in enrolment, with QR code.
+async fn setup_page(pool: &StoragePool, acc_id: i64, error: &str) -> actix_web::HttpResponse {
+    let (prefs, emails, totp) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_user_account_emails(acc_id)?,
+                conn.get_totp(acc_id)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let secret = match totp {
+        Some(totp) if !totp.enabled => totp.secret,
+        _ => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/profile")
+                .body("Redirecting...")
+        }
+    };
+    let email = emails.first().cloned().unwrap_or_default();
+    let qr = totp::qr_svg(&totp::otpauth_url(&secret, &email));
+    let content = tmpl::totp::tmpl_totp_setup(&prefs, &secret, &qr, error).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+/// Start enrolment with new secret.
+pub async fn handle_post_totp_setup(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let secret = totp::generate_secret();
+    pool.run(move |conn| conn.set_totp_secret(acc_id, &secret))
+        .await
+        .unwrap();
+    setup_page(&pool, acc_id, "").await
+}
+
+/// Confirm enrolment with the first code, then show recovery codes, this is
+/// the only time they're shown.
+pub async fn handle_post_totp_enable(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<TotpCodeParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let code = params.code.clone();
+    let recovery_codes = totp::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|c| totp::hash_recovery_code(c))
+        .collect();
+    let enabled = pool
+        .run(move |conn| {
+            let step = match conn.get_totp(acc_id)? {
+                Some(t) if !t.enabled => totp::verify(&t.secret, &code, Utc::now(), None),
+                _ => None,
+            };
+            match step {
+                Some(step) => {
+                    conn.enable_totp(acc_id, step, &hashes)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+        .await
+        .unwrap();
+    if !enabled {
+        return setup_page(&pool, acc_id, "Invalid code").await;
+    }
+    let prefs = pool.get_sess_preferences(cookie.value()).await;
+    let content = tmpl::totp::tmpl_totp_enabled(&prefs, &recovery_codes).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+/// Turn TOTP off, takes current TOTP code or recovery code.
+pub async fn handle_post_totp_disable(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<TotpCodeParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let code = params.code.clone();
+    let disabled = pool
+        .run(move |conn| {
+            let ok = totp::check_code(conn, acc_id, &code, Utc::now())?;
+            if ok {
+                conn.delete_totp(acc_id)?;
+            }
+            Ok(ok)
+        })
+        .await
+        .unwrap();
+    if !disabled {
+        let prefs: Preferences = pool.get_sess_preferences(cookie.value()).await;
+        let content = tmpl::totp::tmpl_totp_invalid_code(&prefs).into_string();
+        return actix_web::HttpResponse::BadRequest()
+            .content_type("text/html")
+            .body(content);
+    }
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "/profile")
+        .body("Redirecting...")
+}
@@ -0,0 +1,130 @@
+//! Webhooks called when entries or bank accounts change, managed on
+//! profile page, see `webhook` module.
+use actix_web::HttpMessage;
+
+use crate::model::Config;
+use crate::storage::StoragePool;
+use crate::tmpl;
+use crate::webhook;
+
+/// Deliveries shown in the log.
+const LOG_SIZE: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct CreateWebhookParams {
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteWebhookParams {
+    pub id: i64,
+}
+
+/// Add webhook and show its secret, this is the only time it's shown.
+pub async fn handle_post_webhook(
+    request: actix_web::HttpRequest,
+    config: actix_web::web::Data<Config>,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Form<CreateWebhookParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let url = params.url.trim();
+    let (url_arg, allowed_hosts) = (url.to_string(), config.webhook_allowed_hosts.clone());
+    match actix_web::web::block(move || webhook::check_url(&url_arg, &allowed_hosts)).await {
+        Ok(()) => (),
+        Err(actix_web::error::BlockingError::Error(e)) => {
+            return actix_web::HttpResponse::BadRequest().body(e)
+        }
+        Err(actix_web::error::BlockingError::Canceled) => {
+            return actix_web::HttpResponse::InternalServerError().body("Internal server error")
+        }
+    }
+    let secret = webhook::new_secret();
+    let (url_arg, secret_arg) = (url.to_string(), secret.clone());
+    let prefs = pool
+        .run(move |conn| {
+            conn.insert_webhook(acc_id, &url_arg, &secret_arg)?;
+            conn.get_preferences(acc_id)
+        })
+        .await
+        .unwrap();
+    let content = tmpl::webhooks::tmpl_webhook_created(&prefs, url, &secret).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
+
+pub async fn handle_delete_webhook(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+    params: actix_web::web::Query<DeleteWebhookParams>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let webhook_id = params.id;
+    pool.run(move |conn| conn.delete_webhook(acc_id, webhook_id))
+        .await
+        .unwrap();
+    actix_web::HttpResponse::SeeOther()
+        .header("Location", "/profile")
+        .body("Redirecting...")
+}
+
+/// Latest deliveries to webhooks of the user account.
+pub async fn handle_webhook_deliveries(
+    request: actix_web::HttpRequest,
+    pool: actix_web::web::Data<StoragePool>,
+) -> impl actix_web::Responder {
+    let cookie = match request.cookie("session") {
+        Some(c) => c,
+        None => {
+            return actix_web::HttpResponse::SeeOther()
+                .header("Location", "/new-session")
+                .body("Redirecting...")
+        }
+    };
+    let acc_id: i64 = pool
+        .get_sess_val(cookie.value(), "account")
+        .await
+        .unwrap()
+        .parse()
+        .unwrap();
+    let (prefs, deliveries) = pool
+        .run(move |conn| {
+            Ok((
+                conn.get_preferences(acc_id)?,
+                conn.get_webhook_deliveries(acc_id, LOG_SIZE)?,
+            ))
+        })
+        .await
+        .unwrap();
+    let content = tmpl::webhooks::tmpl_webhook_deliveries(&prefs, &deliveries).into_string();
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html")
+        .body(content)
+}
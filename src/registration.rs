@@ -0,0 +1,146 @@
+//! Registration policy: who can create user account by logging in, set with
+//! `registration` in config, see `RegistrationMode`.
+//!
+//! The policy is checked on login form, so user gets a clear message, and
+//! again when the login link or OpenID Connect login creates the account.
+//! Invite codes are made with `cashlog admin create-invite` and used up
+//! when the account is created.
+
+use crate::db::DbError;
+use crate::model::Config;
+use crate::model::RegistrationMode;
+use crate::storage::Storage;
+
+/// Characters in invite code, without dashes.
+const INVITE_LEN: usize = 12;
+
+/// Why user account can't be created.
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    Closed,
+    Domain,
+    InviteRequired,
+    InvalidInvite,
+}
+
+impl Rejection {
+    /// Message for the login page.
+    pub fn message(&self, conf: &Config) -> String {
+        match self {
+            Rejection::Closed => {
+                "Registration is closed, ask the administrator for a user account.".to_string()
+            }
+            Rejection::Domain => format!(
+                "Only email addresses at {} can create a user account.",
+                conf.registration_domains.join(", ")
+            ),
+            Rejection::InviteRequired => {
+                "An invite code is required to create a user account.".to_string()
+            }
+            Rejection::InvalidInvite => "Invalid or already used invite code.".to_string(),
+        }
+    }
+}
+
+/// New random invite code, e.g. "K3M9-QX7D-2HFA".
+pub fn generate_invite() -> String {
+    let mut buf = [0u8; 8];
+    getrandom::getrandom(&mut buf).expect("Error getting random bytes");
+    let code = base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &buf);
+    normalize_invite(&code[..INVITE_LEN])
+}
+
+/// Invite code as stored: uppercase, in groups of four, so user can type
+/// it any way.
+pub fn normalize_invite(code: &str) -> String {
+    let chars: Vec<char> = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    chars
+        .chunks(4)
+        .map(|c| c.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// Email domain is one of `domains` exactly, subdomains aren't allowed.
+fn domain_allowed(domains: &[String], email: &str) -> bool {
+    let domain = match email.trim().rsplit_once('@') {
+        Some((_, domain)) => domain.to_lowercase(),
+        None => return false,
+    };
+    domains
+        .iter()
+        .any(|d| d.trim().trim_start_matches('@').to_lowercase() == domain)
+}
+
+/// Check if new user account can be created for the email, `None` if it
+/// can. The invite code is checked, but not used up.
+pub fn check(
+    conn: &mut dyn Storage,
+    conf: &Config,
+    email: &str,
+    invite: Option<&str>,
+) -> Result<Option<Rejection>, DbError> {
+    Ok(match conf.registration {
+        RegistrationMode::Open => None,
+        RegistrationMode::Closed => Some(Rejection::Closed),
+        RegistrationMode::Domains if domain_allowed(&conf.registration_domains, email) => None,
+        RegistrationMode::Domains => Some(Rejection::Domain),
+        RegistrationMode::Invite => match invite {
+            None => Some(Rejection::InviteRequired),
+            Some(code) if conn.is_invite_valid(code)? => None,
+            Some(_) => Some(Rejection::InvalidInvite),
+        },
+    })
+}
+
+/// Create user account if the policy allows it, using up the invite code.
+pub fn create_account(
+    conn: &mut dyn Storage,
+    conf: &Config,
+    email: &str,
+    invite: Option<&str>,
+) -> Result<Result<i64, Rejection>, DbError> {
+    if let Some(rejection) = check(conn, conf, email, invite)? {
+        return Ok(Err(rejection));
+    }
+    if let (RegistrationMode::Invite, Some(code)) = (conf.registration, invite) {
+        if !conn.use_invite(code)? {
+            return Ok(Err(Rejection::InvalidInvite));
+        }
+    }
+    Ok(Ok(conn.create_acc_with_email(email)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invites() {
+        assert_eq!(normalize_invite("K3M9-QX7D-2HFA"), "K3M9-QX7D-2HFA");
+        assert_eq!(normalize_invite(" k3m9 qx7d\t2hfa "), "K3M9-QX7D-2HFA");
+        assert_eq!(normalize_invite("k3m9qx7d2hfa"), "K3M9-QX7D-2HFA");
+        assert_eq!(normalize_invite("K3M9-QX"), "K3M9-QX");
+        assert_eq!(normalize_invite(" - "), "");
+        let code = generate_invite();
+        assert_eq!(code.len(), INVITE_LEN + INVITE_LEN / 4 - 1);
+        assert_eq!(normalize_invite(&code.to_lowercase()), code);
+    }
+
+    #[test]
+    fn domains() {
+        let domains = vec!["example.com".to_string(), " @Corp.Example.org ".to_string()];
+        assert!(domain_allowed(&domains, "jane@example.com"));
+        assert!(domain_allowed(&domains, " Jane@EXAMPLE.com "));
+        assert!(domain_allowed(&domains, "john@corp.example.org"));
+        assert!(!domain_allowed(&domains, "jane@mail.example.com"));
+        assert!(!domain_allowed(&domains, "jane@evil-example.com"));
+        assert!(!domain_allowed(&domains, "jane@example.com.evil.org"));
+        assert!(!domain_allowed(&domains, "example.com"));
+        assert!(!domain_allowed(&[], "jane@example.com"));
+    }
+}
@@ -0,0 +1,453 @@
+//! Period-change report.
+//!
+//! Entries are balance snapshots, so the interesting number is the change
+//! between them. For every period boundary we take the last snapshot at or
+//! before it and compare it with the previous boundary.
+
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::NaiveDate;
+use chrono::Utc;
+
+use crate::model::EntryInfo;
+
+/// Length of a report period.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Period {
+    Month,
+    Year,
+}
+
+impl Period {
+    pub fn parse(s: &str) -> Option<Period> {
+        match s {
+            "month" => Some(Period::Month),
+            "year" => Some(Period::Year),
+            _ => None,
+        }
+    }
+
+    /// Name as used in query params.
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Month => "month",
+            Period::Year => "year",
+        }
+    }
+
+    /// First day of the period that contains given day.
+    fn start(self, d: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Month => NaiveDate::from_ymd_opt(d.year(), d.month(), 1).unwrap(),
+            Period::Year => NaiveDate::from_ymd_opt(d.year(), 1, 1).unwrap(),
+        }
+    }
+
+    /// First day of the period that follows the one starting at given day.
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Month if start.month() == 12 => {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1).unwrap()
+            }
+            Period::Month => NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1).unwrap(),
+            Period::Year => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1).unwrap(),
+        }
+    }
+
+    fn label(self, start: NaiveDate) -> String {
+        match self {
+            Period::Month => start.format("%Y-%m").to_string(),
+            Period::Year => start.format("%Y").to_string(),
+        }
+    }
+}
+
+/// Balance at the end of one period.
+pub struct ReportRow {
+    pub period: String,
+    /// Bank account name, `None` in currency totals.
+    pub bank_account: Option<String>,
+    pub currency: String,
+    pub balance: f64,
+    /// Change since the end of previous period, if there was a balance then.
+    pub change: Option<f64>,
+    /// The change as percent of the previous balance.
+    pub change_pct: Option<f64>,
+}
+
+pub struct Report {
+    /// Rows per bank account, oldest period first.
+    pub rows: Vec<ReportRow>,
+    /// Rows per currency, oldest period first. Liabilities are subtracted.
+    pub totals: Vec<ReportRow>,
+}
+
+/// Balance at given ts.
+pub type Snapshot = (DateTime<Utc>, f64);
+
+fn compute_change(prev: Option<f64>, balance: f64) -> (Option<f64>, Option<f64>) {
+    match prev {
+        Some(prev) => {
+            let change = balance - prev;
+            let pct = if prev != 0.0 {
+                Some(change / prev.abs() * 100.0)
+            } else {
+                None
+            };
+            (Some(change), pct)
+        }
+        None => (None, None),
+    }
+}
+
+/// Build the report from entries, periods end at (and include) the one
+/// containing `now`.
+pub fn build_report(entries: &[EntryInfo], period: Period, now: DateTime<Utc>) -> Report {
+    let mut report = Report {
+        rows: Vec::new(),
+        totals: Vec::new(),
+    };
+    // Snapshots by (bank account name, currency, id), ordered by ts, with
+    // the sign of the bank account in totals. Name only orders the rows,
+    // shared bank accounts may have the same name and currency.
+    let mut snapshots: BTreeMap<(&str, &str, i64), (f64, Vec<Snapshot>)> = BTreeMap::new();
+    for e in entries {
+        // Holdings without price can't be valued, skip them.
+        let value = match &e.value {
+            Some(value) => value.parse().unwrap(),
+            None => continue,
+        };
+        snapshots
+            .entry((&e.bank_account, &e.currency, e.bank_account_id))
+            .or_insert_with(|| (e.kind.sign(), Vec::new()))
+            .1
+            .push((e.ts, value));
+    }
+    for (_, v) in snapshots.values_mut() {
+        v.sort_by_key(|s| s.0);
+    }
+    let first_ts = match snapshots
+        .values()
+        .filter_map(|(_, v)| v.first())
+        .map(|s| s.0)
+        .min()
+    {
+        Some(ts) => ts,
+        None => return report,
+    };
+    let last_start = period.start(std::cmp::max(first_ts, now).date_naive());
+    let mut start = period.start(first_ts.date_naive());
+    let mut prev_balances: BTreeMap<(&str, &str, i64), f64> = BTreeMap::new();
+    let mut prev_totals: BTreeMap<&str, f64> = BTreeMap::new();
+    while start <= last_start {
+        let end = period.next(start);
+        let end_ts = end.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let label = period.label(start);
+        let mut totals: BTreeMap<&str, f64> = BTreeMap::new();
+        for (&key, (sign, v)) in snapshots.iter() {
+            let (name, currency, _) = key;
+            let balance = match v.iter().rev().find(|s| s.0 < end_ts) {
+                Some(s) => s.1,
+                None => continue,
+            };
+            let (change, change_pct) = compute_change(prev_balances.get(&key).cloned(), balance);
+            report.rows.push(ReportRow {
+                period: label.clone(),
+                bank_account: Some(name.to_string()),
+                currency: currency.to_string(),
+                balance,
+                change,
+                change_pct,
+            });
+            prev_balances.insert(key, balance);
+            *totals.entry(currency).or_insert(0.0) += sign * balance;
+        }
+        for (&currency, &balance) in totals.iter() {
+            let (change, change_pct) = compute_change(prev_totals.get(currency).cloned(), balance);
+            report.totals.push(ReportRow {
+                period: label.clone(),
+                bank_account: None,
+                currency: currency.to_string(),
+                balance,
+                change,
+                change_pct,
+            });
+        }
+        prev_totals = totals;
+        start = end;
+    }
+    report
+}
+
+/// Combine snapshots of several bank accounts into one series.
+/// At every entry's ts the value is the sum of latest snapshots of all
+/// bank accounts seen so far, liabilities are subtracted.
+/// Entries must be ordered by ts, entries without value are skipped.
+pub fn sum_series<'a>(entries: impl IntoIterator<Item = &'a EntryInfo>) -> Vec<Snapshot> {
+    let mut latest: BTreeMap<i64, f64> = BTreeMap::new();
+    let mut series: Vec<Snapshot> = Vec::new();
+    for e in entries {
+        let value: f64 = match &e.value {
+            Some(value) => value.parse().unwrap(),
+            None => continue,
+        };
+        latest.insert(e.bank_account_id, e.kind.sign() * value);
+        let total = latest.values().sum();
+        match series.last_mut() {
+            // Several entries at the same ts make one point.
+            Some(last) if last.0 == e.ts => last.1 = total,
+            _ => series.push((e.ts, total)),
+        }
+    }
+    series
+}
+
+/// Points of the series from `from` to `to`, both inclusive. The last point
+/// at or before `from` is moved to `from`, so series compared on one graph
+/// all start at the range start. Empty if `to` is before `from`.
+pub fn clip_series(
+    points: &[Snapshot],
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Vec<Snapshot> {
+    let mut clipped: Vec<Snapshot> = Vec::new();
+    if let (Some(from), Some(to)) = (from, to) {
+        if to < from {
+            return clipped;
+        }
+    }
+    for &(ts, amount) in points {
+        if to.is_some_and(|to| ts > to) {
+            break;
+        }
+        match from {
+            Some(from) if ts <= from => {
+                clipped.clear();
+                clipped.push((from, amount));
+            }
+            _ => clipped.push((ts, amount)),
+        }
+    }
+    clipped
+}
+
+/// Series scaled so that it starts at 100, `None` if it starts at zero.
+pub fn normalize_series(points: &[Snapshot]) -> Option<Vec<Snapshot>> {
+    let base = points.first()?.1;
+    if base == 0.0 {
+        return None;
+    }
+    Some(
+        points
+            .iter()
+            .map(|&(ts, amount)| (ts, amount / base * 100.0))
+            .collect(),
+    )
+}
+
+/// One row of comparison of balances at two instants.
+pub struct DiffRow {
+    /// Bank account name, or currency in currency comparison.
+    pub name: String,
+    pub currency: String,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+    /// The change, missing balance counts as zero.
+    pub change: f64,
+}
+
+/// Balance with bank account id, `None` in currency comparison, name and
+/// currency.
+pub type KeyedBalance = (Option<i64>, String, String, f64);
+
+/// Compare balances at two instants, rows are matched by bank account id,
+/// or currency in currency comparison, and ordered by name and currency.
+pub fn diff_balances(before: &[KeyedBalance], after: &[KeyedBalance]) -> Vec<DiffRow> {
+    // Balances before and after by name, currency and id.
+    type Key<'a> = (&'a str, &'a str, Option<i64>);
+    let mut rows: BTreeMap<Key, [Option<f64>; 2]> = BTreeMap::new();
+    for (id, name, currency, balance) in before {
+        rows.entry((name, currency, *id)).or_default()[0] = Some(*balance);
+    }
+    for (id, name, currency, balance) in after {
+        rows.entry((name, currency, *id)).or_default()[1] = Some(*balance);
+    }
+    rows.into_iter()
+        .map(|((name, currency, _), [before, after])| DiffRow {
+            name: name.to_string(),
+            currency: currency.to_string(),
+            before,
+            after,
+            change: after.unwrap_or(0.0) - before.unwrap_or(0.0),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::BankAccountKind;
+    use crate::model::BankAccountKind::Asset;
+    use crate::model::BankAccountKind::Liability;
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        crate::util::parse_ts(s).unwrap()
+    }
+
+    /// Entry of bank account with id from the length of `name`, shown with
+    /// its first four letters, so "Bank" and "Bank2" are different bank
+    /// accounts with the same name.
+    fn entry(name: &str, kind: BankAccountKind, ts_str: &str, value: &str) -> EntryInfo {
+        EntryInfo {
+            amount: value.to_string(),
+            bank_account: name[..4].to_string(),
+            bank_account_id: name.len() as i64,
+            currency: "PLN".to_string(),
+            id: 0,
+            ts: ts(ts_str),
+            kind,
+            commodity: None,
+            value: Some(value.to_string()),
+            role: None,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn report_months() {
+        let entries = vec![
+            entry("Bank", Asset, "2024-01-10 12:00:00", "100"),
+            entry("Bank", Asset, "2024-01-31 23:59:59", "120"),
+            // Exactly at the boundary, belongs to February.
+            entry("Bank", Asset, "2024-02-01 00:00:00", "150"),
+            entry("Card", Liability, "2024-03-05 12:00:00", "50"),
+        ];
+        let report = build_report(&entries, Period::Month, ts("2024-04-15 00:00:00"));
+        let periods: Vec<&str> = report.totals.iter().map(|r| r.period.as_str()).collect();
+        assert_eq!(periods, vec!["2024-01", "2024-02", "2024-03", "2024-04"]);
+        let bank: Vec<(f64, Option<f64>)> = report
+            .rows
+            .iter()
+            .filter(|r| r.bank_account.as_deref() == Some("Bank"))
+            .map(|r| (r.balance, r.change))
+            .collect();
+        assert_eq!(
+            bank,
+            vec![
+                (120.0, None),
+                (150.0, Some(30.0)),
+                (150.0, Some(0.0)),
+                (150.0, Some(0.0))
+            ]
+        );
+        assert_eq!(report.rows[1].change_pct, Some(25.0));
+        // Liabilities are subtracted in totals.
+        assert_eq!(report.totals[2].balance, 100.0);
+        assert_eq!(report.totals[2].change, Some(-50.0));
+    }
+
+    #[test]
+    fn report_years() {
+        let entries = vec![
+            entry("Bank", Asset, "2022-12-31 23:59:59", "10"),
+            entry("Bank", Asset, "2023-01-01 00:00:00", "20"),
+        ];
+        let report = build_report(&entries, Period::Year, ts("2023-06-01 00:00:00"));
+        let periods: Vec<&str> = report.rows.iter().map(|r| r.period.as_str()).collect();
+        assert_eq!(periods, vec!["2022", "2023"]);
+        assert_eq!(report.rows[1].change_pct, Some(100.0));
+    }
+
+    #[test]
+    fn report_change_from_zero() {
+        let entries = vec![
+            entry("Bank", Asset, "2024-01-10 12:00:00", "0"),
+            entry("Bank", Asset, "2024-02-10 12:00:00", "100"),
+        ];
+        let report = build_report(&entries, Period::Month, ts("2024-02-15 00:00:00"));
+        assert_eq!(report.rows[1].change, Some(100.0));
+        assert_eq!(report.rows[1].change_pct, None);
+    }
+
+    #[test]
+    fn report_same_names() {
+        let entries = vec![
+            entry("Bank", Asset, "2024-01-10 12:00:00", "100"),
+            entry("Bank2", Asset, "2024-01-11 12:00:00", "10"),
+        ];
+        let report = build_report(&entries, Period::Month, ts("2024-01-15 00:00:00"));
+        let balances: Vec<f64> = report.rows.iter().map(|r| r.balance).collect();
+        assert_eq!(balances, vec![100.0, 10.0]);
+        assert_eq!(report.totals[0].balance, 110.0);
+    }
+
+    #[test]
+    fn sum_same_names() {
+        let entries = vec![
+            entry("Bank", Asset, "2024-01-10 12:00:00", "100"),
+            entry("Bank2", Asset, "2024-01-11 12:00:00", "10"),
+            entry("Bank", Asset, "2024-01-12 12:00:00", "50"),
+        ];
+        let totals: Vec<f64> = sum_series(&entries).iter().map(|s| s.1).collect();
+        assert_eq!(totals, vec![100.0, 110.0, 60.0]);
+    }
+
+    #[test]
+    fn diff_same_names() {
+        let balance = |id, amount| (Some(id), "Bank".to_string(), "PLN".to_string(), amount);
+        let rows = diff_balances(&[balance(1, 100.0)], &[balance(1, 150.0), balance(2, 10.0)]);
+        let changes: Vec<f64> = rows.iter().map(|r| r.change).collect();
+        assert_eq!(changes, vec![50.0, 10.0]);
+    }
+
+    #[test]
+    fn clip() {
+        let points = vec![
+            (ts("2024-01-10 00:00:00"), 1.0),
+            (ts("2024-01-20 00:00:00"), 2.0),
+            (ts("2024-01-30 00:00:00"), 3.0),
+        ];
+        let day = |d: &str| Some(ts(&format!("2024-01-{} 00:00:00", d)));
+        assert_eq!(clip_series(&points, None, None), points);
+        // Point before the range start is moved to it.
+        assert_eq!(
+            clip_series(&points, day("15"), day("25")),
+            vec![(ts("2024-01-15 00:00:00"), 1.0), points[1]]
+        );
+        assert_eq!(clip_series(&points, day("20"), None), points[1..]);
+        // Nothing before the first point, it's not moved.
+        assert_eq!(clip_series(&points, day("01"), day("20")), points[..2]);
+        assert!(clip_series(&points, None, day("05")).is_empty());
+        assert!(clip_series(&points, day("25"), day("15")).is_empty());
+        assert!(clip_series(&[], day("01"), day("20")).is_empty());
+    }
+
+    #[test]
+    fn normalize() {
+        let points = vec![
+            (ts("2024-01-10 00:00:00"), 50.0),
+            (ts("2024-01-20 00:00:00"), 75.0),
+            (ts("2024-01-30 00:00:00"), -25.0),
+        ];
+        let scaled: Vec<f64> = normalize_series(&points)
+            .unwrap()
+            .iter()
+            .map(|p| p.1)
+            .collect();
+        assert_eq!(scaled, vec![100.0, 150.0, -50.0]);
+        // Negative start, growing debt is above 100.
+        let scaled = normalize_series(&[(points[0].0, -50.0), (points[1].0, -75.0)]).unwrap();
+        assert_eq!(scaled[1].1, 150.0);
+        assert_eq!(normalize_series(&[(points[0].0, 0.0), points[1]]), None);
+        assert_eq!(normalize_series(&[]), None);
+    }
+
+    #[test]
+    fn report_empty() {
+        let report = build_report(&[], Period::Month, ts("2024-02-15 00:00:00"));
+        assert!(report.rows.is_empty());
+        assert!(report.totals.is_empty());
+    }
+}
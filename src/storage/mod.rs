@@ -0,0 +1,503 @@
+//! Storage of everything CashLog persists, behind `Storage` trait.
+//!
+//! PostgreSQL (see `db` module) is the main implementation, SQLite is for
+//! small personal installs that don't want to run a database server.
+//! Which one is used is set by `storage` in config, see `open_pool`.
+//!
+//! Methods have the same names and semantics as the `db` functions: ids of
+//! the user account are used for access checks, amounts are decimal
+//! strings. Changes of entries and bank accounts also queue webhook
+//! deliveries, see `webhook` module.
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::backup::Backup;
+use crate::backup::RestoreSummary;
+use crate::common;
+use crate::common::PoolSettings;
+use crate::db::DbError;
+use crate::model::AccountInfo;
+use crate::model::AccountSummary;
+use crate::model::ApiToken;
+use crate::model::BankAccount;
+use crate::model::BankAccountInfo;
+use crate::model::BankAccountKind;
+use crate::model::BankAccountShare;
+use crate::model::Config;
+use crate::model::CurrencyInfo;
+use crate::model::DeliveryStatus;
+use crate::model::DigestSubscription;
+use crate::model::EntryInfo;
+use crate::model::GroupInfo;
+use crate::model::Invite;
+use crate::model::LoginRequest;
+use crate::model::Preferences;
+use crate::model::Price;
+use crate::model::PriceInfo;
+use crate::model::ShareRole;
+use crate::model::Totp;
+use crate::model::Webhook;
+use crate::model::WebhookDelivery;
+
+pub mod postgres;
+pub mod sqlite;
+
+pub trait Storage {
+    // Sessions.
+
+    fn get_sess_val(&mut self, sess_key: &str, name: &str) -> Option<String>;
+
+    fn set_session_value(
+        &mut self,
+        session_key: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), DbError>;
+
+    /// Add one to the number stored as session value `name`, in one
+    /// statement, so concurrent requests all count. Returns the new number,
+    /// `None` if the session has no such value.
+    fn increment_session_value(
+        &mut self,
+        session_key: &str,
+        name: &str,
+    ) -> Result<Option<i64>, DbError>;
+
+    fn delete_session(&mut self, session_key: &str) -> Result<(), DbError>;
+
+    // User accounts.
+
+    fn get_acc_id_by_email(&mut self, email: &str) -> Result<Option<i64>, DbError>;
+
+    fn create_acc_with_email(&mut self, email: &str) -> Result<i64, DbError>;
+
+    fn get_user_account_emails(&mut self, acc_id: i64) -> Result<Box<Vec<String>>, DbError>;
+
+    fn get_user_account_info(&mut self, acc_id: i64) -> Result<Option<AccountInfo>, DbError>;
+
+    /// Disable or enable user account. Disabling also logs out its sessions.
+    fn set_account_disabled(&mut self, account_id: i64, disabled: bool) -> Result<(), DbError>;
+
+    fn is_account_disabled(&mut self, account_id: i64) -> Result<bool, DbError>;
+
+    fn get_account_summaries(&mut self) -> Result<Vec<AccountSummary>, DbError>;
+
+    /// Delete user account and all its rows, see `db::delete_account`.
+    fn delete_account(&mut self, account_id: i64) -> Result<(), DbError>;
+
+    // Login, account deletion and API tokens.
+
+    fn insert_login_token(&mut self, account_id: &i64, token: &str) -> Result<(), DbError>;
+
+    fn insert_delete_account_token(&mut self, account_id: i64, token: &str) -> Result<(), DbError>;
+
+    /// Get account id by login token, only active tokens of enabled
+    /// accounts are queried.
+    fn get_login_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError>;
+
+    /// Mark login token as used and set `values` of the session, in one
+    /// transaction, so each token logs in once. False, with nothing set,
+    /// if the token is unknown, already used or its account is disabled.
+    fn use_login_token(
+        &mut self,
+        token: &str,
+        session_key: &str,
+        values: &[(&str, &str)],
+    ) -> Result<bool, DbError>;
+
+    /// Get account id by account deletion token, valid for a day.
+    fn get_delete_account_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError>;
+
+    /// Record login form submission at `now` and delete ones older than a
+    /// day. `token` is set when the email has no user account yet, with
+    /// the `invite` code if user gave one, see `use_login_request_token`.
+    /// Nothing is recorded if the email or IP address is over the rate
+    /// limits, then it returns when to retry, see `login_limit`. The check
+    /// and the insert are one transaction, so concurrent requests are
+    /// counted. Email is recorded and counted normalized by
+    /// `login_limit::normalize_email`.
+    fn insert_login_request(
+        &mut self,
+        email: &str,
+        ip: Option<&str>,
+        token: Option<&str>,
+        invite: Option<&str>,
+        now: &DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, DbError>;
+
+    /// Get login form submissions for the email or from the IP address
+    /// since given time, oldest first.
+    fn get_login_requests(
+        &mut self,
+        email: &str,
+        ip: Option<&str>,
+        since: &DateTime<Utc>,
+    ) -> Result<Vec<LoginRequest>, DbError>;
+
+    /// Mark login request token as used and get its email and invite code.
+    /// `None` if the token is unknown, already used or older than a day.
+    fn use_login_request_token(
+        &mut self,
+        token: &str,
+    ) -> Result<Option<(String, Option<String>)>, DbError>;
+
+    // Invite codes, see `registration` module.
+
+    fn insert_invite(&mut self, code: &str, note: Option<&str>) -> Result<(), DbError>;
+
+    /// Get invite codes, newest first.
+    fn get_invites(&mut self) -> Result<Vec<Invite>, DbError>;
+
+    /// Check that invite code exists and wasn't used.
+    fn is_invite_valid(&mut self, code: &str) -> Result<bool, DbError>;
+
+    /// Mark invite code as used. False if it doesn't exist or was used.
+    fn use_invite(&mut self, code: &str) -> Result<bool, DbError>;
+
+    // Two-factor authentication, see `totp` module.
+
+    fn get_totp(&mut self, account_id: i64) -> Result<Option<Totp>, DbError>;
+
+    /// Start TOTP enrolment with new secret, unless TOTP is already enabled.
+    fn set_totp_secret(&mut self, account_id: i64, secret: &str) -> Result<(), DbError>;
+
+    /// Finish enrolment: enable TOTP, note the step of the confirmed code
+    /// and replace recovery codes with the given hashes.
+    fn enable_totp(
+        &mut self,
+        account_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), DbError>;
+
+    /// Note step of accepted code. False if it's not later than the last
+    /// one, then the code was already used.
+    fn set_totp_step(&mut self, account_id: i64, step: i64) -> Result<bool, DbError>;
+
+    /// Delete recovery code by its hash. False if there was no such code.
+    fn use_recovery_code(&mut self, account_id: i64, code_hash: &str) -> Result<bool, DbError>;
+
+    /// Turn TOTP off and delete recovery codes.
+    fn delete_totp(&mut self, account_id: i64) -> Result<(), DbError>;
+
+    fn insert_api_token(&mut self, account_id: i64, name: &str, token: &str)
+        -> Result<(), DbError>;
+
+    fn get_api_tokens(&mut self, account_id: i64) -> Result<Vec<ApiToken>, DbError>;
+
+    fn delete_api_token(&mut self, account_id: i64, token_id: i64) -> Result<(), DbError>;
+
+    /// Get account id by API token, and note that the token was used.
+    fn get_api_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError>;
+
+    // Entries.
+
+    /// Insert entry, nothing is inserted if the user account can't change
+    /// entries of the bank account.
+    fn insert_entry(
+        &mut self,
+        account_id: &i64,
+        bank_account: &i64,
+        ts: &DateTime<Utc>,
+        amount_str: &str,
+        note: Option<&str>,
+    ) -> Result<(), DbError>;
+
+    /// Insert entries of many bank accounts at once, see
+    /// `db::insert_entries`.
+    fn insert_entries(
+        &mut self,
+        account_id: i64,
+        ts: &DateTime<Utc>,
+        amounts: &[(i64, String)],
+    ) -> Result<u64, DbError>;
+
+    fn get_entry(&mut self, acc_id: i64, entry_id: i64) -> Result<Option<EntryInfo>, DbError>;
+
+    /// Update amount and note of entry.
+    fn update_entry(
+        &mut self,
+        account_id: i64,
+        entry_id: i64,
+        amount: String,
+        note: Option<&str>,
+    ) -> Result<(), DbError>;
+
+    /// Latest live entries, newest first.
+    fn get_entries(&mut self, account_id: i64) -> Result<Vec<EntryInfo>, DbError>;
+
+    /// Live entries, newest first, `limit` of them after `offset` newer
+    /// ones. With `search` only entries with note containing it, ignoring
+    /// case.
+    fn get_entries_page(
+        &mut self,
+        account_id: i64,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<EntryInfo>, DbError>;
+
+    /// All live entries, oldest first.
+    fn get_all_entries(&mut self, account_id: i64) -> Result<Vec<EntryInfo>, DbError>;
+
+    fn get_entries_by_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Vec<EntryInfo>, DbError>;
+
+    fn get_entries_by_group(
+        &mut self,
+        account_id: i64,
+        group_name: &str,
+        currency: &str,
+    ) -> Result<Vec<EntryInfo>, DbError>;
+
+    fn delete_entry(&mut self, account_id: i64, entry_id: i64) -> Result<(), DbError>;
+
+    // Bank accounts.
+
+    /// Get bank accounts the user account owns or that are shared with it.
+    fn get_bank_accounts(&mut self, account_id: i64) -> Result<Vec<BankAccount>, DbError>;
+
+    fn get_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Option<BankAccount>, DbError>;
+
+    /// Update editable bank account details, only owner can do it.
+    fn update_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+        group_name: Option<&str>,
+        kind: BankAccountKind,
+    ) -> Result<(), DbError>;
+
+    /// Get bank accounts with their last entry at or before `as_of`,
+    /// `None` means now.
+    fn get_bank_account_infos(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<BankAccountInfo>, DbError>;
+
+    fn insert_bank_account(
+        &mut self,
+        account_id: i64,
+        name: &str,
+        currency: &str,
+        group_name: Option<&str>,
+        kind: BankAccountKind,
+        commodity: Option<&str>,
+    ) -> Result<(), DbError>;
+
+    fn get_currency_info(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<CurrencyInfo>, DbError>;
+
+    fn get_group_info(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<GroupInfo>, DbError>;
+
+    // Prices.
+
+    fn get_prices(&mut self, account_id: i64) -> Result<Vec<PriceInfo>, DbError>;
+
+    fn insert_prices(&mut self, account_id: i64, prices: &[Price]) -> Result<(), DbError>;
+
+    fn delete_price(&mut self, account_id: i64, price_id: i64) -> Result<(), DbError>;
+
+    // Sharing.
+
+    fn get_bank_account_shares(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Vec<BankAccountShare>, DbError>;
+
+    /// Returns false if there's no other user account with this email, or
+    /// the bank account isn't one of the user account's.
+    fn share_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+        email: &str,
+        role: ShareRole,
+    ) -> Result<bool, DbError>;
+
+    fn delete_bank_account_share(&mut self, account_id: i64, share_id: i64) -> Result<(), DbError>;
+
+    // Backup.
+
+    fn get_backup(&mut self, account_id: i64) -> Result<Backup, DbError>;
+
+    fn restore_backup(
+        &mut self,
+        account_id: i64,
+        backup: &Backup,
+        dry_run: bool,
+    ) -> Result<RestoreSummary, DbError>;
+
+    // Preferences.
+
+    /// Preferences of user account, defaults if never saved.
+    fn get_preferences(&mut self, account_id: i64) -> Result<Preferences, DbError>;
+
+    fn set_preferences(
+        &mut self,
+        account_id: i64,
+        preferences: &Preferences,
+    ) -> Result<(), DbError>;
+
+    // Webhooks.
+
+    fn insert_webhook(&mut self, account_id: i64, url: &str, secret: &str) -> Result<(), DbError>;
+
+    fn get_webhooks(&mut self, account_id: i64) -> Result<Vec<Webhook>, DbError>;
+
+    /// Delete webhook with its deliveries.
+    fn delete_webhook(&mut self, account_id: i64, webhook_id: i64) -> Result<(), DbError>;
+
+    /// Latest deliveries to webhooks of the user account, newest first.
+    fn get_webhook_deliveries(
+        &mut self,
+        account_id: i64,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, DbError>;
+
+    /// Pending deliveries of all user accounts due at `now`, oldest first,
+    /// with secrets of their webhooks.
+    fn get_due_webhook_deliveries(
+        &mut self,
+        now: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(WebhookDelivery, String)>, DbError>;
+
+    /// Save result of delivery attempt, `next_attempt` is set for retries.
+    fn record_webhook_attempt(
+        &mut self,
+        delivery_id: i64,
+        status: DeliveryStatus,
+        response_status: Option<i64>,
+        error: Option<&str>,
+        next_attempt: Option<&DateTime<Utc>>,
+    ) -> Result<(), DbError>;
+
+    // Email digest, turned on and off with preferences.
+
+    /// Digest subscriptions of all enabled user accounts.
+    fn get_digest_subscriptions(&mut self) -> Result<Vec<DigestSubscription>, DbError>;
+
+    fn set_digest_sent(&mut self, account_id: i64, sent: &DateTime<Utc>) -> Result<(), DbError>;
+}
+
+/// Pool of connections to the storage set in config.
+#[derive(Clone)]
+pub enum StoragePool {
+    Postgres(common::DatabasePool),
+    Sqlite(sqlite::SqlitePool),
+}
+
+impl StoragePool {
+    /// Get connection, this blocks until one is free. Request handlers
+    /// should use `run` instead.
+    pub fn get(&self) -> Result<Box<dyn Storage>, DbError> {
+        match self {
+            StoragePool::Postgres(pool) => Ok(Box::new(postgres::PostgresStorage::new(
+                pool.get().map_err(DbError::new)?,
+            ))),
+            StoragePool::Sqlite(pool) => Ok(Box::new(sqlite::SqliteStorage::new(
+                pool.get().map_err(DbError::new)?,
+            ))),
+        }
+    }
+
+    /// Maximum number of connections in the pool.
+    pub fn max_size(&self) -> u32 {
+        match self {
+            StoragePool::Postgres(pool) => pool.max_size(),
+            StoragePool::Sqlite(pool) => pool.max_size(),
+        }
+    }
+
+    /// Run `f` with a connection on actix blocking thread pool, so that
+    /// waiting for the connection or for slow queries doesn't hold up the
+    /// async workers serving other requests.
+    pub async fn run<F, T>(&self, f: F) -> Result<T, DbError>
+    where
+        F: FnOnce(&mut dyn Storage) -> Result<T, DbError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.clone();
+        actix_web::web::block(move || {
+            let mut conn = pool.get()?;
+            f(&mut *conn)
+        })
+        .await
+        .map_err(|e| match e {
+            actix_web::error::BlockingError::Error(e) => e,
+            actix_web::error::BlockingError::Canceled => {
+                DbError::new("Database thread pool is gone")
+            }
+        })
+    }
+
+    /// Session value, like `Storage::get_sess_val`, but run on blocking
+    /// thread pool. Nothing is found if the database fails.
+    pub async fn get_sess_val(&self, sess_key: &str, name: &str) -> Option<String> {
+        let sess_key = sess_key.to_string();
+        let name = name.to_string();
+        self.run(move |conn| Ok(conn.get_sess_val(&sess_key, &name)))
+            .await
+            .unwrap_or(None)
+    }
+
+    /// Preferences of the user account logged in with session, defaults if
+    /// there is none or the database fails.
+    pub async fn get_sess_preferences(&self, sess_key: &str) -> Preferences {
+        match self.get_sess_val(sess_key, "account").await {
+            Some(account_id) => match account_id.parse::<i64>() {
+                Ok(account_id) => self
+                    .run(move |conn| conn.get_preferences(account_id))
+                    .await
+                    .unwrap_or_default(),
+                Err(_) => Preferences::default(),
+            },
+            None => Preferences::default(),
+        }
+    }
+}
+
+/// Open pool of the storage set in config: `storage = "postgres"` (the
+/// default) uses the `db_*` settings, `storage = "sqlite"` uses the
+/// database file in `sqlite_path`.
+pub fn open_pool(conf: &Config) -> Result<StoragePool, DbError> {
+    match conf.storage.as_deref().unwrap_or("postgres") {
+        "postgres" => Ok(StoragePool::Postgres(common::create_database_pool(
+            &conf.db_host,
+            conf.db_port,
+            &conf.db_name,
+            &conf.db_username,
+            &conf.db_password,
+            &PoolSettings::from_config(conf, 100),
+        ))),
+        "sqlite" => {
+            let path = conf
+                .sqlite_path
+                .as_deref()
+                .ok_or_else(|| DbError::new("sqlite_path is required with SQLite storage"))?;
+            Ok(StoragePool::Sqlite(sqlite::create_pool(
+                path,
+                &PoolSettings::from_config(conf, 8),
+            )?))
+        }
+        storage => Err(DbError::new(format!("Unknown storage: {}", storage))),
+    }
+}
@@ -0,0 +1,486 @@
+//! PostgreSQL storage, a thin wrapper over the `db` functions.
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use super::Storage;
+use crate::backup::Backup;
+use crate::backup::RestoreSummary;
+use crate::db;
+use crate::db::DbError;
+use crate::model::AccountInfo;
+use crate::model::AccountSummary;
+use crate::model::ApiToken;
+use crate::model::BankAccount;
+use crate::model::BankAccountInfo;
+use crate::model::BankAccountKind;
+use crate::model::BankAccountShare;
+use crate::model::CurrencyInfo;
+use crate::model::DeliveryStatus;
+use crate::model::DigestSubscription;
+use crate::model::EntryInfo;
+use crate::model::GroupInfo;
+use crate::model::Invite;
+use crate::model::LoginRequest;
+use crate::model::Preferences;
+use crate::model::Price;
+use crate::model::PriceInfo;
+use crate::model::ShareRole;
+use crate::model::Totp;
+use crate::model::Webhook;
+use crate::model::WebhookDelivery;
+
+pub type PostgresConnection =
+    r2d2::PooledConnection<r2d2_postgres::PostgresConnectionManager<postgres::NoTls>>;
+
+pub struct PostgresStorage {
+    conn: PostgresConnection,
+}
+
+impl PostgresStorage {
+    pub fn new(conn: PostgresConnection) -> PostgresStorage {
+        PostgresStorage { conn }
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn get_sess_val(&mut self, sess_key: &str, name: &str) -> Option<String> {
+        db::get_sess_val(&mut self.conn, sess_key, name)
+    }
+
+    fn set_session_value(
+        &mut self,
+        session_key: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), DbError> {
+        db::set_session_value(&mut *self.conn, session_key, name, value)
+    }
+
+    fn increment_session_value(
+        &mut self,
+        session_key: &str,
+        name: &str,
+    ) -> Result<Option<i64>, DbError> {
+        db::increment_session_value(&mut self.conn, session_key, name)
+    }
+
+    fn delete_session(&mut self, session_key: &str) -> Result<(), DbError> {
+        db::delete_session(&mut self.conn, session_key)
+    }
+
+    fn get_acc_id_by_email(&mut self, email: &str) -> Result<Option<i64>, DbError> {
+        db::get_acc_id_by_email(&mut self.conn, email)
+    }
+
+    fn create_acc_with_email(&mut self, email: &str) -> Result<i64, DbError> {
+        db::create_acc_with_email(&mut self.conn, email)
+    }
+
+    fn get_user_account_emails(&mut self, acc_id: i64) -> Result<Box<Vec<String>>, DbError> {
+        db::get_user_account_emails(&mut self.conn, acc_id)
+    }
+
+    fn get_user_account_info(&mut self, acc_id: i64) -> Result<Option<AccountInfo>, DbError> {
+        db::get_user_account_info(&mut self.conn, acc_id)
+    }
+
+    fn set_account_disabled(&mut self, account_id: i64, disabled: bool) -> Result<(), DbError> {
+        db::set_account_disabled(&mut self.conn, account_id, disabled)
+    }
+
+    fn is_account_disabled(&mut self, account_id: i64) -> Result<bool, DbError> {
+        db::is_account_disabled(&mut self.conn, account_id)
+    }
+
+    fn get_account_summaries(&mut self) -> Result<Vec<AccountSummary>, DbError> {
+        db::get_account_summaries(&mut self.conn)
+    }
+
+    fn delete_account(&mut self, account_id: i64) -> Result<(), DbError> {
+        db::delete_account(&mut self.conn, account_id)
+    }
+
+    fn insert_login_token(&mut self, account_id: &i64, token: &str) -> Result<(), DbError> {
+        db::insert_login_token(&mut self.conn, account_id, token)
+    }
+
+    fn insert_delete_account_token(&mut self, account_id: i64, token: &str) -> Result<(), DbError> {
+        db::insert_delete_account_token(&mut self.conn, account_id, token)
+    }
+
+    fn get_login_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError> {
+        db::get_login_token_account(&mut self.conn, token)
+    }
+
+    fn use_login_token(
+        &mut self,
+        token: &str,
+        session_key: &str,
+        values: &[(&str, &str)],
+    ) -> Result<bool, DbError> {
+        db::use_login_token(&mut self.conn, token, session_key, values)
+    }
+
+    fn get_delete_account_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError> {
+        db::get_delete_account_token_account(&mut self.conn, token)
+    }
+
+    fn insert_login_request(
+        &mut self,
+        email: &str,
+        ip: Option<&str>,
+        token: Option<&str>,
+        invite: Option<&str>,
+        now: &DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, DbError> {
+        db::insert_login_request(&mut self.conn, email, ip, token, invite, now)
+    }
+
+    fn get_login_requests(
+        &mut self,
+        email: &str,
+        ip: Option<&str>,
+        since: &DateTime<Utc>,
+    ) -> Result<Vec<LoginRequest>, DbError> {
+        db::get_login_requests(&mut *self.conn, email, ip, since)
+    }
+
+    fn use_login_request_token(
+        &mut self,
+        token: &str,
+    ) -> Result<Option<(String, Option<String>)>, DbError> {
+        db::use_login_request_token(&mut self.conn, token)
+    }
+
+    fn insert_invite(&mut self, code: &str, note: Option<&str>) -> Result<(), DbError> {
+        db::insert_invite(&mut self.conn, code, note)
+    }
+
+    fn get_invites(&mut self) -> Result<Vec<Invite>, DbError> {
+        db::get_invites(&mut self.conn)
+    }
+
+    fn is_invite_valid(&mut self, code: &str) -> Result<bool, DbError> {
+        db::is_invite_valid(&mut self.conn, code)
+    }
+
+    fn use_invite(&mut self, code: &str) -> Result<bool, DbError> {
+        db::use_invite(&mut self.conn, code)
+    }
+
+    fn get_totp(&mut self, account_id: i64) -> Result<Option<Totp>, DbError> {
+        db::get_totp(&mut self.conn, account_id)
+    }
+
+    fn set_totp_secret(&mut self, account_id: i64, secret: &str) -> Result<(), DbError> {
+        db::set_totp_secret(&mut self.conn, account_id, secret)
+    }
+
+    fn enable_totp(
+        &mut self,
+        account_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), DbError> {
+        db::enable_totp(&mut self.conn, account_id, step, recovery_code_hashes)
+    }
+
+    fn set_totp_step(&mut self, account_id: i64, step: i64) -> Result<bool, DbError> {
+        db::set_totp_step(&mut self.conn, account_id, step)
+    }
+
+    fn use_recovery_code(&mut self, account_id: i64, code_hash: &str) -> Result<bool, DbError> {
+        db::use_recovery_code(&mut self.conn, account_id, code_hash)
+    }
+
+    fn delete_totp(&mut self, account_id: i64) -> Result<(), DbError> {
+        db::delete_totp(&mut self.conn, account_id)
+    }
+
+    fn insert_api_token(
+        &mut self,
+        account_id: i64,
+        name: &str,
+        token: &str,
+    ) -> Result<(), DbError> {
+        db::insert_api_token(&mut self.conn, account_id, name, token)
+    }
+
+    fn get_api_tokens(&mut self, account_id: i64) -> Result<Vec<ApiToken>, DbError> {
+        db::get_api_tokens(&mut self.conn, account_id)
+    }
+
+    fn delete_api_token(&mut self, account_id: i64, token_id: i64) -> Result<(), DbError> {
+        db::delete_api_token(&mut self.conn, account_id, token_id)
+    }
+
+    fn get_api_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError> {
+        db::get_api_token_account(&mut self.conn, token)
+    }
+
+    fn insert_entry(
+        &mut self,
+        account_id: &i64,
+        bank_account: &i64,
+        ts: &DateTime<Utc>,
+        amount_str: &str,
+        note: Option<&str>,
+    ) -> Result<(), DbError> {
+        db::insert_entry(
+            &mut self.conn,
+            account_id,
+            bank_account,
+            ts,
+            amount_str,
+            note,
+        )
+    }
+
+    fn insert_entries(
+        &mut self,
+        account_id: i64,
+        ts: &DateTime<Utc>,
+        amounts: &[(i64, String)],
+    ) -> Result<u64, DbError> {
+        db::insert_entries(&mut self.conn, account_id, ts, amounts)
+    }
+
+    fn get_entry(&mut self, acc_id: i64, entry_id: i64) -> Result<Option<EntryInfo>, DbError> {
+        db::get_entry(&mut self.conn, acc_id, entry_id)
+    }
+
+    fn update_entry(
+        &mut self,
+        account_id: i64,
+        entry_id: i64,
+        amount: String,
+        note: Option<&str>,
+    ) -> Result<(), DbError> {
+        db::update_entry(&mut self.conn, account_id, entry_id, amount, note)
+    }
+
+    fn get_entries(&mut self, account_id: i64) -> Result<Vec<EntryInfo>, DbError> {
+        db::get_entries(&mut self.conn, account_id)
+    }
+
+    fn get_entries_page(
+        &mut self,
+        account_id: i64,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        db::get_entries_page(&mut self.conn, account_id, search, limit, offset)
+    }
+
+    fn get_all_entries(&mut self, account_id: i64) -> Result<Vec<EntryInfo>, DbError> {
+        db::get_all_entries(&mut self.conn, account_id)
+    }
+
+    fn get_entries_by_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        db::get_entries_by_bank_account(&mut self.conn, account_id, bank_account_id)
+    }
+
+    fn get_entries_by_group(
+        &mut self,
+        account_id: i64,
+        group_name: &str,
+        currency: &str,
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        db::get_entries_by_group(&mut self.conn, account_id, group_name, currency)
+    }
+
+    fn delete_entry(&mut self, account_id: i64, entry_id: i64) -> Result<(), DbError> {
+        db::delete_entry(&mut self.conn, account_id, entry_id)
+    }
+
+    fn get_bank_accounts(&mut self, account_id: i64) -> Result<Vec<BankAccount>, DbError> {
+        db::get_bank_accounts(&mut self.conn, account_id)
+    }
+
+    fn get_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Option<BankAccount>, DbError> {
+        db::get_bank_account(&mut self.conn, account_id, bank_account_id)
+    }
+
+    fn update_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+        group_name: Option<&str>,
+        kind: BankAccountKind,
+    ) -> Result<(), DbError> {
+        db::update_bank_account(
+            &mut self.conn,
+            account_id,
+            bank_account_id,
+            group_name,
+            kind,
+        )
+    }
+
+    fn get_bank_account_infos(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<BankAccountInfo>, DbError> {
+        db::get_bank_account_infos(&mut self.conn, account_id, as_of)
+    }
+
+    fn insert_bank_account(
+        &mut self,
+        account_id: i64,
+        name: &str,
+        currency: &str,
+        group_name: Option<&str>,
+        kind: BankAccountKind,
+        commodity: Option<&str>,
+    ) -> Result<(), DbError> {
+        db::insert_bank_account(
+            &mut self.conn,
+            account_id,
+            name,
+            currency,
+            group_name,
+            kind,
+            commodity,
+        )
+    }
+
+    fn get_currency_info(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<CurrencyInfo>, DbError> {
+        db::get_currency_info(&mut self.conn, account_id, as_of)
+    }
+
+    fn get_group_info(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<GroupInfo>, DbError> {
+        db::get_group_info(&mut self.conn, account_id, as_of)
+    }
+
+    fn get_prices(&mut self, account_id: i64) -> Result<Vec<PriceInfo>, DbError> {
+        db::get_prices(&mut self.conn, account_id)
+    }
+
+    fn insert_prices(&mut self, account_id: i64, prices: &[Price]) -> Result<(), DbError> {
+        db::insert_prices(&mut self.conn, account_id, prices)
+    }
+
+    fn delete_price(&mut self, account_id: i64, price_id: i64) -> Result<(), DbError> {
+        db::delete_price(&mut self.conn, account_id, price_id)
+    }
+
+    fn get_bank_account_shares(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Vec<BankAccountShare>, DbError> {
+        db::get_bank_account_shares(&mut self.conn, account_id, bank_account_id)
+    }
+
+    fn share_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+        email: &str,
+        role: ShareRole,
+    ) -> Result<bool, DbError> {
+        db::share_bank_account(&mut self.conn, account_id, bank_account_id, email, role)
+    }
+
+    fn delete_bank_account_share(&mut self, account_id: i64, share_id: i64) -> Result<(), DbError> {
+        db::delete_bank_account_share(&mut self.conn, account_id, share_id)
+    }
+
+    fn get_backup(&mut self, account_id: i64) -> Result<Backup, DbError> {
+        db::get_backup(&mut self.conn, account_id)
+    }
+
+    fn restore_backup(
+        &mut self,
+        account_id: i64,
+        backup: &Backup,
+        dry_run: bool,
+    ) -> Result<RestoreSummary, DbError> {
+        db::restore_backup(&mut self.conn, account_id, backup, dry_run)
+    }
+
+    fn get_preferences(&mut self, account_id: i64) -> Result<Preferences, DbError> {
+        db::get_preferences(&mut *self.conn, account_id)
+    }
+
+    fn set_preferences(
+        &mut self,
+        account_id: i64,
+        preferences: &Preferences,
+    ) -> Result<(), DbError> {
+        db::set_preferences(&mut *self.conn, account_id, preferences)
+    }
+
+    fn insert_webhook(&mut self, account_id: i64, url: &str, secret: &str) -> Result<(), DbError> {
+        db::insert_webhook(&mut self.conn, account_id, url, secret)
+    }
+
+    fn get_webhooks(&mut self, account_id: i64) -> Result<Vec<Webhook>, DbError> {
+        db::get_webhooks(&mut self.conn, account_id)
+    }
+
+    fn delete_webhook(&mut self, account_id: i64, webhook_id: i64) -> Result<(), DbError> {
+        db::delete_webhook(&mut self.conn, account_id, webhook_id)
+    }
+
+    fn get_webhook_deliveries(
+        &mut self,
+        account_id: i64,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, DbError> {
+        db::get_webhook_deliveries(&mut self.conn, account_id, limit)
+    }
+
+    fn get_due_webhook_deliveries(
+        &mut self,
+        now: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(WebhookDelivery, String)>, DbError> {
+        db::get_due_webhook_deliveries(&mut self.conn, now, limit)
+    }
+
+    fn record_webhook_attempt(
+        &mut self,
+        delivery_id: i64,
+        status: DeliveryStatus,
+        response_status: Option<i64>,
+        error: Option<&str>,
+        next_attempt: Option<&DateTime<Utc>>,
+    ) -> Result<(), DbError> {
+        db::record_webhook_attempt(
+            &mut self.conn,
+            delivery_id,
+            status,
+            response_status,
+            error,
+            next_attempt,
+        )
+    }
+
+    fn get_digest_subscriptions(&mut self) -> Result<Vec<DigestSubscription>, DbError> {
+        db::get_digest_subscriptions(&mut self.conn)
+    }
+
+    fn set_digest_sent(&mut self, account_id: i64, sent: &DateTime<Utc>) -> Result<(), DbError> {
+        db::set_digest_sent(&mut self.conn, account_id, sent)
+    }
+}
@@ -0,0 +1,2276 @@
+//! SQLite storage, for small personal installs.
+//!
+//! Schema is in `cashlog-sqlite.sql` and is applied when the pool is
+//! created. Queries mirror the PostgreSQL ones in `db` module, see the
+//! schema for how timestamps and amounts are stored.
+
+use chrono::DateTime;
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+
+use super::Storage;
+use crate::backup;
+use crate::backup::Backup;
+use crate::backup::RestoreSummary;
+use crate::common::PoolSettings;
+use crate::db::can_read_sql;
+use crate::db::can_write_sql;
+use crate::db::parse_db_kind;
+use crate::db::parse_db_role;
+use crate::db::parse_db_ts;
+use crate::db::role_sql;
+use crate::db::DbError;
+use crate::login_limit;
+use crate::model::AccountInfo;
+use crate::model::AccountSummary;
+use crate::model::ApiToken;
+use crate::model::BankAccount;
+use crate::model::BankAccountInfo;
+use crate::model::BankAccountKind;
+use crate::model::BankAccountShare;
+use crate::model::CurrencyInfo;
+use crate::model::DeliveryStatus;
+use crate::model::DigestFrequency;
+use crate::model::DigestSubscription;
+use crate::model::EntryInfo;
+use crate::model::GroupInfo;
+use crate::model::Invite;
+use crate::model::LandingPage;
+use crate::model::LoginRequest;
+use crate::model::Preferences;
+use crate::model::Price;
+use crate::model::PriceInfo;
+use crate::model::ShareRole;
+use crate::model::Theme;
+use crate::model::Totp;
+use crate::model::Webhook;
+use crate::model::WebhookDelivery;
+use crate::model::WebhookEvent;
+use crate::model::MAX_ENTRIES_PER_PAGE;
+use crate::webhook;
+use crate::webhook::WebhookBankAccount;
+use crate::webhook::WebhookEntry;
+
+const SCHEMA: &str = include_str!("../../cashlog-sqlite.sql");
+
+/// Compares after any timestamp, used for `as_of` of `None`.
+const MAX_TS: &str = "9999-12-31 23:59:59.999999";
+
+pub type SqlitePool = r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>;
+
+pub type SqliteConnection = r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>;
+
+impl std::convert::From<rusqlite::Error> for DbError {
+    fn from(error: rusqlite::Error) -> DbError {
+        DbError::new(format!("SQLite Error: {}", error))
+    }
+}
+
+/// Create pool of connections to SQLite database file, the file and
+/// tables are created if needed.
+pub fn create_pool(path: &str, settings: &PoolSettings) -> Result<SqlitePool, DbError> {
+    let manager = r2d2_sqlite::SqliteConnectionManager::file(path).with_init(|conn| {
+        // Wait for other connections instead of failing when the database
+        // is locked, WAL lets readers work while one connection writes.
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "wal")?;
+        conn.pragma_update(None, "foreign_keys", true)
+    });
+    let pool = settings.builder().build(manager).map_err(DbError::new)?;
+    let conn = pool.get().map_err(DbError::new)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(pool)
+}
+
+fn format_ts(ts: &DateTime<Utc>) -> String {
+    ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+fn now() -> String {
+    format_ts(&Utc::now())
+}
+
+/// Timestamp from user input or backup, stored with 6 fraction digits so
+/// text comparison works.
+fn normalize_ts(s: &str) -> Result<String, DbError> {
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|ts| format_ts(&ts.and_utc()))
+        .map_err(|e| DbError::new(format!("Invalid timestamp {}: {}", s, e)))
+}
+
+/// PostgreSQL rejects amounts that are not numeric, so do the same.
+fn check_amount(amount: &str) -> Result<&str, DbError> {
+    let amount = amount.trim();
+    match amount.parse::<f64>() {
+        Ok(_) => Ok(amount),
+        Err(_) => Err(DbError::new(format!("Invalid amount: {}", amount))),
+    }
+}
+
+fn as_of_param(as_of: Option<&DateTime<Utc>>) -> String {
+    as_of.map_or(MAX_TS.to_string(), format_ts)
+}
+
+/// Like `db::value_sql`, the value is formatted as `util::format_amount`
+/// does.
+fn value_sql(bank_account: &str, amount: &str, ts: &str) -> String {
+    format!(
+        "
+        case
+            when {bank_account}.commodity is null then {amount}
+            else (
+                select printf('%.2f', cast({amount} as real) * cast(price.price as real))
+                from price
+                where
+                    price.account = {bank_account}.account
+                    and price.commodity = {bank_account}.commodity
+                    and price.currency = {bank_account}.currency
+                    and price.ts <= {ts}
+                order by price.ts desc
+                limit 1
+            )
+        end",
+        bank_account = bank_account,
+        amount = amount,
+        ts = ts
+    )
+}
+
+/// Like `db::entry_info_columns`, with the user account as `?1`.
+fn entry_info_columns() -> String {
+    format!(
+        "
+            entry.id,
+            bank_account.name,
+            entry.amount,
+            bank_account.currency,
+            entry.ts,
+            bank_account.kind,
+            bank_account.commodity,
+            {},
+            {},
+            entry.note,
+            bank_account.id",
+        value_sql("bank_account", "entry.amount", "entry.ts"),
+        role_sql("bank_account", "?1")
+    )
+}
+
+fn entry_info_from_row(row: &rusqlite::Row) -> rusqlite::Result<EntryInfo> {
+    Ok(EntryInfo {
+        id: row.get(0)?,
+        bank_account: row.get(1)?,
+        amount: row.get(2)?,
+        currency: row.get(3)?,
+        ts: parse_db_ts(&row.get::<_, String>(4)?),
+        kind: parse_db_kind(&row.get::<_, String>(5)?),
+        commodity: row.get(6)?,
+        value: row.get(7)?,
+        role: parse_db_role(row.get::<_, Option<String>>(8)?.as_deref()),
+        note: row.get(9)?,
+        bank_account_id: row.get(10)?,
+    })
+}
+
+/// Like `db::bank_account_columns`, with the user account as `?1`.
+fn bank_account_columns() -> String {
+    format!(
+        "
+            bank_account.id,
+            bank_account.name,
+            bank_account.currency,
+            bank_account.group_name,
+            bank_account.kind,
+            bank_account.commodity,
+            {}",
+        role_sql("bank_account", "?1")
+    )
+}
+
+fn bank_account_from_row(row: &rusqlite::Row) -> rusqlite::Result<BankAccount> {
+    Ok(BankAccount {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        currency: row.get(2)?,
+        group_name: row.get(3)?,
+        kind: parse_db_kind(&row.get::<_, String>(4)?),
+        commodity: row.get(5)?,
+        role: parse_db_role(row.get::<_, Option<String>>(6)?.as_deref()),
+    })
+}
+
+/// Like `db::bank_account_value_sql`, the value is real.
+fn bank_account_value_sql() -> String {
+    format!(
+        "
+            select
+                bank_account.group_name,
+                bank_account.currency,
+                bank_account.kind,
+                last_entry.ts,
+                cast(({}) as real) as value
+            from
+                bank_account
+                join entry as last_entry on (
+                    last_entry.id = (
+                        select entry.id
+                        from entry
+                        where
+                            entry.bank_account = bank_account.id
+                            and entry.deleted = false
+                            and entry.ts <= ?2
+                        order by entry.ts desc
+                        limit 1
+                    )
+                )
+            where
+                {}
+                and bank_account.deleted = false",
+        value_sql("bank_account", "last_entry.amount", "?2"),
+        can_read_sql("bank_account", "?1")
+    )
+}
+
+fn query<T, P, F>(
+    conn: &rusqlite::Connection,
+    sql: &str,
+    params: P,
+    f: F,
+) -> Result<Vec<T>, DbError>
+where
+    P: rusqlite::Params,
+    F: FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+{
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, f)?;
+    Ok(rows.collect::<Result<Vec<T>, _>>()?)
+}
+
+/// See `db::insert_entry_row`.
+fn insert_entry_row(
+    conn: &rusqlite::Connection,
+    account_id: i64,
+    bank_account: i64,
+    ts: &DateTime<Utc>,
+    amount_str: &str,
+    note: Option<&str>,
+) -> Result<usize, DbError> {
+    let sql = format!(
+        "
+        insert into entry (
+            bank_account,
+            ts,
+            amount,
+            note,
+            deleted,
+            created,
+            modified
+        )
+        select
+            bank_account.id,
+            ?3,
+            ?4,
+            ?6,
+            false,
+            ?5,
+            ?5
+        from bank_account
+        where
+            bank_account.id = ?2
+            and bank_account.deleted = false
+            and {}
+        returning id",
+        can_write_sql("bank_account", "?1")
+    );
+    let ids = query(
+        conn,
+        sql.as_str(),
+        rusqlite::params![
+            account_id,
+            bank_account,
+            format_ts(ts),
+            check_amount(amount_str)?,
+            now(),
+            note
+        ],
+        |row| row.get(0),
+    )?;
+    for id in &ids {
+        queue_entry_event(conn, *id, WebhookEvent::EntryCreated)?;
+    }
+    Ok(ids.len())
+}
+
+/// See `db::queue_webhook_event`.
+fn queue_webhook_event(
+    conn: &rusqlite::Connection,
+    bank_account_id: i64,
+    event: WebhookEvent,
+    payload: &str,
+) -> Result<usize, DbError> {
+    Ok(conn.execute(
+        "
+        insert into webhook_delivery (
+            webhook,
+            event,
+            payload,
+            status,
+            attempts,
+            next_attempt,
+            created,
+            modified
+        )
+        select webhook.id, ?2, ?3, 'pending', 0, ?4, ?4, ?4
+        from webhook
+        where webhook.account in (
+            select account from bank_account where id = ?1
+            union
+            select account from bank_account_share where bank_account = ?1
+        )",
+        rusqlite::params![bank_account_id, event.as_str(), payload, now()],
+    )?)
+}
+
+/// See `db::queue_entry_event`.
+fn queue_entry_event(
+    conn: &rusqlite::Connection,
+    entry_id: i64,
+    event: WebhookEvent,
+) -> Result<(), DbError> {
+    let entry = conn.query_row(
+        "
+        select
+            entry.id,
+            bank_account.id,
+            bank_account.name,
+            bank_account.currency,
+            entry.amount,
+            entry.ts,
+            entry.note
+        from
+            entry
+            join bank_account on (bank_account.id = entry.bank_account)
+        where entry.id = ?1",
+        [entry_id],
+        |row| {
+            Ok(WebhookEntry {
+                id: row.get(0)?,
+                bank_account_id: row.get(1)?,
+                bank_account: row.get(2)?,
+                currency: row.get(3)?,
+                amount: row.get(4)?,
+                ts: row.get(5)?,
+                note: row.get(6)?,
+            })
+        },
+    )?;
+    queue_webhook_event(
+        conn,
+        entry.bank_account_id,
+        event,
+        &webhook::payload(event, &entry),
+    )?;
+    Ok(())
+}
+
+/// See `db::queue_bank_account_event`.
+fn queue_bank_account_event(
+    conn: &rusqlite::Connection,
+    bank_account_id: i64,
+    event: WebhookEvent,
+) -> Result<(), DbError> {
+    let bank_account = conn.query_row(
+        "
+        select id, name, currency, group_name, kind, commodity
+        from bank_account
+        where id = ?1",
+        [bank_account_id],
+        |row| {
+            Ok(WebhookBankAccount {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                currency: row.get(2)?,
+                group_name: row.get(3)?,
+                kind: row.get(4)?,
+                commodity: row.get(5)?,
+            })
+        },
+    )?;
+    queue_webhook_event(
+        conn,
+        bank_account_id,
+        event,
+        &webhook::payload(event, &bank_account),
+    )?;
+    Ok(())
+}
+
+/// Columns read by `webhook_delivery_from_row`, like
+/// `db::WEBHOOK_DELIVERY_COLUMNS`.
+const WEBHOOK_DELIVERY_COLUMNS: &str = "
+    webhook_delivery.id,
+    webhook.id,
+    webhook.url,
+    webhook_delivery.event,
+    webhook_delivery.payload,
+    webhook_delivery.status,
+    webhook_delivery.attempts,
+    webhook_delivery.next_attempt,
+    webhook_delivery.last_attempt,
+    webhook_delivery.response_status,
+    webhook_delivery.error,
+    webhook_delivery.created,
+    webhook.secret";
+
+fn webhook_delivery_from_row(row: &rusqlite::Row) -> rusqlite::Result<WebhookDelivery> {
+    Ok(WebhookDelivery {
+        id: row.get(0)?,
+        webhook: row.get(1)?,
+        url: row.get(2)?,
+        event: row.get(3)?,
+        payload: row.get(4)?,
+        status: DeliveryStatus::parse(&row.get::<_, String>(5)?).unwrap(),
+        attempts: row.get(6)?,
+        next_attempt: row.get::<_, Option<String>>(7)?.as_deref().map(parse_db_ts),
+        last_attempt: row.get::<_, Option<String>>(8)?.as_deref().map(parse_db_ts),
+        response_status: row.get(9)?,
+        error: row.get(10)?,
+        created: parse_db_ts(&row.get::<_, String>(11)?),
+    })
+}
+
+/// See `Storage::set_session_value`.
+fn insert_session_value(
+    conn: &rusqlite::Connection,
+    session_key: &str,
+    name: &str,
+    value: &str,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        insert into session (
+            key,
+            name,
+            value,
+            created,
+            modified
+        ) values (?1, ?2, ?3, ?4, ?4)
+        on conflict (key, name)
+        do update set
+            value = ?3,
+            modified = ?4",
+        rusqlite::params![session_key, name, value, now()],
+    )?;
+    Ok(())
+}
+
+/// See `db::delete_account_sessions`.
+fn delete_account_sessions(conn: &rusqlite::Connection, account_id: i64) -> Result<(), DbError> {
+    delete_sessions_by(conn, account_id, "account")?;
+    delete_sessions_by(conn, account_id, "totp_account")
+}
+
+/// See `db::delete_sessions_by`.
+fn delete_sessions_by(
+    conn: &rusqlite::Connection,
+    account_id: i64,
+    name: &str,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        delete from session
+        where key in (
+            select key
+            from session
+            where name = ?2 and value = cast(?1 as text)
+        )",
+        rusqlite::params![account_id, name],
+    )?;
+    Ok(())
+}
+
+/// See `Storage::get_login_requests`.
+fn select_login_requests(
+    conn: &rusqlite::Connection,
+    email: &str,
+    ip: Option<&str>,
+    since: &DateTime<Utc>,
+) -> Result<Vec<LoginRequest>, DbError> {
+    query(
+        conn,
+        "
+        select email, ip, created
+        from login_request
+        where
+            (email = ?1 or ip = ?2)
+            and created > ?3
+        order by created, id",
+        rusqlite::params![email, ip, format_ts(since)],
+        |row| {
+            Ok(LoginRequest {
+                email: row.get(0)?,
+                ip: row.get(1)?,
+                created: parse_db_ts(&row.get::<_, String>(2)?),
+            })
+        },
+    )
+}
+
+/// Preferences of user account, see `Storage::get_preferences`.
+fn read_preferences(conn: &rusqlite::Connection, account_id: i64) -> Result<Preferences, DbError> {
+    let defaults = Preferences::default();
+    Ok(conn
+        .query_row(
+            "
+            select
+                theme,
+                landing_page,
+                default_bank_account,
+                entries_per_page,
+                (select frequency from account_digest where account = ?1)
+            from account_preference
+            where account = ?1",
+            [account_id],
+            |row| {
+                Ok(Preferences {
+                    theme: Theme::parse(&row.get::<_, String>(0)?).unwrap_or(defaults.theme),
+                    landing_page: LandingPage::parse(&row.get::<_, String>(1)?)
+                        .unwrap_or(defaults.landing_page),
+                    default_bank_account: row.get(2)?,
+                    entries_per_page: row.get(3)?,
+                    digest: row
+                        .get::<_, Option<String>>(4)?
+                        .as_deref()
+                        .and_then(DigestFrequency::parse)
+                        .unwrap_or(defaults.digest),
+                })
+            },
+        )
+        .optional()?
+        .unwrap_or(defaults))
+}
+
+/// Set preferences of user account, call it in a transaction.
+fn write_preferences(
+    conn: &rusqlite::Connection,
+    account_id: i64,
+    preferences: &Preferences,
+) -> Result<(), DbError> {
+    conn.execute(
+        "
+        insert into account_preference (
+            account,
+            theme,
+            landing_page,
+            default_bank_account,
+            entries_per_page,
+            created,
+            modified
+        )
+        values (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+        on conflict (account) do update set
+            theme = excluded.theme,
+            landing_page = excluded.landing_page,
+            default_bank_account = excluded.default_bank_account,
+            entries_per_page = excluded.entries_per_page,
+            modified = excluded.modified",
+        rusqlite::params![
+            account_id,
+            preferences.theme.as_str(),
+            preferences.landing_page.as_str(),
+            preferences.default_bank_account,
+            preferences.entries_per_page,
+            now()
+        ],
+    )?;
+    if preferences.digest == DigestFrequency::Off {
+        conn.execute(
+            "delete from account_digest where account = ?1",
+            [account_id],
+        )?;
+    } else {
+        conn.execute(
+            "
+            insert into account_digest (account, frequency, created, modified)
+            values (?1, ?2, ?3, ?3)
+            on conflict (account) do update set
+                frequency = excluded.frequency,
+                modified = excluded.modified",
+            rusqlite::params![account_id, preferences.digest.as_str(), now()],
+        )?;
+    }
+    Ok(())
+}
+
+pub struct SqliteStorage {
+    conn: SqliteConnection,
+}
+
+impl SqliteStorage {
+    pub fn new(conn: SqliteConnection) -> SqliteStorage {
+        SqliteStorage { conn }
+    }
+
+    fn insert_token(&mut self, account_id: i64, token: &str, purpose: &str) -> Result<(), DbError> {
+        let now = now();
+        self.conn.execute(
+            "
+            insert into login_token (
+                account,
+                token,
+                purpose,
+                used,
+                used_ts,
+                created,
+                modified
+            ) values (?1, ?2, ?3, false, null, ?4, ?4)",
+            rusqlite::params![account_id, token, purpose, now],
+        )?;
+        Ok(())
+    }
+
+    fn query_entries(
+        &mut self,
+        condition: &str,
+        order: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        let sql = format!(
+            "
+            select
+                {}
+            from
+                entry
+                join bank_account on (bank_account.id = entry.bank_account)
+            where
+                {}
+                and entry.deleted = false
+                and bank_account.deleted = false
+                {}
+            {}",
+            entry_info_columns(),
+            can_read_sql("bank_account", "?1"),
+            condition,
+            order
+        );
+        query(&self.conn, &sql, params, entry_info_from_row)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn get_sess_val(&mut self, sess_key: &str, name: &str) -> Option<String> {
+        let value = self
+            .conn
+            .query_row(
+                "select value from session where key = ?1 and name = ?2",
+                [sess_key, name],
+                |row| row.get(0),
+            )
+            .optional();
+        match value {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Failed to get session value: {}", e);
+                None
+            }
+        }
+    }
+
+    fn set_session_value(
+        &mut self,
+        session_key: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), DbError> {
+        insert_session_value(&self.conn, session_key, name, value)
+    }
+
+    fn increment_session_value(
+        &mut self,
+        session_key: &str,
+        name: &str,
+    ) -> Result<Option<i64>, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "
+                update session
+                set
+                    value = cast(cast(value as integer) + 1 as text),
+                    modified = ?3
+                where key = ?1 and name = ?2
+                returning cast(value as integer)",
+                rusqlite::params![session_key, name, now()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn delete_session(&mut self, session_key: &str) -> Result<(), DbError> {
+        self.conn
+            .execute("delete from session where key = ?1", [session_key])?;
+        Ok(())
+    }
+
+    fn get_acc_id_by_email(&mut self, email: &str) -> Result<Option<i64>, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "select account from account_email where email = ?1",
+                [email],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn create_acc_with_email(&mut self, email: &str) -> Result<i64, DbError> {
+        let now = now();
+        let transaction = self.conn.transaction()?;
+        transaction.execute(
+            "insert into account (created, modified) values (?1, ?1)",
+            [&now],
+        )?;
+        let acc_id = transaction.last_insert_rowid();
+        transaction.execute(
+            "
+            insert into account_email (
+                account,
+                email,
+                created,
+                modified
+            ) values (?1, ?2, ?3, ?3)",
+            rusqlite::params![acc_id, email, now],
+        )?;
+        transaction.commit()?;
+        Ok(acc_id)
+    }
+
+    fn get_user_account_emails(&mut self, acc_id: i64) -> Result<Box<Vec<String>>, DbError> {
+        Ok(Box::new(query(
+            &self.conn,
+            "select email from account_email where account = ?1 order by id",
+            [acc_id],
+            |row| row.get(0),
+        )?))
+    }
+
+    fn get_user_account_info(&mut self, acc_id: i64) -> Result<Option<AccountInfo>, DbError> {
+        let emails = self.get_user_account_emails(acc_id)?;
+        let times: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "select created, modified from account where id = ?1",
+                [acc_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(times.map(|(created, modified)| AccountInfo {
+            created_at: parse_db_ts(&created),
+            modified_at: parse_db_ts(&modified),
+            emails,
+        }))
+    }
+
+    fn set_account_disabled(&mut self, account_id: i64, disabled: bool) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        transaction.execute(
+            "update account set disabled = ?2, modified = ?3 where id = ?1",
+            rusqlite::params![account_id, disabled, now()],
+        )?;
+        if disabled {
+            delete_account_sessions(&transaction, account_id)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn is_account_disabled(&mut self, account_id: i64) -> Result<bool, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "select disabled from account where id = ?1",
+                [account_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(false))
+    }
+
+    fn get_account_summaries(&mut self) -> Result<Vec<AccountSummary>, DbError> {
+        query(
+            &self.conn,
+            "
+            select
+                account.id,
+                (
+                    select group_concat(email, ' ')
+                    from (
+                        select email
+                        from account_email
+                        where account = account.id
+                        order by email
+                    )
+                ),
+                account.created,
+                account.disabled,
+                (
+                    select count(*)
+                    from bank_account
+                    where account = account.id and deleted = false
+                ),
+                (
+                    select count(*)
+                    from entry
+                    join bank_account on bank_account.id = entry.bank_account
+                    where
+                        bank_account.account = account.id
+                        and bank_account.deleted = false
+                        and entry.deleted = false
+                )
+            from account
+            order by account.id",
+            [],
+            |row| {
+                Ok(AccountSummary {
+                    id: row.get(0)?,
+                    emails: row
+                        .get::<_, Option<String>>(1)?
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(String::from)
+                        .collect(),
+                    created: parse_db_ts(&row.get::<_, String>(2)?),
+                    disabled: row.get(3)?,
+                    bank_accounts: row.get(4)?,
+                    entries: row.get(5)?,
+                })
+            },
+        )
+    }
+
+    fn delete_account(&mut self, account_id: i64) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        delete_account_sessions(&transaction, account_id)?;
+        transaction.execute(
+            "
+            delete from bank_account_share
+            where
+                account = ?1
+                or bank_account in (select id from bank_account where account = ?1)",
+            [account_id],
+        )?;
+        transaction.execute(
+            "
+            delete from entry
+            where bank_account in (select id from bank_account where account = ?1)",
+            [account_id],
+        )?;
+        transaction.execute(
+            "
+            update account_preference
+            set default_bank_account = null
+            where default_bank_account in (select id from bank_account where account = ?1)",
+            [account_id],
+        )?;
+        transaction.execute(
+            "
+            delete from webhook_delivery
+            where webhook in (select id from webhook where account = ?1)",
+            [account_id],
+        )?;
+        for table in &[
+            "account_preference",
+            "account_digest",
+            "account_recovery_code",
+            "account_totp",
+            "webhook",
+            "bank_account",
+            "price",
+            "login_token",
+            "api_token",
+            "account_email",
+        ] {
+            let sql = format!("delete from {} where account = ?1", table);
+            transaction.execute(sql.as_str(), [account_id])?;
+        }
+        transaction.execute("delete from account where id = ?1", [account_id])?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_login_token(&mut self, account_id: &i64, token: &str) -> Result<(), DbError> {
+        self.insert_token(*account_id, token, "login")
+    }
+
+    fn insert_delete_account_token(&mut self, account_id: i64, token: &str) -> Result<(), DbError> {
+        self.insert_token(account_id, token, "delete-account")
+    }
+
+    fn get_login_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "
+                select login_token.account
+                from login_token
+                join account on account.id = login_token.account
+                where
+                    login_token.token = ?1
+                    and login_token.used = false
+                    and login_token.purpose = 'login'
+                    and account.disabled = false",
+                [token],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn use_login_token(
+        &mut self,
+        token: &str,
+        session_key: &str,
+        values: &[(&str, &str)],
+    ) -> Result<bool, DbError> {
+        let now = now();
+        let transaction = self.conn.transaction()?;
+        let n = transaction.execute(
+            "
+            update login_token
+            set
+                used = true,
+                used_ts = ?2,
+                modified = ?2
+            where
+                token = ?1
+                and used = false
+                and purpose = 'login'
+                and account in (select id from account where disabled = false)",
+            rusqlite::params![token, now],
+        )?;
+        if n == 0 {
+            return Ok(false);
+        }
+        for (name, value) in values {
+            insert_session_value(&transaction, session_key, name, value)?;
+        }
+        transaction.commit()?;
+        Ok(true)
+    }
+
+    fn get_delete_account_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError> {
+        let day_ago = format_ts(&(Utc::now() - chrono::Duration::days(1)));
+        Ok(self
+            .conn
+            .query_row(
+                "
+                select account
+                from login_token
+                where
+                    token = ?1
+                    and used = false
+                    and purpose = 'delete-account'
+                    and created > ?2",
+                rusqlite::params![token, day_ago],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn insert_login_request(
+        &mut self,
+        email: &str,
+        ip: Option<&str>,
+        token: Option<&str>,
+        invite: Option<&str>,
+        now: &DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, DbError> {
+        let email = &login_limit::normalize_email(email);
+        let day_ago = format_ts(&(*now - chrono::Duration::days(1)));
+        // Takes the write lock now, so concurrent login requests wait and
+        // each sees the ones before.
+        let transaction = self
+            .conn
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let requests =
+            select_login_requests(&transaction, email, ip, &login_limit::window_start(*now))?;
+        if let Some(retry) = login_limit::retry_at(&requests, email, ip, *now) {
+            transaction.rollback()?;
+            return Ok(Some(retry));
+        }
+        transaction.execute("delete from login_request where created < ?1", [&day_ago])?;
+        transaction.execute(
+            "
+            insert into login_request (
+                email,
+                ip,
+                token,
+                invite,
+                used,
+                created,
+                modified
+            ) values (?1, ?2, ?3, ?4, false, ?5, ?5)",
+            rusqlite::params![email, ip, token, invite, format_ts(now)],
+        )?;
+        transaction.commit()?;
+        Ok(None)
+    }
+
+    fn get_login_requests(
+        &mut self,
+        email: &str,
+        ip: Option<&str>,
+        since: &DateTime<Utc>,
+    ) -> Result<Vec<LoginRequest>, DbError> {
+        select_login_requests(&self.conn, email, ip, since)
+    }
+
+    fn use_login_request_token(
+        &mut self,
+        token: &str,
+    ) -> Result<Option<(String, Option<String>)>, DbError> {
+        let day_ago = format_ts(&(Utc::now() - chrono::Duration::days(1)));
+        Ok(self
+            .conn
+            .query_row(
+                "
+                update login_request
+                set
+                    used = true,
+                    modified = ?3
+                where
+                    token = ?1
+                    and used = false
+                    and created > ?2
+                returning email, invite",
+                rusqlite::params![token, day_ago, now()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    fn insert_invite(&mut self, code: &str, note: Option<&str>) -> Result<(), DbError> {
+        self.conn.execute(
+            "
+            insert into invite (
+                code,
+                note,
+                used,
+                created,
+                modified
+            ) values (?1, ?2, null, ?3, ?3)",
+            rusqlite::params![code, note, now()],
+        )?;
+        Ok(())
+    }
+
+    fn get_invites(&mut self) -> Result<Vec<Invite>, DbError> {
+        query(
+            &self.conn,
+            "
+            select code, note, created, used
+            from invite
+            order by created desc, id desc",
+            [],
+            |row| {
+                Ok(Invite {
+                    code: row.get(0)?,
+                    note: row.get(1)?,
+                    created: parse_db_ts(&row.get::<_, String>(2)?),
+                    used: row.get::<_, Option<String>>(3)?.as_deref().map(parse_db_ts),
+                })
+            },
+        )
+    }
+
+    fn is_invite_valid(&mut self, code: &str) -> Result<bool, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "select id from invite where code = ?1 and used is null",
+                [code],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    fn use_invite(&mut self, code: &str) -> Result<bool, DbError> {
+        let n = self.conn.execute(
+            "update invite set used = ?2, modified = ?2 where code = ?1 and used is null",
+            rusqlite::params![code, now()],
+        )?;
+        Ok(n > 0)
+    }
+
+    fn get_totp(&mut self, account_id: i64) -> Result<Option<Totp>, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "
+                select
+                    secret,
+                    enabled,
+                    last_step,
+                    (
+                        select count(*)
+                        from account_recovery_code
+                        where account_recovery_code.account = account_totp.account
+                    )
+                from account_totp
+                where account = ?1",
+                [account_id],
+                |row| {
+                    Ok(Totp {
+                        secret: row.get(0)?,
+                        enabled: row.get(1)?,
+                        last_step: row.get(2)?,
+                        recovery_codes: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn set_totp_secret(&mut self, account_id: i64, secret: &str) -> Result<(), DbError> {
+        self.conn.execute(
+            "
+            insert into account_totp (
+                account,
+                secret,
+                enabled,
+                last_step,
+                created,
+                modified
+            ) values (?1, ?2, false, null, ?3, ?3)
+            on conflict (account) do update
+            set
+                secret = excluded.secret,
+                last_step = null,
+                modified = excluded.modified
+            where account_totp.enabled = false",
+            rusqlite::params![account_id, secret, now()],
+        )?;
+        Ok(())
+    }
+
+    fn enable_totp(
+        &mut self,
+        account_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), DbError> {
+        let now = now();
+        let transaction = self.conn.transaction()?;
+        transaction.execute(
+            "
+            update account_totp
+            set
+                enabled = true,
+                last_step = ?2,
+                modified = ?3
+            where account = ?1",
+            rusqlite::params![account_id, step, now],
+        )?;
+        transaction.execute(
+            "delete from account_recovery_code where account = ?1",
+            [account_id],
+        )?;
+        for code_hash in recovery_code_hashes {
+            transaction.execute(
+                "
+                insert into account_recovery_code (
+                    account,
+                    code_hash,
+                    created
+                ) values (?1, ?2, ?3)",
+                rusqlite::params![account_id, code_hash, now],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn set_totp_step(&mut self, account_id: i64, step: i64) -> Result<bool, DbError> {
+        let n = self.conn.execute(
+            "
+            update account_totp
+            set
+                last_step = ?2,
+                modified = ?3
+            where
+                account = ?1
+                and (last_step is null or last_step < ?2)",
+            rusqlite::params![account_id, step, now()],
+        )?;
+        Ok(n > 0)
+    }
+
+    fn use_recovery_code(&mut self, account_id: i64, code_hash: &str) -> Result<bool, DbError> {
+        let n = self.conn.execute(
+            "delete from account_recovery_code where account = ?1 and code_hash = ?2",
+            rusqlite::params![account_id, code_hash],
+        )?;
+        Ok(n > 0)
+    }
+
+    fn delete_totp(&mut self, account_id: i64) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        transaction.execute(
+            "delete from account_recovery_code where account = ?1",
+            [account_id],
+        )?;
+        transaction.execute("delete from account_totp where account = ?1", [account_id])?;
+        // Logins waiting for the code can't finish now.
+        delete_sessions_by(&transaction, account_id, "totp_account")?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_api_token(
+        &mut self,
+        account_id: i64,
+        name: &str,
+        token: &str,
+    ) -> Result<(), DbError> {
+        self.conn.execute(
+            "
+            insert into api_token (
+                account,
+                name,
+                token,
+                last_used,
+                created,
+                modified
+            ) values (?1, ?2, ?3, null, ?4, ?4)",
+            rusqlite::params![account_id, name, token, now()],
+        )?;
+        Ok(())
+    }
+
+    fn get_api_tokens(&mut self, account_id: i64) -> Result<Vec<ApiToken>, DbError> {
+        query(
+            &self.conn,
+            "
+            select id, name, created, last_used
+            from api_token
+            where account = ?1
+            order by id",
+            [account_id],
+            |row| {
+                Ok(ApiToken {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created: parse_db_ts(&row.get::<_, String>(2)?),
+                    last_used: row.get::<_, Option<String>>(3)?.as_deref().map(parse_db_ts),
+                })
+            },
+        )
+    }
+
+    fn delete_api_token(&mut self, account_id: i64, token_id: i64) -> Result<(), DbError> {
+        self.conn.execute(
+            "delete from api_token where account = ?1 and id = ?2",
+            [account_id, token_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_api_token_account(&mut self, token: &str) -> Result<Option<i64>, DbError> {
+        Ok(self
+            .conn
+            .query_row(
+                "
+                update api_token
+                set last_used = ?2
+                where
+                    token = ?1
+                    and account in (select id from account where disabled = false)
+                returning account",
+                rusqlite::params![token, now()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn insert_entry(
+        &mut self,
+        account_id: &i64,
+        bank_account: &i64,
+        ts: &DateTime<Utc>,
+        amount_str: &str,
+        note: Option<&str>,
+    ) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        insert_entry_row(
+            &transaction,
+            *account_id,
+            *bank_account,
+            ts,
+            amount_str,
+            note,
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_entries(
+        &mut self,
+        account_id: i64,
+        ts: &DateTime<Utc>,
+        amounts: &[(i64, String)],
+    ) -> Result<u64, DbError> {
+        let transaction = self.conn.transaction()?;
+        let mut inserted = 0;
+        for (bank_account, amount) in amounts {
+            inserted +=
+                insert_entry_row(&transaction, account_id, *bank_account, ts, amount, None)?;
+        }
+        transaction.commit()?;
+        Ok(inserted as u64)
+    }
+
+    fn get_entry(&mut self, acc_id: i64, entry_id: i64) -> Result<Option<EntryInfo>, DbError> {
+        Ok(self
+            .query_entries("and entry.id = ?2", "", &[&acc_id, &entry_id])?
+            .into_iter()
+            .next())
+    }
+
+    fn update_entry(
+        &mut self,
+        account_id: i64,
+        entry_id: i64,
+        amount: String,
+        note: Option<&str>,
+    ) -> Result<(), DbError> {
+        let sql = format!(
+            "
+            update entry
+            set
+                amount = ?1,
+                note = ?5,
+                modified = ?4
+            where
+                id = (
+                    select
+                        entry.id
+                    from
+                        entry
+                        join bank_account on (bank_account.id = entry.bank_account)
+                    where
+                        entry.id = ?2
+                        and {}
+                        and entry.deleted = false
+                        and bank_account.deleted = false
+                )
+            returning id",
+            can_write_sql("bank_account", "?3")
+        );
+        let transaction = self.conn.transaction()?;
+        let ids: Vec<i64> = query(
+            &transaction,
+            sql.as_str(),
+            rusqlite::params![check_amount(&amount)?, entry_id, account_id, now(), note],
+            |row| row.get(0),
+        )?;
+        for id in ids {
+            queue_entry_event(&transaction, id, WebhookEvent::EntryUpdated)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_entries(&mut self, account_id: i64) -> Result<Vec<EntryInfo>, DbError> {
+        self.get_entries_page(account_id, None, MAX_ENTRIES_PER_PAGE, 0)
+    }
+
+    fn get_entries_page(
+        &mut self,
+        account_id: i64,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        self.query_entries(
+            "and (?4 is null or instr(lower(entry.note), lower(?4)) > 0)",
+            "order by entry.ts desc, entry.id desc limit ?2 offset ?3",
+            &[&account_id, &limit, &offset, &search],
+        )
+    }
+
+    fn get_all_entries(&mut self, account_id: i64) -> Result<Vec<EntryInfo>, DbError> {
+        self.query_entries("", "order by entry.ts, entry.id", &[&account_id])
+    }
+
+    fn get_entries_by_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        self.query_entries(
+            "and bank_account.id = ?2",
+            "order by entry.ts limit 4096",
+            &[&account_id, &bank_account_id],
+        )
+    }
+
+    fn get_entries_by_group(
+        &mut self,
+        account_id: i64,
+        group_name: &str,
+        currency: &str,
+    ) -> Result<Vec<EntryInfo>, DbError> {
+        self.query_entries(
+            "and bank_account.group_name = ?2 and bank_account.currency = ?3",
+            "order by entry.ts, entry.id",
+            &[&account_id, &group_name, &currency],
+        )
+    }
+
+    fn delete_entry(&mut self, account_id: i64, entry_id: i64) -> Result<(), DbError> {
+        let sql = format!(
+            "
+            update entry
+            set deleted = true, modified = ?3
+            where
+                id = ?2
+                and deleted = false
+                and bank_account in (select id from bank_account where {})
+            returning id",
+            can_write_sql("bank_account", "?1")
+        );
+        let transaction = self.conn.transaction()?;
+        let ids: Vec<i64> = query(
+            &transaction,
+            sql.as_str(),
+            rusqlite::params![account_id, entry_id, now()],
+            |row| row.get(0),
+        )?;
+        for id in ids {
+            queue_entry_event(&transaction, id, WebhookEvent::EntryDeleted)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_bank_accounts(&mut self, account_id: i64) -> Result<Vec<BankAccount>, DbError> {
+        let sql = format!(
+            "
+            select
+                {}
+            from bank_account
+            where
+                {}
+                and bank_account.deleted = false
+            order by bank_account.name, bank_account.id",
+            bank_account_columns(),
+            can_read_sql("bank_account", "?1")
+        );
+        query(&self.conn, &sql, [account_id], bank_account_from_row)
+    }
+
+    fn get_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Option<BankAccount>, DbError> {
+        let sql = format!(
+            "
+            select
+                {}
+            from bank_account
+            where
+                {}
+                and bank_account.id = ?2
+                and bank_account.deleted = false",
+            bank_account_columns(),
+            can_read_sql("bank_account", "?1")
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, [account_id, bank_account_id], bank_account_from_row)
+            .optional()?)
+    }
+
+    fn update_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+        group_name: Option<&str>,
+        kind: BankAccountKind,
+    ) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        let ids: Vec<i64> = query(
+            &transaction,
+            "
+            update bank_account
+            set
+                group_name = ?1,
+                kind = ?2,
+                modified = ?5
+            where
+                account = ?3
+                and id = ?4
+                and deleted = false
+            returning id",
+            rusqlite::params![
+                group_name,
+                kind.as_str(),
+                account_id,
+                bank_account_id,
+                now()
+            ],
+            |row| row.get(0),
+        )?;
+        for id in ids {
+            queue_bank_account_event(&transaction, id, WebhookEvent::BankAccountUpdated)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_bank_account_infos(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<BankAccountInfo>, DbError> {
+        let sql = format!(
+            "
+            select
+                bank_account.id,
+                bank_account.name,
+                bank_account.group_name,
+                bank_account.kind,
+                last_entry.amount,
+                bank_account.currency,
+                last_entry.ts,
+                bank_account.commodity,
+                {},
+                {}
+            from
+                bank_account
+                join entry as last_entry on (
+                    last_entry.id = (
+                        select entry.id
+                        from entry
+                        where
+                            entry.bank_account = bank_account.id
+                            and entry.deleted = false
+                            and entry.ts <= ?2
+                        order by entry.ts desc
+                        limit 1
+                    )
+                )
+            where
+                {}
+                and bank_account.deleted = false
+            order by bank_account.name, bank_account.currency",
+            value_sql("bank_account", "last_entry.amount", "?2"),
+            role_sql("bank_account", "?1"),
+            can_read_sql("bank_account", "?1")
+        );
+        query(
+            &self.conn,
+            &sql,
+            rusqlite::params![account_id, as_of_param(as_of)],
+            |row| {
+                Ok(BankAccountInfo {
+                    id: row.get(0)?,
+                    bank_account: row.get(1)?,
+                    group_name: row.get(2)?,
+                    kind: parse_db_kind(&row.get::<_, String>(3)?),
+                    amount: row.get(4)?,
+                    currency: row.get(5)?,
+                    ts: parse_db_ts(&row.get::<_, String>(6)?),
+                    commodity: row.get(7)?,
+                    value: row.get(8)?,
+                    role: parse_db_role(row.get::<_, Option<String>>(9)?.as_deref()),
+                })
+            },
+        )
+    }
+
+    fn insert_bank_account(
+        &mut self,
+        account_id: i64,
+        name: &str,
+        currency: &str,
+        group_name: Option<&str>,
+        kind: BankAccountKind,
+        commodity: Option<&str>,
+    ) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        let id = transaction.query_row(
+            "
+            insert into bank_account (
+                account,
+                name,
+                currency,
+                group_name,
+                kind,
+                commodity,
+                created,
+                modified
+            ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+            returning id",
+            rusqlite::params![
+                account_id,
+                name,
+                currency,
+                group_name,
+                kind.as_str(),
+                commodity,
+                now()
+            ],
+            |row| row.get(0),
+        )?;
+        queue_bank_account_event(&transaction, id, WebhookEvent::BankAccountCreated)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_currency_info(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<CurrencyInfo>, DbError> {
+        let sql = format!(
+            "
+            select
+                currency,
+                printf('%.2f', coalesce(sum(case when kind = 'liability' then 0 else value end), 0)),
+                printf('%.2f', coalesce(sum(case when kind = 'liability' then value else 0 end), 0)),
+                printf('%.2f', coalesce(sum(case when kind = 'liability' then -value else value end), 0)),
+                max(ts)
+            from ({}) as bank_account_value
+            group by currency
+            order by currency",
+            bank_account_value_sql()
+        );
+        query(
+            &self.conn,
+            &sql,
+            rusqlite::params![account_id, as_of_param(as_of)],
+            |row| {
+                Ok(CurrencyInfo {
+                    currency: row.get(0)?,
+                    assets: row.get(1)?,
+                    liabilities: row.get(2)?,
+                    amount: row.get(3)?,
+                    ts: parse_db_ts(&row.get::<_, String>(4)?),
+                })
+            },
+        )
+    }
+
+    fn get_group_info(
+        &mut self,
+        account_id: i64,
+        as_of: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<GroupInfo>, DbError> {
+        let sql = format!(
+            "
+            select
+                group_name,
+                currency,
+                printf('%.2f', coalesce(sum(case when kind = 'liability' then -value else value end), 0)),
+                max(ts)
+            from ({}) as bank_account_value
+            where group_name is not null
+            group by group_name, currency
+            order by group_name, currency",
+            bank_account_value_sql()
+        );
+        query(
+            &self.conn,
+            &sql,
+            rusqlite::params![account_id, as_of_param(as_of)],
+            |row| {
+                Ok(GroupInfo {
+                    group_name: row.get(0)?,
+                    currency: row.get(1)?,
+                    amount: row.get(2)?,
+                    ts: parse_db_ts(&row.get::<_, String>(3)?),
+                })
+            },
+        )
+    }
+
+    fn get_prices(&mut self, account_id: i64) -> Result<Vec<PriceInfo>, DbError> {
+        query(
+            &self.conn,
+            "
+            select id, commodity, currency, ts, price
+            from price
+            where account = ?1
+            order by commodity, currency, ts desc",
+            [account_id],
+            |row| {
+                Ok(PriceInfo {
+                    id: row.get(0)?,
+                    price: Price {
+                        commodity: row.get(1)?,
+                        currency: row.get(2)?,
+                        ts: parse_db_ts(&row.get::<_, String>(3)?),
+                        price: row.get(4)?,
+                    },
+                })
+            },
+        )
+    }
+
+    fn insert_prices(&mut self, account_id: i64, prices: &[Price]) -> Result<(), DbError> {
+        let now = now();
+        let transaction = self.conn.transaction()?;
+        for price in prices {
+            transaction.execute(
+                "
+                insert into price (
+                    account,
+                    commodity,
+                    currency,
+                    ts,
+                    price,
+                    created,
+                    modified
+                ) values (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                on conflict (account, commodity, currency, ts)
+                do update set
+                    price = ?5,
+                    modified = ?6",
+                rusqlite::params![
+                    account_id,
+                    price.commodity,
+                    price.currency,
+                    format_ts(&price.ts),
+                    check_amount(&price.price)?,
+                    now
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn delete_price(&mut self, account_id: i64, price_id: i64) -> Result<(), DbError> {
+        self.conn.execute(
+            "delete from price where account = ?1 and id = ?2",
+            [account_id, price_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_bank_account_shares(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+    ) -> Result<Vec<BankAccountShare>, DbError> {
+        query(
+            &self.conn,
+            "
+            select
+                bank_account_share.id,
+                (
+                    select min(account_email.email)
+                    from account_email
+                    where account_email.account = bank_account_share.account
+                ),
+                bank_account_share.role
+            from
+                bank_account_share
+                join bank_account on (bank_account.id = bank_account_share.bank_account)
+            where
+                bank_account.account = ?1
+                and bank_account.id = ?2
+            order by bank_account_share.id",
+            [account_id, bank_account_id],
+            |row| {
+                Ok(BankAccountShare {
+                    id: row.get(0)?,
+                    email: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    role: parse_db_role(row.get::<_, Option<String>>(2)?.as_deref())
+                        .unwrap_or(ShareRole::Viewer),
+                })
+            },
+        )
+    }
+
+    fn share_bank_account(
+        &mut self,
+        account_id: i64,
+        bank_account_id: i64,
+        email: &str,
+        role: ShareRole,
+    ) -> Result<bool, DbError> {
+        let n = self.conn.execute(
+            "
+            insert into bank_account_share (
+                bank_account,
+                account,
+                role,
+                created,
+                modified
+            )
+            select
+                bank_account.id,
+                account_email.account,
+                ?4,
+                ?5,
+                ?5
+            from
+                bank_account
+                join account_email on (account_email.email = ?3)
+            where
+                bank_account.account = ?1
+                and bank_account.id = ?2
+                and bank_account.deleted = false
+                and account_email.account <> ?1
+            on conflict (bank_account, account)
+            do update set
+                role = ?4,
+                modified = ?5",
+            rusqlite::params![account_id, bank_account_id, email, role.as_str(), now()],
+        )?;
+        Ok(n > 0)
+    }
+
+    fn delete_bank_account_share(&mut self, account_id: i64, share_id: i64) -> Result<(), DbError> {
+        self.conn.execute(
+            "
+            delete from bank_account_share
+            where
+                id = ?2
+                and bank_account in (select id from bank_account where account = ?1)",
+            [account_id, share_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_backup(&mut self, account_id: i64) -> Result<Backup, DbError> {
+        let transaction = self.conn.transaction()?;
+        let emails: Vec<String> = query(
+            &transaction,
+            "select email from account_email where account = ?1 order by id",
+            [account_id],
+            |row| row.get(0),
+        )?;
+        let mut bank_accounts: Vec<backup::BackupBankAccount> = Vec::new();
+        let mut bank_account_ids: Vec<i64> = Vec::new();
+        let rows = query(
+            &transaction,
+            "
+            select
+                id,
+                name,
+                currency,
+                group_name,
+                kind,
+                commodity,
+                deleted,
+                created,
+                modified
+            from bank_account
+            where account = ?1
+            order by id",
+            [account_id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    backup::BackupBankAccount {
+                        name: row.get(1)?,
+                        currency: row.get(2)?,
+                        group_name: row.get(3)?,
+                        kind: row.get(4)?,
+                        commodity: row.get(5)?,
+                        deleted: row.get(6)?,
+                        created: row.get(7)?,
+                        modified: row.get(8)?,
+                        entries: Vec::new(),
+                    },
+                ))
+            },
+        )?;
+        for (bank_account_id, mut bank_account) in rows {
+            bank_account.entries = query(
+                &transaction,
+                "
+                select ts, amount, deleted, created, modified, note
+                from entry
+                where bank_account = ?1
+                order by ts, id",
+                [bank_account_id],
+                |row| {
+                    Ok(backup::BackupEntry {
+                        ts: row.get(0)?,
+                        amount: row.get(1)?,
+                        deleted: row.get(2)?,
+                        created: row.get(3)?,
+                        modified: row.get(4)?,
+                        note: row.get(5)?,
+                    })
+                },
+            )?;
+            bank_accounts.push(bank_account);
+            bank_account_ids.push(bank_account_id);
+        }
+        let prices = query(
+            &transaction,
+            "
+            select commodity, currency, ts, price
+            from price
+            where account = ?1
+            order by commodity, currency, ts",
+            [account_id],
+            |row| {
+                Ok(backup::BackupPrice {
+                    commodity: row.get(0)?,
+                    currency: row.get(1)?,
+                    ts: row.get(2)?,
+                    price: row.get(3)?,
+                })
+            },
+        )?;
+        let preferences = read_preferences(&transaction, account_id)?;
+        transaction.commit()?;
+        Ok(Backup {
+            version: backup::VERSION,
+            exported: now(),
+            emails,
+            bank_accounts,
+            prices,
+            preferences: Some(backup::BackupPreferences::new(
+                &preferences,
+                &bank_account_ids,
+            )),
+        })
+    }
+
+    fn restore_backup(
+        &mut self,
+        account_id: i64,
+        backup: &Backup,
+        dry_run: bool,
+    ) -> Result<RestoreSummary, DbError> {
+        let now = now();
+        let mut summary = RestoreSummary::default();
+        let transaction = self.conn.transaction()?;
+        for email in &backup.emails {
+            let existing: Option<i64> = transaction
+                .query_row(
+                    "select 1 from account_email where account = ?1 and email = ?2",
+                    rusqlite::params![account_id, email],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if existing.is_some() {
+                summary.emails_existing += 1;
+            } else {
+                summary.emails_skipped += 1;
+            }
+        }
+        let mut bank_account_ids: Vec<i64> = Vec::new();
+        for ba in &backup.bank_accounts {
+            let existing: Option<i64> = transaction
+                .query_row(
+                    "
+                    select id
+                    from bank_account
+                    where account = ?1 and name = ?2 and currency = ?3",
+                    rusqlite::params![account_id, ba.name, ba.currency],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let bank_account_id: i64 = match existing {
+                Some(id) => {
+                    summary.bank_accounts_existing += 1;
+                    id
+                }
+                None => {
+                    summary.bank_accounts_added += 1;
+                    transaction.execute(
+                        "
+                        insert into bank_account (
+                            account,
+                            name,
+                            currency,
+                            group_name,
+                            kind,
+                            commodity,
+                            deleted,
+                            created,
+                            modified
+                        ) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                        rusqlite::params![
+                            account_id,
+                            ba.name,
+                            ba.currency,
+                            ba.group_name,
+                            ba.kind,
+                            ba.commodity,
+                            ba.deleted,
+                            normalize_ts(&ba.created)?,
+                            normalize_ts(&ba.modified)?
+                        ],
+                    )?;
+                    transaction.last_insert_rowid()
+                }
+            };
+            bank_account_ids.push(bank_account_id);
+            for e in &ba.entries {
+                let n = transaction.execute(
+                    "
+                    insert into entry (
+                        bank_account,
+                        ts,
+                        amount,
+                        deleted,
+                        created,
+                        modified,
+                        note
+                    )
+                    select ?1, ?2, ?3, ?4, ?5, ?6, ?7
+                    where not exists (
+                        select 1
+                        from entry
+                        where
+                            bank_account = ?1
+                            and ts = ?2
+                            and cast(amount as real) = cast(?3 as real)
+                    )",
+                    rusqlite::params![
+                        bank_account_id,
+                        normalize_ts(&e.ts)?,
+                        check_amount(&e.amount)?,
+                        e.deleted,
+                        normalize_ts(&e.created)?,
+                        normalize_ts(&e.modified)?,
+                        e.note
+                    ],
+                )?;
+                if n > 0 {
+                    summary.entries_added += 1;
+                } else {
+                    summary.entries_existing += 1;
+                }
+            }
+        }
+        for p in &backup.prices {
+            let n = transaction.execute(
+                "
+                insert into price (
+                    account,
+                    commodity,
+                    currency,
+                    ts,
+                    price,
+                    created,
+                    modified
+                ) values (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                on conflict (account, commodity, currency, ts) do nothing",
+                rusqlite::params![
+                    account_id,
+                    p.commodity,
+                    p.currency,
+                    normalize_ts(&p.ts)?,
+                    check_amount(&p.price)?,
+                    now
+                ],
+            )?;
+            if n > 0 {
+                summary.prices_added += 1;
+            } else {
+                summary.prices_existing += 1;
+            }
+        }
+        if let Some(ref preferences) = backup.preferences {
+            write_preferences(
+                &transaction,
+                account_id,
+                &preferences.to_preferences(&bank_account_ids),
+            )?;
+            summary.preferences_restored = true;
+        }
+        if dry_run {
+            transaction.rollback()?;
+        } else {
+            transaction.commit()?;
+        }
+        Ok(summary)
+    }
+
+    fn get_preferences(&mut self, account_id: i64) -> Result<Preferences, DbError> {
+        read_preferences(&self.conn, account_id)
+    }
+
+    fn set_preferences(
+        &mut self,
+        account_id: i64,
+        preferences: &Preferences,
+    ) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        write_preferences(&transaction, account_id, preferences)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn insert_webhook(&mut self, account_id: i64, url: &str, secret: &str) -> Result<(), DbError> {
+        self.conn.execute(
+            "
+            insert into webhook (
+                account,
+                url,
+                secret,
+                created,
+                modified
+            ) values (?1, ?2, ?3, ?4, ?4)",
+            rusqlite::params![account_id, url, secret, now()],
+        )?;
+        Ok(())
+    }
+
+    fn get_webhooks(&mut self, account_id: i64) -> Result<Vec<Webhook>, DbError> {
+        query(
+            &self.conn,
+            "
+            select id, url, created
+            from webhook
+            where account = ?1
+            order by id",
+            [account_id],
+            |row| {
+                Ok(Webhook {
+                    id: row.get(0)?,
+                    url: row.get(1)?,
+                    created: parse_db_ts(&row.get::<_, String>(2)?),
+                })
+            },
+        )
+    }
+
+    fn delete_webhook(&mut self, account_id: i64, webhook_id: i64) -> Result<(), DbError> {
+        let transaction = self.conn.transaction()?;
+        transaction.execute(
+            "
+            delete from webhook_delivery
+            where webhook in (select id from webhook where account = ?1 and id = ?2)",
+            [account_id, webhook_id],
+        )?;
+        transaction.execute(
+            "delete from webhook where account = ?1 and id = ?2",
+            [account_id, webhook_id],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn get_webhook_deliveries(
+        &mut self,
+        account_id: i64,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, DbError> {
+        let sql = format!(
+            "
+            select {}
+            from
+                webhook_delivery
+                join webhook on (webhook.id = webhook_delivery.webhook)
+            where webhook.account = ?1
+            order by webhook_delivery.id desc
+            limit ?2",
+            WEBHOOK_DELIVERY_COLUMNS
+        );
+        query(
+            &self.conn,
+            &sql,
+            [account_id, limit],
+            webhook_delivery_from_row,
+        )
+    }
+
+    fn get_due_webhook_deliveries(
+        &mut self,
+        now: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(WebhookDelivery, String)>, DbError> {
+        let sql = format!(
+            "
+            select {}
+            from
+                webhook_delivery
+                join webhook on (webhook.id = webhook_delivery.webhook)
+            where
+                webhook_delivery.status = 'pending'
+                and webhook_delivery.next_attempt <= ?1
+            order by webhook_delivery.next_attempt, webhook_delivery.id
+            limit ?2",
+            WEBHOOK_DELIVERY_COLUMNS
+        );
+        query(
+            &self.conn,
+            &sql,
+            rusqlite::params![format_ts(now), limit],
+            |row| Ok((webhook_delivery_from_row(row)?, row.get(12)?)),
+        )
+    }
+
+    fn record_webhook_attempt(
+        &mut self,
+        delivery_id: i64,
+        status: DeliveryStatus,
+        response_status: Option<i64>,
+        error: Option<&str>,
+        next_attempt: Option<&DateTime<Utc>>,
+    ) -> Result<(), DbError> {
+        self.conn.execute(
+            "
+            update webhook_delivery
+            set
+                status = ?2,
+                attempts = attempts + 1,
+                response_status = ?3,
+                error = ?4,
+                next_attempt = ?5,
+                last_attempt = ?6,
+                modified = ?6
+            where id = ?1",
+            rusqlite::params![
+                delivery_id,
+                status.as_str(),
+                response_status,
+                error,
+                next_attempt.map(format_ts),
+                now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_digest_subscriptions(&mut self) -> Result<Vec<DigestSubscription>, DbError> {
+        query(
+            &self.conn,
+            "
+            select account_digest.account, account_digest.frequency, account_digest.sent
+            from
+                account_digest
+                join account on (account.id = account_digest.account)
+            where account.disabled = false
+            order by account_digest.account",
+            [],
+            |row| {
+                Ok(DigestSubscription {
+                    account_id: row.get(0)?,
+                    frequency: DigestFrequency::parse(&row.get::<_, String>(1)?).unwrap(),
+                    sent: row.get::<_, Option<String>>(2)?.as_deref().map(parse_db_ts),
+                })
+            },
+        )
+    }
+
+    fn set_digest_sent(&mut self, account_id: i64, sent: &DateTime<Utc>) -> Result<(), DbError> {
+        self.conn.execute(
+            "update account_digest set sent = ?2 where account = ?1",
+            rusqlite::params![account_id, format_ts(sent)],
+        )?;
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
 use maud;
 use maud::html;
 
+use crate::model::Preferences;
 use crate::tmpl::common::tmpl_base;
 
-pub fn tmpl_about() -> maud::Markup {
+pub fn tmpl_about(prefs: &Preferences) -> maud::Markup {
     let content = html! {
         h1 {
             "About"
@@ -27,5 +28,5 @@ pub fn tmpl_about() -> maud::Markup {
             }
         }
     };
-    tmpl_base("About", content)
+    tmpl_base("About", prefs, content)
 }
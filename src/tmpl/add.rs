@@ -4,9 +4,15 @@ use maud::Markup;
 use std::fmt::Display;
 
 use crate::model::BankAccount;
+use crate::model::Preferences;
 use crate::tmpl::common;
 
-fn select_field<N: Display>(label: &str, key: &str, options: &[(N, &String)]) -> Markup {
+fn select_field<N: Display + PartialEq>(
+    label: &str,
+    key: &str,
+    options: &[(N, &String)],
+    selected: Option<&N>,
+) -> Markup {
     html! {
         tr {
             td {
@@ -15,7 +21,7 @@ fn select_field<N: Display>(label: &str, key: &str, options: &[(N, &String)]) ->
             td {
                 select name=(key) {
                     @for &(ref k, ref n) in options {
-                        option value=(k) {
+                        option value=(k) selected[Some(k) == selected] {
                             (n)
                         }
                     }
@@ -38,7 +44,7 @@ fn simple_field(title: &str, key: &str, value: &str) -> Markup {
     }
 }
 
-pub fn tmpl_add(title: &str, bank_accounts: &Vec<BankAccount>) -> Markup {
+pub fn tmpl_add(prefs: &Preferences, title: &str, bank_accounts: &Vec<BankAccount>) -> Markup {
     let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let bank_account_select_options: Vec<(i64, &String)> =
         bank_accounts.iter().map(|ba| (ba.id, &ba.name)).collect();
@@ -49,9 +55,11 @@ pub fn tmpl_add(title: &str, bank_accounts: &Vec<BankAccount>) -> Markup {
                     (select_field(
                         "Bank Account",
                         "bank_account",
-                        bank_account_select_options.as_slice()))
+                        bank_account_select_options.as_slice(),
+                        prefs.default_bank_account.as_ref()))
                     (simple_field("Date/Time:", "ts", &now))
                     (simple_field("Amount:", "amount", ""))
+                    (simple_field("Note:", "note", ""))
                     tr {
                         td colspan="3" align="right" {
                             button type="submit" {
@@ -63,5 +71,5 @@ pub fn tmpl_add(title: &str, bank_accounts: &Vec<BankAccount>) -> Markup {
             }
         }
     };
-    common::tmpl_base(title, form)
+    common::tmpl_base(title, prefs, form)
 }
@@ -1,6 +1,9 @@
 use maud::html;
 use maud::Markup;
 
+use crate::currency::CURRENCIES;
+use crate::model::BankAccountKind;
+use crate::model::Preferences;
 use crate::tmpl::common::tmpl_base;
 
 pub struct AddBankAccountTmplData {
@@ -8,9 +11,43 @@ pub struct AddBankAccountTmplData {
     pub name_err: String,
     pub curr: String,
     pub curr_err: String,
+    pub group_name: String,
+    pub group_err: String,
+    pub kind: BankAccountKind,
+    pub commodity: String,
+    pub commodity_err: String,
 }
 
-pub fn add_bank_account(data: &AddBankAccountTmplData) -> Markup {
+/// Bank account kind select, shared with edit bank account form.
+pub fn kind_select(selected: BankAccountKind) -> Markup {
+    html! {
+        select name="kind" {
+            @for kind in &[BankAccountKind::Asset, BankAccountKind::Liability] {
+                option value=(kind.as_str()) selected[*kind == selected] {
+                    (kind.as_str())
+                }
+            }
+        }
+    }
+}
+
+/// Currency picker over the ISO 4217 registry.
+fn currency_select(selected: &str) -> Markup {
+    html! {
+        select name="currency" {
+            option value="" selected[selected.is_empty()] {
+                "(choose)"
+            }
+            @for currency in CURRENCIES {
+                option value=(currency.code) selected[currency.code == selected] {
+                    (currency.label())
+                }
+            }
+        }
+    }
+}
+
+pub fn add_bank_account(prefs: &Preferences, data: &AddBankAccountTmplData) -> Markup {
     let content = html! {
         form method="post" {
             table class="form" {
@@ -33,15 +70,52 @@ pub fn add_bank_account(data: &AddBankAccountTmplData) -> Markup {
                         td label {
                             "Currency:"
                         }
+                        td {
+                            (currency_select(&data.curr))
+                        }
+                        td class="error" {
+                            (data.curr_err)
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Group:"
+                        }
                         td {
                             input
-                                name="currency"
+                                name="group_name"
                                 type="text"
-                                value=(data.curr)
+                                value=(data.group_name)
                                 /
                         }
                         td class="error" {
-                            (data.curr_err)
+                            (data.group_err)
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Type:"
+                        }
+                        td {
+                            (kind_select(data.kind))
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Commodity:"
+                        }
+                        td {
+                            input
+                                name="commodity"
+                                type="text"
+                                value=(data.commodity)
+                                /
+                        }
+                        td {
+                            "Only for holdings, e.g. ticker. Amounts are then quantities."
+                        }
+                        td class="error" {
+                            (data.commodity_err)
                         }
                     }
                     tr {
@@ -55,5 +129,5 @@ pub fn add_bank_account(data: &AddBankAccountTmplData) -> Markup {
             }
         }
     };
-    tmpl_base("Add Bank Account", content)
+    tmpl_base("Add Bank Account", prefs, content)
 }
@@ -0,0 +1,84 @@
+use maud::html;
+use maud::Markup;
+
+use crate::model::ApiToken;
+use crate::model::Preferences;
+use crate::tmpl::common::tmpl_base;
+use crate::util::format_ts;
+
+/// Part of profile page that lists API tokens.
+pub fn tmpl_api_tokens(tokens: &[ApiToken]) -> Markup {
+    html! {
+        h2 {
+            "API Tokens"
+        }
+        p style="font-size: small" {
+            "Personal tokens let scripts and cashlog-cli use your account."
+        }
+        @if !tokens.is_empty() {
+            table class="data" {
+                thead {
+                    tr {
+                        th {
+                            "name"
+                        }
+                        th {
+                            "created"
+                        }
+                        th {
+                            "last used"
+                        }
+                        th /
+                    }
+                }
+                tbody {
+                    @for token in tokens {
+                        tr {
+                            td {
+                                (token.name)
+                            }
+                            td {
+                                (format_ts(token.created))
+                            }
+                            td {
+                                @if let Some(last_used) = token.last_used {
+                                    (format_ts(last_used))
+                                }
+                            }
+                            td {
+                                a href=(format!("/api-tokens/revoke?id={}", token.id)) {
+                                    "revoke"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        form method="post" action="/api-tokens" {
+            "Name: "
+            input type="text" name="name" /
+            " "
+            button type="submit" {
+                "Create token"
+            }
+        }
+    }
+}
+
+pub fn tmpl_api_token_created(prefs: &Preferences, name: &str, token: &str) -> Markup {
+    let content = html! {
+        p {
+            "Token " (name) " created. Copy it now, it won't be shown again:"
+        }
+        pre {
+            (token)
+        }
+        p {
+            a href="/profile" {
+                "Back to profile"
+            }
+        }
+    };
+    tmpl_base("API Token", prefs, content)
+}
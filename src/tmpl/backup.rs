@@ -0,0 +1,124 @@
+//! Template for full backup and restore.
+
+use maud;
+use maud::html;
+use maud::Markup;
+
+use crate::backup::RestoreSummary;
+use crate::model::Preferences;
+use crate::tmpl::common::tmpl_base;
+
+fn tmpl_summary(summary: &RestoreSummary, dry_run: bool) -> Markup {
+    let rows = [
+        (
+            "bank accounts",
+            summary.bank_accounts_added,
+            summary.bank_accounts_existing,
+        ),
+        ("entries", summary.entries_added, summary.entries_existing),
+        ("prices", summary.prices_added, summary.prices_existing),
+    ];
+    html! {
+        p {
+            @if dry_run {
+                "Dry run, nothing was changed. Restore would do:"
+            } @else {
+                "Restored:"
+            }
+        }
+        table class="data" {
+            thead {
+                tr {
+                    th {
+                        ""
+                    }
+                    th {
+                        "added"
+                    }
+                    th {
+                        "already there"
+                    }
+                }
+            }
+            tbody {
+                @for (name, added, existing) in rows.iter() {
+                    tr {
+                        td {
+                            (name)
+                        }
+                        td {
+                            (added)
+                        }
+                        td {
+                            (existing)
+                        }
+                    }
+                }
+            }
+        }
+        @if summary.preferences_restored {
+            p {
+                @if dry_run {
+                    "Preferences would be replaced by the ones in the backup."
+                } @else {
+                    "Preferences were replaced by the ones in the backup."
+                }
+            }
+        }
+        @if summary.emails_skipped > 0 {
+            p {
+                (summary.emails_skipped) " email(s) are not in this user account and were skipped, restore doesn't add emails."
+            }
+        }
+    }
+}
+
+/// Backup page, `result` is the summary of restore that was just done,
+/// with dry run flag.
+pub fn tmpl_backup(
+    prefs: &Preferences,
+    base_href: &str,
+    error: Option<&str>,
+    result: Option<(&RestoreSummary, bool)>,
+) -> Markup {
+    let now = chrono::Utc::now();
+    let filename = format!("cashlog-backup-{}.json", now.format("%Y%m%d%H%M%S"));
+    let href = format!("{}/backup/{}", base_href, filename);
+    let content = html! {
+        p {
+            "Download everything in JSON format: "
+            a href=(href) {
+                (filename)
+            }
+        }
+        h2 {
+            "Restore"
+        }
+        @if let Some(error) = error {
+            p class="error" {
+                (error)
+            }
+        }
+        @if let Some((summary, dry_run)) = result {
+            (tmpl_summary(summary, dry_run))
+        }
+        form method="post" action=(format!("{}/backup/restore", base_href)) {
+            p style="font-size: small" {
+                "Paste backup JSON. Bank accounts, entries and prices already here are kept, "
+                "so restoring the same backup twice is safe. Preferences are replaced by the "
+                "ones in the backup."
+            }
+            textarea name="json" rows="12" cols="60" {}
+            br /
+            label {
+                input type="checkbox" name="dry_run" value="1" checked /
+                " dry run"
+            }
+            " "
+            button type="submit" {
+                "Restore"
+            }
+        }
+    };
+    tmpl_base("Backup", prefs, content)
+}
@@ -1,28 +1,205 @@
+use chrono::DateTime;
+use chrono::Utc;
 use maud::html;
 use maud::Markup;
 
 use crate::model::BankAccountInfo;
+use crate::model::GroupInfo;
+use crate::model::Preferences;
+use crate::model::ShareRole;
+use crate::report::DiffRow;
 use crate::tmpl::common::tmpl_base;
 use crate::util;
 
-pub fn tmpl_bank_accounts(bank_accounts: &Vec<BankAccountInfo>) -> Markup {
+/// Point in time shown on Accounts and Currency pages, `None` means now.
+pub struct AsOf {
+    pub as_of: Option<DateTime<Utc>>,
+    pub compare: Option<DateTime<Utc>>,
+    /// Raw values, to be shown back in the form.
+    pub as_of_str: String,
+    pub compare_str: String,
+}
+
+impl AsOf {
+    /// Whether two dates are compared.
+    pub fn is_diff(&self) -> bool {
+        self.compare.is_some()
+    }
+}
+
+fn as_of_label(s: &str) -> &str {
+    if s.is_empty() {
+        "now"
+    } else {
+        s
+    }
+}
+
+/// Form to pick the date(s) of Accounts and Currency pages.
+pub fn tmpl_as_of_form(as_of: &AsOf) -> Markup {
+    html! {
+        form method="get" style="font-size: small" {
+            "As of: "
+            input type="text" name="as_of" size="10" placeholder="YYYY-MM-DD" value=(as_of.as_of_str) /
+            " compare with: "
+            input type="text" name="compare" size="10" placeholder="YYYY-MM-DD" value=(as_of.compare_str) /
+            " "
+            button type="submit" {
+                "Show"
+            }
+        }
+    }
+}
+
+/// Balances at both dates side by side.
+/// With `with_currency` the first column is bank account name followed by
+/// currency, otherwise it's currency.
+pub fn tmpl_diff_table(as_of: &AsOf, rows: &[DiffRow], with_currency: bool) -> Markup {
+    let amount = |a: Option<f64>| a.map(util::format_amount).unwrap_or_default();
+    html! {
+        table class="data" {
+            thead {
+                tr {
+                    th {
+                        @if with_currency {
+                            "account"
+                        } @else {
+                            "currency"
+                        }
+                    }
+                    @if with_currency {
+                        th {
+                            "currency"
+                        }
+                    }
+                    th {
+                        (as_of_label(&as_of.as_of_str))
+                    }
+                    th {
+                        (as_of_label(&as_of.compare_str))
+                    }
+                    th {
+                        "change"
+                    }
+                }
+            }
+            tbody {
+                @for row in rows {
+                    tr {
+                        td {
+                            (row.name)
+                        }
+                        @if with_currency {
+                            td {
+                                (row.currency)
+                            }
+                        }
+                        td {
+                            (amount(row.before))
+                        }
+                        td {
+                            (amount(row.after))
+                        }
+                        td {
+                            (util::format_amount(row.change))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Subtotals by bank account group, shown on Accounts and Currency pages.
+pub fn tmpl_group_subtotals(groups: &[GroupInfo]) -> Markup {
+    html! {
+        @if !groups.is_empty() {
+            h2 {
+                "Groups"
+            }
+            table class="data" {
+                thead {
+                    tr {
+                        th {
+                            "group"
+                        }
+                        th colspan="2" {
+                            "amount"
+                        }
+                        th {
+                            "updated"
+                        }
+                        th /
+                    }
+                }
+                tbody {
+                    @for group_info in groups {
+                        tr {
+                            td {
+                                (group_info.group_name)
+                            }
+                            td {
+                                (group_info.amount)
+                            }
+                            td {
+                                (group_info.currency)
+                            }
+                            td {
+                                (util::format_ts(group_info.ts))
+                            }
+                            td {
+                                a href=(format!(
+                                    "graph?group={}&currency={}",
+                                    util::url_encode(&group_info.group_name),
+                                    util::url_encode(&group_info.currency))) {
+                                    "graph"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn tmpl_bank_accounts(
+    prefs: &Preferences,
+    bank_accounts: &Vec<BankAccountInfo>,
+    groups: &[GroupInfo],
+    as_of: &AsOf,
+) -> Markup {
     let content = html! {
         p style="font-size: small" {
             "[ "
             a href="add-bank-account" {
                 "Add Bank Account"
             }
+            " ] [ "
+            a href="graph" {
+                "Compare Accounts"
+            }
             " ]"
         }
+        (tmpl_as_of_form(as_of))
         table class="data" {
             thead {
                 tr {
                     th {
                         "account"
                     }
+                    th {
+                        "group"
+                    }
+                    th {
+                        "type"
+                    }
                     th colspan="2" {
                         "amount"
                     }
+                    th {
+                        "value"
+                    }
                     th {
                         "updated"
                     }
@@ -34,26 +211,72 @@ pub fn tmpl_bank_accounts(bank_accounts: &Vec<BankAccountInfo>) -> Markup {
                     tr {
                         td {
                             (bank_account_info.bank_account)
+                            @if let Some(role) = bank_account_info.role {
+                                span style="font-size: small" {
+                                    " (shared, " (role.as_str()) ")"
+                                }
+                            }
+                        }
+                        td {
+                            (bank_account_info.group_name.as_deref().unwrap_or(""))
+                        }
+                        td {
+                            (bank_account_info.kind.as_str())
                         }
                         td {
                             (bank_account_info.amount)
                         }
                         td {
-                            (bank_account_info.currency)
+                            (bank_account_info.commodity.as_ref().unwrap_or(&bank_account_info.currency))
+                        }
+                        td {
+                            @if let Some(ref value) = bank_account_info.value {
+                                (value) " " (bank_account_info.currency)
+                            }
                         }
                         td {
                             (util::format_ts(bank_account_info.ts))
                         }
                         td {
-                            a href=(format!("graph?account={}", bank_account_info.bank_account)) {
+                            a href=(format!("graph?id={}", bank_account_info.id)) {
                                 "graph"
                             }
+                            @if ShareRole::can_edit(bank_account_info.role) {
+                                ", "
+                                a href=(format!("quick/{}", bank_account_info.id)) {
+                                    "quick add"
+                                }
+                            }
+                            @if bank_account_info.role.is_none() {
+                                ", "
+                                a href=(format!("edit-bank-account?id={}", bank_account_info.id)) {
+                                    "edit"
+                                }
+                            }
                         }
                     }
                 }
             }
         }
+        (tmpl_group_subtotals(groups))
     };
     let title = "Accounts";
-    tmpl_base(title, content)
+    tmpl_base(title, prefs, content)
+}
+
+pub fn tmpl_bank_accounts_diff(
+    prefs: &Preferences,
+    as_of: &AsOf,
+    rows: &[DiffRow],
+    currency_rows: &[DiffRow],
+) -> Markup {
+    let content = html! {
+        (tmpl_as_of_form(as_of))
+        (tmpl_diff_table(as_of, rows, true))
+        h2 {
+            "Currency"
+        }
+        (tmpl_diff_table(as_of, currency_rows, false))
+    };
+    tmpl_base("Accounts", prefs, content)
 }
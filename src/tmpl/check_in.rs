@@ -0,0 +1,90 @@
+//! Template for monthly check-in page.
+
+use chrono::DateTime;
+use chrono::Utc;
+use maud;
+use maud::html;
+
+use crate::model::BankAccount;
+use crate::model::Preferences;
+use crate::tmpl::common::tmpl_base;
+use crate::util::format_ts;
+
+/// Bank account in check-in form, with its last entry if any.
+pub struct CheckInRow {
+    pub bank_account: BankAccount,
+    pub last_amount: Option<String>,
+    pub last_ts: Option<DateTime<Utc>>,
+    /// Amount as entered, kept when the form is shown again with error.
+    pub amount: String,
+}
+
+pub fn tmpl_check_in(
+    prefs: &Preferences,
+    ts: &str,
+    rows: &[CheckInRow],
+    error: Option<&str>,
+) -> maud::Markup {
+    let content = html! {
+        @if let Some(error) = error {
+            p class="error" {
+                (error)
+            }
+        }
+        form method="post" action="check-in" {
+            p {
+                "Date/Time: "
+                input type="text" name="ts" value=(ts) /
+            }
+            table class="data" {
+                thead {
+                    tr {
+                        th {
+                            "account"
+                        }
+                        th colspan="2" {
+                            "last amount"
+                        }
+                        th {
+                            "last ts"
+                        }
+                        th {
+                            "amount"
+                        }
+                    }
+                }
+                tbody {
+                    @for row in rows {
+                        tr {
+                            td {
+                                (row.bank_account.name)
+                            }
+                            td {
+                                (row.last_amount.as_deref().unwrap_or(""))
+                            }
+                            td {
+                                (row.bank_account.commodity.as_ref().unwrap_or(&row.bank_account.currency))
+                            }
+                            td {
+                                (row.last_ts.map(format_ts).unwrap_or_default())
+                            }
+                            td {
+                                input
+                                    type="text"
+                                    name=(format!("amount-{}", row.bank_account.id))
+                                    value=(row.amount) /
+                            }
+                        }
+                    }
+                }
+            }
+            p {
+                "Blank amounts are skipped. "
+                button type="submit" {
+                    "Save"
+                }
+            }
+        }
+    };
+    tmpl_base("Check-in", prefs, content)
+}
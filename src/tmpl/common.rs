@@ -3,11 +3,14 @@ use maud::html;
 use maud::Markup;
 use maud::PreEscaped;
 
-pub fn tmpl_base(title: &str, content: Markup) -> Markup {
+use crate::model::Preferences;
+use crate::model::Theme;
+
+pub fn tmpl_base(title: &str, prefs: &Preferences, content: Markup) -> Markup {
     html! {
         (maud::DOCTYPE)
         html {
-            (tmpl_head(title))
+            (tmpl_head(title, prefs.theme))
             body {
                 (tmpl_menu(true))
                 div class="content" {
@@ -19,6 +22,21 @@ pub fn tmpl_base(title: &str, content: Markup) -> Markup {
     }
 }
 
+/// Page without menu and footer, for small screens.
+pub fn tmpl_minimal(title: &str, prefs: &Preferences, content: Markup) -> Markup {
+    html! {
+        (maud::DOCTYPE)
+        html {
+            (tmpl_head(title, prefs.theme))
+            body {
+                div class="content" {
+                    (content)
+                }
+            }
+        }
+    }
+}
+
 /// Create the footer div.
 fn tmpl_foot() -> Markup {
     /*
@@ -48,7 +66,7 @@ fn tmpl_foot() -> Markup {
     }
 }
 
-fn tmpl_css() -> Markup {
+fn tmpl_css(theme: Theme) -> Markup {
     html! {
         style {
             (PreEscaped(
@@ -163,13 +181,117 @@ fn tmpl_css() -> Markup {
                 table.form > tbody > tr > td.error {
                     font-size: 90%;
                     color: red;
+                }
+
+                p.error {
+                    color: red;
+                }
+
+                /* quick add */
+
+                form.quick input, form.quick button {
+                    font-size: 150%;
+                    width: 100%;
+                    margin-bottom: 6pt;
                 }"
             ))
+            (PreEscaped(tmpl_theme_css(theme)))
+        }
+    }
+}
+
+/// Colors overriding the light ones of `tmpl_css`.
+fn tmpl_theme_css(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Light => "",
+        Theme::Dark => {
+            "
+                body {
+                    background-color: #1E1C17;
+                    color: #E2D7B7;
+                }
+
+                a,
+                a:hover,
+                a:active,
+                a:visited,
+                div.menu > div.menu-item a,
+                div.menu > div.menu-item a:visited {
+                    color: #F08A5D;
+                }
+
+                div.menu > div.menu-item {
+                    color: #E2D7B7;
+                }
+
+                table.data > thead > tr > th {
+                    border-color: #E2D7B7;
+                    background-color: #3A352A;
+                }
+
+                table.data > tbody > tr > td {
+                    border-color: #8A8170;
+                }
+
+                input, select, textarea, button {
+                    background-color: #2A261D;
+                    color: #E2D7B7;
+                    border: 1px solid #8A8170;
+                }
+
+                p.error, table.form > tbody > tr > td.error {
+                    color: #FF7B72;
+                }"
+        }
+        Theme::HighContrast => {
+            "
+                body {
+                    background-color: #FFFFFF;
+                    color: #000000;
+                    font-size: 18px;
+                }
+
+                a,
+                a:hover,
+                a:active,
+                a:visited,
+                div.menu > div.menu-item a,
+                div.menu > div.menu-item a:visited {
+                    color: #0000CC;
+                    text-decoration: underline;
+                }
+
+                div.menu > div.menu-item {
+                    color: #000000;
+                }
+
+                table.data {
+                    font-size: 100%;
+                }
+
+                table.data > thead > tr > th {
+                    border: 2px solid #000000;
+                    background-color: #000000;
+                    color: #FFFFFF;
+                }
+
+                table.data > tbody > tr > td {
+                    border: 2px solid #000000;
+                }
+
+                input, select, textarea, button {
+                    border: 2px solid #000000;
+                }
+
+                p.error, table.form > tbody > tr > td.error {
+                    color: #B00000;
+                    font-weight: bold;
+                }"
         }
     }
 }
 
-fn tmpl_head(title: &str) -> Markup {
+fn tmpl_head(title: &str, theme: Theme) -> Markup {
     let title = {
         let lt = title.to_lowercase();
         if !&lt.starts_with("cashlog ") {
@@ -184,7 +306,7 @@ fn tmpl_head(title: &str) -> Markup {
                 (title)
             }
             meta name="viewport" content="width=device-width, initial-scale=1" /
-            (tmpl_css())
+            (tmpl_css(theme))
         }
     }
 }
@@ -194,7 +316,7 @@ fn tmpl_menu(logged_in: bool) -> Markup {
         div.menu {
             div.menu-item {
                 "[ "
-                a href="/" {
+                a href="/entries" {
                     "Entries"
                 }
                 " ]"
@@ -213,10 +335,14 @@ fn tmpl_menu(logged_in: bool) -> Markup {
                 }
                 " ]"
             }
+            div.menu-item { "[ " a href="/prices" { "Prices" } " ]" }
+            div.menu-item { "[ " a href="/report" { "Report" } " ]" }
             div.menu-item { "[ " a href="/export" { "Export" } " ]" }
+            div.menu-item { "[ " a href="/backup" { "Backup" } " ]" }
             div.menu-spacer {}
             div.menu-item { "[ " a href="/about" { "About" } " ]" }
             @if logged_in {
+                div.menu-item { "[ " a href="/settings" { "Settings" } " ]"}
                 div.menu-item { "[ " a href="/profile" { "Profile" } " ]"}
                 div.menu-item { "[ " a href="/logout" { "Logout" } " ]"}
             } @else {
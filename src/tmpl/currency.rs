@@ -2,11 +2,24 @@ use maud;
 use maud::html;
 
 use crate::model::CurrencyInfo;
+use crate::model::GroupInfo;
+use crate::model::Preferences;
+use crate::report::DiffRow;
+use crate::tmpl::bank_accounts::tmpl_as_of_form;
+use crate::tmpl::bank_accounts::tmpl_diff_table;
+use crate::tmpl::bank_accounts::tmpl_group_subtotals;
+use crate::tmpl::bank_accounts::AsOf;
 use crate::tmpl::common::tmpl_base;
 use crate::util;
 
-pub fn tmpl_currency(currency_info: Vec<CurrencyInfo>) -> maud::Markup {
+pub fn tmpl_currency(
+    prefs: &Preferences,
+    currency_info: Vec<CurrencyInfo>,
+    groups: &[GroupInfo],
+    as_of: &AsOf,
+) -> maud::Markup {
     let content = html! {
+        (tmpl_as_of_form(as_of))
         table class="data" {
             thead {
                 tr {
@@ -14,7 +27,13 @@ pub fn tmpl_currency(currency_info: Vec<CurrencyInfo>) -> maud::Markup {
                         "currency"
                     }
                     th {
-                        "amount"
+                        "assets"
+                    }
+                    th {
+                        "liabilities"
+                    }
+                    th {
+                        "net"
                     }
                     th {
                         "updated"
@@ -27,6 +46,12 @@ pub fn tmpl_currency(currency_info: Vec<CurrencyInfo>) -> maud::Markup {
                         td {
                             (currency_info.currency)
                         }
+                        td {
+                            (currency_info.assets)
+                        }
+                        td {
+                            (currency_info.liabilities)
+                        }
                         td {
                             (currency_info.amount)
                         }
@@ -37,6 +62,15 @@ pub fn tmpl_currency(currency_info: Vec<CurrencyInfo>) -> maud::Markup {
                 }
             }
         }
+        (tmpl_group_subtotals(groups))
+    };
+    tmpl_base("Currency", prefs, content)
+}
+
+pub fn tmpl_currency_diff(prefs: &Preferences, as_of: &AsOf, rows: &[DiffRow]) -> maud::Markup {
+    let content = html! {
+        (tmpl_as_of_form(as_of))
+        (tmpl_diff_table(as_of, rows, false))
     };
-    tmpl_base("Currency", content)
+    tmpl_base("Currency", prefs, content)
 }
@@ -0,0 +1,77 @@
+use maud::html;
+use maud::Markup;
+
+use crate::model::Preferences;
+use crate::tmpl::common::tmpl_base;
+
+/// Part of profile page that starts account deletion.
+pub fn tmpl_delete_account_form() -> Markup {
+    html! {
+        h2 {
+            "Delete Account"
+        }
+        p {
+            "This deletes your account with all bank accounts, entries and prices, "
+            "and it can't be undone. You may want to download the "
+            a href="/backup" {
+                "backup"
+            }
+            " or "
+            a href="/export" {
+                "export"
+            }
+            " first."
+        }
+        form method="post" action="/delete-account" {
+            button type="submit" {
+                "Send confirmation email"
+            }
+        }
+    }
+}
+
+pub fn tmpl_delete_account_email_sent(prefs: &Preferences) -> Markup {
+    let content = html! {
+        p {
+            "Email sent, click the link in it to confirm deletion."
+        }
+    };
+    tmpl_base("Delete Account", prefs, content)
+}
+
+pub fn tmpl_delete_account_confirm(prefs: &Preferences) -> Markup {
+    let content = html! {
+        form method="post" {
+            table.form {
+                tbody {
+                    tr {
+                        td {
+                            "Delete your account and all its data? This can't be undone."
+                        }
+                    }
+                    tr {
+                        td align="right" {
+                            a href="/" {
+                                "No"
+                            }
+                            " "
+                            button type="submit" {
+                                "Yes, delete"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    tmpl_base("Delete Account", prefs, content)
+}
+
+pub fn tmpl_delete_account_done(prefs: &Preferences) -> Markup {
+    let content = html! {
+        p {
+            "Your account was deleted."
+        }
+    };
+    tmpl_base("Delete Account", prefs, content)
+}
@@ -0,0 +1,162 @@
+//! Template for HTML part of email digest, the text part is
+//! `digest::format_text`.
+
+use maud;
+use maud::html;
+
+use crate::digest::Digest;
+use crate::digest::STALE_DAYS;
+use crate::util;
+
+/// Standalone document, mail clients don't get the page CSS.
+pub fn tmpl_digest(digest: &Digest, base_url: &str) -> maud::Markup {
+    let amount = |a: Option<f64>| a.map(util::format_amount).unwrap_or_default();
+    let title = format!("CashLog {} Digest", digest.frequency.label());
+    html! {
+        (maud::DOCTYPE)
+        html {
+            head {
+                meta charset="utf-8" /
+                title {
+                    (title)
+                }
+                style {
+                    "table { border-collapse: collapse; } "
+                    "th, td { border: 1px solid #ccc; padding: 2px 6px; } "
+                    "td.amount { text-align: right; }"
+                }
+            }
+            body {
+                h1 {
+                    (title)
+                }
+                p {
+                    "Changes since " (util::format_ts(digest.since)) "."
+                }
+                h2 {
+                    "Net worth by currency"
+                }
+                table {
+                    thead {
+                        tr {
+                            th {
+                                "currency"
+                            }
+                            th {
+                                "assets"
+                            }
+                            th {
+                                "liabilities"
+                            }
+                            th {
+                                "net"
+                            }
+                        }
+                    }
+                    tbody {
+                        @for c in &digest.currencies {
+                            tr {
+                                td {
+                                    (c.currency)
+                                }
+                                td class="amount" {
+                                    (c.assets)
+                                }
+                                td class="amount" {
+                                    (c.liabilities)
+                                }
+                                td class="amount" {
+                                    (c.amount)
+                                }
+                            }
+                        }
+                    }
+                }
+                h2 {
+                    "Changes by account"
+                }
+                table {
+                    thead {
+                        tr {
+                            th {
+                                "account"
+                            }
+                            th {
+                                "currency"
+                            }
+                            th {
+                                "before"
+                            }
+                            th {
+                                "now"
+                            }
+                            th {
+                                "change"
+                            }
+                        }
+                    }
+                    tbody {
+                        @for row in &digest.changes {
+                            tr {
+                                td {
+                                    (row.name)
+                                }
+                                td {
+                                    (row.currency)
+                                }
+                                td class="amount" {
+                                    (amount(row.before))
+                                }
+                                td class="amount" {
+                                    (amount(row.after))
+                                }
+                                td class="amount" {
+                                    (format!("{:+.2}", row.change))
+                                }
+                            }
+                        }
+                    }
+                }
+                @if !digest.stale.is_empty() {
+                    h2 {
+                        "Not updated for " (STALE_DAYS) " days"
+                    }
+                    table {
+                        thead {
+                            tr {
+                                th {
+                                    "account"
+                                }
+                                th {
+                                    "last entry"
+                                }
+                            }
+                        }
+                        tbody {
+                            @for i in &digest.stale {
+                                tr {
+                                    td {
+                                        (i.bank_account)
+                                    }
+                                    td {
+                                        (util::format_ts(i.ts))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                p {
+                    a href=(format!("{}/", base_url)) {
+                        "Open CashLog"
+                    }
+                    " or "
+                    a href=(format!("{}/settings", base_url)) {
+                        "turn the digest off"
+                    }
+                    "."
+                }
+            }
+        }
+    }
+}
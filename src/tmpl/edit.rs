@@ -1,14 +1,16 @@
 use maud;
 use maud::html;
 
+use crate::model::Preferences;
 use crate::tmpl::common::tmpl_base;
 
 pub struct FormData {
     pub id: i64,
     pub amount: (String, Option<String>),
+    pub note: (String, Option<String>),
 }
 
-pub fn tmpl_edit(form_data: &FormData) -> maud::Markup {
+pub fn tmpl_edit(prefs: &Preferences, form_data: &FormData) -> maud::Markup {
     let body = html! {
         form method="post" {
             table {
@@ -28,6 +30,21 @@ pub fn tmpl_edit(form_data: &FormData) -> maud::Markup {
                             }
                         }
                     }
+                    tr {
+                        td {
+                            label {
+                                "Note"
+                            }
+                        }
+                        td {
+                            input name="note" type="text" value=(form_data.note.0) /
+                        }
+                        td class="error" {
+                            @if let Some(ref err) = form_data.note.1 {
+                                (err)
+                            }
+                        }
+                    }
                     tr {
                         td align="right" colspan="2" {
                             input name="id" type="hidden" value=(form_data.id) /
@@ -40,5 +57,5 @@ pub fn tmpl_edit(form_data: &FormData) -> maud::Markup {
             }
         }
     };
-    tmpl_base("Edit", body)
+    tmpl_base("Edit", prefs, body)
 }
@@ -0,0 +1,151 @@
+use maud::html;
+use maud::Markup;
+
+use crate::model::BankAccount;
+use crate::model::BankAccountShare;
+use crate::model::Preferences;
+use crate::tmpl::add_bank_account::kind_select;
+use crate::tmpl::common::tmpl_base;
+
+fn tmpl_shares(bank_account: &BankAccount, shares: &[BankAccountShare]) -> Markup {
+    html! {
+        h2 {
+            "Shared with"
+        }
+        @if !shares.is_empty() {
+            table class="data" {
+                tbody {
+                    @for share in shares {
+                        tr {
+                            td {
+                                (share.email)
+                            }
+                            td {
+                                (share.role.as_str())
+                            }
+                            td {
+                                a href=(format!("unshare-bank-account?id={}&share_id={}", bank_account.id, share.id)) {
+                                    "remove"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        form method="post" action="share-bank-account" {
+            table class="form" {
+                tbody {
+                    tr {
+                        td label {
+                            "Email:"
+                        }
+                        td {
+                            input name="email" type="text" /
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Role:"
+                        }
+                        td {
+                            select name="role" {
+                                option value="viewer" {
+                                    "viewer"
+                                }
+                                option value="editor" {
+                                    "editor"
+                                }
+                            }
+                        }
+                    }
+                    tr {
+                        td colspan="2" align="right" {
+                            input name="id" type="hidden" value=(bank_account.id) /
+                            button type="submit" {
+                                "Share"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn tmpl_edit_bank_account(
+    prefs: &Preferences,
+    bank_account: &BankAccount,
+    shares: &[BankAccountShare],
+    error: Option<&str>,
+) -> Markup {
+    let content = html! {
+        @if let Some(error) = error {
+            p class="error" {
+                (error)
+            }
+        }
+        form method="post" {
+            table class="form" {
+                tbody {
+                    tr {
+                        td {
+                            "Name:"
+                        }
+                        td {
+                            (bank_account.name)
+                        }
+                    }
+                    tr {
+                        td {
+                            "Currency:"
+                        }
+                        td {
+                            (bank_account.currency)
+                        }
+                    }
+                    @if let Some(ref commodity) = bank_account.commodity {
+                        tr {
+                            td {
+                                "Commodity:"
+                            }
+                            td {
+                                (commodity)
+                            }
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Group:"
+                        }
+                        td {
+                            input
+                                name="group_name"
+                                type="text"
+                                value=(bank_account.group_name.as_deref().unwrap_or(""))
+                                /
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Type:"
+                        }
+                        td {
+                            (kind_select(bank_account.kind))
+                        }
+                    }
+                    tr {
+                        td colspan="2" align="right" {
+                            input name="id" type="hidden" value=(bank_account.id) /
+                            button type="submit" {
+                                "Save"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        (tmpl_shares(bank_account, shares))
+    };
+    tmpl_base("Edit Bank Account", prefs, content)
+}
@@ -2,9 +2,10 @@ use chrono;
 use maud;
 use maud::html;
 
+use crate::model::Preferences;
 use crate::tmpl::common::tmpl_base;
 
-pub fn tmpl_export(base_href: &str) -> maud::Markup {
+pub fn tmpl_export(prefs: &Preferences, base_href: &str) -> maud::Markup {
     let now = chrono::Utc::now();
     let now_str = now.format("%Y%m%d%H%M%S");
     let filename = format!("cashlog-export-{}.csv", now_str);
@@ -17,5 +18,5 @@ pub fn tmpl_export(base_href: &str) -> maud::Markup {
             }
         }
     };
-    tmpl_base("Export", content)
+    tmpl_base("Export", prefs, content)
 }
@@ -1,20 +1,223 @@
 use maud;
 use maud::html;
 
-use crate::model::EntryInfo;
+use crate::forecast::GoalDate;
+use crate::forecast::Model;
+use crate::model::BankAccount;
+use crate::model::Preferences;
+use crate::report::Snapshot;
 use crate::tmpl::common::tmpl_base;
+use crate::util;
 
-pub fn tmpl_graph(entries: &Vec<EntryInfo>) -> maud::Markup {
-    let d: Vec<(String, f64)> = entries
+/// Line colors, the default palette of Google Charts. The HTML legend uses
+/// them too.
+const COLORS: [&str; 10] = [
+    "#3366cc", "#dc3912", "#ff9900", "#109618", "#990099", "#0099c6", "#dd4477", "#66aa00",
+    "#b82e2e", "#316395",
+];
+
+/// Color of the trend line.
+const TREND_COLOR: &str = "#999999";
+
+/// How amounts are graphed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scale {
+    Absolute,
+    /// Every line starts at 100 at the range start, to compare growth of
+    /// different sizes and currencies.
+    Normalized,
+}
+
+impl Scale {
+    pub fn parse(s: &str) -> Option<Scale> {
+        match s {
+            "absolute" => Some(Scale::Absolute),
+            "normalized" => Some(Scale::Normalized),
+            _ => None,
+        }
+    }
+
+    /// Name as used in query params.
+    pub fn name(self) -> &'static str {
+        match self {
+            Scale::Absolute => "absolute",
+            Scale::Normalized => "normalized",
+        }
+    }
+}
+
+/// Lines and range as chosen in the form.
+pub struct GraphForm {
+    pub ids: Vec<i64>,
+    pub currencies: Vec<String>,
+    /// Currencies are summed over this group only, if not empty.
+    pub group: String,
+    /// Raw values, to be shown back in the form.
+    pub from: String,
+    pub to: String,
+    pub scale: Scale,
+}
+
+/// One line of the graph, `link` leads to the graph of the line alone.
+pub struct Series {
+    pub name: String,
+    pub link: String,
+    pub points: Vec<Snapshot>,
+}
+
+/// Projection settings as chosen in the form.
+pub struct ProjectionForm {
+    pub model: Option<Model>,
+    /// Months.
+    pub horizon: u32,
+    pub goal: String,
+}
+
+/// Trend overlaid on the graph, with numbers derived from it.
+pub struct Projection {
+    pub points: Vec<Snapshot>,
+    pub monthly_change: f64,
+    pub monthly_rate_pct: Option<f64>,
+    /// Some if goal was given.
+    pub goal_date: Option<GoalDate>,
+}
+
+/// Series as JSON to be used in script, user input is kept from closing
+/// the script tag.
+fn series_json(series: &[(&str, &[Snapshot])]) -> String {
+    let j: Vec<serde_json::Value> = series
         .iter()
-        .map(|e| {
-            let t = e.ts.format("%Y-%m-%d %H:%M:%S").to_string();
-            let a = e.amount.parse().unwrap();
-            (t, a)
+        .map(|(name, points)| {
+            let d: Vec<(String, f64)> = points
+                .iter()
+                .map(|&(ts, a)| {
+                    let t = ts.format("%Y-%m-%d %H:%M:%S").to_string();
+                    (t, a)
+                })
+                .collect();
+            json!({"name": name, "points": d})
         })
         .collect();
-    let j = json!(d);
-    let data_js = format!("<script>var data_raw = {};</script>", j);
+    json!(j).to_string().replace('<', "\\u003c")
+}
+
+/// Choice of bank accounts, currencies, range and scale, with the
+/// projection settings.
+fn tmpl_graph_form(
+    bank_accounts: &[BankAccount],
+    graph_form: &GraphForm,
+    form: &ProjectionForm,
+) -> maud::Markup {
+    let mut currencies: Vec<&str> = bank_accounts.iter().map(|b| b.currency.as_str()).collect();
+    currencies.sort_unstable();
+    currencies.dedup();
+    let mut groups: Vec<&str> = bank_accounts
+        .iter()
+        .filter_map(|b| b.group_name.as_deref())
+        .collect();
+    groups.sort_unstable();
+    groups.dedup();
+    html! {
+        form method="get" style="font-size: small" {
+            p {
+                "Accounts: "
+                @for b in bank_accounts {
+                    label {
+                        input type="checkbox" name="id" value=(b.id) checked[graph_form.ids.contains(&b.id)] /
+                        " " (b.name) " (" (b.currency) ")"
+                    }
+                    " "
+                }
+            }
+            p {
+                "Currencies: "
+                @for currency in &currencies {
+                    label {
+                        input type="checkbox" name="currency" value=(currency) checked[graph_form.currencies.iter().any(|c| c == currency)] /
+                        " " (currency)
+                    }
+                    " "
+                }
+                " in group: "
+                select name="group" {
+                    option value="" selected[graph_form.group.is_empty()] {
+                        "(all accounts)"
+                    }
+                    @for group in &groups {
+                        option value=(group) selected[graph_form.group == *group] {
+                            (group)
+                        }
+                    }
+                }
+            }
+            p {
+                "From: "
+                input type="text" name="from" size="10" placeholder="YYYY-MM-DD" value=(graph_form.from) /
+                " to: "
+                input type="text" name="to" size="10" placeholder="YYYY-MM-DD" value=(graph_form.to) /
+                " scale: "
+                select name="scale" {
+                    option value="absolute" selected[graph_form.scale == Scale::Absolute] {
+                        "absolute"
+                    }
+                    option value="normalized" selected[graph_form.scale == Scale::Normalized] {
+                        "100 at start"
+                    }
+                }
+            }
+            "Trend: "
+            select name="projection" {
+                option value="" selected[form.model.is_none()] {
+                    "none"
+                }
+                option value="linear" selected[form.model == Some(Model::Linear)] {
+                    "linear"
+                }
+                option value="exp" selected[form.model == Some(Model::Exponential)] {
+                    "exponential"
+                }
+            }
+            " horizon (months): "
+            input type="text" name="horizon" size="3" value=(form.horizon) /
+            " goal: "
+            input type="text" name="goal" size="10" value=(form.goal) /
+            " "
+            button type="submit" {
+                "Show"
+            }
+        }
+    }
+}
+
+/// Lines on a shared time axis with a legend linking to each line alone,
+/// `notes` explain lines that can't be shown. Trend is projected when
+/// there's one line on absolute scale.
+#[allow(clippy::too_many_arguments)]
+pub fn tmpl_graph(
+    prefs: &Preferences,
+    title: &str,
+    lines: &[Series],
+    notes: &[String],
+    bank_accounts: &[BankAccount],
+    graph_form: &GraphForm,
+    form: &ProjectionForm,
+    projection: Option<&Projection>,
+) -> maud::Markup {
+    let mut series: Vec<(&str, &[Snapshot])> = lines
+        .iter()
+        .map(|l| (l.name.as_str(), l.points.as_slice()))
+        .collect();
+    let mut colors: Vec<&str> = (0..lines.len()).map(|i| COLORS[i % COLORS.len()]).collect();
+    if let Some(projection) = projection {
+        series.push(("Trend", &projection.points));
+        colors.push(TREND_COLOR);
+    }
+    let data_js = format!(
+        "<script>var series_raw = {}; var series_colors = {}; var data_title = {};</script>",
+        series_json(&series),
+        json!(colors),
+        json!(title).to_string().replace('<', "\\u003c")
+    );
     let chart_js = "
         <script type=\"text/javascript\" src=\"https://www.gstatic.com/charts/loader.js\"></script>
         <script type=\"text/javascript\">
@@ -23,20 +226,30 @@ pub fn tmpl_graph(entries: &Vec<EntryInfo>) -> maud::Markup {
             function drawChart() {
                 var data_table = new google.visualization.DataTable();
                 data_table.addColumn('datetime', 'TS');
-                data_table.addColumn('number', 'Amount');
-                for(var i = 0; i < data_raw.length; ++i) {
-                    var date_str = data_raw[i][0];
-                    var amount = data_raw[i][1];
-                    var date_parts = date_str.split(/[^0-9]/);
-                    var date = new Date (
-                        date_parts[0], date_parts[1]-1, date_parts[2],
-                        date_parts[3], date_parts[4], date_parts[5]);
-                    var row = [date, amount];
-                    data_table.addRow(row);
+                for(var s = 0; s < series_raw.length; ++s) {
+                    data_table.addColumn('number', series_raw[s].name);
+                }
+                for(var s = 0; s < series_raw.length; ++s) {
+                    var data_raw = series_raw[s].points;
+                    for(var i = 0; i < data_raw.length; ++i) {
+                        var date_str = data_raw[i][0];
+                        var amount = data_raw[i][1];
+                        var date_parts = date_str.split(/[^0-9]/);
+                        var date = new Date (
+                            date_parts[0], date_parts[1]-1, date_parts[2],
+                            date_parts[3], date_parts[4], date_parts[5]);
+                        var row = [date];
+                        for(var c = 0; c < series_raw.length; ++c) {
+                            row.push(c == s ? amount : null);
+                        }
+                        data_table.addRow(row);
+                    }
                 }
                 var options = {
-                    title: 'Amount over Time',
-                    legend: { position: 'bottom' }
+                    title: data_title,
+                    legend: { position: 'none' },
+                    colors: series_colors,
+                    interpolateNulls: true
                 };
                 var chart = new google.visualization.LineChart(document.getElementById('chart'));
                 chart.draw(data_table, options);
@@ -44,9 +257,63 @@ pub fn tmpl_graph(entries: &Vec<EntryInfo>) -> maud::Markup {
         </script>
         ";
     let c = html! {
-        (maud::PreEscaped(data_js))
-        (maud::PreEscaped(chart_js))
-        div id="chart" /
+        @if lines.is_empty() {
+            p {
+                "Choose bank accounts or currencies to graph."
+            }
+        } @else {
+            (maud::PreEscaped(data_js))
+            (maud::PreEscaped(chart_js))
+            div id="chart" /
+            p style="font-size: small" {
+                @for (line, color) in lines.iter().zip(&colors) {
+                    span style=(format!("color: {}", color)) { "\u{25a0}" }
+                    " "
+                    a href=(line.link) {
+                        (line.name)
+                    }
+                    " "
+                }
+                @if projection.is_some() {
+                    span style=(format!("color: {}", TREND_COLOR)) { "\u{25a0}" }
+                    " Trend"
+                }
+            }
+        }
+        @for note in notes {
+            p {
+                (note)
+            }
+        }
+        (tmpl_graph_form(bank_accounts, graph_form, form))
+        @if let Some(projection) = projection {
+            p {
+                "Implied monthly change: "
+                (util::format_amount(projection.monthly_change))
+                @if let Some(rate) = projection.monthly_rate_pct {
+                    " (" (util::format_amount(rate)) "% per month)"
+                }
+                "."
+                @if let Some(goal_date) = &projection.goal_date {
+                    br /
+                    @match goal_date {
+                        GoalDate::Reached => "Goal already reached.",
+                        GoalDate::At(goal_date) => {
+                            "Goal reached on " (goal_date.format("%Y-%m-%d")) "."
+                        }
+                        GoalDate::Never => "Goal not reached on current trend.",
+                    }
+                }
+            }
+        } @else if form.model.is_some() && lines.len() > 1 {
+            p {
+                "Trend is shown for a single line only."
+            }
+        } @else if form.model.is_some() && !lines.is_empty() {
+            p {
+                "Not enough data for the trend."
+            }
+        }
     };
-    tmpl_base("Graph", c)
+    tmpl_base("Graph", prefs, c)
 }
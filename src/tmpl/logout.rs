@@ -1,9 +1,10 @@
 use maud;
 use maud::html;
 
+use crate::model::Preferences;
 use crate::tmpl::common::tmpl_base;
 
-pub fn tmpl_logout() -> maud::Markup {
+pub fn tmpl_logout(prefs: &Preferences) -> maud::Markup {
     let content = html! {
         form method="post" {
             table.form {
@@ -27,5 +28,5 @@ pub fn tmpl_logout() -> maud::Markup {
             }
         }
     };
-    tmpl_base("Logout", content)
+    tmpl_base("Logout", prefs, content)
 }
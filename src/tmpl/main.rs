@@ -2,10 +2,29 @@ use maud;
 use maud::html;
 
 use crate::model::EntryInfo;
+use crate::model::Preferences;
+use crate::model::ShareRole;
 use crate::tmpl::common::tmpl_base;
 use crate::util::format_ts;
+use crate::util::url_encode;
 
-pub fn tmpl_main(title: &str, entries: &Vec<EntryInfo>) -> maud::Markup {
+/// Entries page, `search` is the note search, blank for all entries.
+/// `page` counts from 1, `has_more` is set if there are older entries.
+pub fn tmpl_main(
+    prefs: &Preferences,
+    title: &str,
+    entries: &Vec<EntryInfo>,
+    search: &str,
+    page: i64,
+    has_more: bool,
+) -> maud::Markup {
+    let page_url = |page: i64| {
+        if search.is_empty() {
+            format!("entries?page={}", page)
+        } else {
+            format!("entries?page={}&q={}", page, url_encode(search))
+        }
+    };
     // let entries_html = tmpl_entries(entries);
     // tmpl_base(title, &entries_html)
     let content = html! {
@@ -14,6 +33,24 @@ pub fn tmpl_main(title: &str, entries: &Vec<EntryInfo>) -> maud::Markup {
             a href="add" {
                 "Add" " ]"
             }
+            " [ "
+            a href="check-in" {
+                "Check-in"
+            }
+            " ]"
+        }
+        form method="get" action="entries" {
+            input type="text" name="q" value=(search) placeholder="Search notes" /
+            " "
+            button type="submit" {
+                "Search"
+            }
+            @if !search.is_empty() {
+                " "
+                a href="entries" {
+                    "Clear"
+                }
+            }
         }
         table class="data" {
             thead {
@@ -27,6 +64,9 @@ pub fn tmpl_main(title: &str, entries: &Vec<EntryInfo>) -> maud::Markup {
                     th colspan="2" {
                         "amount"
                     }
+                    th {
+                        "note"
+                    }
                     th {
                         ""
                     }
@@ -45,21 +85,45 @@ pub fn tmpl_main(title: &str, entries: &Vec<EntryInfo>) -> maud::Markup {
                             (entry.amount)
                         }
                         td {
-                            (entry.currency)
+                            (entry.commodity.as_ref().unwrap_or(&entry.currency))
                         }
                         td {
-                            a href=(format!("edit?id={}", entry.id)) {
-                                "edit"
-                            }
-                            ", "
-                            a href=(format!("delete?id={}", entry.id)) {
-                                "delete"
+                            (entry.note.as_deref().unwrap_or(""))
+                        }
+                        td {
+                            @if ShareRole::can_edit(entry.role) {
+                                a href=(format!("edit?id={}", entry.id)) {
+                                    "edit"
+                                }
+                                ", "
+                                a href=(format!("delete?id={}", entry.id)) {
+                                    "delete"
+                                }
                             }
                         }
                     }
                 }
             }
         }
+        @if page > 1 || has_more {
+            p style="font-size: small" {
+                @if page > 1 {
+                    "[ "
+                    a href=(page_url(page - 1)) {
+                        "Newer"
+                    }
+                    " ] "
+                }
+                "Page " (page)
+                @if has_more {
+                    " [ "
+                    a href=(page_url(page + 1)) {
+                        "Older"
+                    }
+                    " ]"
+                }
+            }
+        }
     };
-    tmpl_base(title, content)
+    tmpl_base(title, prefs, content)
 }
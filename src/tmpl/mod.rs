@@ -3,12 +3,24 @@ mod common;
 pub mod about;
 pub mod add;
 pub mod add_bank_account;
+pub mod api_tokens;
+pub mod backup;
 pub mod bank_accounts;
+pub mod check_in;
 pub mod currency;
+pub mod delete_account;
+pub mod digest;
 pub mod edit;
+pub mod edit_bank_account;
 pub mod export;
 pub mod graph;
 pub mod logout;
 pub mod main;
 pub mod new_session;
+pub mod prices;
 pub mod profile;
+pub mod quick;
+pub mod report;
+pub mod settings;
+pub mod totp;
+pub mod webhooks;
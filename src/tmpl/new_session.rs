@@ -1,9 +1,18 @@
 use maud::html;
 use maud::Markup;
 
+use crate::model::Preferences;
 use crate::tmpl::common::tmpl_base;
 
-pub fn tmpl_new_session() -> Markup {
+/// Login form, `invite` is the invite code field if registration needs
+/// one, `oidc` is the name of OpenID Connect provider if it's configured.
+pub fn tmpl_new_session(
+    prefs: &Preferences,
+    email: &str,
+    invite: Option<&str>,
+    error: &str,
+    oidc: Option<&str>,
+) -> Markup {
     let content = html! {
         p {
             "Give me your e-mail and I'll send you the login link."
@@ -13,7 +22,21 @@ pub fn tmpl_new_session() -> Markup {
                 tbody {
                     tr {
                         td {
-                            input type="text" name="email" {}
+                            input type="text" name="email" value=(email) {}
+                        }
+                    }
+                    @if let Some(invite) = invite {
+                        tr {
+                            td {
+                                input type="text" name="invite" value=(invite) placeholder="Invite code (new users)" {}
+                            }
+                        }
+                    }
+                    @if !error.is_empty() {
+                        tr {
+                            td class="error" {
+                                (error)
+                            }
                         }
                     }
                     tr {
@@ -26,20 +49,62 @@ pub fn tmpl_new_session() -> Markup {
                 }
             }
         }
+        @if let Some(oidc) = oidc {
+            p {
+                a href="/oidc/login" {
+                    "Log in with " (oidc)
+                }
+            }
+        }
     };
-    tmpl_base("New Session", content)
+    tmpl_base("New Session", prefs, content)
 }
 
-pub fn tmpl_new_session_email_sent() -> Markup {
+pub fn tmpl_new_session_email_sent(prefs: &Preferences) -> Markup {
     let content = html! {
         p {
             "Email sent."
         }
     };
-    tmpl_base("New Session", content)
+    tmpl_base("New Session", prefs, content)
+}
+
+/// Code entry step of login for accounts with TOTP enabled.
+pub fn tmpl_new_session_code(prefs: &Preferences, error: &str) -> Markup {
+    let content = html! {
+        p {
+            "Enter the code from your authenticator app, or one of your recovery codes."
+        }
+        form method="post" action="/new-session-code" {
+            table.form {
+                tbody {
+                    tr {
+                        td {
+                            input type="text" name="code" autocomplete="one-time-code" autofocus {}
+                        }
+                    }
+                    @if !error.is_empty() {
+                        tr {
+                            td class="error" {
+                                (error)
+                            }
+                        }
+                    }
+                    tr {
+                        td align="right" {
+                            button type="submit" {
+                                "Log in"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    tmpl_base("New Session", prefs, content)
 }
 
-pub fn tmpl_new_session_result(success: bool) -> Markup {
+pub fn tmpl_new_session_result(prefs: &Preferences, success: bool) -> Markup {
     let title = "New Session";
     let text = match success {
         true => "Login successful",
@@ -47,6 +112,7 @@ pub fn tmpl_new_session_result(success: bool) -> Markup {
     };
     tmpl_base(
         title,
+        prefs,
         html! {
             p {
                 (text)
@@ -0,0 +1,119 @@
+//! Template for commodity prices page.
+
+use maud;
+use maud::html;
+
+use crate::model::Preferences;
+use crate::model::PriceInfo;
+use crate::tmpl::common::tmpl_base;
+use crate::util::format_ts;
+
+pub fn tmpl_prices(prefs: &Preferences, prices: &[PriceInfo], error: Option<&str>) -> maud::Markup {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let content = html! {
+        @if let Some(error) = error {
+            p class="error" {
+                (error)
+            }
+        }
+        form method="post" action="prices" {
+            table class="form" {
+                tbody {
+                    tr {
+                        td {
+                            "Commodity:"
+                        }
+                        td {
+                            input type="text" name="commodity" /
+                        }
+                    }
+                    tr {
+                        td {
+                            "Currency:"
+                        }
+                        td {
+                            input type="text" name="currency" /
+                        }
+                    }
+                    tr {
+                        td {
+                            "Date/Time:"
+                        }
+                        td {
+                            input type="text" name="ts" value=(now) /
+                        }
+                    }
+                    tr {
+                        td {
+                            "Price:"
+                        }
+                        td {
+                            input type="text" name="price" /
+                        }
+                    }
+                    tr {
+                        td colspan="2" align="right" {
+                            button type="submit" {
+                                "Add"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        form method="post" action="prices/import" {
+            p {
+                "Import CSV, one "
+                code {
+                    "commodity,currency,ts,price"
+                }
+                " per line:"
+            }
+            textarea name="csv" rows="6" cols="60" {}
+            br /
+            button type="submit" {
+                "Import"
+            }
+        }
+        table class="data" {
+            thead {
+                tr {
+                    th {
+                        "commodity"
+                    }
+                    th {
+                        "ts"
+                    }
+                    th colspan="2" {
+                        "price"
+                    }
+                    th /
+                }
+            }
+            tbody {
+                @for p in prices {
+                    tr {
+                        td {
+                            (p.price.commodity)
+                        }
+                        td {
+                            (format_ts(p.price.ts))
+                        }
+                        td {
+                            (p.price.price)
+                        }
+                        td {
+                            (p.price.currency)
+                        }
+                        td {
+                            a href=(format!("prices/delete?id={}", p.id)) {
+                                "delete"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    tmpl_base("Prices", prefs, content)
+}
@@ -4,15 +4,30 @@ use maud;
 use maud::html;
 
 use crate::model;
+use crate::model::Preferences;
+use crate::tmpl::api_tokens::tmpl_api_tokens;
 use crate::tmpl::common::tmpl_base;
+use crate::tmpl::delete_account::tmpl_delete_account_form;
+use crate::tmpl::totp::tmpl_totp;
+use crate::tmpl::webhooks::tmpl_webhooks;
 use crate::util::format_ts;
 
-pub fn tmpl_profile(acc: &model::AccountInfo) -> maud::Markup {
+pub fn tmpl_profile(
+    prefs: &Preferences,
+    acc: &model::AccountInfo,
+    api_tokens: &[model::ApiToken],
+    webhooks: &[model::Webhook],
+    totp: Option<&model::Totp>,
+) -> maud::Markup {
     let content = html! {
         h1 {"Profile"}
         p {(format!("Profile created: {}.", format_ts(acc.created_at)))}
         p {(format!("Profile modified: {}.", format_ts(acc.modified_at)))}
         p {(format!("Emails: {:?}.", acc.emails))}
+        (tmpl_totp(totp))
+        (tmpl_api_tokens(api_tokens))
+        (tmpl_webhooks(webhooks))
+        (tmpl_delete_account_form())
     };
-    tmpl_base("Profile", content)
+    tmpl_base("Profile", prefs, content)
 }
@@ -0,0 +1,57 @@
+//! Template for quick-add page of one bank account, meant for phones.
+
+use maud;
+use maud::html;
+
+use crate::model::BankAccount;
+use crate::model::BankAccountInfo;
+use crate::model::Preferences;
+use crate::tmpl::common::tmpl_minimal;
+use crate::util::format_ts;
+
+pub fn tmpl_quick(
+    prefs: &Preferences,
+    bank_account: &BankAccount,
+    last: Option<&BankAccountInfo>,
+    amount: &str,
+    error: Option<&str>,
+) -> maud::Markup {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let unit = bank_account
+        .commodity
+        .as_ref()
+        .unwrap_or(&bank_account.currency);
+    let content = html! {
+        h2 {
+            (bank_account.name)
+        }
+        p {
+            @if let Some(last) = last {
+                "Last: " (last.amount) " " (unit) ", " (format_ts(last.ts))
+            } @else {
+                "No entries yet"
+            }
+        }
+        @if let Some(error) = error {
+            p class="error" {
+                (error)
+            }
+        }
+        form class="quick" method="post" {
+            input type="text" name="amount" value=(amount) inputmode="decimal"
+                placeholder="Amount" autofocus? /
+            input type="text" name="ts" value=(now) /
+            button type="submit" {
+                "Save"
+            }
+        }
+        p style="font-size: small" {
+            "[ "
+            a href="/" {
+                "Entries"
+            }
+            " ]"
+        }
+    };
+    tmpl_minimal(&bank_account.name, prefs, content)
+}
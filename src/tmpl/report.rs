@@ -0,0 +1,110 @@
+//! Template for the period-change report.
+
+use maud;
+use maud::html;
+use maud::Markup;
+
+use crate::model::Preferences;
+use crate::report::Period;
+use crate::report::Report;
+use crate::report::ReportRow;
+use crate::tmpl::common::tmpl_base;
+use crate::util::format_amount;
+
+fn tmpl_report_table(rows: &[ReportRow], with_account: bool) -> Markup {
+    html! {
+        table class="data" {
+            thead {
+                tr {
+                    th {
+                        "period"
+                    }
+                    @if with_account {
+                        th {
+                            "account"
+                        }
+                    }
+                    th colspan="2" {
+                        "balance"
+                    }
+                    th {
+                        "change"
+                    }
+                    th {
+                        "change %"
+                    }
+                }
+            }
+            tbody {
+                // Most recent periods first, like the entries.
+                @for row in rows.iter().rev() {
+                    tr {
+                        td {
+                            (row.period)
+                        }
+                        @if with_account {
+                            td {
+                                (row.bank_account.as_deref().unwrap_or(""))
+                            }
+                        }
+                        td {
+                            (format_amount(row.balance))
+                        }
+                        td {
+                            (row.currency)
+                        }
+                        td {
+                            @if let Some(change) = row.change {
+                                (format_amount(change))
+                            }
+                        }
+                        td {
+                            @if let Some(change_pct) = row.change_pct {
+                                (format_amount(change_pct))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn tmpl_report(prefs: &Preferences, period: Period, report: &Report) -> maud::Markup {
+    let now_str = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let filename = format!("cashlog-report-{}-{}.csv", period.name(), now_str);
+    let content = html! {
+        p style="font-size: small" {
+            "[ "
+            @if period == Period::Month {
+                "Monthly"
+            } @else {
+                a href="report?period=month" {
+                    "Monthly"
+                }
+            }
+            " | "
+            @if period == Period::Year {
+                "Yearly"
+            } @else {
+                a href="report?period=year" {
+                    "Yearly"
+                }
+            }
+            " ] [ "
+            a href=(format!("report/{}?period={}", filename, period.name())) {
+                "CSV"
+            }
+            " ]"
+        }
+        h2 {
+            "Totals"
+        }
+        (tmpl_report_table(&report.totals, false))
+        h2 {
+            "Accounts"
+        }
+        (tmpl_report_table(&report.rows, true))
+    };
+    tmpl_base("Report", prefs, content)
+}
@@ -0,0 +1,141 @@
+//! Template for settings page.
+
+use maud;
+use maud::html;
+
+use crate::model::BankAccount;
+use crate::model::DigestFrequency;
+use crate::model::LandingPage;
+use crate::model::Preferences;
+use crate::model::Theme;
+use crate::model::MAX_ENTRIES_PER_PAGE;
+use crate::tmpl::common::tmpl_base;
+
+/// Values of settings form, entries per page is kept as entered so that
+/// the form can be shown again with error.
+pub struct SettingsForm {
+    pub theme: Theme,
+    pub landing_page: LandingPage,
+    pub default_bank_account: Option<i64>,
+    pub entries_per_page: String,
+    pub digest: DigestFrequency,
+}
+
+impl SettingsForm {
+    pub fn from_preferences(prefs: &Preferences) -> SettingsForm {
+        SettingsForm {
+            theme: prefs.theme,
+            landing_page: prefs.landing_page,
+            default_bank_account: prefs.default_bank_account,
+            entries_per_page: prefs.entries_per_page.to_string(),
+            digest: prefs.digest,
+        }
+    }
+}
+
+/// Settings page, `bank_accounts` are the ones that can be the default of
+/// the add page.
+pub fn tmpl_settings(
+    prefs: &Preferences,
+    form: &SettingsForm,
+    bank_accounts: &[BankAccount],
+    error: Option<&str>,
+) -> maud::Markup {
+    let content = html! {
+        @if let Some(error) = error {
+            p class="error" {
+                (error)
+            }
+        }
+        form method="post" action="settings" {
+            table class="form" {
+                tbody {
+                    tr {
+                        td label {
+                            "Theme:"
+                        }
+                        td {
+                            select name="theme" {
+                                @for theme in Theme::ALL.iter() {
+                                    option value=(theme.as_str()) selected[*theme == form.theme] {
+                                        (theme.label())
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Landing page:"
+                        }
+                        td {
+                            select name="landing_page" {
+                                @for page in LandingPage::ALL.iter() {
+                                    option value=(page.as_str()) selected[*page == form.landing_page] {
+                                        (page.label())
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Default bank account:"
+                        }
+                        td {
+                            select name="default_bank_account" {
+                                option value="" selected[form.default_bank_account.is_none()] {
+                                    "(none)"
+                                }
+                                @for ba in bank_accounts {
+                                    option value=(ba.id) selected[Some(ba.id) == form.default_bank_account] {
+                                        (ba.name)
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Entries per page:"
+                        }
+                        td {
+                            input
+                                type="text"
+                                name="entries_per_page"
+                                value=(form.entries_per_page) /
+                        }
+                        td {
+                            "1 to " (MAX_ENTRIES_PER_PAGE)
+                        }
+                    }
+                    tr {
+                        td label {
+                            "Email digest:"
+                        }
+                        td {
+                            select name="digest" {
+                                @for digest in DigestFrequency::ALL.iter() {
+                                    option value=(digest.as_str()) selected[*digest == form.digest] {
+                                        (digest.label())
+                                    }
+                                }
+                            }
+                        }
+                        td {
+                            "Balances and changes, by email"
+                        }
+                    }
+                    tr {
+                        td colspan="2" align="right" {
+                            button type="submit" {
+                                "Save"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    tmpl_base("Settings", prefs, content)
+}
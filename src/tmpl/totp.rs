@@ -0,0 +1,113 @@
+//! Templates for TOTP setup.
+
+use maud::html;
+use maud::Markup;
+use maud::PreEscaped;
+
+use crate::model::Preferences;
+use crate::model::Totp;
+use crate::tmpl::common::tmpl_base;
+
+/// Part of profile page with TOTP status.
+pub fn tmpl_totp(totp: Option<&Totp>) -> Markup {
+    html! {
+        h2 {
+            "Two-Factor Authentication"
+        }
+        @match totp {
+            Some(totp) if totp.enabled => {
+                p {
+                    (format!(
+                        "Enabled, login links ask for a code from your authenticator app. {} recovery codes left.",
+                        totp.recovery_codes
+                    ))
+                }
+                form method="post" action="/totp/disable" {
+                    "Code: "
+                    input type="text" name="code" autocomplete="one-time-code" /
+                    " "
+                    button type="submit" {
+                        "Disable"
+                    }
+                }
+            }
+            _ => {
+                p style="font-size: small" {
+                    "Ask for a code from an authenticator app after the login link."
+                }
+                form method="post" action="/totp/setup" {
+                    button type="submit" {
+                        "Set up"
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn tmpl_totp_setup(prefs: &Preferences, secret: &str, qr_svg: &str, error: &str) -> Markup {
+    let content = html! {
+        p {
+            "Scan the QR code with your authenticator app, or enter the key by hand:"
+        }
+        (PreEscaped(qr_svg))
+        pre {
+            (secret)
+        }
+        p {
+            "Then enter the code the app shows to finish."
+        }
+        form method="post" action="/totp/enable" {
+            "Code: "
+            input type="text" name="code" autocomplete="one-time-code" autofocus /
+            " "
+            button type="submit" {
+                "Enable"
+            }
+            @if !error.is_empty() {
+                span class="error" {
+                    " " (error)
+                }
+            }
+        }
+        p {
+            a href="/profile" {
+                "Cancel"
+            }
+        }
+    };
+    tmpl_base("Two-Factor Authentication", prefs, content)
+}
+
+pub fn tmpl_totp_enabled(prefs: &Preferences, recovery_codes: &[String]) -> Markup {
+    let content = html! {
+        p {
+            "Two-factor authentication enabled. Save these recovery codes now, they won't be shown again. Each can be used once instead of a code from the app:"
+        }
+        pre {
+            @for code in recovery_codes {
+                (code) "\n"
+            }
+        }
+        p {
+            a href="/profile" {
+                "Back to profile"
+            }
+        }
+    };
+    tmpl_base("Two-Factor Authentication", prefs, content)
+}
+
+pub fn tmpl_totp_invalid_code(prefs: &Preferences) -> Markup {
+    let content = html! {
+        p {
+            "Invalid code, two-factor authentication is still enabled."
+        }
+        p {
+            a href="/profile" {
+                "Back to profile"
+            }
+        }
+    };
+    tmpl_base("Two-Factor Authentication", prefs, content)
+}
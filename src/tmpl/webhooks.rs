@@ -0,0 +1,169 @@
+use maud::html;
+use maud::Markup;
+
+use crate::model::Preferences;
+use crate::model::Webhook;
+use crate::model::WebhookDelivery;
+use crate::tmpl::common::tmpl_base;
+use crate::util::format_ts;
+
+/// Part of profile page that lists webhooks.
+pub fn tmpl_webhooks(webhooks: &[Webhook]) -> Markup {
+    html! {
+        h2 {
+            "Webhooks"
+        }
+        p style="font-size: small" {
+            "Webhooks get signed JSON when entries or bank accounts change. "
+            a href="/webhooks/deliveries" {
+                "Delivery log"
+            }
+        }
+        @if !webhooks.is_empty() {
+            table class="data" {
+                thead {
+                    tr {
+                        th {
+                            "url"
+                        }
+                        th {
+                            "created"
+                        }
+                        th /
+                    }
+                }
+                tbody {
+                    @for webhook in webhooks {
+                        tr {
+                            td {
+                                (webhook.url)
+                            }
+                            td {
+                                (format_ts(webhook.created))
+                            }
+                            td {
+                                a href=(format!("/webhooks/delete?id={}", webhook.id)) {
+                                    "delete"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        form method="post" action="/webhooks" {
+            "URL: "
+            input type="text" name="url" /
+            " "
+            button type="submit" {
+                "Add webhook"
+            }
+        }
+    }
+}
+
+pub fn tmpl_webhook_created(prefs: &Preferences, url: &str, secret: &str) -> Markup {
+    let content = html! {
+        p {
+            "Webhook " (url) " added. Payloads are signed with HMAC-SHA256 of this secret, "
+            "in X-CashLog-Signature header. Copy it now, it won't be shown again:"
+        }
+        pre {
+            (secret)
+        }
+        p {
+            a href="/profile" {
+                "Back to profile"
+            }
+        }
+    };
+    tmpl_base("Webhook", prefs, content)
+}
+
+pub fn tmpl_webhook_deliveries(prefs: &Preferences, deliveries: &[WebhookDelivery]) -> Markup {
+    let content = html! {
+        h1 {
+            "Webhook Deliveries"
+        }
+        @if deliveries.is_empty() {
+            p {
+                "Nothing delivered yet."
+            }
+        } @else {
+            table class="data" {
+                thead {
+                    tr {
+                        th {
+                            "created"
+                        }
+                        th {
+                            "event"
+                        }
+                        th {
+                            "url"
+                        }
+                        th {
+                            "status"
+                        }
+                        th {
+                            "attempts"
+                        }
+                        th {
+                            "last attempt"
+                        }
+                        th {
+                            "response"
+                        }
+                        th {
+                            "next attempt"
+                        }
+                    }
+                }
+                tbody {
+                    @for delivery in deliveries {
+                        tr {
+                            td {
+                                (format_ts(delivery.created))
+                            }
+                            td title=(delivery.payload) {
+                                (delivery.event)
+                            }
+                            td {
+                                (delivery.url)
+                            }
+                            td {
+                                (delivery.status.as_str())
+                            }
+                            td {
+                                (delivery.attempts)
+                            }
+                            td {
+                                @if let Some(last_attempt) = delivery.last_attempt {
+                                    (format_ts(last_attempt))
+                                }
+                            }
+                            td {
+                                @if let Some(error) = &delivery.error {
+                                    (error)
+                                } @else if let Some(status) = delivery.response_status {
+                                    (status)
+                                }
+                            }
+                            td {
+                                @if let Some(next_attempt) = delivery.next_attempt {
+                                    (format_ts(next_attempt))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        p {
+            a href="/profile" {
+                "Back to profile"
+            }
+        }
+    };
+    tmpl_base("Webhook Deliveries", prefs, content)
+}
@@ -0,0 +1,237 @@
+//! TOTP second factor, RFC 6238 with the usual authenticator app settings:
+//! HMAC-SHA1, 6 digits, 30 second steps.
+//!
+//! When TOTP is enabled for a user account, the login link leads to a code
+//! entry step instead of a session, see `page::new_session`. One-time
+//! recovery codes can be used instead of a TOTP code, only their SHA-256
+//! hashes are stored.
+
+use chrono::DateTime;
+use chrono::Utc;
+use hmac::Hmac;
+use hmac::KeyInit;
+use hmac::Mac;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use sha1::Sha1;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::db::DbError;
+use crate::storage::Storage;
+
+/// Seconds per time step.
+const STEP_SECS: i64 = 30;
+
+/// Digits in a code.
+const DIGITS: usize = 6;
+
+/// Accepted clock drift in steps, both ways.
+const DRIFT_STEPS: i64 = 1;
+
+/// Secret size in bytes, as recommended for HMAC-SHA1.
+const SECRET_LEN: usize = 20;
+
+/// Recovery codes generated when TOTP is enabled.
+pub const RECOVERY_CODES: usize = 10;
+
+/// Issuer shown in authenticator apps.
+const ISSUER: &str = "CashLog";
+
+fn random_bytes(n: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; n];
+    getrandom::getrandom(&mut buf).expect("Error getting random bytes");
+    buf
+}
+
+fn base32_alphabet() -> base32::Alphabet {
+    base32::Alphabet::Rfc4648 { padding: false }
+}
+
+/// New random secret, base32 encoded as authenticator apps expect.
+pub fn generate_secret() -> String {
+    base32::encode(base32_alphabet(), &random_bytes(SECRET_LEN))
+}
+
+/// Time step of given time.
+pub fn step(now: DateTime<Utc>) -> i64 {
+    now.timestamp().div_euclid(STEP_SECS)
+}
+
+/// HOTP code of the step, RFC 4226.
+fn code(key: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes key of any size");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(DIGITS as u32),
+        width = DIGITS
+    )
+}
+
+/// Check code against the secret. Returns the matched step, it has to be
+/// later than `last_step`, so each code can be used once.
+pub fn verify(
+    secret: &str,
+    code_str: &str,
+    now: DateTime<Utc>,
+    last_step: Option<i64>,
+) -> Option<i64> {
+    let code_str: String = code_str.chars().filter(|c| !c.is_whitespace()).collect();
+    if code_str.len() != DIGITS || !code_str.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let key = base32::decode(base32_alphabet(), secret)?;
+    let current = step(now);
+    (current - DRIFT_STEPS..=current + DRIFT_STEPS)
+        .filter(|s| last_step.is_none_or(|last| *s > last))
+        .find(|s| code(&key, *s) == code_str)
+}
+
+/// Provisioning URL for authenticator apps, usually shown as QR code.
+pub fn otpauth_url(secret: &str, email: &str) -> String {
+    let mut url = url::Url::parse("otpauth://totp/").unwrap();
+    url.set_path(&format!("{}:{}", ISSUER, email));
+    url.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", ISSUER)
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP_SECS.to_string());
+    url.to_string()
+}
+
+/// QR code of the text as SVG element, to embed in HTML.
+pub fn qr_svg(text: &str) -> String {
+    let svg = QrCode::new(text.as_bytes())
+        .expect("Text fits in QR code")
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build();
+    // Drop the XML declaration.
+    match svg.find("<svg") {
+        Some(i) => svg[i..].to_string(),
+        None => svg,
+    }
+}
+
+/// New random recovery codes, e.g. "3f2a9-0c81d".
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let hex: String = random_bytes(5)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            format!("{}-{}", &hex[..5], &hex[5..])
+        })
+        .collect()
+}
+
+/// Hash of recovery code as stored, ignores case, spaces and dashes.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Check TOTP code or recovery code of user account, using it up.
+/// False if TOTP is not enabled.
+pub fn check_code(
+    conn: &mut dyn Storage,
+    account_id: i64,
+    code: &str,
+    now: DateTime<Utc>,
+) -> Result<bool, DbError> {
+    let totp = match conn.get_totp(account_id)? {
+        Some(totp) if totp.enabled => totp,
+        _ => return Ok(false),
+    };
+    match verify(&totp.secret, code, now, totp.last_step) {
+        Some(step) => conn.set_totp_step(account_id, step),
+        None => conn.use_recovery_code(account_id, &hash_recovery_code(code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Secret of RFC 6238 test vectors, ASCII "12345678901234567890".
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn rfc_6238_vectors() {
+        // SHA-1 vectors from RFC 6238 appendix B, last 6 of 8 digits.
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        let key = base32::decode(base32_alphabet(), SECRET).unwrap();
+        for (secs, expected) in vectors.iter() {
+            assert_eq!(code(&key, step(at(*secs))), *expected);
+            assert_eq!(
+                verify(SECRET, expected, at(*secs), None),
+                Some(step(at(*secs)))
+            );
+        }
+        assert_eq!(verify(SECRET, "287 082", at(59), None), Some(1));
+        assert_eq!(verify(SECRET, "94287082", at(59), None), None);
+        assert_eq!(verify(SECRET, "287083", at(59), None), None);
+    }
+
+    #[test]
+    fn used_step_is_rejected() {
+        assert_eq!(verify(SECRET, "287082", at(59), Some(1)), None);
+        assert_eq!(verify(SECRET, "287082", at(59), Some(2)), None);
+        assert_eq!(verify(SECRET, "287082", at(59), Some(0)), Some(1));
+    }
+
+    #[test]
+    fn clock_drift() {
+        // The code of step 1 (30..59 s) is accepted one step before and after.
+        assert_eq!(verify(SECRET, "287082", at(0), None), Some(1));
+        assert_eq!(verify(SECRET, "287082", at(89), None), Some(1));
+        assert_eq!(verify(SECRET, "287082", at(90), None), None);
+        let key = base32::decode(base32_alphabet(), SECRET).unwrap();
+        assert_eq!(verify(SECRET, &code(&key, 4), at(89), None), None);
+        // Code of the step before is still accepted after a later step,
+        // unless that later step was used.
+        assert_eq!(verify(SECRET, &code(&key, 2), at(89), Some(1)), Some(2));
+        assert_eq!(verify(SECRET, &code(&key, 2), at(89), Some(3)), None);
+    }
+
+    #[test]
+    fn recovery_code_hash() {
+        assert_eq!(
+            hash_recovery_code("3F2A9-0C81D"),
+            hash_recovery_code(" 3f2a90c81d")
+        );
+        assert_ne!(
+            hash_recovery_code("3f2a9-0c81d"),
+            hash_recovery_code("3f2a9-0c81e")
+        );
+    }
+}
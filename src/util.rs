@@ -3,18 +3,75 @@ use chrono::DateTime;
 use chrono::TimeZone;
 use chrono::Utc;
 
+use crate::model::MAX_NOTE_LENGTH;
+
 pub fn parse_ts(s: &str) -> Result<DateTime<Utc>, String> {
     chrono::Utc
         .datetime_from_str(s, "%Y-%m-%d %H:%M:%S")
         .map_err(|e| format!("Error parsing datetime: {}", e))
 }
 
+/// Parse entry note from form or API, blank note is no note. Notes are one
+/// line, so that they fit in CSV export.
+pub fn parse_note(s: Option<&str>) -> Result<Option<String>, String> {
+    let note = s.unwrap_or("").replace(['\n', '\r'], " ");
+    let note = note.trim();
+    if note.chars().count() > MAX_NOTE_LENGTH {
+        Err(format!(
+            "Note can have at most {} characters",
+            MAX_NOTE_LENGTH
+        ))
+    } else if note.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(note.to_string()))
+    }
+}
+
+/// Parse the "as of" param of balance views. Either date, meaning the end of
+/// that day, or full timestamp.
+pub fn parse_as_of(s: &str) -> Result<DateTime<Utc>, String> {
+    match chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(d) => Ok(d.and_hms_micro_opt(23, 59, 59, 999_999).unwrap().and_utc()),
+        Err(_) => parse_ts(s),
+    }
+}
+
+/// Parse the "since" param of entry lists. Either date, meaning the start of
+/// that day, or full timestamp.
+pub fn parse_since(s: &str) -> Result<DateTime<Utc>, String> {
+    match chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(d) => Ok(d.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+        Err(_) => parse_ts(s),
+    }
+}
+
 /// Format timestamp using default format.
 pub fn format_ts(dt: DateTime<Utc>) -> String {
     let fmt = "%Y-%m-%d %H:%M:%S";
     dt.format(fmt).to_string()
 }
 
+/// Encode string to be used as query param value.
+pub fn url_encode(s: &str) -> String {
+    url::form_urlencoded::byte_serialize(s.as_bytes()).collect()
+}
+
+/// Quote CSV field if needed. Names and notes are free text, but one line,
+/// see `parse_note`.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Format amount computed on Rust side, e.g. in reports.
+pub fn format_amount(a: f64) -> String {
+    format!("{:.2}", a)
+}
+
 /*
 pub fn human_bytes(b: u64) -> String {
     let mut f = b as f64;
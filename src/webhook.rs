@@ -0,0 +1,317 @@
+//! Outgoing webhooks, called when entries or bank accounts change.
+//!
+//! Storage queues a delivery to every webhook of user accounts that can
+//! read the bank account, in the same transaction as the change, and the
+//! worker started by `start_worker` sends them. Failed deliveries are
+//! retried with exponential backoff, `MAX_ATTEMPTS` times in all.
+//!
+//! Deliveries are POST requests with JSON body:
+//!
+//! ```text
+//! {"event": "entry.created", "created": "...", "data": {...}}
+//! ```
+//!
+//! where data is `WebhookEntry` or `WebhookBankAccount`. Timestamps are
+//! formatted as in the JSON API. The `X-CashLog-Signature` header is
+//! `sha256=` and hex of HMAC-SHA256 of the body, keyed by the secret of the
+//! webhook. Restoring backup doesn't call webhooks.
+//!
+//! Webhooks can't call the server itself or other hosts in its network:
+//! addresses that aren't public are rejected when the webhook is added and
+//! again on every delivery, since DNS can change, unless the host is in
+//! `webhook_allowed_hosts` config. Redirects aren't followed.
+
+use std::io;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use hmac::Hmac;
+use hmac::KeyInit;
+use hmac::Mac;
+use sha2::Sha256;
+
+use crate::api::format_api_ts;
+use crate::db::DbError;
+use crate::model::Config;
+use crate::model::DeliveryStatus;
+use crate::model::WebhookDelivery;
+use crate::model::WebhookEvent;
+use crate::storage::StoragePool;
+
+/// Attempts of delivery before it fails.
+pub const MAX_ATTEMPTS: i64 = 8;
+
+/// Delay before the first retry, it doubles with each next one.
+const FIRST_RETRY_SECONDS: i64 = 30;
+
+/// How often the worker looks for due deliveries.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Deliveries sent per poll.
+const BATCH_SIZE: i64 = 100;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Same as `webhook.url` column size.
+const MAX_URL_LENGTH: usize = 1024;
+
+/// Entry in payload of `entry.*` events.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookEntry {
+    pub id: i64,
+    pub bank_account_id: i64,
+    pub bank_account: String,
+    pub currency: String,
+    pub amount: String,
+    pub ts: String,
+    pub note: Option<String>,
+}
+
+/// Bank account in payload of `bank_account.*` events.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookBankAccount {
+    pub id: i64,
+    pub name: String,
+    pub currency: String,
+    pub group_name: Option<String>,
+    /// See `BankAccountKind::as_str`.
+    pub kind: String,
+    pub commodity: Option<String>,
+}
+
+/// JSON body of delivery.
+pub fn payload<T: serde::Serialize>(event: WebhookEvent, data: &T) -> String {
+    json!({
+        "event": event.as_str(),
+        "created": format_api_ts(Utc::now()),
+        "data": data,
+    })
+    .to_string()
+}
+
+/// Value of `X-CashLog-Signature` header.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes key of any size");
+    mac.update(body.as_bytes());
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// Random secret for new webhook.
+pub fn new_secret() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Whether the address is reachable on the internet, and not loopback,
+/// private, link-local or otherwise special.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space, RFC 6598.
+                || (a == 100 && (64..128).contains(&b))
+                // Multicast and reserved.
+                || a >= 224)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7.
+                    || first & 0xfe00 == 0xfc00
+                    // Link-local, fe80::/10.
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolve "host:port" to public addresses only, unless the host is
+/// allowed. Errors if nothing is left, so the request isn't made.
+fn resolve(netloc: &str, allowed_hosts: &[String]) -> io::Result<Vec<SocketAddr>> {
+    let host = match netloc.rsplit_once(':') {
+        Some((host, _)) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => netloc,
+    };
+    let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.collect();
+    if allowed_hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+        return Ok(addrs);
+    }
+    let addrs: Vec<SocketAddr> = addrs.into_iter().filter(|a| is_public(a.ip())).collect();
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} doesn't resolve to a public address", host),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Check URL of new webhook: it has to be HTTP(S) and its host has to
+/// resolve to public addresses, or be in `allowed_hosts`. This blocks,
+/// call it from `web::block`.
+pub fn check_url(url: &str, allowed_hosts: &[String]) -> Result<(), String> {
+    if url.len() > MAX_URL_LENGTH {
+        return Err(format!("URL is longer than {} characters", MAX_URL_LENGTH));
+    }
+    let parsed = url::Url::parse(url).map_err(|_| "Invalid URL".to_string())?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err("URL must be http or https".to_string());
+    }
+    let (host, port) = match (parsed.host_str(), parsed.port_or_known_default()) {
+        (Some(host), Some(port)) => (host, port),
+        _ => return Err("Invalid URL".to_string()),
+    };
+    resolve(&format!("{}:{}", host, port), allowed_hosts)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid URL: {}", e))
+}
+
+/// When to retry after `attempts` failed ones, `None` when out of attempts.
+pub fn next_attempt(attempts: i64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if attempts >= MAX_ATTEMPTS {
+        None
+    } else {
+        Some(now + chrono::Duration::seconds(FIRST_RETRY_SECONDS << (attempts - 1).max(0)))
+    }
+}
+
+/// POST the payload, returns response status, or the status if any and
+/// error if it's not 2xx.
+fn send(
+    agent: &ureq::Agent,
+    delivery: &WebhookDelivery,
+    secret: &str,
+) -> Result<u16, (Option<u16>, String)> {
+    let result = agent
+        .post(&delivery.url)
+        .set("Content-Type", "application/json")
+        .set("X-CashLog-Event", &delivery.event)
+        .set("X-CashLog-Delivery", &delivery.id.to_string())
+        .set("X-CashLog-Signature", &sign(secret, &delivery.payload))
+        .send_string(&delivery.payload);
+    match result {
+        Ok(response) => Ok(response.status()),
+        Err(ureq::Error::Status(status, _)) => Err((Some(status), format!("HTTP {}", status))),
+        Err(e) => Err((None, e.to_string())),
+    }
+}
+
+/// Send deliveries due now, returns how many were attempted.
+pub fn deliver_due(pool: &StoragePool, agent: &ureq::Agent) -> Result<usize, DbError> {
+    let due = pool
+        .get()?
+        .get_due_webhook_deliveries(&Utc::now(), BATCH_SIZE)?;
+    // Connection isn't held while waiting for the endpoints.
+    for (delivery, secret) in &due {
+        let attempts = delivery.attempts + 1;
+        let (status, response_status, error, next) = match send(agent, delivery, secret) {
+            Ok(status) => (DeliveryStatus::Delivered, Some(status), None, None),
+            Err((status, error)) => match next_attempt(attempts, Utc::now()) {
+                Some(next) => (DeliveryStatus::Pending, status, Some(error), Some(next)),
+                None => (DeliveryStatus::Failed, status, Some(error), None),
+            },
+        };
+        if let Some(error) = &error {
+            warn!(
+                "Webhook delivery {} to {} failed: {}.",
+                delivery.id, delivery.url, error
+            );
+        }
+        pool.get()?.record_webhook_attempt(
+            delivery.id,
+            status,
+            response_status.map(i64::from),
+            error.as_deref(),
+            next.as_ref(),
+        )?;
+    }
+    Ok(due.len())
+}
+
+/// Start thread that sends due deliveries until the process exits.
+pub fn start_worker(pool: StoragePool, conf: Config) {
+    let allowed_hosts = conf.webhook_allowed_hosts;
+    let agent = ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .redirects(0)
+        .resolver(move |netloc: &str| resolve(netloc, &allowed_hosts))
+        .build();
+    std::thread::spawn(move || loop {
+        match deliver_due(&pool, &agent) {
+            // More may be due already.
+            Ok(n) if n as i64 == BATCH_SIZE => continue,
+            Ok(_) => (),
+            Err(e) => error!("Failed to deliver webhooks: {}.", e),
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_addresses() {
+        let public = |s: &str| is_public(s.parse().unwrap());
+        assert!(public("93.184.216.34"));
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+        for ip in &[
+            "127.0.0.1",
+            "0.0.0.0",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!public(ip), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn urls() {
+        let allowed = vec!["localhost".to_string()];
+        assert!(check_url("http://93.184.216.34/hook", &[]).is_ok());
+        assert!(check_url("ftp://93.184.216.34/hook", &[]).is_err());
+        assert!(check_url("http://127.0.0.1:8080/hook", &[]).is_err());
+        assert!(check_url("http://[::1]/hook", &[]).is_err());
+        assert!(check_url("http://169.254.169.254/latest", &[]).is_err());
+        assert!(check_url("http://localhost/hook", &[]).is_err());
+        assert!(check_url("http://localhost/hook", &allowed).is_ok());
+        assert!(check_url("http://127.0.0.1/hook", &allowed).is_err());
+        let long = format!("http://localhost/{}", "a".repeat(MAX_URL_LENGTH));
+        assert!(check_url(&long, &allowed).is_err());
+    }
+}
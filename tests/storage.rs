@@ -0,0 +1,920 @@
+//! Behavioral tests every `Storage` implementation has to pass.
+//!
+//! SQLite tests run on a temporary database file. PostgreSQL tests run when
+//! `CASHLOG_TEST_CONFIG` is set to a config file of a database that has
+//! the schema and migrations applied, otherwise they are skipped. Tests use
+//! random emails and delete their user accounts, so any database will do.
+
+extern crate cashlog;
+
+use chrono::TimeZone;
+use chrono::Utc;
+
+use cashlog::backup;
+use cashlog::common;
+use cashlog::common::PoolSettings;
+use cashlog::model::BankAccountKind;
+use cashlog::model::DeliveryStatus;
+use cashlog::model::DigestFrequency;
+use cashlog::model::LandingPage;
+use cashlog::model::Preferences;
+use cashlog::model::Price;
+use cashlog::model::ShareRole;
+use cashlog::model::Theme;
+use cashlog::model::WebhookDelivery;
+use cashlog::storage;
+use cashlog::storage::sqlite;
+use cashlog::storage::Storage;
+use cashlog::storage::StoragePool;
+
+/// Storage for one test, SQLite database file is removed when dropped.
+struct TestStorage {
+    storage: Box<dyn Storage>,
+    path: Option<std::path::PathBuf>,
+}
+
+impl Drop for TestStorage {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            for suffix in &["", "-wal", "-shm"] {
+                let mut file = path.clone().into_os_string();
+                file.push(suffix);
+                let _ = std::fs::remove_file(file);
+            }
+        }
+    }
+}
+
+fn sqlite_storage() -> TestStorage {
+    let path = std::env::temp_dir().join(format!("cashlog-test-{}.db", uuid::Uuid::new_v4()));
+    let settings = PoolSettings {
+        max_size: 1,
+        connection_timeout: std::time::Duration::from_secs(5),
+        max_lifetime: None,
+        idle_timeout: None,
+    };
+    let pool = sqlite::create_pool(path.to_str().unwrap(), &settings).unwrap();
+    TestStorage {
+        storage: StoragePool::Sqlite(pool).get().unwrap(),
+        path: Some(path),
+    }
+}
+
+fn postgres_storage() -> Option<TestStorage> {
+    let config = match std::env::var("CASHLOG_TEST_CONFIG") {
+        Ok(config) => config,
+        Err(_) => {
+            eprintln!("CASHLOG_TEST_CONFIG is not set, skipping PostgreSQL test");
+            return None;
+        }
+    };
+    let conf = common::load_config_or_exit(&config);
+    let pool = storage::open_pool(&conf).unwrap();
+    Some(TestStorage {
+        storage: pool.get().unwrap(),
+        path: None,
+    })
+}
+
+fn email() -> String {
+    format!("{}@example.com", uuid::Uuid::new_v4())
+}
+
+fn ts(day: u32) -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 3, day, 12, 0, 0).unwrap()
+}
+
+fn amount(s: &str) -> f64 {
+    s.parse().unwrap()
+}
+
+/// Create bank account and return its id.
+fn bank_account(
+    s: &mut dyn Storage,
+    account_id: i64,
+    name: &str,
+    group_name: Option<&str>,
+    kind: BankAccountKind,
+    commodity: Option<&str>,
+) -> i64 {
+    s.insert_bank_account(account_id, name, "PLN", group_name, kind, commodity)
+        .unwrap();
+    s.get_bank_accounts(account_id)
+        .unwrap()
+        .into_iter()
+        .find(|ba| ba.name == name)
+        .unwrap()
+        .id
+}
+
+fn sessions(s: &mut dyn Storage) {
+    let key = uuid::Uuid::new_v4().to_string();
+    assert_eq!(s.get_sess_val(&key, "account"), None);
+    s.set_session_value(&key, "account", "1").unwrap();
+    s.set_session_value(&key, "account", "2").unwrap();
+    s.set_session_value(&key, "other", "x").unwrap();
+    assert_eq!(s.get_sess_val(&key, "account").as_deref(), Some("2"));
+    assert_eq!(s.increment_session_value(&key, "account").unwrap(), Some(3));
+    assert_eq!(s.get_sess_val(&key, "account").as_deref(), Some("3"));
+    assert_eq!(s.increment_session_value(&key, "missing").unwrap(), None);
+    s.delete_session(&key).unwrap();
+    assert_eq!(s.get_sess_val(&key, "account"), None);
+    assert_eq!(s.get_sess_val(&key, "other"), None);
+}
+
+fn accounts(s: &mut dyn Storage) {
+    let email = email();
+    assert_eq!(s.get_acc_id_by_email(&email).unwrap(), None);
+    let id = s.create_acc_with_email(&email).unwrap();
+    assert_eq!(s.get_acc_id_by_email(&email).unwrap(), Some(id));
+    assert!(s.create_acc_with_email(&email).is_err());
+    assert_eq!(*s.get_user_account_emails(id).unwrap(), vec![email.clone()]);
+    let info = s.get_user_account_info(id).unwrap().unwrap();
+    assert_eq!(*info.emails, vec![email.clone()]);
+    assert!(s.get_user_account_info(-1).unwrap().is_none());
+    let summary = s
+        .get_account_summaries()
+        .unwrap()
+        .into_iter()
+        .find(|a| a.id == id)
+        .unwrap();
+    assert_eq!(summary.emails, vec![email]);
+    assert_eq!((summary.bank_accounts, summary.entries), (0, 0));
+    assert!(!summary.disabled);
+    s.delete_account(id).unwrap();
+}
+
+fn login_tokens(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let login = uuid::Uuid::new_v4().to_string();
+    let delete = uuid::Uuid::new_v4().to_string();
+    s.insert_login_token(&id, &login).unwrap();
+    s.insert_delete_account_token(id, &delete).unwrap();
+    assert_eq!(s.get_login_token_account(&login).unwrap(), Some(id));
+    assert_eq!(
+        s.get_delete_account_token_account(&delete).unwrap(),
+        Some(id)
+    );
+    // Tokens only work for their purpose.
+    assert_eq!(s.get_login_token_account(&delete).unwrap(), None);
+    assert_eq!(s.get_delete_account_token_account(&login).unwrap(), None);
+    assert_eq!(s.get_login_token_account("no-such-token").unwrap(), None);
+    // Login token logs in once, setting the session values with it.
+    let session = uuid::Uuid::new_v4().to_string();
+    let values = [("account", "1"), ("other", "2")];
+    assert!(!s.use_login_token(&delete, &session, &values).unwrap());
+    assert_eq!(s.get_sess_val(&session, "account"), None);
+    assert!(s.use_login_token(&login, &session, &values).unwrap());
+    assert_eq!(s.get_sess_val(&session, "other").as_deref(), Some("2"));
+    assert_eq!(s.get_login_token_account(&login).unwrap(), None);
+    let again = uuid::Uuid::new_v4().to_string();
+    assert!(!s.use_login_token(&login, &again, &values).unwrap());
+    assert_eq!(s.get_sess_val(&again, "account"), None);
+    s.delete_session(&session).unwrap();
+    s.delete_account(id).unwrap();
+}
+
+fn login_requests(s: &mut dyn Storage) {
+    let (email_a, email_b) = (email(), email());
+    let ip = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let since = now - chrono::Duration::minutes(1);
+    let token = uuid::Uuid::new_v4().to_string();
+    let (token_b, invite) = (uuid::Uuid::new_v4().to_string(), "ABCD-EFGH-JKLM");
+    let insert = |s: &mut dyn Storage, email: &str, ip: Option<&str>, now| {
+        s.insert_login_request(email, ip, None, None, &now).unwrap()
+    };
+    assert_eq!(
+        s.insert_login_request(&email_a, Some(&ip), Some(&token), None, &now)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        s.insert_login_request(&email_b, Some(&ip), Some(&token_b), Some(invite), &now)
+            .unwrap(),
+        None
+    );
+    // Same email again too soon isn't recorded.
+    let retry = insert(s, &email_b, None, now).unwrap();
+    assert!(retry > now);
+    // Also when typed differently.
+    let typed = format!(" {} ", email_b.to_uppercase());
+    assert_eq!(insert(s, &typed, None, now), Some(retry));
+    // Requests match by email or by IP address.
+    let requests = s.get_login_requests(&email_a, Some(&ip), &since).unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].email, email_a);
+    assert_eq!(requests[1].ip.as_deref(), Some(ip.as_str()));
+    assert_eq!(
+        s.get_login_requests(&email_b, None, &since).unwrap().len(),
+        1
+    );
+    // IP address is limited across emails.
+    for _ in 0..18 {
+        assert_eq!(insert(s, &email(), Some(&ip), now), None);
+    }
+    assert!(insert(s, &email(), Some(&ip), now).is_some());
+    assert_eq!(insert(s, &email(), None, now), None);
+    assert_eq!(
+        s.get_login_requests(&email_a, None, &Utc::now())
+            .unwrap()
+            .len(),
+        0
+    );
+    // Token can be used once, no account is created by storage.
+    assert_eq!(
+        s.use_login_request_token(&token).unwrap(),
+        Some((email_a.clone(), None))
+    );
+    assert_eq!(s.use_login_request_token(&token).unwrap(), None);
+    assert_eq!(
+        s.use_login_request_token(&token_b).unwrap(),
+        Some((email_b.clone(), Some(invite.to_string())))
+    );
+    assert_eq!(s.use_login_request_token("no-such-token").unwrap(), None);
+    assert_eq!(s.get_acc_id_by_email(&email_a).unwrap(), None);
+}
+
+fn invites(s: &mut dyn Storage) {
+    let (code_a, code_b) = (
+        uuid::Uuid::new_v4().to_string()[..14].to_string(),
+        uuid::Uuid::new_v4().to_string()[..14].to_string(),
+    );
+    s.insert_invite(&code_a, Some("for Bob")).unwrap();
+    s.insert_invite(&code_b, None).unwrap();
+    let invites = s.get_invites().unwrap();
+    let a = invites.iter().find(|i| i.code == code_a).unwrap();
+    assert_eq!(a.note.as_deref(), Some("for Bob"));
+    assert!(a.used.is_none());
+    assert!(s.is_invite_valid(&code_a).unwrap());
+    assert!(!s.is_invite_valid("no-such-code").unwrap());
+    // Codes work once.
+    assert!(s.use_invite(&code_a).unwrap());
+    assert!(!s.use_invite(&code_a).unwrap());
+    assert!(!s.is_invite_valid(&code_a).unwrap());
+    assert!(!s.use_invite("no-such-code").unwrap());
+    let invites = s.get_invites().unwrap();
+    assert!(invites
+        .iter()
+        .find(|i| i.code == code_a)
+        .unwrap()
+        .used
+        .is_some());
+    assert!(invites
+        .iter()
+        .find(|i| i.code == code_b)
+        .unwrap()
+        .used
+        .is_none());
+}
+
+fn totp(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    assert!(s.get_totp(id).unwrap().is_none());
+    s.set_totp_secret(id, "AAAA").unwrap();
+    s.set_totp_secret(id, "BBBB").unwrap();
+    let totp = s.get_totp(id).unwrap().unwrap();
+    assert_eq!(totp.secret, "BBBB");
+    assert!(!totp.enabled);
+    let hashes = vec!["h1".to_string(), "h2".to_string()];
+    s.enable_totp(id, 100, &hashes).unwrap();
+    let totp = s.get_totp(id).unwrap().unwrap();
+    assert!(totp.enabled);
+    assert_eq!(totp.last_step, Some(100));
+    assert_eq!(totp.recovery_codes, 2);
+    // Enabled secret is not replaced by new enrolment.
+    s.set_totp_secret(id, "CCCC").unwrap();
+    assert_eq!(s.get_totp(id).unwrap().unwrap().secret, "BBBB");
+    // Steps only move forward, so codes can't be reused.
+    assert!(!s.set_totp_step(id, 100).unwrap());
+    assert!(s.set_totp_step(id, 101).unwrap());
+    assert!(!s.set_totp_step(id, 101).unwrap());
+    assert!(s.use_recovery_code(id, "h1").unwrap());
+    assert!(!s.use_recovery_code(id, "h1").unwrap());
+    assert_eq!(s.get_totp(id).unwrap().unwrap().recovery_codes, 1);
+    let (pending, session) = (
+        uuid::Uuid::new_v4().to_string(),
+        uuid::Uuid::new_v4().to_string(),
+    );
+    s.set_session_value(&pending, "totp_account", &id.to_string())
+        .unwrap();
+    s.set_session_value(&session, "account", &id.to_string())
+        .unwrap();
+    s.delete_totp(id).unwrap();
+    assert!(s.get_totp(id).unwrap().is_none());
+    // Pending logins are dropped, but user stays logged in.
+    assert_eq!(s.get_sess_val(&pending, "totp_account"), None);
+    assert!(s.get_sess_val(&session, "account").is_some());
+    assert!(!s.use_recovery_code(id, "h2").unwrap());
+    s.set_totp_secret(id, "DDDD").unwrap();
+    s.enable_totp(id, 1, &hashes).unwrap();
+    s.delete_account(id).unwrap();
+    assert!(s.get_totp(id).unwrap().is_none());
+}
+
+fn api_tokens(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let token = uuid::Uuid::new_v4().to_string();
+    s.insert_api_token(id, "cron", &token).unwrap();
+    let tokens = s.get_api_tokens(id).unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].name, "cron");
+    assert!(tokens[0].last_used.is_none());
+    assert_eq!(s.get_api_token_account(&token).unwrap(), Some(id));
+    assert!(s.get_api_tokens(id).unwrap()[0].last_used.is_some());
+    s.delete_api_token(id, tokens[0].id).unwrap();
+    assert_eq!(s.get_api_token_account(&token).unwrap(), None);
+    s.delete_account(id).unwrap();
+}
+
+fn disabled_accounts(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let login = uuid::Uuid::new_v4().to_string();
+    let api = uuid::Uuid::new_v4().to_string();
+    let (session, pending) = (
+        uuid::Uuid::new_v4().to_string(),
+        uuid::Uuid::new_v4().to_string(),
+    );
+    s.insert_login_token(&id, &login).unwrap();
+    s.insert_api_token(id, "cron", &api).unwrap();
+    s.set_session_value(&session, "account", &id.to_string())
+        .unwrap();
+    s.set_session_value(&pending, "totp_account", &id.to_string())
+        .unwrap();
+    s.set_account_disabled(id, true).unwrap();
+    assert!(s.is_account_disabled(id).unwrap());
+    assert_eq!(s.get_login_token_account(&login).unwrap(), None);
+    assert_eq!(s.get_api_token_account(&api).unwrap(), None);
+    assert_eq!(s.get_sess_val(&session, "account"), None);
+    assert_eq!(s.get_sess_val(&pending, "totp_account"), None);
+    s.set_account_disabled(id, false).unwrap();
+    assert!(!s.is_account_disabled(id).unwrap());
+    assert_eq!(s.get_login_token_account(&login).unwrap(), Some(id));
+    assert_eq!(s.get_api_token_account(&api).unwrap(), Some(id));
+    s.delete_account(id).unwrap();
+}
+
+fn bank_accounts(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let cash = bank_account(s, id, "Cash", None, BankAccountKind::Asset, None);
+    let card = bank_account(s, id, "Card", None, BankAccountKind::Asset, None);
+    let names: Vec<String> = s
+        .get_bank_accounts(id)
+        .unwrap()
+        .into_iter()
+        .map(|ba| ba.name)
+        .collect();
+    assert_eq!(names, vec!["Card", "Cash"]);
+    s.update_bank_account(id, card, Some("Daily"), BankAccountKind::Liability)
+        .unwrap();
+    let card_ba = s.get_bank_account(id, card).unwrap().unwrap();
+    assert_eq!(card_ba.group_name.as_deref(), Some("Daily"));
+    assert_eq!(card_ba.kind, BankAccountKind::Liability);
+    assert!(card_ba.role.is_none());
+    // Bank accounts without entries have no balance.
+    assert!(s.get_bank_account_infos(id, None).unwrap().is_empty());
+    s.insert_entry(&id, &cash, &ts(1), "100.50", None).unwrap();
+    s.insert_entry(&id, &cash, &ts(3), "200", None).unwrap();
+    s.insert_entry(&id, &card, &ts(2), "30", None).unwrap();
+    let infos = s.get_bank_account_infos(id, None).unwrap();
+    assert_eq!(infos.len(), 2);
+    assert_eq!(infos[1].bank_account, "Cash");
+    assert_eq!(amount(&infos[1].amount), 200.0);
+    assert_eq!(infos[1].ts, ts(3));
+    let infos = s.get_bank_account_infos(id, Some(&ts(2))).unwrap();
+    assert_eq!(amount(&infos[1].amount), 100.5);
+    let currency = s.get_currency_info(id, None).unwrap();
+    assert_eq!(currency.len(), 1);
+    assert_eq!(amount(&currency[0].assets), 200.0);
+    assert_eq!(amount(&currency[0].liabilities), 30.0);
+    assert_eq!(amount(&currency[0].amount), 170.0);
+    let groups = s.get_group_info(id, None).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].group_name, "Daily");
+    assert_eq!(amount(&groups[0].amount), -30.0);
+    s.delete_account(id).unwrap();
+}
+
+fn entries(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let cash = bank_account(s, id, "Cash", Some("Daily"), BankAccountKind::Asset, None);
+    let bank = bank_account(s, id, "Bank", None, BankAccountKind::Asset, None);
+    s.insert_entry(&id, &cash, &ts(2), "20", None).unwrap();
+    s.insert_entry(&id, &cash, &ts(1), "10", None).unwrap();
+    s.insert_entry(&id, &bank, &ts(3), "30", None).unwrap();
+    let newest_first: Vec<f64> = s
+        .get_entries(id)
+        .unwrap()
+        .iter()
+        .map(|e| amount(&e.amount))
+        .collect();
+    assert_eq!(newest_first, vec![30.0, 20.0, 10.0]);
+    let all = s.get_all_entries(id).unwrap();
+    assert_eq!(
+        all.iter().map(|e| e.ts).collect::<Vec<_>>(),
+        vec![ts(1), ts(2), ts(3)]
+    );
+    assert_eq!(all[0].bank_account, "Cash");
+    assert_eq!(all[0].bank_account_id, cash);
+    assert_eq!(all[2].bank_account_id, bank);
+    assert_eq!(all[0].currency, "PLN");
+    assert_eq!(amount(all[0].value.as_deref().unwrap()), 10.0);
+    assert_eq!(s.get_entries_by_bank_account(id, cash).unwrap().len(), 2);
+    assert_eq!(s.get_entries_by_group(id, "Daily", "PLN").unwrap().len(), 2);
+    assert!(s
+        .get_entries_by_group(id, "Daily", "EUR")
+        .unwrap()
+        .is_empty());
+    let entry_id = all[0].id;
+    s.update_entry(id, entry_id, "15".to_string(), None)
+        .unwrap();
+    assert_eq!(
+        amount(&s.get_entry(id, entry_id).unwrap().unwrap().amount),
+        15.0
+    );
+    assert!(s.update_entry(id, entry_id, "x".to_string(), None).is_err());
+    s.delete_entry(id, entry_id).unwrap();
+    assert!(s.get_entry(id, entry_id).unwrap().is_none());
+    assert_eq!(s.get_all_entries(id).unwrap().len(), 2);
+    // Other user accounts can't see or change the entries.
+    let other = s.create_acc_with_email(&email()).unwrap();
+    let bank_entry = s.get_entries_by_bank_account(id, bank).unwrap()[0].id;
+    assert!(s.get_entry(other, bank_entry).unwrap().is_none());
+    assert!(s.get_all_entries(other).unwrap().is_empty());
+    s.insert_entry(&other, &bank, &ts(4), "40", None).unwrap();
+    s.update_entry(other, bank_entry, "0".to_string(), None)
+        .unwrap();
+    s.delete_entry(other, bank_entry).unwrap();
+    let bank_entries = s.get_entries_by_bank_account(id, bank).unwrap();
+    assert_eq!(bank_entries.len(), 1);
+    assert_eq!(amount(&bank_entries[0].amount), 30.0);
+    let summary = s
+        .get_account_summaries()
+        .unwrap()
+        .into_iter()
+        .find(|a| a.id == id)
+        .unwrap();
+    assert_eq!((summary.bank_accounts, summary.entries), (2, 2));
+    s.delete_account(other).unwrap();
+    s.delete_account(id).unwrap();
+}
+
+fn entry_notes(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let cash = bank_account(s, id, "Cash", None, BankAccountKind::Asset, None);
+    s.insert_entry(&id, &cash, &ts(1), "10", Some("Salary"))
+        .unwrap();
+    s.insert_entry(&id, &cash, &ts(2), "5", Some("Bought car"))
+        .unwrap();
+    s.insert_entry(&id, &cash, &ts(3), "6", None).unwrap();
+    let search = |s: &mut dyn Storage, q| -> Vec<f64> {
+        s.get_entries_page(id, q, 10, 0)
+            .unwrap()
+            .iter()
+            .map(|e| amount(&e.amount))
+            .collect()
+    };
+    assert_eq!(search(s, None), vec![6.0, 5.0, 10.0]);
+    assert_eq!(search(s, Some("CAR")), vec![5.0]);
+    assert_eq!(search(s, Some("a")), vec![5.0, 10.0]);
+    assert!(search(s, Some("bonus")).is_empty());
+    let entry_id = s.get_entries_page(id, Some("car"), 10, 0).unwrap()[0].id;
+    let entry = s.get_entry(id, entry_id).unwrap().unwrap();
+    assert_eq!(entry.note.as_deref(), Some("Bought car"));
+    s.update_entry(id, entry_id, "7".to_string(), Some("Sold car"))
+        .unwrap();
+    assert_eq!(
+        s.get_entry(id, entry_id).unwrap().unwrap().note.as_deref(),
+        Some("Sold car")
+    );
+    s.update_entry(id, entry_id, "7".to_string(), None).unwrap();
+    assert_eq!(s.get_entry(id, entry_id).unwrap().unwrap().note, None);
+    assert!(search(s, Some("car")).is_empty());
+    s.delete_account(id).unwrap();
+}
+
+fn bulk_entries(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let cash = bank_account(s, id, "Cash", None, BankAccountKind::Asset, None);
+    let bank = bank_account(s, id, "Bank", None, BankAccountKind::Asset, None);
+    let other = s.create_acc_with_email(&email()).unwrap();
+    let others = bank_account(s, other, "Other", None, BankAccountKind::Asset, None);
+    // Bank accounts of other user accounts are skipped.
+    let amounts = vec![
+        (cash, "10".to_string()),
+        (bank, "20".to_string()),
+        (others, "30".to_string()),
+    ];
+    assert_eq!(s.insert_entries(id, &ts(1), &amounts).unwrap(), 2);
+    let all = s.get_all_entries(id).unwrap();
+    assert_eq!(all.len(), 2);
+    assert!(all.iter().all(|e| e.ts == ts(1)));
+    assert!(s.get_all_entries(other).unwrap().is_empty());
+    // Nothing is inserted if any amount is invalid.
+    let amounts = vec![(cash, "11".to_string()), (bank, "x".to_string())];
+    assert!(s.insert_entries(id, &ts(2), &amounts).is_err());
+    assert_eq!(s.get_all_entries(id).unwrap().len(), 2);
+    s.delete_account(id).unwrap();
+    s.delete_account(other).unwrap();
+}
+
+fn entry_pages(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let cash = bank_account(s, id, "Cash", None, BankAccountKind::Asset, None);
+    for day in 1..=5 {
+        s.insert_entry(&id, &cash, &ts(day), &day.to_string(), None)
+            .unwrap();
+    }
+    let page = |s: &mut dyn Storage, limit, offset| -> Vec<f64> {
+        s.get_entries_page(id, None, limit, offset)
+            .unwrap()
+            .iter()
+            .map(|e| amount(&e.amount))
+            .collect()
+    };
+    assert_eq!(page(s, 2, 0), vec![5.0, 4.0]);
+    assert_eq!(page(s, 2, 2), vec![3.0, 2.0]);
+    assert_eq!(page(s, 2, 4), vec![1.0]);
+    assert!(page(s, 2, 6).is_empty());
+    s.delete_account(id).unwrap();
+}
+
+fn preferences(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    assert_eq!(s.get_preferences(id).unwrap(), Preferences::default());
+    let cash = bank_account(s, id, "Cash", None, BankAccountKind::Asset, None);
+    let mut preferences = Preferences {
+        theme: Theme::Dark,
+        landing_page: LandingPage::CheckIn,
+        default_bank_account: Some(cash),
+        entries_per_page: 20,
+        digest: DigestFrequency::Off,
+    };
+    s.set_preferences(id, &preferences).unwrap();
+    assert_eq!(s.get_preferences(id).unwrap(), preferences);
+    preferences.theme = Theme::HighContrast;
+    preferences.default_bank_account = None;
+    s.set_preferences(id, &preferences).unwrap();
+    assert_eq!(s.get_preferences(id).unwrap(), preferences);
+    // Bank account shared with other user account can be its default, and
+    // owner can still delete the user account.
+    let other = s.create_acc_with_email(&email()).unwrap();
+    let other_email = s.get_user_account_emails(other).unwrap()[0].clone();
+    assert!(s
+        .share_bank_account(id, cash, &other_email, ShareRole::Editor)
+        .unwrap());
+    preferences.default_bank_account = Some(cash);
+    s.set_preferences(other, &preferences).unwrap();
+    s.delete_account(id).unwrap();
+    assert_eq!(s.get_preferences(other).unwrap().default_bank_account, None);
+    s.delete_account(other).unwrap();
+    assert_eq!(s.get_preferences(other).unwrap(), Preferences::default());
+}
+
+fn digests(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let subscription = |s: &mut dyn Storage| {
+        s.get_digest_subscriptions()
+            .unwrap()
+            .into_iter()
+            .find(|d| d.account_id == id)
+            .map(|d| (d.frequency, d.sent))
+    };
+    assert_eq!(subscription(s), None);
+    let mut preferences = Preferences {
+        digest: DigestFrequency::Weekly,
+        ..Preferences::default()
+    };
+    s.set_preferences(id, &preferences).unwrap();
+    assert_eq!(s.get_preferences(id).unwrap(), preferences);
+    assert_eq!(subscription(s), Some((DigestFrequency::Weekly, None)));
+    s.set_digest_sent(id, &ts(1)).unwrap();
+    // Changing frequency keeps when the last digest was sent.
+    preferences.digest = DigestFrequency::Monthly;
+    s.set_preferences(id, &preferences).unwrap();
+    assert_eq!(
+        subscription(s),
+        Some((DigestFrequency::Monthly, Some(ts(1))))
+    );
+    // Disabled user accounts don't get digest.
+    s.set_account_disabled(id, true).unwrap();
+    assert_eq!(subscription(s), None);
+    s.set_account_disabled(id, false).unwrap();
+    preferences.digest = DigestFrequency::Off;
+    s.set_preferences(id, &preferences).unwrap();
+    assert_eq!(subscription(s), None);
+    assert_eq!(s.get_preferences(id).unwrap(), preferences);
+    preferences.digest = DigestFrequency::Weekly;
+    s.set_preferences(id, &preferences).unwrap();
+    s.delete_account(id).unwrap();
+    assert_eq!(subscription(s), None);
+}
+
+fn sharing(s: &mut dyn Storage) {
+    let owner = s.create_acc_with_email(&email()).unwrap();
+    let viewer_email = email();
+    let viewer = s.create_acc_with_email(&viewer_email).unwrap();
+    let cash = bank_account(s, owner, "Cash", None, BankAccountKind::Asset, None);
+    s.insert_entry(&owner, &cash, &ts(1), "10", None).unwrap();
+    assert!(!s
+        .share_bank_account(owner, cash, "nobody@example.com", ShareRole::Viewer)
+        .unwrap());
+    assert!(s
+        .share_bank_account(owner, cash, &viewer_email, ShareRole::Viewer)
+        .unwrap());
+    let shared = s.get_bank_account(viewer, cash).unwrap().unwrap();
+    assert_eq!(shared.role, Some(ShareRole::Viewer));
+    assert_eq!(s.get_all_entries(viewer).unwrap().len(), 1);
+    assert_eq!(s.get_bank_account_infos(viewer, None).unwrap().len(), 1);
+    // Viewers can't add entries, editors can.
+    s.insert_entry(&viewer, &cash, &ts(2), "20", None).unwrap();
+    assert_eq!(s.get_all_entries(owner).unwrap().len(), 1);
+    s.share_bank_account(owner, cash, &viewer_email, ShareRole::Editor)
+        .unwrap();
+    s.insert_entry(&viewer, &cash, &ts(2), "20", None).unwrap();
+    assert_eq!(s.get_all_entries(owner).unwrap().len(), 2);
+    let shares = s.get_bank_account_shares(owner, cash).unwrap();
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].email, viewer_email);
+    assert_eq!(shares[0].role, ShareRole::Editor);
+    assert!(s.get_bank_account_shares(viewer, cash).unwrap().is_empty());
+    s.delete_bank_account_share(owner, shares[0].id).unwrap();
+    assert!(s.get_bank_account(viewer, cash).unwrap().is_none());
+    s.delete_account(viewer).unwrap();
+    s.delete_account(owner).unwrap();
+}
+
+fn prices(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let gold = bank_account(s, id, "Gold", None, BankAccountKind::Asset, Some("XAU"));
+    s.insert_entry(&id, &gold, &ts(2), "2", None).unwrap();
+    assert!(s.get_all_entries(id).unwrap()[0].value.is_none());
+    let price = |day, price: &str| Price {
+        commodity: "XAU".to_string(),
+        currency: "PLN".to_string(),
+        ts: ts(day),
+        price: price.to_string(),
+    };
+    s.insert_prices(id, &[price(1, "100"), price(3, "300")])
+        .unwrap();
+    s.insert_prices(id, &[price(1, "150")]).unwrap();
+    let prices = s.get_prices(id).unwrap();
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices[0].price.ts, ts(3));
+    assert_eq!(amount(&prices[1].price.price), 150.0);
+    // Holding is valued by the latest price at or before the entry.
+    let entry = &s.get_all_entries(id).unwrap()[0];
+    assert_eq!(amount(entry.value.as_deref().unwrap()), 300.0);
+    let info = &s.get_bank_account_infos(id, None).unwrap()[0];
+    assert_eq!(amount(info.value.as_deref().unwrap()), 600.0);
+    assert_eq!(
+        amount(&s.get_currency_info(id, None).unwrap()[0].amount),
+        600.0
+    );
+    s.delete_price(id, prices[0].id).unwrap();
+    assert_eq!(s.get_prices(id).unwrap().len(), 1);
+    s.delete_account(id).unwrap();
+}
+
+fn backups(s: &mut dyn Storage) {
+    let id = s.create_acc_with_email(&email()).unwrap();
+    let cash = bank_account(s, id, "Cash", Some("Daily"), BankAccountKind::Asset, None);
+    s.insert_entry(&id, &cash, &ts(1), "10.25", None).unwrap();
+    s.insert_entry(&id, &cash, &ts(2), "20", Some("bonus"))
+        .unwrap();
+    s.insert_prices(
+        id,
+        &[Price {
+            commodity: "XAU".to_string(),
+            currency: "PLN".to_string(),
+            ts: ts(1),
+            price: "100".to_string(),
+        }],
+    )
+    .unwrap();
+    let preferences = Preferences {
+        theme: Theme::Dark,
+        landing_page: LandingPage::Report,
+        default_bank_account: Some(cash),
+        entries_per_page: 20,
+        digest: DigestFrequency::Weekly,
+    };
+    s.set_preferences(id, &preferences).unwrap();
+    let json = serde_json::to_string(&s.get_backup(id).unwrap()).unwrap();
+    let mut backup = backup::parse(&json).unwrap();
+    assert_eq!(backup.bank_accounts.len(), 1);
+    assert_eq!(backup.bank_accounts[0].entries.len(), 2);
+    let restored_email = email();
+    let restored = s.create_acc_with_email(&restored_email).unwrap();
+    backup.emails.push(restored_email);
+    backup.emails.push(email());
+    let dry_run = s.restore_backup(restored, &backup, true).unwrap();
+    assert_eq!(dry_run.emails_existing, 1);
+    assert_eq!(dry_run.emails_skipped, 2);
+    assert_eq!(dry_run.bank_accounts_added, 1);
+    assert_eq!(dry_run.entries_added, 2);
+    assert_eq!(dry_run.prices_added, 1);
+    assert!(dry_run.preferences_restored);
+    assert!(s.get_all_entries(restored).unwrap().is_empty());
+    assert_eq!(s.get_preferences(restored).unwrap().theme, Theme::Light);
+    let summary = s.restore_backup(restored, &backup, false).unwrap();
+    // Emails are never added, even if no user account has them.
+    assert_eq!(summary.emails_skipped, 2);
+    assert_eq!(s.get_user_account_emails(restored).unwrap().len(), 1);
+    let again = s.restore_backup(restored, &backup, false).unwrap();
+    assert_eq!(again.bank_accounts_existing, 1);
+    assert_eq!(again.entries_existing, 2);
+    assert_eq!(again.entries_added, 0);
+    assert_eq!(again.prices_existing, 1);
+    let entries = s.get_all_entries(restored).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(amount(&entries[0].amount), 10.25);
+    assert_eq!(entries[0].ts, ts(1));
+    assert_eq!(entries[1].note.as_deref(), Some("bonus"));
+    let ba = &s.get_bank_accounts(restored).unwrap()[0];
+    assert_eq!(ba.group_name.as_deref(), Some("Daily"));
+    let restored_preferences = s.get_preferences(restored).unwrap();
+    assert_eq!(restored_preferences.theme, Theme::Dark);
+    assert_eq!(restored_preferences.landing_page, LandingPage::Report);
+    assert_eq!(restored_preferences.default_bank_account, Some(ba.id));
+    assert_eq!(restored_preferences.entries_per_page, 20);
+    assert_eq!(restored_preferences.digest, DigestFrequency::Weekly);
+    // Backups made before preferences were included restore without them.
+    backup.preferences = None;
+    assert!(
+        !s.restore_backup(restored, &backup, true)
+            .unwrap()
+            .preferences_restored
+    );
+    s.delete_account(restored).unwrap();
+    s.delete_account(id).unwrap();
+}
+
+fn webhooks(s: &mut dyn Storage) {
+    let owner = s.create_acc_with_email(&email()).unwrap();
+    let other_email = email();
+    let other = s.create_acc_with_email(&other_email).unwrap();
+    s.insert_webhook(owner, "http://localhost/owner", "secret")
+        .unwrap();
+    s.insert_webhook(other, "http://localhost/other", "other secret")
+        .unwrap();
+    let webhooks = s.get_webhooks(owner).unwrap();
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].url, "http://localhost/owner");
+    let cash = bank_account(s, owner, "Cash", None, BankAccountKind::Asset, None);
+    s.update_bank_account(owner, cash, Some("Daily"), BankAccountKind::Asset)
+        .unwrap();
+    s.insert_entry(&owner, &cash, &ts(1), "10", None).unwrap();
+    let entry = s.get_all_entries(owner).unwrap()[0].id;
+    s.update_entry(owner, entry, "12".to_string(), None)
+        .unwrap();
+    s.delete_entry(owner, entry).unwrap();
+    // Already deleted, nothing changes.
+    s.delete_entry(owner, entry).unwrap();
+    let deliveries = s.get_webhook_deliveries(owner, 10).unwrap();
+    let events: Vec<&str> = deliveries.iter().map(|d| d.event.as_str()).collect();
+    assert_eq!(
+        events,
+        vec![
+            "entry.deleted",
+            "entry.updated",
+            "entry.created",
+            "bank_account.updated",
+            "bank_account.created"
+        ]
+    );
+    assert!(deliveries
+        .iter()
+        .all(|d| d.status == DeliveryStatus::Pending && d.attempts == 0));
+    let payload: serde_json::Value = serde_json::from_str(&deliveries[1].payload).unwrap();
+    assert_eq!(payload["event"], "entry.updated");
+    assert_eq!(payload["data"]["id"], entry);
+    assert_eq!(payload["data"]["bank_account_id"], cash);
+    assert_eq!(amount(payload["data"]["amount"].as_str().unwrap()), 12.0);
+    let payload: serde_json::Value = serde_json::from_str(&deliveries[3].payload).unwrap();
+    assert_eq!(payload["data"]["group_name"], "Daily");
+    // Other user account can't see the bank account until it's shared.
+    assert!(s.get_webhook_deliveries(other, 10).unwrap().is_empty());
+    assert!(s
+        .share_bank_account(owner, cash, &other_email, ShareRole::Viewer)
+        .unwrap());
+    s.insert_entry(&owner, &cash, &ts(2), "20", None).unwrap();
+    assert_eq!(s.get_webhook_deliveries(other, 10).unwrap().len(), 1);
+    let due = |s: &mut dyn Storage| -> Vec<(WebhookDelivery, String)> {
+        s.get_due_webhook_deliveries(&(Utc::now() + chrono::Duration::minutes(1)), 1000)
+            .unwrap()
+            .into_iter()
+            .filter(|(d, _)| d.webhook == webhooks[0].id)
+            .collect()
+    };
+    let owner_due = due(s);
+    assert_eq!(owner_due.len(), 6);
+    assert_eq!(owner_due[0].0.event, "bank_account.created");
+    assert_eq!(owner_due[0].1, "secret");
+    let retry = Utc::now() + chrono::Duration::hours(1);
+    s.record_webhook_attempt(
+        owner_due[0].0.id,
+        DeliveryStatus::Pending,
+        Some(500),
+        Some("HTTP 500"),
+        Some(&retry),
+    )
+    .unwrap();
+    s.record_webhook_attempt(
+        owner_due[1].0.id,
+        DeliveryStatus::Delivered,
+        Some(200),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(due(s).len(), 4);
+    let deliveries = s.get_webhook_deliveries(owner, 10).unwrap();
+    let retried = deliveries
+        .iter()
+        .find(|d| d.id == owner_due[0].0.id)
+        .unwrap();
+    assert_eq!(retried.status, DeliveryStatus::Pending);
+    assert_eq!(retried.attempts, 1);
+    assert_eq!(retried.response_status, Some(500));
+    assert_eq!(retried.error.as_deref(), Some("HTTP 500"));
+    assert!(retried.next_attempt.unwrap() > Utc::now());
+    assert!(retried.last_attempt.is_some());
+    let delivered = deliveries
+        .iter()
+        .find(|d| d.id == owner_due[1].0.id)
+        .unwrap();
+    assert_eq!(delivered.status, DeliveryStatus::Delivered);
+    assert_eq!(delivered.next_attempt, None);
+    s.delete_webhook(owner, webhooks[0].id).unwrap();
+    assert!(s.get_webhooks(owner).unwrap().is_empty());
+    assert!(s.get_webhook_deliveries(owner, 10).unwrap().is_empty());
+    s.delete_account(owner).unwrap();
+    s.delete_account(other).unwrap();
+}
+
+fn account_deletion(s: &mut dyn Storage) {
+    let email = email();
+    let id = s.create_acc_with_email(&email).unwrap();
+    let cash = bank_account(s, id, "Cash", None, BankAccountKind::Asset, None);
+    s.insert_entry(&id, &cash, &ts(1), "10", None).unwrap();
+    let session = uuid::Uuid::new_v4().to_string();
+    s.set_session_value(&session, "account", &id.to_string())
+        .unwrap();
+    s.delete_account(id).unwrap();
+    assert_eq!(s.get_acc_id_by_email(&email).unwrap(), None);
+    assert_eq!(s.get_sess_val(&session, "account"), None);
+    assert!(s.get_user_account_info(id).unwrap().is_none());
+    // The email can be used again.
+    let id = s.create_acc_with_email(&email).unwrap();
+    assert!(s.get_bank_accounts(id).unwrap().is_empty());
+    s.delete_account(id).unwrap();
+}
+
+/// Run each test function against every storage.
+macro_rules! storage_tests {
+    ($($name:ident),*) => {
+        mod sqlite_storage {
+            $(
+                #[test]
+                fn $name() {
+                    super::$name(&mut *super::sqlite_storage().storage);
+                }
+            )*
+        }
+
+        mod postgres_storage {
+            $(
+                #[test]
+                fn $name() {
+                    if let Some(mut s) = super::postgres_storage() {
+                        super::$name(&mut *s.storage);
+                    }
+                }
+            )*
+        }
+    };
+}
+
+storage_tests!(
+    sessions,
+    accounts,
+    login_tokens,
+    login_requests,
+    invites,
+    totp,
+    api_tokens,
+    disabled_accounts,
+    bank_accounts,
+    entries,
+    entry_notes,
+    bulk_entries,
+    entry_pages,
+    sharing,
+    prices,
+    backups,
+    preferences,
+    digests,
+    webhooks,
+    account_deletion
+);